@@ -0,0 +1,128 @@
+use std::path::PathBuf;
+
+use foray_data_model::{
+    context::ComputeContext,
+    node::{Dict, PortData},
+};
+use foray_graph::graph::GraphNode;
+use foray_ui::{headless::execute, network::Network as UiNetwork};
+use pyo3::{
+    exceptions::{PyIOError, PyKeyError, PyRuntimeError, PyValueError},
+    prelude::*,
+};
+
+/// A foray network, loaded from a `.network` file, that can be run from python.
+///
+/// ```python
+/// import foray
+/// network = foray.Network.load("networks/recon.network")
+/// network.set_parameter(3, "sigma", 2.0)
+/// outputs = network.run(inputs={"1.kspace": kspace})
+/// image = outputs["4.image"]
+/// ```
+/// Ports are named `"<node id>.<port name>"`, see `Network.nodes()` for the ids of each node
+#[pyclass(unsendable)]
+struct Network {
+    network: UiNetwork,
+}
+
+#[pymethods]
+impl Network {
+    #[staticmethod]
+    fn load(path: PathBuf) -> PyResult<Self> {
+        UiNetwork::load_network(&path)
+            .map(|network| Self { network })
+            .map_err(|e| PyIOError::new_err(format!("could not load network {path:?}: {e:?}")))
+    }
+
+    /// Node ids, and their names
+    fn nodes(&self) -> Dict<u32, String> {
+        let graph = &self.network.graph;
+        graph
+            .nodes_ref()
+            .into_iter()
+            .map(|nx| (nx, graph.get_node(nx).template.name()))
+            .collect()
+    }
+
+    fn parameters(&self, node: u32) -> PyResult<Dict<String, PortData>> {
+        self.check_node(node)?;
+        Ok(self.network.graph.get_node(node).parameters_values.clone())
+    }
+
+    fn set_parameter(&mut self, node: u32, name: String, value: PortData) -> PyResult<()> {
+        self.check_node(node)?;
+        self.network.set_parameter(node, name, value);
+        // Nothing is drawn meanwhile, so slow configuration isn't left to the background
+        self.network.configure(node);
+        Ok(())
+    }
+
+    /// Run every node once, returning the value of every output port.
+    /// `inputs` replace whatever is connected to the given input ports
+    #[pyo3(signature = (inputs = None))]
+    fn run(&mut self, inputs: Option<Dict<String, PortData>>) -> PyResult<Dict<String, PortData>> {
+        let inputs: Dict<(u32, String), PortData> = inputs
+            .unwrap_or_default()
+            .into_iter()
+            .map(|(port, value)| Ok((self.parse_input_port(&port)?, value)))
+            .collect::<PyResult<_>>()?;
+
+        let graph = &mut self.network.graph;
+        execute(graph, &inputs, |_, _| ComputeContext::default()).map_err(|(nx, e)| {
+            PyRuntimeError::new_err(format!(
+                "node {} #{nx} failed:\n{e}",
+                graph.get_node(nx).template.name()
+            ))
+        })?;
+
+        Ok(graph
+            .nodes_ref()
+            .into_iter()
+            .flat_map(|nx| {
+                graph
+                    .get_output_data(&nx)
+                    .into_iter()
+                    .map(move |(port, value)| (format!("{nx}.{port}"), value))
+            })
+            .map(|(port, value)| (port, value.read().unwrap().clone()))
+            .collect())
+    }
+}
+
+impl Network {
+    fn check_node(&self, node: u32) -> PyResult<()> {
+        match self.network.graph.nodes_ref().contains(&node) {
+            true => Ok(()),
+            false => Err(PyKeyError::new_err(format!("no node with id {node}"))),
+        }
+    }
+
+    /// Split a `"<node id>.<port name>"` into its parts, checking the input port exists
+    fn parse_input_port(&self, port: &str) -> PyResult<(u32, String)> {
+        let (node, name) = port
+            .split_once('.')
+            .and_then(|(node, name)| Some((node.parse::<u32>().ok()?, name.to_string())))
+            .ok_or_else(|| {
+                PyValueError::new_err(format!(
+                    "port {port:?} should be formatted as \"<node id>.<port name>\""
+                ))
+            })?;
+        self.check_node(node)?;
+
+        let node_data = self.network.graph.get_node(node);
+        match node_data.inputs().contains_key(&name) {
+            true => Ok((node, name)),
+            false => Err(PyKeyError::new_err(format!(
+                "node {} #{node} has no port {name:?}",
+                node_data.template.name()
+            ))),
+        }
+    }
+}
+
+/// Imported from python as `foray_bindings`, and re-exported by the `foray` package
+#[pymodule]
+fn foray_bindings(m: &Bound<'_, PyModule>) -> PyResult<()> {
+    m.add_class::<Network>()
+}
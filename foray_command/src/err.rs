@@ -0,0 +1,65 @@
+use std::fmt::Display;
+
+use foray_formats::err::FormatError;
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Serialize, Deserialize)]
+pub enum CommandError {
+    /// The spec file could not be read, or is invalid
+    Spec(String),
+    /// The node's inputs don't match its ports
+    Input(String),
+    /// Temporary files could not be written or read back
+    Io(String),
+    /// The command could not be started
+    Spawn(String),
+    /// The command exited unsuccessfully, `code` is `None` if it was killed by a signal
+    Exit {
+        program: String,
+        code: Option<i32>,
+        stdout: String,
+        stderr: String,
+    },
+    /// The run was cancelled, and the command killed
+    Cancelled,
+}
+
+impl From<std::io::Error> for CommandError {
+    fn from(value: std::io::Error) -> Self {
+        CommandError::Io(value.to_string())
+    }
+}
+
+impl From<FormatError> for CommandError {
+    fn from(value: FormatError) -> Self {
+        CommandError::Io(value.to_string())
+    }
+}
+
+impl Display for CommandError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            CommandError::Spec(e) => write!(f, "Invalid command spec: {e}"),
+            CommandError::Input(e) => write!(f, "Invalid input: {e}"),
+            CommandError::Io(e) => write!(f, "{e}"),
+            CommandError::Spawn(e) => write!(f, "Could not run command: {e}"),
+            CommandError::Exit {
+                program,
+                code,
+                stderr,
+                ..
+            } => {
+                match code {
+                    Some(code) => write!(f, "{program} exited with code {code}")?,
+                    None => write!(f, "{program} was killed")?,
+                }
+                // The last line is usually the most specific
+                match stderr.lines().rev().find(|line| !line.trim().is_empty()) {
+                    Some(line) => write!(f, ": {}", line.trim()),
+                    None => Ok(()),
+                }
+            }
+            CommandError::Cancelled => write!(f, "Cancelled"),
+        }
+    }
+}
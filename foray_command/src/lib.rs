@@ -0,0 +1,8 @@
+//! Nodes that run external command-line tools.
+//!
+//! A node is declared in a `*.command.toml` spec, listing its ports, parameters, and the command
+//! to run. Inputs are written to temporary files before the run, and outputs read back after it
+pub mod err;
+pub mod node;
+mod run;
+pub mod spec;
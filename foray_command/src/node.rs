@@ -0,0 +1,311 @@
+use std::{
+    fs,
+    path::{Path, PathBuf},
+    process::Command,
+};
+
+use foray_data_model::{
+    WireDataContainer,
+    node::{Dict, ForayArray, PortData, PortType, UIParameter},
+};
+use foray_formats::{complex_array, port_array};
+use log::{debug, info, warn};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    err::CommandError,
+    run::run,
+    spec::{COMMAND_EXTENSION, CommandSpec, ElementType, FileFormat, PortSpec},
+};
+
+/// Template of a node that runs a command-line tool
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq, PartialOrd)]
+pub struct CommandNodeTemplate {
+    /// Spec file that declares the node
+    pub path: PathBuf,
+    pub spec: CommandSpec,
+}
+
+impl CommandNodeTemplate {
+    pub fn name(&self) -> &str {
+        &self.spec.name
+    }
+
+    pub fn inputs(&self) -> Dict<String, PortType> {
+        port_types(&self.spec.inputs)
+    }
+
+    pub fn outputs(&self) -> Dict<String, PortType> {
+        port_types(&self.spec.outputs)
+    }
+
+    pub fn parameters(&self) -> Dict<String, UIParameter> {
+        self.spec
+            .parameters
+            .iter()
+            .map(|(name, parameter)| (name.clone(), parameter.ui_parameter()))
+            .collect()
+    }
+
+    /// Write the inputs to a temporary directory, run the command in it, and read back the outputs
+    pub fn compute(
+        &self,
+        inputs: Dict<String, WireDataContainer<PortData>>,
+        parameters: &Dict<String, PortData>,
+    ) -> Result<Dict<String, PortData>, CommandError> {
+        let dir = tempfile::Builder::new()
+            .prefix("foray-command-")
+            .tempdir()?;
+        let mut values = Dict::new();
+
+        //// Inputs
+        for (name, port) in &self.spec.inputs {
+            let data = inputs
+                .get(name)
+                .ok_or_else(|| CommandError::Input(format!("{name} is not connected")))?
+                .read()
+                .unwrap();
+            let array =
+                port_array(&data).map_err(|e| CommandError::Input(format!("{name}: {e}")))?;
+            let path = file_path(dir.path(), name, port);
+            port.format.save(&path, &array)?;
+            values.insert(name.clone(), path.to_string_lossy().into_owned());
+        }
+        for (name, port) in &self.spec.outputs {
+            if port.format.is_file() {
+                let path = file_path(dir.path(), name, port);
+                values.insert(name.clone(), path.to_string_lossy().into_owned());
+            }
+        }
+        for (name, parameter) in &self.spec.parameters {
+            let value = parameters
+                .get(name)
+                .cloned()
+                .unwrap_or_else(|| parameter.ui_parameter().default_value());
+            values.insert(name.clone(), parameter.argument(&value));
+        }
+
+        //// Run
+        let mut arguments = self.spec.arguments(&values)?.into_iter();
+        let program = self.program(&arguments.next().unwrap_or_default());
+        let arguments: Vec<String> = arguments.collect();
+        info!("Running {program:?} {}", arguments.join(" "));
+        let mut command = Command::new(&program);
+        command.args(&arguments).current_dir(dir.path());
+        let output = run(command)?;
+        debug!("{} stdout:\n{}", self.name(), output.stdout);
+        debug!("{} stderr:\n{}", self.name(), output.stderr);
+        if !output.status.success() {
+            return Err(CommandError::Exit {
+                program: program.to_string_lossy().into_owned(),
+                code: output.status.code(),
+                stdout: output.stdout,
+                stderr: output.stderr,
+            });
+        }
+
+        //// Outputs
+        self.spec
+            .outputs
+            .iter()
+            .map(|(name, port)| {
+                let data = match port.format {
+                    FileFormat::Stdout => PortData::String(output.stdout.clone()),
+                    FileFormat::Stderr => PortData::String(output.stderr.clone()),
+                    format => {
+                        let array = format
+                            .load(&file_path(dir.path(), name, port))
+                            .map_err(|e| CommandError::Io(format!("output {name}: {e}")))?;
+                        output_data(array, port)
+                            .map_err(|e| CommandError::Io(format!("output {name}: {e}")))?
+                    }
+                };
+                Ok((name.clone(), data))
+            })
+            .collect()
+    }
+
+    /// Programs given as a relative path are relative to the spec file, others are found on `PATH`
+    fn program(&self, program: &str) -> PathBuf {
+        let path = Path::new(program);
+        match (
+            path.is_relative() && path.components().count() > 1,
+            self.path.parent(),
+        ) {
+            (true, Some(dir)) => dir.join(path),
+            _ => path.to_path_buf(),
+        }
+    }
+}
+
+fn port_types(ports: &Dict<String, PortSpec>) -> Dict<String, PortType> {
+    ports
+        .iter()
+        .map(|(name, port)| (name.clone(), port.port_type()))
+        .collect()
+}
+
+fn file_path(dir: &Path, name: &str, port: &PortSpec) -> PathBuf {
+    port.format
+        .path(dir, name)
+        .expect("port is passed in a file")
+}
+
+/// Convert an array read from a file to a port's type, widening its elements if needed
+fn output_data(array: ForayArray, port: &PortSpec) -> Result<PortData, String> {
+    let found = PortType::from(&array);
+    let array = match (port.element_type, array) {
+        (ElementType::Integer, ForayArray::Integer(a)) => ForayArray::Integer(a),
+        (ElementType::Integer, ForayArray::Boolean(a)) => ForayArray::Integer(a.mapv(i32::from)),
+        (ElementType::Float, ForayArray::Float(a)) => ForayArray::Float(a),
+        (ElementType::Float, ForayArray::Integer(a)) => ForayArray::Float(a.mapv(f64::from)),
+        (ElementType::Complex, a) => {
+            ForayArray::Complex(complex_array(&a).map_err(|e| e.to_string())?)
+        }
+        (ElementType::Boolean, ForayArray::Boolean(a)) => ForayArray::Boolean(a),
+        (element_type, _) => return Err(format!("expected {element_type:?}, found {found:?}")),
+    };
+    let ndim = match &array {
+        ForayArray::Integer(a) => a.ndim(),
+        ForayArray::Float(a) => a.ndim(),
+        ForayArray::Complex(a) => a.ndim(),
+        ForayArray::Boolean(a) => a.ndim(),
+        ForayArray::String(a) => a.ndim(),
+        ForayArray::Object(a) => a.ndim(),
+    };
+    match port.ndim {
+        // Formats that can't store scalars save them as one element arrays
+        Some(0) => into_scalar(array).ok_or_else(|| format!("expected a scalar, found {found:?}")),
+        Some(expected) if expected != ndim => {
+            Err(format!("expected {expected} dimensions, found {found:?}"))
+        }
+        _ => Ok(PortData::Array(array)),
+    }
+}
+
+fn into_scalar(array: ForayArray) -> Option<PortData> {
+    match array {
+        ForayArray::Integer(a) if a.len() == 1 => a.first().map(|v| PortData::Integer(*v)),
+        ForayArray::Float(a) if a.len() == 1 => a.first().map(|v| PortData::Float(*v)),
+        ForayArray::Complex(a) if a.len() == 1 => {
+            a.first().map(|v| PortData::Complex((v.re, v.im)))
+        }
+        ForayArray::Boolean(a) if a.len() == 1 => a.first().map(|v| PortData::Boolean(*v)),
+        _ => None,
+    }
+}
+
+/// Load the node declared by a `*.command.toml` spec
+pub fn load_command(path: &Path) -> Result<CommandNodeTemplate, CommandError> {
+    let source = fs::read_to_string(path)
+        .map_err(|e| CommandError::Spec(format!("could not read {path:?}: {e}")))?;
+    let name = path
+        .file_name()
+        .map(|name| name.to_string_lossy())
+        .unwrap_or_default();
+    let name = name.strip_suffix(COMMAND_EXTENSION).unwrap_or(&name);
+    Ok(CommandNodeTemplate {
+        path: path.to_path_buf(),
+        spec: CommandSpec::from_toml(&source, name)?,
+    })
+}
+
+/// Find and load command specs in `dirs` and their subdirectories
+pub fn find_commands(
+    dirs: &[PathBuf],
+) -> Vec<(PathBuf, Result<CommandNodeTemplate, CommandError>)> {
+    fn specs(dir: &Path) -> Vec<PathBuf> {
+        let Ok(entries) = fs::read_dir(dir) else {
+            return vec![];
+        };
+        entries
+            .flatten()
+            .map(|entry| entry.path())
+            .flat_map(|path| match path.is_dir() {
+                true => specs(&path),
+                false => match is_command_file(&path) {
+                    true => vec![path],
+                    false => vec![],
+                },
+            })
+            .collect()
+    }
+
+    dirs.iter()
+        .flat_map(|dir| specs(dir))
+        .map(|path| {
+            let node = load_command(&path);
+            if let Err(e) = &node {
+                warn!("{path:?}: {e}");
+            }
+            (path, node)
+        })
+        .collect()
+}
+
+pub fn is_command_file(path: &Path) -> bool {
+    path.file_name()
+        .is_some_and(|name| name.to_string_lossy().ends_with(COMMAND_EXTENSION))
+}
+
+#[cfg(all(test, unix))]
+mod test {
+    use std::sync::{Arc, RwLock};
+
+    use ndarray::array;
+
+    use super::*;
+
+    fn template(spec: &str) -> CommandNodeTemplate {
+        CommandNodeTemplate {
+            path: PathBuf::from("test.command.toml"),
+            spec: CommandSpec::from_toml(spec, "test").unwrap(),
+        }
+    }
+
+    #[test]
+    fn round_trip_through_files() {
+        let node = template(
+            r#"
+command = ["cp", "{a}", "{out}"]
+inputs.a = { type = "float", ndim = 2 }
+outputs.out = { type = "complex", ndim = 2 }
+"#,
+        );
+        let a = array![[1.0, 2.0], [3.0, 4.0]].into_dyn();
+        let inputs = [(
+            "a".to_string(),
+            Arc::new(RwLock::new(PortData::Array(ForayArray::Float(a.clone())))),
+        )]
+        .into();
+        let outputs = node.compute(inputs, &Dict::new()).unwrap();
+        assert_eq!(
+            outputs["out"],
+            PortData::Array(ForayArray::Complex(
+                complex_array(&ForayArray::Float(a)).unwrap()
+            ))
+        );
+    }
+
+    #[test]
+    fn exit_code_and_output() {
+        let node = template(
+            r#"
+command = ["sh", "-c", "echo {greeting}; echo oops >&2; exit {code}"]
+outputs.log = { type = "string", format = "stdout" }
+parameters.greeting = { widget = "text_input", default = "hello" }
+parameters.code = { widget = "number_field", default = 0 }
+"#,
+        );
+        let outputs = node.compute(Dict::new(), &Dict::new()).unwrap();
+        assert_eq!(outputs["log"], PortData::String("hello\n".to_string()));
+
+        let parameters = [("code".to_string(), PortData::Float(3.0))].into();
+        match node.compute(Dict::new(), &parameters) {
+            Err(e @ CommandError::Exit { code: Some(3), .. }) => {
+                assert_eq!(e.to_string(), "sh exited with code 3: oops")
+            }
+            result => panic!("unexpected result {result:?}"),
+        }
+    }
+}
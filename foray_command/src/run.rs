@@ -0,0 +1,63 @@
+use std::{
+    io::Read,
+    process::{Command, ExitStatus, Stdio},
+    thread::{self, JoinHandle},
+    time::Duration,
+};
+
+use foray_data_model::context::is_cancelled;
+use log::info;
+
+use crate::err::CommandError;
+
+/// How often a running command is checked for cancellation
+const POLL_INTERVAL: Duration = Duration::from_millis(50);
+
+pub(crate) struct Output {
+    pub status: ExitStatus,
+    pub stdout: String,
+    pub stderr: String,
+}
+
+/// Run a command to completion, capturing what it prints.
+/// The command is killed if the node computing on this thread is cancelled
+pub(crate) fn run(mut command: Command) -> Result<Output, CommandError> {
+    let program = command.get_program().to_string_lossy().into_owned();
+    let mut child = command
+        .stdin(Stdio::null())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .map_err(|e| CommandError::Spawn(format!("{program}: {e}")))?;
+    // Pipes are drained as the command runs, so that it doesn't block once they are full
+    let stdout = read_pipe(child.stdout.take());
+    let stderr = read_pipe(child.stderr.take());
+
+    let status = loop {
+        if let Some(status) = child.try_wait()? {
+            break status;
+        }
+        if is_cancelled() {
+            info!("Killing {program}, its node was cancelled");
+            let _ = child.kill();
+            let _ = child.wait();
+            return Err(CommandError::Cancelled);
+        }
+        thread::sleep(POLL_INTERVAL);
+    };
+    Ok(Output {
+        status,
+        stdout: stdout.join().unwrap_or_default(),
+        stderr: stderr.join().unwrap_or_default(),
+    })
+}
+
+fn read_pipe(pipe: Option<impl Read + Send + 'static>) -> JoinHandle<String> {
+    thread::spawn(move || {
+        let mut bytes = vec![];
+        if let Some(mut pipe) = pipe {
+            let _ = pipe.read_to_end(&mut bytes);
+        }
+        String::from_utf8_lossy(&bytes).into_owned()
+    })
+}
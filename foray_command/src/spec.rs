@@ -0,0 +1,438 @@
+use std::{
+    mem,
+    path::{Path, PathBuf},
+};
+
+use foray_data_model::node::{Dict, ForayArray, PortData, PortType, UIParameter};
+use foray_formats::{cfl, nifti, npy};
+use serde::{Deserialize, Serialize};
+
+use crate::err::CommandError;
+
+/// Extension of command spec files, after the node's name
+pub const COMMAND_EXTENSION: &str = ".command.toml";
+
+/// Declaration of a node that runs a command-line tool, read from a `*.command.toml` file
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq, PartialOrd)]
+#[serde(deny_unknown_fields)]
+pub struct CommandSpec {
+    /// Defaults to the name of the spec file
+    #[serde(default)]
+    pub name: String,
+    #[serde(default)]
+    pub description: Option<String>,
+    /// Program followed by its arguments.
+    /// `{name}` is replaced by the file of a port, or the value of a parameter
+    pub command: Vec<String>,
+    #[serde(default)]
+    pub inputs: Dict<String, PortSpec>,
+    #[serde(default)]
+    pub outputs: Dict<String, PortSpec>,
+    #[serde(default)]
+    pub parameters: Dict<String, ParameterSpec>,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq, PartialOrd)]
+#[serde(deny_unknown_fields)]
+pub struct PortSpec {
+    #[serde(rename = "type")]
+    pub element_type: ElementType,
+    /// Number of dimensions, any number if it isn't given
+    #[serde(default)]
+    pub ndim: Option<usize>,
+    #[serde(default)]
+    pub format: FileFormat,
+}
+
+#[derive(Clone, Copy, Debug, Serialize, Deserialize, PartialEq, Eq, PartialOrd)]
+#[serde(rename_all = "lowercase")]
+pub enum ElementType {
+    Integer,
+    Float,
+    Complex,
+    Boolean,
+    String,
+}
+
+/// How a port's data is passed to and from the command
+#[derive(Clone, Copy, Debug, Default, Serialize, Deserialize, PartialEq, Eq, PartialOrd)]
+#[serde(rename_all = "lowercase")]
+pub enum FileFormat {
+    #[default]
+    Npy,
+    /// BART's `.cfl`/`.hdr` pair, passed to the command without an extension
+    Cfl,
+    Nifti,
+    /// Output only, the text the command printed
+    Stdout,
+    /// Output only, the text the command printed to stderr
+    Stderr,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq, PartialOrd)]
+#[serde(tag = "widget", rename_all = "snake_case")]
+pub enum ParameterSpec {
+    NumberField {
+        default: f64,
+    },
+    Slider {
+        start: f64,
+        stop: f64,
+        default: f64,
+    },
+    /// Replaced by `flag` when checked.
+    /// Arguments left empty by their placeholders are not passed to the command
+    CheckBox {
+        #[serde(default)]
+        default: bool,
+        flag: String,
+    },
+    TextInput {
+        #[serde(default)]
+        default: String,
+    },
+    FilePicker,
+}
+
+/// Part of a command argument
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Segment<'a> {
+    Text(String),
+    Placeholder(&'a str),
+}
+
+/// Split an argument into text and `{name}` placeholders, `{{` and `}}` are literal braces
+pub fn parse_argument(argument: &str) -> Result<Vec<Segment<'_>>, CommandError> {
+    let invalid = |e: &str| Err(CommandError::Spec(format!("{e} in argument '{argument}'")));
+    let mut segments = vec![];
+    let mut text = String::new();
+    let mut chars = argument.char_indices().peekable();
+    while let Some((i, c)) = chars.next() {
+        match c {
+            '{' if chars.next_if(|(_, c)| *c == '{').is_some() => text.push('{'),
+            '}' if chars.next_if(|(_, c)| *c == '}').is_some() => text.push('}'),
+            '{' => {
+                let Some(len) = argument[i + 1..].find('}') else {
+                    return invalid("unclosed '{'");
+                };
+                let name = &argument[i + 1..i + 1 + len];
+                if !is_identifier(name) {
+                    return invalid(&format!("invalid placeholder '{{{name}}}'"));
+                }
+                if !text.is_empty() {
+                    segments.push(Segment::Text(mem::take(&mut text)));
+                }
+                segments.push(Segment::Placeholder(name));
+                while chars.next_if(|(j, _)| *j <= i + 1 + len).is_some() {}
+            }
+            '}' => return invalid("unmatched '}'"),
+            c => text.push(c),
+        }
+    }
+    if !text.is_empty() {
+        segments.push(Segment::Text(text));
+    }
+    Ok(segments)
+}
+
+/// Names are used as file names and placeholders
+fn is_identifier(name: &str) -> bool {
+    !name.is_empty() && name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_')
+}
+
+impl CommandSpec {
+    /// Parse and validate a spec, `name` is used if the spec doesn't name the node
+    pub fn from_toml(source: &str, name: &str) -> Result<Self, CommandError> {
+        let mut spec: CommandSpec =
+            toml::from_str(source).map_err(|e| CommandError::Spec(e.to_string()))?;
+        if spec.name.is_empty() {
+            spec.name = name.to_string();
+        }
+        spec.validate()?;
+        Ok(spec)
+    }
+
+    fn validate(&self) -> Result<(), CommandError> {
+        let invalid = |e: String| Err(CommandError::Spec(e));
+        if self.command.is_empty() {
+            return invalid("command is empty".to_string());
+        }
+
+        let names: Vec<&String> = self
+            .inputs
+            .keys()
+            .chain(self.outputs.keys())
+            .chain(self.parameters.keys())
+            .collect();
+        for name in &names {
+            if !is_identifier(name) {
+                return invalid(format!(
+                    "'{name}' should only contain letters, digits and underscores"
+                ));
+            }
+            if names.iter().filter(|n| n == &name).count() > 1 {
+                return invalid(format!("'{name}' is declared more than once"));
+            }
+        }
+
+        for (name, port) in &self.inputs {
+            if !port.format.is_file() {
+                return invalid(format!("input {name} can't be passed as {:?}", port.format));
+            }
+        }
+        for (name, port) in self.inputs.iter().chain(&self.outputs) {
+            match (port.format.is_file(), port.element_type) {
+                (true, ElementType::String) => {
+                    return invalid(format!("{name}: strings can't be passed in files"));
+                }
+                (false, element_type) if element_type != ElementType::String => {
+                    return invalid(format!("{name}: {:?} is read as a string", port.format));
+                }
+                _ => {}
+            }
+        }
+        for (name, port) in &self.outputs {
+            if port.format == FileFormat::Cfl && port.element_type != ElementType::Complex {
+                return invalid(format!("output {name}: cfl files hold complex numbers"));
+            }
+        }
+
+        let mut placeholders = vec![];
+        for argument in &self.command {
+            for segment in parse_argument(argument)? {
+                if let Segment::Placeholder(name) = segment {
+                    placeholders.push(name);
+                }
+            }
+        }
+        for name in &placeholders {
+            let is_file = |port: &PortSpec| port.format.is_file();
+            if !self.inputs.contains_key(*name)
+                && !self.outputs.get(*name).is_some_and(is_file)
+                && !self.parameters.contains_key(*name)
+            {
+                return invalid(format!("{{{name}}} is not a port file or parameter"));
+            }
+        }
+        for (name, port) in self.inputs.iter().chain(&self.outputs) {
+            if port.format.is_file() && !placeholders.contains(&name.as_str()) {
+                return invalid(format!("the command does not use {{{name}}}"));
+            }
+        }
+        Ok(())
+    }
+
+    /// The command with its placeholders replaced by `values`.
+    /// Arguments that only contain placeholders which are empty are left out
+    pub fn arguments(&self, values: &Dict<String, String>) -> Result<Vec<String>, CommandError> {
+        let mut arguments = vec![];
+        for argument in &self.command {
+            let mut expanded = String::new();
+            let mut has_text = false;
+            for segment in parse_argument(argument)? {
+                match segment {
+                    Segment::Text(text) => {
+                        has_text = true;
+                        expanded.push_str(&text);
+                    }
+                    Segment::Placeholder(name) => {
+                        expanded.push_str(values.get(name).ok_or_else(|| {
+                            CommandError::Spec(format!("no value for {{{name}}}"))
+                        })?);
+                    }
+                }
+            }
+            if has_text || !expanded.is_empty() {
+                arguments.push(expanded);
+            }
+        }
+        Ok(arguments)
+    }
+}
+
+impl PortSpec {
+    pub fn port_type(&self) -> PortType {
+        let element = match self.element_type {
+            ElementType::Integer => PortType::Integer,
+            ElementType::Float => PortType::Float,
+            ElementType::Complex => PortType::Complex,
+            ElementType::Boolean => PortType::Boolean,
+            ElementType::String => PortType::String,
+        };
+        match (self.format.is_file(), self.ndim) {
+            (false, _) | (true, Some(0)) => element,
+            (true, Some(ndim)) => PortType::Array(Box::new(element), vec![None; ndim]),
+            (true, None) => PortType::Array(Box::new(element), vec![]),
+        }
+    }
+}
+
+impl FileFormat {
+    pub fn is_file(self) -> bool {
+        !matches!(self, FileFormat::Stdout | FileFormat::Stderr)
+    }
+
+    /// File a port is passed in, in `dir`. This is also the command's argument
+    pub fn path(self, dir: &Path, port: &str) -> Option<PathBuf> {
+        match self {
+            FileFormat::Npy => Some(dir.join(format!("{port}.npy"))),
+            FileFormat::Cfl => Some(dir.join(port)),
+            FileFormat::Nifti => Some(dir.join(format!("{port}.nii"))),
+            FileFormat::Stdout | FileFormat::Stderr => None,
+        }
+    }
+
+    pub(crate) fn save(self, path: &Path, array: &ForayArray) -> Result<(), CommandError> {
+        match self {
+            FileFormat::Npy => npy::save(path, array)?,
+            FileFormat::Cfl => cfl::save(path, array)?,
+            // The header's shape and datatype are taken from the array
+            FileFormat::Nifti => nifti::save(
+                path,
+                &nifti::Header::new(&[], nifti::datatype::UNKNOWN),
+                array,
+            )?,
+            FileFormat::Stdout | FileFormat::Stderr => {}
+        }
+        Ok(())
+    }
+
+    pub(crate) fn load(self, path: &Path) -> Result<ForayArray, CommandError> {
+        Ok(match self {
+            FileFormat::Npy => npy::load(path)?,
+            FileFormat::Cfl => cfl::load(path)?,
+            FileFormat::Nifti => nifti::load(path)?.1,
+            FileFormat::Stdout | FileFormat::Stderr => {
+                unreachable!("only files are loaded")
+            }
+        })
+    }
+}
+
+impl ParameterSpec {
+    pub fn ui_parameter(&self) -> UIParameter {
+        match self {
+            ParameterSpec::NumberField { default } => UIParameter::NumberField(*default),
+            ParameterSpec::Slider {
+                start,
+                stop,
+                default,
+            } => UIParameter::Slider(*start, *stop, *default),
+            ParameterSpec::CheckBox { default, .. } => UIParameter::CheckBox(*default),
+            ParameterSpec::TextInput { default } => UIParameter::TextInput(default.clone()),
+            ParameterSpec::FilePicker => UIParameter::FilePicker(String::new()),
+        }
+    }
+
+    /// Text a parameter's placeholders are replaced with
+    pub fn argument(&self, value: &PortData) -> String {
+        match (self, value) {
+            (ParameterSpec::CheckBox { flag, .. }, PortData::Boolean(true)) => flag.clone(),
+            (ParameterSpec::CheckBox { .. }, _) => String::new(),
+            // Whole numbers are written without a decimal point
+            (_, PortData::Float(v)) => v.to_string(),
+            (_, PortData::Integer(v)) => v.to_string(),
+            (_, PortData::Boolean(v)) => v.to_string(),
+            (_, PortData::String(v)) => v.clone(),
+            _ => String::new(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    const SPEC: &str = r#"
+command = ["bart", "fft", "{unitary}", "{flags}", "{input}", "{output}"]
+
+[inputs.input]
+type = "complex"
+format = "cfl"
+
+[outputs.output]
+type = "complex"
+format = "cfl"
+
+[outputs.log]
+type = "string"
+format = "stdout"
+
+[parameters.flags]
+widget = "number_field"
+default = 3
+
+[parameters.unitary]
+widget = "check_box"
+flag = "-u"
+"#;
+
+    #[test]
+    fn parse_spec() {
+        let spec = CommandSpec::from_toml(SPEC, "fft").unwrap();
+        assert_eq!(spec.name, "fft");
+        assert_eq!(spec.inputs["input"].format, FileFormat::Cfl);
+        assert_eq!(spec.outputs["log"].port_type(), PortType::String);
+        assert_eq!(
+            spec.parameters["unitary"].ui_parameter(),
+            UIParameter::CheckBox(false)
+        );
+
+        let example = include_str!("../examples/fft.command.toml");
+        let spec = CommandSpec::from_toml(example, "fft").unwrap();
+        assert_eq!(spec.name, "bart fft");
+    }
+
+    #[test]
+    fn invalid_specs() {
+        let unused = SPEC.replace("\"{output}\"", "\"out\"");
+        assert!(CommandSpec::from_toml(&unused, "fft").is_err());
+        let unknown = SPEC.replace("{flags}", "{axes}");
+        assert!(CommandSpec::from_toml(&unknown, "fft").is_err());
+        let real_cfl = SPEC.replace(
+            "type = \"complex\"\nformat = \"cfl\"\n\n[outputs.log]",
+            "type = \"float\"\nformat = \"cfl\"\n\n[outputs.log]",
+        );
+        assert!(CommandSpec::from_toml(&real_cfl, "fft").is_err());
+        let stdout_input = SPEC.replace("format = \"cfl\"", "format = \"stdout\"");
+        assert!(CommandSpec::from_toml(&stdout_input, "fft").is_err());
+    }
+
+    #[test]
+    fn templating() {
+        assert_eq!(
+            parse_argument("--size={n}x{{}}").unwrap(),
+            vec![
+                Segment::Text("--size=".to_string()),
+                Segment::Placeholder("n"),
+                Segment::Text("x{}".to_string()),
+            ]
+        );
+        assert!(parse_argument("{n").is_err());
+        assert!(parse_argument("n}").is_err());
+        assert!(parse_argument("{a b}").is_err());
+
+        let spec = CommandSpec::from_toml(SPEC, "fft").unwrap();
+        let mut values: Dict<String, String> = [
+            ("input", "/tmp/input"),
+            ("output", "/tmp/output"),
+            ("unitary", ""),
+        ]
+        .into_iter()
+        .map(|(k, v)| (k.to_string(), v.to_string()))
+        .collect();
+        values.insert(
+            "flags".to_string(),
+            spec.parameters["flags"].argument(&PortData::Float(3.0)),
+        );
+        assert_eq!(
+            spec.arguments(&values).unwrap(),
+            vec!["bart", "fft", "3", "/tmp/input", "/tmp/output"]
+        );
+        values.insert(
+            "unitary".to_string(),
+            spec.parameters["unitary"].argument(&PortData::Boolean(true)),
+        );
+        assert_eq!(spec.arguments(&values).unwrap()[2], "-u");
+    }
+}
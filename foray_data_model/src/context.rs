@@ -0,0 +1,127 @@
+use std::{
+    cell::RefCell,
+    sync::{
+        Arc,
+        atomic::{AtomicBool, Ordering},
+    },
+    time::Duration,
+};
+
+/// Progress update sent by a node while it is computing
+#[derive(Clone, Debug, PartialEq, PartialOrd)]
+pub struct Progress {
+    /// Completed portion of work, from 0.0 to 1.0
+    pub fraction: f32,
+    pub message: String,
+}
+
+impl Progress {
+    pub fn new(fraction: f32, message: impl Into<String>) -> Self {
+        Self {
+            fraction: if fraction.is_nan() {
+                0.0
+            } else {
+                fraction.clamp(0.0, 1.0)
+            },
+            message: message.into(),
+        }
+    }
+
+    /// Estimate the remaining time, assuming work has progressed linearly since the node started
+    pub fn eta(&self, elapsed: Duration) -> Option<Duration> {
+        if self.fraction <= 0.0 {
+            return None;
+        }
+        Some(elapsed.mul_f32((1.0 - self.fraction) / self.fraction))
+    }
+
+    /// Single line summary, e.g. "42% ~3s left: reconstructing slice 4"
+    pub fn summary(&self, elapsed: Duration) -> String {
+        let percent = format!("{:.0}%", self.fraction * 100.0);
+        let eta = self
+            .eta(elapsed)
+            .map(|eta| format!(" ~{} left", format_duration(eta)))
+            .unwrap_or_default();
+        if self.message.is_empty() {
+            format!("{percent}{eta}")
+        } else {
+            format!("{percent}{eta}: {}", self.message)
+        }
+    }
+}
+
+fn format_duration(duration: Duration) -> String {
+    let seconds = duration.as_secs();
+    match seconds {
+        0..60 => format!("{seconds}s"),
+        60..3600 => format!("{}m {}s", seconds / 60, seconds % 60),
+        _ => format!("{}h {}m", seconds / 3600, (seconds % 3600) / 60),
+    }
+}
+
+pub type ProgressReporter = Arc<dyn Fn(Progress) + Send + Sync>;
+
+/// Shared flag asking a running node to stop early.
+/// Nodes that can be interrupted, e.g. ones waiting on another process, check it while they run
+#[derive(Clone, Debug, Default)]
+pub struct Cancellation(Arc<AtomicBool>);
+
+impl Cancellation {
+    pub fn cancel(&self) {
+        self.0.store(true, Ordering::Relaxed)
+    }
+    pub fn is_cancelled(&self) -> bool {
+        self.0.load(Ordering::Relaxed)
+    }
+}
+
+/// Runtime information made available to a node while its compute function is running.
+/// The context is bound to the thread running the compute function, see [`ComputeContext::scope`]
+#[derive(Clone, Default)]
+pub struct ComputeContext {
+    /// Receives progress updates sent by the node
+    pub progress: Option<ProgressReporter>,
+    /// Set when the node's result is no longer wanted
+    pub cancellation: Option<Cancellation>,
+}
+
+thread_local! {
+    static CURRENT_CONTEXT: RefCell<Option<ComputeContext>> = const { RefCell::new(None) };
+}
+
+impl ComputeContext {
+    /// Run `f` with `self` as the current thread's compute context.
+    /// The previous context is restored afterwards, even if `f` panics
+    pub fn scope<R>(self, f: impl FnOnce() -> R) -> R {
+        struct Restore(Option<ComputeContext>);
+        impl Drop for Restore {
+            fn drop(&mut self) {
+                let previous = self.0.take();
+                CURRENT_CONTEXT.with(|c| *c.borrow_mut() = previous);
+            }
+        }
+
+        let _restore = Restore(CURRENT_CONTEXT.with(|c| c.replace(Some(self))));
+        f()
+    }
+
+    /// The compute context of the current thread, if a node is computing on it
+    pub fn current() -> Option<ComputeContext> {
+        CURRENT_CONTEXT.with(|c| c.borrow().clone())
+    }
+}
+
+/// Report progress for the node computing on the current thread.
+/// Does nothing if no one is listening
+pub fn report_progress(progress: Progress) {
+    if let Some(reporter) = ComputeContext::current().and_then(|c| c.progress) {
+        reporter(progress)
+    }
+}
+
+/// Whether the node computing on the current thread has been asked to stop
+pub fn is_cancelled() -> bool {
+    ComputeContext::current()
+        .and_then(|c| c.cancellation)
+        .is_some_and(|c| c.is_cancelled())
+}
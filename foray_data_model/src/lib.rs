@@ -1,5 +1,6 @@
 use std::sync::{Arc, RwLock, RwLockReadGuard};
 
+pub mod context;
 pub mod node;
 pub type WireDataContainer<T> = Arc<RwLock<T>>;
 pub type WireDataReference<'a, T> = RwLockReadGuard<'a, T>;
@@ -15,6 +15,14 @@ pub enum UIParameter {
     Slider(f64, f64, f64),
     TextDisplay(String),
     FilePicker(String),
+    /// Editable text, with its default value
+    TextInput(String),
+    /// Multi-line source code, with its default value
+    CodeEditor(String),
+    /// Path of a directory, chosen like a file
+    DirectoryPicker(String),
+    /// One of a list of options, with the default option
+    Dropdown(Vec<String>, String),
 }
 impl UIParameter {
     pub fn default_value(&self) -> PortData {
@@ -24,6 +32,10 @@ impl UIParameter {
             UIParameter::Slider(_, _, v) => PortData::Float(*v),
             UIParameter::TextDisplay(v) => PortData::String(v.clone()),
             UIParameter::FilePicker(v) => PortData::String(v.clone()),
+            UIParameter::TextInput(v) => PortData::String(v.clone()),
+            UIParameter::CodeEditor(v) => PortData::String(v.clone()),
+            UIParameter::DirectoryPicker(v) => PortData::String(v.clone()),
+            UIParameter::Dropdown(_, v) => PortData::String(v.clone()),
         }
     }
 }
@@ -91,6 +103,44 @@ impl<'py> FromPyObject<'py> for UIParameter {
                     };
                     UIParameter::FilePicker(path)
                 }
+                "TextInput" => match o.get("default") {
+                    Some(o) => match o.extract::<String>() {
+                        Ok(v) => UIParameter::TextInput(v),
+                        Err(_) => Err(PyTypeError::new_err("expected a string value"))?,
+                    },
+                    None => Err(PyTypeError::new_err("expected a 'default' key"))?,
+                },
+                "CodeEditor" => match o.get("default") {
+                    Some(o) => match o.extract::<String>() {
+                        Ok(v) => UIParameter::CodeEditor(v),
+                        Err(_) => Err(PyTypeError::new_err("expected a string value"))?,
+                    },
+                    None => Err(PyTypeError::new_err("expected a 'default' key"))?,
+                },
+                "DirectoryPicker" => match o.get("path") {
+                    Some(o) => match o.extract::<String>() {
+                        Ok(v) => UIParameter::DirectoryPicker(v),
+                        Err(_) => Err(PyTypeError::new_err("expected a string value"))?,
+                    },
+                    None => Err(PyTypeError::new_err("expected a 'path' key"))?,
+                },
+                "Dropdown" => {
+                    let options = match o.get("options") {
+                        Some(o) => match o.extract::<Vec<String>>() {
+                            Ok(v) => v,
+                            Err(_) => Err(PyTypeError::new_err("expected a list of strings"))?,
+                        },
+                        None => Err(PyTypeError::new_err("expected an 'options' key"))?,
+                    };
+                    let default = match o.get("default") {
+                        Some(o) => match o.extract::<String>() {
+                            Ok(v) => v,
+                            Err(_) => Err(PyTypeError::new_err("expected a string value"))?,
+                        },
+                        None => Err(PyTypeError::new_err("expected a 'default' key"))?,
+                    };
+                    UIParameter::Dropdown(options, default)
+                }
                 _ => Err(PyTypeError::new_err(format!("Unsupported data type: {s}")))?,
             }),
             Err(_) => Err(PyTypeError::new_err("Unsupported format for parameter"))?,
@@ -105,6 +155,8 @@ pub enum PortType {
     Complex,
     Boolean,
     String,
+    /// Any type of data, for nodes that work the same regardless of element type
+    Any,
     Array(Box<PortType>, Shape),
     Object(Dict<String, PortType>),
 }
@@ -118,6 +170,7 @@ impl<'py> FromPyObject<'py> for PortType {
                 "Complex" => PortType::Complex,
                 "Boolean" => PortType::Boolean,
                 "String" => PortType::String,
+                "Any" => PortType::Any,
                 _ => Err(PyTypeError::new_err(format!("Unsupported data type: {s}")))?,
             }),
             Err(_) => match ob.extract::<(PortType, Shape)>() {
@@ -275,6 +328,15 @@ impl PortData {
 #[derive(Clone, Serialize, Deserialize, PartialEq, Eq, Display, Debug, PartialOrd)]
 pub enum NodeError {
     Input(String),
+    /// A node failed while computing, and can be computed again once its inputs change
+    Compute(String),
+    /// Text typed into a node couldn't be parsed, `column` is the character where parsing failed
+    #[display("{message}, at column {}", column + 1)]
+    Parse {
+        message: String,
+        source: String,
+        column: usize,
+    },
     Err,
 }
 
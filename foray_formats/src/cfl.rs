@@ -0,0 +1,129 @@
+//! BART's format, a pair of files sharing a base name: `.hdr` lists the dimensions of the array
+//! as text, and `.cfl` holds its elements as complex float32, with the first axis varying fastest.
+//!
+//! Tools refer to the pair by the base name, without an extension
+
+use std::{
+    fs::{self, File},
+    io::{BufReader, BufWriter, Read, Write},
+    path::{Path, PathBuf},
+};
+
+use foray_data_model::node::ForayArray;
+use ndarray::ArrayD;
+use num_complex::Complex64;
+
+use crate::{
+    complex, complex_array, elements, err::FormatError, fortran_order_iter, from_memory_order,
+};
+
+fn with_extension(base: &Path, extension: &str) -> PathBuf {
+    let mut path = base.as_os_str().to_owned();
+    path.push(".");
+    path.push(extension);
+    path.into()
+}
+
+/// Parse the dimensions listed in a `.hdr` file.
+/// BART pads dimensions to 16 with ones, trailing ones are removed
+pub fn parse_header(header: &str) -> Result<Vec<usize>, FormatError> {
+    let dims = header
+        .lines()
+        .map(str::trim)
+        .find(|line| !line.is_empty() && !line.starts_with('#'))
+        .ok_or_else(|| FormatError::Invalid("header has no dimensions".to_string()))?;
+    let mut dims = dims
+        .split_whitespace()
+        .map(|dim| {
+            dim.parse()
+                .map_err(|_| FormatError::Invalid(format!("invalid dimension '{dim}'")))
+        })
+        .collect::<Result<Vec<usize>, _>>()?;
+    while dims.len() > 1 && dims.last() == Some(&1) {
+        dims.pop();
+    }
+    Ok(dims)
+}
+
+pub fn write_header(writer: &mut impl Write, shape: &[usize]) -> Result<(), FormatError> {
+    let dims = match shape {
+        [] => "1".to_string(),
+        shape => shape
+            .iter()
+            .map(|l| l.to_string())
+            .collect::<Vec<_>>()
+            .join(" "),
+    };
+    writeln!(writer, "# Dimensions\n{dims}")?;
+    Ok(())
+}
+
+pub fn read_data(
+    reader: &mut impl Read,
+    shape: &[usize],
+) -> Result<ArrayD<Complex64>, FormatError> {
+    let mut bytes = vec![0; shape.iter().product::<usize>() * 8];
+    reader.read_exact(&mut bytes)?;
+    let data = complex(elements(&bytes, false, |b| f32::from_le_bytes(b) as f64));
+    from_memory_order(shape, data, true)
+}
+
+pub fn write_data(writer: &mut impl Write, array: &ArrayD<Complex64>) -> Result<(), FormatError> {
+    fortran_order_iter(array).try_for_each(|v| {
+        writer.write_all(&(v.re as f32).to_le_bytes())?;
+        writer.write_all(&(v.im as f32).to_le_bytes())
+    })?;
+    Ok(())
+}
+
+/// Load the array stored in `base.hdr` and `base.cfl`
+pub fn load(base: &Path) -> Result<ForayArray, FormatError> {
+    let shape = parse_header(&fs::read_to_string(with_extension(base, "hdr"))?)?;
+    let mut reader = BufReader::new(File::open(with_extension(base, "cfl"))?);
+    Ok(ForayArray::Complex(read_data(&mut reader, &shape)?))
+}
+
+/// Save an array to `base.hdr` and `base.cfl`, real arrays are saved as complex
+pub fn save(base: &Path, array: &ForayArray) -> Result<(), FormatError> {
+    let array = complex_array(array)?;
+    let mut header = File::create(with_extension(base, "hdr"))?;
+    write_header(&mut header, array.shape())?;
+    let mut writer = BufWriter::new(File::create(with_extension(base, "cfl"))?);
+    write_data(&mut writer, &array)?;
+    writer.flush()?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use ndarray::array;
+
+    use super::*;
+
+    #[test]
+    fn round_trip() {
+        let a = array![
+            [Complex64::new(1.0, 2.0), Complex64::new(3.0, 4.0)],
+            [Complex64::new(5.0, 6.0), Complex64::new(7.0, 8.0)],
+            [Complex64::new(9.0, 0.0), Complex64::new(0.0, -1.0)],
+        ]
+        .into_dyn();
+        let mut header = vec![];
+        write_header(&mut header, a.shape()).unwrap();
+        let mut data = vec![];
+        write_data(&mut data, &a).unwrap();
+
+        // The first axis varies fastest
+        assert_eq!(&data[8..12], &5.0f32.to_le_bytes());
+
+        let shape = parse_header(&String::from_utf8(header).unwrap()).unwrap();
+        assert_eq!(read_data(&mut data.as_slice(), &shape).unwrap(), a);
+    }
+
+    #[test]
+    fn bart_header() {
+        let header = "# Dimensions\n128 64 1 8 1 1 1 1 1 1 1 1 1 1 1 1 \n";
+        assert_eq!(parse_header(header).unwrap(), vec![128, 64, 1, 8]);
+        assert_eq!(parse_header("# Dimensions\n1 1 1\n").unwrap(), vec![1]);
+    }
+}
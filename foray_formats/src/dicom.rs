@@ -0,0 +1,843 @@
+//! DICOM images, as exported by scanners: a directory of files, usually one per slice, which
+//! belong to a series by their `SeriesInstanceUID`. Each file is a header of tagged elements,
+//! followed by the pixels of one or more frames.
+//!
+//! Only uncompressed (and deflated) transfer syntaxes of single channel images are read.
+//! See <https://dicom.nema.org/medical/dicom/current/output/html/part10.html> and
+//! <https://dicom.nema.org/medical/dicom/current/output/html/part05.html>
+
+use std::{
+    collections::BTreeMap,
+    fs,
+    io::Read,
+    path::{Path, PathBuf},
+};
+
+use flate2::read::DeflateDecoder;
+use foray_data_model::node::ForayArray;
+use ndarray::{ArrayD, IxDyn};
+use serde::{Deserialize, Serialize};
+
+use crate::err::FormatError;
+
+/// Group and element number of a data element
+pub type Tag = (u16, u16);
+
+/// Tags of the elements needed to assemble a series, and some commonly inspected ones
+pub mod tag {
+    use super::Tag;
+
+    pub const TRANSFER_SYNTAX_UID: Tag = (0x0002, 0x0010);
+    pub const STUDY_DATE: Tag = (0x0008, 0x0020);
+    pub const MODALITY: Tag = (0x0008, 0x0060);
+    pub const SERIES_DESCRIPTION: Tag = (0x0008, 0x103E);
+    pub const SLICE_THICKNESS: Tag = (0x0018, 0x0050);
+    pub const REPETITION_TIME: Tag = (0x0018, 0x0080);
+    pub const ECHO_TIME: Tag = (0x0018, 0x0081);
+    pub const MAGNETIC_FIELD_STRENGTH: Tag = (0x0018, 0x0087);
+    pub const FLIP_ANGLE: Tag = (0x0018, 0x1314);
+    pub const STUDY_INSTANCE_UID: Tag = (0x0020, 0x000D);
+    pub const SERIES_INSTANCE_UID: Tag = (0x0020, 0x000E);
+    pub const SERIES_NUMBER: Tag = (0x0020, 0x0011);
+    pub const INSTANCE_NUMBER: Tag = (0x0020, 0x0013);
+    pub const IMAGE_POSITION_PATIENT: Tag = (0x0020, 0x0032);
+    pub const IMAGE_ORIENTATION_PATIENT: Tag = (0x0020, 0x0037);
+    pub const SAMPLES_PER_PIXEL: Tag = (0x0028, 0x0002);
+    pub const NUMBER_OF_FRAMES: Tag = (0x0028, 0x0008);
+    pub const ROWS: Tag = (0x0028, 0x0010);
+    pub const COLUMNS: Tag = (0x0028, 0x0011);
+    pub const PIXEL_SPACING: Tag = (0x0028, 0x0030);
+    pub const BITS_ALLOCATED: Tag = (0x0028, 0x0100);
+    pub const BITS_STORED: Tag = (0x0028, 0x0101);
+    pub const PIXEL_REPRESENTATION: Tag = (0x0028, 0x0103);
+    pub const RESCALE_INTERCEPT: Tag = (0x0028, 0x1052);
+    pub const RESCALE_SLOPE: Tag = (0x0028, 0x1053);
+    pub const PIXEL_DATA: Tag = (0x7FE0, 0x0010);
+}
+
+const IMPLICIT_LITTLE_ENDIAN: &str = "1.2.840.10008.1.2";
+const EXPLICIT_LITTLE_ENDIAN: &str = "1.2.840.10008.1.2.1";
+const DEFLATED_EXPLICIT_LITTLE_ENDIAN: &str = "1.2.840.10008.1.2.1.99";
+const EXPLICIT_BIG_ENDIAN: &str = "1.2.840.10008.1.2.2";
+
+const ITEM: Tag = (0xFFFE, 0xE000);
+const ITEM_DELIMITATION: Tag = (0xFFFE, 0xE00D);
+const SEQUENCE_DELIMITATION: Tag = (0xFFFE, 0xE0DD);
+const UNDEFINED_LENGTH: u32 = 0xFFFF_FFFF;
+
+/// Value representations whose length is stored in 4 bytes, after 2 reserved bytes
+const LONG_VRS: [&[u8; 2]; 13] = [
+    b"OB", b"OD", b"OF", b"OL", b"OV", b"OW", b"SQ", b"SV", b"UC", b"UN", b"UR", b"UT", b"UV",
+];
+
+/// Value representation of elements in implicit VR files, whose values are read as binary
+/// numbers. Other elements are read as text
+fn implicit_vr(tag: Tag) -> [u8; 2] {
+    use tag::*;
+    match tag {
+        SAMPLES_PER_PIXEL | ROWS | COLUMNS | BITS_ALLOCATED | BITS_STORED
+        | PIXEL_REPRESENTATION => *b"US",
+        PIXEL_DATA => *b"OW",
+        _ => *b"UN",
+    }
+}
+
+/// Value of a data element, as stored in the file
+#[derive(Clone, Debug, PartialEq)]
+pub struct Element {
+    pub vr: [u8; 2],
+    pub value: Vec<u8>,
+}
+
+/// Top level elements of a file. Sequences are skipped
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct DataSet {
+    pub elements: BTreeMap<Tag, Element>,
+    pub big_endian: bool,
+}
+
+impl DataSet {
+    /// Text of an element, without padding
+    pub fn string(&self, tag: Tag) -> Option<&str> {
+        let element = self.elements.get(&tag)?;
+        std::str::from_utf8(&element.value)
+            .ok()
+            .map(|s| s.trim_matches(|c: char| c == '\0' || c.is_whitespace()))
+            .filter(|s| !s.is_empty())
+    }
+
+    /// Values of a numeric element, either binary or the `\` separated numbers of text
+    pub fn numbers(&self, tag: Tag) -> Option<Vec<f64>> {
+        let element = self.elements.get(&tag)?;
+        let value = &element.value;
+        let big_endian = self.big_endian;
+        let numbers = match &element.vr {
+            b"US" => crate::elements(value, big_endian, u16::from_le_bytes)
+                .into_iter()
+                .map(f64::from)
+                .collect(),
+            b"SS" => crate::elements(value, big_endian, i16::from_le_bytes)
+                .into_iter()
+                .map(f64::from)
+                .collect(),
+            b"UL" => crate::elements(value, big_endian, u32::from_le_bytes)
+                .into_iter()
+                .map(f64::from)
+                .collect(),
+            b"SL" => crate::elements(value, big_endian, i32::from_le_bytes)
+                .into_iter()
+                .map(f64::from)
+                .collect(),
+            b"FL" => crate::elements(value, big_endian, f32::from_le_bytes)
+                .into_iter()
+                .map(f64::from)
+                .collect(),
+            b"FD" => crate::elements(value, big_endian, f64::from_le_bytes),
+            _ => self
+                .string(tag)?
+                .split('\\')
+                .map(|n| n.trim().parse().ok())
+                .collect::<Option<_>>()?,
+        };
+        Some(numbers)
+    }
+
+    /// First value of a numeric element
+    pub fn number(&self, tag: Tag) -> Option<f64> {
+        self.numbers(tag)?.first().copied()
+    }
+
+    fn required(&self, tag: Tag, name: &str) -> Result<usize, FormatError> {
+        self.number(tag)
+            .map(|n| n as usize)
+            .ok_or_else(|| FormatError::Invalid(format!("image has no {name}")))
+    }
+
+    /// Rows and columns of each frame, and the number of frames
+    fn frame_shape(&self) -> Result<[usize; 3], FormatError> {
+        Ok([
+            self.number(tag::NUMBER_OF_FRAMES).unwrap_or(1.0) as usize,
+            self.required(tag::ROWS, "rows")?,
+            self.required(tag::COLUMNS, "columns")?,
+        ])
+    }
+
+    /// Stored pixel values of every frame, before rescaling
+    pub fn pixels(&self) -> Result<Vec<i64>, FormatError> {
+        let samples = self.number(tag::SAMPLES_PER_PIXEL).unwrap_or(1.0);
+        if samples != 1.0 {
+            return Err(FormatError::Unsupported(format!(
+                "images with {samples} samples per pixel"
+            )));
+        }
+        let bits = self.required(tag::BITS_ALLOCATED, "bits allocated")?;
+        let stored = self
+            .number(tag::BITS_STORED)
+            .map_or(bits, |stored| stored as usize);
+        let signed = self.number(tag::PIXEL_REPRESENTATION) == Some(1.0);
+        let len = self.frame_shape()?.iter().product::<usize>();
+        let data = &self
+            .elements
+            .get(&tag::PIXEL_DATA)
+            .ok_or_else(|| FormatError::Invalid("image has no pixel data".to_string()))?
+            .value;
+        if data.len() < len * bits / 8 {
+            return Err(FormatError::Invalid(format!(
+                "pixel data has {} bytes, expected {}",
+                data.len(),
+                len * bits / 8
+            )));
+        }
+        let data = &data[..len * bits / 8];
+        let big_endian = self.big_endian;
+        let pixels: Vec<i64> = match (bits, signed) {
+            (8, false) => data.iter().map(|v| *v as i64).collect(),
+            (8, true) => data.iter().map(|v| *v as i8 as i64).collect(),
+            (16, _) => crate::elements(data, big_endian, u16::from_le_bytes)
+                .into_iter()
+                .map(i64::from)
+                .collect(),
+            (32, _) => crate::elements(data, big_endian, u32::from_le_bytes)
+                .into_iter()
+                .map(i64::from)
+                .collect(),
+            (bits, _) => {
+                return Err(FormatError::Unsupported(format!(
+                    "{bits} bits allocated per pixel"
+                )));
+            }
+        };
+        if bits == 8 {
+            return Ok(pixels);
+        }
+        // Only the low `stored` bits hold the value, signed values are two's complement
+        let unused = 64 - stored.clamp(1, bits) as u32;
+        Ok(pixels
+            .into_iter()
+            .map(|v| match signed {
+                true => (v << unused) >> unused,
+                false => ((v as u64) << unused >> unused) as i64,
+            })
+            .collect())
+    }
+
+    /// Unit vector normal to the image plane, from the row and column directions
+    fn normal(&self) -> Option<[f64; 3]> {
+        let o = self.numbers(tag::IMAGE_ORIENTATION_PATIENT)?;
+        let [r0, r1, r2, c0, c1, c2] = o[..] else {
+            return None;
+        };
+        Some([r1 * c2 - r2 * c1, r2 * c0 - r0 * c2, r0 * c1 - r1 * c0])
+    }
+
+    /// Distance of the image along `normal`
+    fn depth(&self, normal: [f64; 3]) -> Option<f64> {
+        let p = self.numbers(tag::IMAGE_POSITION_PATIENT)?;
+        let [x, y, z] = p[..] else {
+            return None;
+        };
+        Some(normal[0] * x + normal[1] * y + normal[2] * z)
+    }
+}
+
+/// Tag, VR and value of an element being read
+type RawElement<'a> = (Tag, [u8; 2], Option<&'a [u8]>);
+
+struct Reader<'a> {
+    bytes: &'a [u8],
+    pos: usize,
+    big_endian: bool,
+    explicit_vr: bool,
+}
+
+impl<'a> Reader<'a> {
+    fn take(&mut self, len: usize) -> Result<&'a [u8], FormatError> {
+        let bytes = self
+            .bytes
+            .get(self.pos..self.pos.saturating_add(len))
+            .ok_or_else(|| FormatError::Invalid("file ends inside an element".to_string()))?;
+        self.pos += len;
+        Ok(bytes)
+    }
+
+    fn u16(&mut self) -> Result<u16, FormatError> {
+        let bytes = self.take(2)?.try_into().expect("2 bytes");
+        Ok(match self.big_endian {
+            true => u16::from_be_bytes(bytes),
+            false => u16::from_le_bytes(bytes),
+        })
+    }
+
+    fn u32(&mut self) -> Result<u32, FormatError> {
+        let bytes = self.take(4)?.try_into().expect("4 bytes");
+        Ok(match self.big_endian {
+            true => u32::from_be_bytes(bytes),
+            false => u32::from_le_bytes(bytes),
+        })
+    }
+
+    fn at_end(&self) -> bool {
+        self.pos >= self.bytes.len()
+    }
+
+    fn tag(&mut self) -> Result<Tag, FormatError> {
+        Ok((self.u16()?, self.u16()?))
+    }
+
+    /// Next element, whose value is `None` if it was skipped: sequences, their items and
+    /// delimiters
+    fn element(&mut self) -> Result<RawElement<'a>, FormatError> {
+        let tag = self.tag()?;
+        // Items and delimiters have no VR, even in explicit VR files
+        if tag.0 == 0xFFFE {
+            match (tag, self.u32()?) {
+                (ITEM, UNDEFINED_LENGTH) => self.skip_until(ITEM_DELIMITATION)?,
+                (ITEM, len) => {
+                    self.take(len as usize)?;
+                }
+                _ => {}
+            }
+            return Ok((tag, *b"  ", None));
+        }
+        let (vr, len) = match self.explicit_vr {
+            true => {
+                let vr: [u8; 2] = self.take(2)?.try_into().expect("2 bytes");
+                let len = match LONG_VRS.contains(&&vr) {
+                    true => {
+                        self.take(2)?;
+                        self.u32()?
+                    }
+                    false => self.u16()? as u32,
+                };
+                (vr, len)
+            }
+            false => (implicit_vr(tag), self.u32()?),
+        };
+        match (tag, len) {
+            (tag::PIXEL_DATA, UNDEFINED_LENGTH) => Err(FormatError::Unsupported(
+                "compressed pixel data".to_string(),
+            )),
+            (_, UNDEFINED_LENGTH) => {
+                self.skip_until(SEQUENCE_DELIMITATION)?;
+                Ok((tag, *b"SQ", None))
+            }
+            (_, len) if &vr == b"SQ" => {
+                self.take(len as usize)?;
+                Ok((tag, vr, None))
+            }
+            (_, len) => Ok((tag, vr, Some(self.take(len as usize)?))),
+        }
+    }
+
+    /// Skip the items of a sequence, or the elements of an item, up to the `end` delimiter
+    fn skip_until(&mut self, end: Tag) -> Result<(), FormatError> {
+        while self.element()?.0 != end {}
+        Ok(())
+    }
+}
+
+/// Parse a DICOM file. Reading stops at the pixel data if `pixels` is false
+pub fn parse(bytes: &[u8], pixels: bool) -> Result<DataSet, FormatError> {
+    let mut meta = Reader {
+        bytes,
+        pos: 0,
+        big_endian: false,
+        explicit_vr: true,
+    };
+    let mut elements = BTreeMap::new();
+    let mut transfer_syntax = IMPLICIT_LITTLE_ENDIAN.to_string();
+    if bytes.get(128..132) == Some(&b"DICM"[..]) {
+        // File meta information, always explicit VR little endian
+        meta.pos = 132;
+        while !meta.at_end() && bytes[meta.pos..].starts_with(&[0x02, 0x00]) {
+            if let (tag, vr, Some(value)) = meta.element()? {
+                let value = value.to_vec();
+                elements.insert(tag, Element { vr, value });
+            }
+        }
+        let data_set = DataSet {
+            elements,
+            big_endian: false,
+        };
+        if let Some(syntax) = data_set.string(tag::TRANSFER_SYNTAX_UID) {
+            transfer_syntax = syntax.to_string();
+        }
+        elements = data_set.elements;
+    } else if bytes.get(..2) != Some(&[0x08u8, 0x00][..]) {
+        // Files without a preamble start with the identifying group
+        return Err(FormatError::Invalid("not a DICOM file".to_string()));
+    }
+
+    let inflated;
+    let (body, big_endian, explicit_vr) = match transfer_syntax.as_str() {
+        IMPLICIT_LITTLE_ENDIAN => (&bytes[meta.pos..], false, false),
+        EXPLICIT_LITTLE_ENDIAN => (&bytes[meta.pos..], false, true),
+        EXPLICIT_BIG_ENDIAN => (&bytes[meta.pos..], true, true),
+        DEFLATED_EXPLICIT_LITTLE_ENDIAN => {
+            let mut buffer = vec![];
+            DeflateDecoder::new(&bytes[meta.pos..]).read_to_end(&mut buffer)?;
+            inflated = buffer;
+            (&inflated[..], false, true)
+        }
+        syntax => {
+            return Err(FormatError::Unsupported(format!(
+                "compressed transfer syntax {syntax}"
+            )));
+        }
+    };
+
+    let mut reader = Reader {
+        bytes: body,
+        pos: 0,
+        big_endian,
+        explicit_vr,
+    };
+    while !reader.at_end() {
+        let start = reader.pos;
+        if !pixels && reader.tag()? >= tag::PIXEL_DATA {
+            break;
+        }
+        reader.pos = start;
+        let (tag, vr, value) = reader.element()?;
+        if let Some(value) = value {
+            elements.insert(
+                tag,
+                Element {
+                    vr,
+                    value: value.to_vec(),
+                },
+            );
+        }
+    }
+    Ok(DataSet {
+        elements,
+        big_endian,
+    })
+}
+
+/// Read a DICOM file, without its pixels unless `pixels`
+pub fn read_file(path: &Path, pixels: bool) -> Result<DataSet, FormatError> {
+    parse(&fs::read(path)?, pixels)
+}
+
+/// Images of a directory that belong to the same series
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq, PartialOrd)]
+pub struct Series {
+    pub uid: String,
+    pub number: Option<i64>,
+    pub description: String,
+    pub files: Vec<PathBuf>,
+}
+
+impl Series {
+    /// Short name to choose the series by, its number and description
+    pub fn label(&self) -> String {
+        let number = self.number.map(|n| n.to_string());
+        let label = [number.as_deref(), Some(self.description.as_str())]
+            .into_iter()
+            .flatten()
+            .filter(|s| !s.is_empty())
+            .collect::<Vec<_>>()
+            .join(" ");
+        match label.is_empty() {
+            true => self.uid.clone(),
+            false => label,
+        }
+    }
+
+    /// Read every image of the series into a volume
+    pub fn load(&self) -> Result<Volume, FormatError> {
+        let images = self
+            .files
+            .iter()
+            .map(|file| {
+                read_file(file, true)
+                    .map_err(|e| FormatError::Invalid(format!("{}: {e}", file.display())))
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+        volume(images)
+    }
+}
+
+/// Find the series of every DICOM file in `dir` and its subdirectories, ordered by series number.
+/// Other files are ignored
+pub fn scan(dir: &Path) -> Result<Vec<Series>, FormatError> {
+    let mut files = vec![];
+    let mut dirs = vec![dir.to_path_buf()];
+    while let Some(dir) = dirs.pop() {
+        for entry in fs::read_dir(&dir)? {
+            let path = entry?.path();
+            if path.is_dir() {
+                dirs.push(path);
+            } else if let Ok(header) = read_file(&path, false) {
+                files.push((path, header));
+            }
+        }
+    }
+    Ok(group_series(files))
+}
+
+fn group_series(files: Vec<(PathBuf, DataSet)>) -> Vec<Series> {
+    let mut series = BTreeMap::<String, Series>::new();
+    for (path, header) in files {
+        let Some(uid) = header.string(tag::SERIES_INSTANCE_UID) else {
+            continue;
+        };
+        // Files like DICOMDIR describe images, without being one
+        if !header.elements.contains_key(&tag::ROWS) {
+            continue;
+        }
+        series
+            .entry(uid.to_string())
+            .or_insert_with(|| Series {
+                uid: uid.to_string(),
+                number: header.number(tag::SERIES_NUMBER).map(|n| n as i64),
+                description: header
+                    .string(tag::SERIES_DESCRIPTION)
+                    .unwrap_or_default()
+                    .to_string(),
+                files: vec![],
+            })
+            .files
+            .push(path);
+    }
+    let mut series = series.into_values().collect::<Vec<_>>();
+    for s in &mut series {
+        s.files.sort();
+    }
+    series.sort_by(|a, b| (a.number, &a.uid).cmp(&(b.number, &b.uid)));
+    series
+}
+
+/// A series read into an array
+#[derive(Clone, Debug, PartialEq)]
+pub struct Volume {
+    /// Pixels, rescaled, with the shape `[slices, rows, columns]`, or
+    /// `[images, slices, rows, columns]` if several images share each slice position.
+    /// A multi-frame image has the shape `[frames, rows, columns]`
+    pub array: ForayArray,
+    /// Header of the first image of the first slice, without its pixels
+    pub header: DataSet,
+    /// Distance between neighbouring slices along the normal of the images
+    pub slice_spacing: Option<f64>,
+}
+
+/// Sort images along the normal of the image plane, then by instance number, and stack them.
+/// Integer pixels stay integers unless rescaling makes them fractional
+pub fn volume(mut images: Vec<DataSet>) -> Result<Volume, FormatError> {
+    let first = images
+        .first()
+        .ok_or_else(|| FormatError::Invalid("series has no images".to_string()))?;
+    let [frames, rows, columns] = first.frame_shape()?;
+    if frames > 1 && images.len() > 1 {
+        return Err(FormatError::Unsupported(
+            "series of several multi-frame images".to_string(),
+        ));
+    }
+    for image in &images {
+        if image.frame_shape()? != [frames, rows, columns] {
+            return Err(FormatError::Invalid(
+                "images of the series have different sizes".to_string(),
+            ));
+        }
+    }
+
+    let normal = first.normal();
+    let depth = |image: &DataSet| normal.and_then(|normal| image.depth(normal));
+    let instance = |image: &DataSet| image.number(tag::INSTANCE_NUMBER).unwrap_or(0.0);
+    images.sort_by(|a, b| {
+        let depth = |image| depth(image).unwrap_or(0.0);
+        depth(a)
+            .total_cmp(&depth(b))
+            .then(instance(a).total_cmp(&instance(b)))
+    });
+
+    // Without positions, images are stacked in the order of their instance numbers
+    let (slices, slice_spacing) = match images.iter().map(depth).collect::<Option<Vec<_>>>() {
+        Some(positions) => {
+            // Images whose positions are this close are at the same slice
+            let mut counts: Vec<(f64, usize)> = vec![];
+            for position in positions {
+                match counts.last_mut() {
+                    Some((slice, count)) if (position - *slice).abs() < 1e-3 => *count += 1,
+                    _ => counts.push((position, 1)),
+                }
+            }
+            let (first, per_slice) = counts[0];
+            if let Some((position, count)) = counts.iter().find(|(_, count)| *count != per_slice) {
+                return Err(FormatError::Invalid(format!(
+                    "slice positions have different numbers of images, {per_slice} at {first} \
+                     and {count} at {position}"
+                )));
+            }
+            (
+                counts.len(),
+                counts.get(1).map(|(second, _)| second - first),
+            )
+        }
+        None => (images.len(), None),
+    };
+    let per_slice = images.len() / slices;
+    if per_slice * slices != images.len() {
+        return Err(FormatError::Invalid(format!(
+            "{} images can't be split evenly between {slices} slice positions",
+            images.len()
+        )));
+    }
+
+    let rescale = |image: &DataSet| {
+        (
+            image.number(tag::RESCALE_SLOPE).unwrap_or(1.0),
+            image.number(tag::RESCALE_INTERCEPT).unwrap_or(0.0),
+        )
+    };
+    let pixels = images
+        .iter()
+        .map(|image| image.pixels())
+        .collect::<Result<Vec<_>, _>>()?;
+    let integer = images.iter().zip(&pixels).all(|(image, pixels)| {
+        let (slope, intercept) = rescale(image);
+        slope == 1.0
+            && intercept.fract() == 0.0
+            && pixels
+                .iter()
+                .all(|v| i32::try_from(v + intercept as i64).is_ok())
+    });
+
+    // Images are sorted by slice, then by instance, the array is indexed by instance first
+    let shape = match (frames, per_slice) {
+        (1, 1) => vec![slices, rows, columns],
+        (1, per_slice) => vec![per_slice, slices, rows, columns],
+        (frames, _) => vec![frames, rows, columns],
+    };
+    let order = (0..per_slice)
+        .flat_map(|i| (0..slices).map(move |s| s * per_slice + i))
+        .collect::<Vec<_>>();
+    let array = match integer {
+        true => ForayArray::Integer(
+            ArrayD::from_shape_vec(
+                IxDyn(&shape),
+                order
+                    .iter()
+                    .flat_map(|&i| {
+                        let intercept = rescale(&images[i]).1 as i64;
+                        pixels[i].iter().map(move |v| (v + intercept) as i32)
+                    })
+                    .collect(),
+            )
+            .map_err(|e| FormatError::Invalid(e.to_string()))?,
+        ),
+        false => ForayArray::Float(
+            ArrayD::from_shape_vec(
+                IxDyn(&shape),
+                order
+                    .iter()
+                    .flat_map(|&i| {
+                        let (slope, intercept) = rescale(&images[i]);
+                        pixels[i].iter().map(move |v| *v as f64 * slope + intercept)
+                    })
+                    .collect(),
+            )
+            .map_err(|e| FormatError::Invalid(e.to_string()))?,
+        ),
+    };
+
+    let mut header = images.swap_remove(0);
+    header.elements.remove(&tag::PIXEL_DATA);
+    Ok(Volume {
+        array,
+        header,
+        slice_spacing,
+    })
+}
+
+#[cfg(test)]
+mod test {
+    use ndarray::array;
+
+    use super::*;
+
+    /// Explicit VR little endian encoding of an element
+    fn encode(tag: Tag, vr: &[u8; 2], value: &[u8]) -> Vec<u8> {
+        let mut bytes = [tag.0.to_le_bytes(), tag.1.to_le_bytes()].concat();
+        bytes.extend(vr);
+        match LONG_VRS.contains(&vr) {
+            true => {
+                bytes.extend([0, 0]);
+                bytes.extend((value.len() as u32).to_le_bytes());
+            }
+            false => bytes.extend((value.len() as u16).to_le_bytes()),
+        }
+        bytes.extend(value);
+        bytes
+    }
+
+    fn file(transfer_syntax: &str, body: &[u8]) -> Vec<u8> {
+        let mut bytes = vec![0; 128];
+        bytes.extend(b"DICM");
+        bytes.extend(encode(
+            tag::TRANSFER_SYNTAX_UID,
+            b"UI",
+            format!("{transfer_syntax}\0").as_bytes(),
+        ));
+        bytes.extend(body);
+        bytes
+    }
+
+    #[test]
+    fn elements_and_pixels() {
+        let pixels = [0x07FFu16, 0x0800, 5, 0xF001]
+            .iter()
+            .flat_map(|v| v.to_le_bytes())
+            .collect::<Vec<_>>();
+        let item = [
+            [0xFE, 0xFF, 0x00, 0xE0, 0xFF, 0xFF, 0xFF, 0xFF].as_slice(),
+            &encode(tag::SERIES_DESCRIPTION, b"LO", b"nested"),
+            &[0xFE, 0xFF, 0x0D, 0xE0, 0, 0, 0, 0],
+        ]
+        .concat();
+        let body = [
+            encode(tag::MODALITY, b"CS", b"MR"),
+            encode(tag::SERIES_DESCRIPTION, b"LO", b"t1 "),
+            // A sequence of undefined length, with one item of undefined length
+            [
+                &[
+                    0x08, 0x00, 0x15, 0x11, b'S', b'Q', 0, 0, 0xFF, 0xFF, 0xFF, 0xFF,
+                ][..],
+                &item,
+                &[0xFE, 0xFF, 0xDD, 0xE0, 0, 0, 0, 0],
+            ]
+            .concat(),
+            encode(tag::ROWS, b"US", &2u16.to_le_bytes()),
+            encode(tag::COLUMNS, b"US", &2u16.to_le_bytes()),
+            encode(tag::PIXEL_SPACING, b"DS", b"0.5\\0.75"),
+            encode(tag::BITS_ALLOCATED, b"US", &16u16.to_le_bytes()),
+            encode(tag::BITS_STORED, b"US", &12u16.to_le_bytes()),
+            encode(tag::PIXEL_REPRESENTATION, b"US", &1u16.to_le_bytes()),
+            encode(tag::PIXEL_DATA, b"OW", &pixels),
+        ]
+        .concat();
+        let data_set = parse(&file(EXPLICIT_LITTLE_ENDIAN, &body), true).unwrap();
+        assert_eq!(data_set.string(tag::MODALITY), Some("MR"));
+        assert_eq!(data_set.string(tag::SERIES_DESCRIPTION), Some("t1"));
+        assert_eq!(data_set.numbers(tag::PIXEL_SPACING), Some(vec![0.5, 0.75]));
+        assert_eq!(data_set.number(tag::ROWS), Some(2.0));
+        // 12 bit two's complement, the unused high bits are ignored
+        assert_eq!(data_set.pixels().unwrap(), vec![2047, -2048, 5, 1]);
+
+        let header = parse(&file(EXPLICIT_LITTLE_ENDIAN, &body), false).unwrap();
+        assert!(!header.elements.contains_key(&tag::PIXEL_DATA));
+
+        // Implicit VR elements are a tag, a 4 byte length, and the value
+        let implicit = [0x28, 0x00, 0x10, 0x00, 2, 0, 0, 0, 3, 0];
+        let data_set = parse(&file(IMPLICIT_LITTLE_ENDIAN, &implicit), false).unwrap();
+        assert_eq!(data_set.number(tag::ROWS), Some(3.0));
+
+        assert_eq!(
+            parse(&file("1.2.840.10008.1.2.4.50", &body), false),
+            Err(FormatError::Unsupported(
+                "compressed transfer syntax 1.2.840.10008.1.2.4.50".to_string()
+            ))
+        );
+    }
+
+    /// Image of 1x2 pixels of 16 bits, at `z`
+    fn image(z: f64, instance: i32, rescale: (f64, f64), pixels: [u16; 2]) -> DataSet {
+        let text = |value: String| Element {
+            vr: *b"DS",
+            value: value.into_bytes(),
+        };
+        let us = |value: u16| Element {
+            vr: *b"US",
+            value: value.to_le_bytes().to_vec(),
+        };
+        DataSet {
+            elements: [
+                (tag::SERIES_INSTANCE_UID, text("1.2.3".to_string())),
+                (tag::INSTANCE_NUMBER, text(instance.to_string())),
+                (tag::IMAGE_POSITION_PATIENT, text(format!("-10\\20\\{z}"))),
+                (
+                    tag::IMAGE_ORIENTATION_PATIENT,
+                    text("1\\0\\0\\0\\1\\0".to_string()),
+                ),
+                (tag::RESCALE_SLOPE, text(rescale.0.to_string())),
+                (tag::RESCALE_INTERCEPT, text(rescale.1.to_string())),
+                (tag::ROWS, us(1)),
+                (tag::COLUMNS, us(2)),
+                (tag::BITS_ALLOCATED, us(16)),
+                (
+                    tag::PIXEL_DATA,
+                    Element {
+                        vr: *b"OW",
+                        value: pixels.iter().flat_map(|v| v.to_le_bytes()).collect(),
+                    },
+                ),
+            ]
+            .into(),
+            big_endian: false,
+        }
+    }
+
+    #[test]
+    fn volumes() {
+        let ct = (1.0, -1024.0);
+        let slices = vec![
+            image(5.0, 3, ct, [1030, 1031]),
+            image(-2.5, 1, ct, [1024, 1025]),
+            image(1.25, 2, ct, [1027, 1028]),
+        ];
+        let volume = volume(slices.clone()).unwrap();
+        assert_eq!(
+            volume.array,
+            ForayArray::Integer(array![[[0, 1]], [[3, 4]], [[6, 7]]].into_dyn())
+        );
+        assert_eq!(volume.slice_spacing, Some(3.75));
+        assert_eq!(volume.header.number(tag::INSTANCE_NUMBER), Some(1.0));
+        assert!(!volume.header.elements.contains_key(&tag::PIXEL_DATA));
+
+        // Two images at each position, ordered by instance number
+        let scaled = (0.5, 0.0);
+        let series = vec![
+            image(1.0, 4, scaled, [6, 6]),
+            image(0.0, 1, scaled, [0, 0]),
+            image(1.0, 3, scaled, [4, 4]),
+            image(0.0, 2, scaled, [2, 2]),
+        ];
+        assert_eq!(
+            super::volume(series).unwrap().array,
+            ForayArray::Float(
+                array![[[[0.0, 0.0]], [[2.0, 2.0]]], [[[1.0, 1.0]], [[3.0, 3.0]]]].into_dyn()
+            )
+        );
+
+        // A repeated slice
+        let uneven = vec![
+            image(0.0, 1, scaled, [0, 0]),
+            image(0.0, 2, scaled, [2, 2]),
+            image(0.0, 3, scaled, [4, 4]),
+            image(1.0, 4, scaled, [6, 6]),
+        ];
+        assert_eq!(
+            super::volume(uneven).unwrap_err(),
+            FormatError::Invalid(
+                "slice positions have different numbers of images, 3 at 0 and 1 at 1".to_string()
+            )
+        );
+
+        let series = group_series(vec![
+            (PathBuf::from("b.dcm"), slices[0].clone()),
+            (PathBuf::from("a.dcm"), slices[1].clone()),
+            (PathBuf::from("DICOMDIR"), DataSet::default()),
+        ]);
+        assert_eq!(
+            series,
+            vec![Series {
+                uid: "1.2.3".to_string(),
+                number: None,
+                description: String::new(),
+                files: vec![PathBuf::from("a.dcm"), PathBuf::from("b.dcm")],
+            }]
+        );
+        assert_eq!(series[0].label(), "1.2.3");
+    }
+}
@@ -0,0 +1,36 @@
+use std::{fmt::Display, io};
+
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd)]
+pub enum FormatError {
+    /// The file could not be read or written
+    Io(String),
+    /// The file isn't valid for its format
+    Invalid(String),
+    /// The file is valid, but uses a feature or data type that isn't supported
+    Unsupported(String),
+}
+
+impl From<io::Error> for FormatError {
+    fn from(e: io::Error) -> Self {
+        Self::Io(e.to_string())
+    }
+}
+
+impl From<zip::result::ZipError> for FormatError {
+    fn from(e: zip::result::ZipError) -> Self {
+        match e {
+            zip::result::ZipError::Io(e) => e.into(),
+            e => Self::Invalid(e.to_string()),
+        }
+    }
+}
+
+impl Display for FormatError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            FormatError::Io(e) => write!(f, "IO Error: {e}"),
+            FormatError::Invalid(e) => write!(f, "Invalid file: {e}"),
+            FormatError::Unsupported(e) => write!(f, "Unsupported: {e}"),
+        }
+    }
+}
@@ -0,0 +1,566 @@
+//! ISMRMRD raw data, `.h5`: an HDF5 file with a group, `dataset` by default, that holds an XML
+//! header describing the scan, `xml`, and a table of acquisitions, `data`. Each acquisition is
+//! one readout for every active coil, with the encoding counters that locate it in k-space.
+//!
+//! Reading the HDF5 file requires the `hdf5` feature, and the HDF5 library.
+//! See <https://ismrmrd.readthedocs.io>
+
+use std::{collections::BTreeMap, path::Path};
+
+use ndarray::{ArrayD, IxDyn};
+use num_complex::Complex64;
+
+use crate::err::FormatError;
+
+/// Flags of an acquisition, `flags & flag::...` is set if the acquisition is of that kind
+pub mod flag {
+    pub const IS_NOISE_MEASUREMENT: u64 = 1 << 18;
+    pub const IS_NAVIGATION_DATA: u64 = 1 << 22;
+    pub const IS_PHASECORR_DATA: u64 = 1 << 23;
+}
+
+/// Smallest, largest and center value of an encoding counter
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct Limit {
+    pub minimum: usize,
+    pub maximum: usize,
+    pub center: usize,
+}
+
+/// The parts of the XML header that describe the first encoding space
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Header {
+    /// Matrix size of the encoded k-space, x, y and z
+    pub encoded_matrix: [usize; 3],
+    /// Field of view of the encoded k-space in mm
+    pub encoded_fov: [f64; 3],
+    pub recon_matrix: [usize; 3],
+    pub recon_fov: [f64; 3],
+    /// `cartesian`, `radial`, `spiral`, ...
+    pub trajectory: String,
+    /// Limits of the encoding counters, by their names in the header,
+    /// e.g. `kspace_encoding_step_1`
+    pub limits: BTreeMap<String, Limit>,
+    pub receiver_channels: Option<usize>,
+    /// Main field strength in T
+    pub field_strength: Option<f64>,
+    /// Repetition, echo and inversion times in ms, one for each contrast
+    pub tr: Vec<f64>,
+    pub te: Vec<f64>,
+    pub ti: Vec<f64>,
+}
+
+type Node<'a> = roxmltree::Node<'a, 'a>;
+
+fn child<'a>(node: Node<'a>, name: &str) -> Option<Node<'a>> {
+    node.children().find(|child| child.has_tag_name(name))
+}
+
+/// Text of the child `name` of `node`
+fn text<'a>(node: Option<Node<'a>>, name: &str) -> Option<&'a str> {
+    node.and_then(|node| child(node, name))
+        .and_then(|node| node.text())
+        .map(str::trim)
+}
+
+fn parse_number(text: &str, name: &str) -> Result<f64, FormatError> {
+    text.trim()
+        .parse()
+        .map_err(|_| FormatError::Invalid(format!("{name} should be a number")))
+}
+
+fn number(node: Option<Node>, name: &str) -> Result<Option<f64>, FormatError> {
+    text(node, name)
+        .map(|text| parse_number(text, name))
+        .transpose()
+}
+
+/// The `x`, `y` and `z` of the child `name` of `node`, 1 if they're missing
+fn xyz(node: Option<Node>, name: &str) -> Result<[f64; 3], FormatError> {
+    let node = node.and_then(|node| child(node, name));
+    Ok([
+        number(node, "x")?.unwrap_or(1.0),
+        number(node, "y")?.unwrap_or(1.0),
+        number(node, "z")?.unwrap_or(1.0),
+    ])
+}
+
+/// Parse the header's XML
+pub fn parse_header(xml: &str) -> Result<Header, FormatError> {
+    let document =
+        roxmltree::Document::parse(xml).map_err(|e| FormatError::Invalid(e.to_string()))?;
+    let root = document.root_element();
+
+    let encoding = child(root, "encoding")
+        .ok_or_else(|| FormatError::Invalid("header has no encoding".to_string()))?;
+    let encoded_space = child(encoding, "encodedSpace");
+    let recon_space = child(encoding, "reconSpace");
+    let mut limits = BTreeMap::new();
+    if let Some(encoding_limits) = child(encoding, "encodingLimits") {
+        for limit in encoding_limits.children().filter(|node| node.is_element()) {
+            let value = |name| number(Some(limit), name).map(|n| n.unwrap_or(0.0) as usize);
+            limits.insert(
+                limit.tag_name().name().to_string(),
+                Limit {
+                    minimum: value("minimum")?,
+                    maximum: value("maximum")?,
+                    center: value("center")?,
+                },
+            );
+        }
+    }
+    let sequence = child(root, "sequenceParameters");
+    let times = |name: &str| -> Result<Vec<f64>, FormatError> {
+        sequence
+            .iter()
+            .flat_map(|sequence| sequence.children())
+            .filter(|node| node.has_tag_name(name))
+            .filter_map(|node| node.text())
+            .map(|text| parse_number(text, name))
+            .collect()
+    };
+    let system = child(root, "acquisitionSystemInformation");
+    Ok(Header {
+        encoded_matrix: xyz(encoded_space, "matrixSize")?.map(|n| n as usize),
+        encoded_fov: xyz(encoded_space, "fieldOfView_mm")?,
+        recon_matrix: xyz(recon_space, "matrixSize")?.map(|n| n as usize),
+        recon_fov: xyz(recon_space, "fieldOfView_mm")?,
+        trajectory: text(Some(encoding), "trajectory")
+            .unwrap_or_default()
+            .to_string(),
+        limits,
+        receiver_channels: number(system, "receiverChannels")?.map(|n| n as usize),
+        field_strength: number(system, "systemFieldStrength_T")?,
+        tr: times("TR")?,
+        te: times("TE")?,
+        ti: times("TI")?,
+    })
+}
+
+/// Position of an acquisition in k-space, and in the other dimensions of the scan
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct EncodingCounters {
+    pub kspace_encode_step_1: u16,
+    pub kspace_encode_step_2: u16,
+    pub average: u16,
+    pub slice: u16,
+    pub contrast: u16,
+    pub phase: u16,
+    pub repetition: u16,
+    pub set: u16,
+    pub segment: u16,
+}
+
+/// One readout, for every active coil
+#[derive(Clone, Debug, PartialEq)]
+pub struct Acquisition {
+    pub flags: u64,
+    pub encoding_space_ref: u16,
+    pub idx: EncodingCounters,
+    pub active_channels: usize,
+    pub number_of_samples: usize,
+    /// Samples of each coil in turn
+    pub data: Vec<Complex64>,
+}
+
+/// Kinds of acquisitions that are left out of k-space
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Exclude {
+    pub noise: bool,
+    pub navigators: bool,
+}
+
+impl Exclude {
+    fn excludes(&self, acquisition: &Acquisition) -> bool {
+        let is = |flag| acquisition.flags & flag != 0;
+        // Phase correction readouts repeat lines of k-space, averaging them in would corrupt it
+        is(flag::IS_PHASECORR_DATA)
+            || (self.noise && is(flag::IS_NOISE_MEASUREMENT))
+            || (self.navigators && is(flag::IS_NAVIGATION_DATA))
+    }
+}
+
+/// Arrange the acquisitions of the first encoding space into k-space, with the shape
+/// `[repetition, contrast, slice, coil, kspace_encode_step_2, kspace_encode_step_1, sample]`.
+///
+/// Each counter's axis covers the header's limit for it, and every counter in the data.
+/// Acquisitions with the same counters, like averages, are averaged.
+/// Phase correction readouts are always left out, parallel imaging calibration lines are kept
+/// as they're lines of k-space.
+/// Noise scans of another size than the imaging acquisitions are left out, even if `exclude`
+/// keeps noise
+pub fn kspace(
+    header: &Header,
+    acquisitions: &[Acquisition],
+    exclude: Exclude,
+) -> Result<ArrayD<Complex64>, FormatError> {
+    let is_noise = |a: &Acquisition| a.flags & flag::IS_NOISE_MEASUREMENT != 0;
+    let acquisitions = acquisitions
+        .iter()
+        .filter(|a| a.encoding_space_ref == 0 && !exclude.excludes(a))
+        .collect::<Vec<_>>();
+    // Readouts are sized by the imaging acquisitions, unless there are only noise scans
+    let first = acquisitions
+        .iter()
+        .find(|a| !is_noise(a))
+        .or(acquisitions.first())
+        .ok_or_else(|| FormatError::Invalid("file has no acquisitions".to_string()))?;
+    let (coils, samples) = (first.active_channels, first.number_of_samples);
+    if coils == 0 || samples == 0 {
+        return Err(FormatError::Invalid(format!(
+            "acquisitions of {coils} coils of {samples} samples"
+        )));
+    }
+    // Noise scans are often read out with another number of samples, those don't fit in
+    // k-space and are left out
+    let acquisitions = acquisitions
+        .into_iter()
+        .filter(|a| !is_noise(a) || (a.active_channels, a.number_of_samples) == (coils, samples))
+        .collect::<Vec<_>>();
+    if let Some(a) = acquisitions
+        .iter()
+        .find(|a| (a.active_channels, a.number_of_samples) != (coils, samples))
+    {
+        return Err(FormatError::Unsupported(format!(
+            "acquisitions of different sizes, {coils} coils of {samples} samples and {} coils \
+             of {} samples",
+            a.active_channels, a.number_of_samples
+        )));
+    }
+
+    let counters = |a: &Acquisition| {
+        let idx = a.idx;
+        [
+            idx.repetition,
+            idx.contrast,
+            idx.slice,
+            idx.kspace_encode_step_2,
+            idx.kspace_encode_step_1,
+        ]
+        .map(usize::from)
+    };
+    let limit_names = [
+        "repetition",
+        "contrast",
+        "slice",
+        "kspace_encoding_step_2",
+        "kspace_encoding_step_1",
+    ];
+    let mut lengths = limit_names.map(|name| header.limits.get(name).map_or(1, |l| l.maximum + 1));
+    for a in &acquisitions {
+        for (length, counter) in lengths.iter_mut().zip(counters(a)) {
+            *length = (*length).max(counter + 1);
+        }
+    }
+    let [repetitions, contrasts, slices, steps_2, steps_1] = lengths;
+
+    let shape = [
+        repetitions,
+        contrasts,
+        slices,
+        coils,
+        steps_2,
+        steps_1,
+        samples,
+    ];
+    let mut kspace = ArrayD::zeros(IxDyn(&shape));
+    let mut count =
+        ArrayD::<f64>::zeros(IxDyn(&[repetitions, contrasts, slices, steps_2, steps_1]));
+    for a in acquisitions {
+        if a.data.len() < coils * samples {
+            return Err(FormatError::Invalid(format!(
+                "acquisition has {} samples, expected {}",
+                a.data.len(),
+                coils * samples
+            )));
+        }
+        let [repetition, contrast, slice, step_2, step_1] = counters(a);
+        for (coil, readout) in a.data.chunks_exact(samples).take(coils).enumerate() {
+            for (sample, value) in readout.iter().enumerate() {
+                kspace[[repetition, contrast, slice, coil, step_2, step_1, sample].as_slice()] +=
+                    value;
+            }
+        }
+        count[[repetition, contrast, slice, step_2, step_1].as_slice()] += 1.0;
+    }
+    for (index, value) in kspace.indexed_iter_mut() {
+        // The count of the readout, which doesn't depend on the coil or sample
+        let n = count[[index[0], index[1], index[2], index[4], index[5]].as_slice()];
+        if n > 1.0 {
+            *value /= n;
+        }
+    }
+    Ok(kspace)
+}
+
+/// Read the XML header and the acquisitions of `group` in an ISMRMRD file
+#[cfg(feature = "hdf5")]
+pub fn load(path: &Path, group: &str) -> Result<(String, Vec<Acquisition>), FormatError> {
+    h5::load(path, group)
+}
+
+#[cfg(not(feature = "hdf5"))]
+pub fn load(_path: &Path, _group: &str) -> Result<(String, Vec<Acquisition>), FormatError> {
+    Err(FormatError::Unsupported(
+        "ISMRMRD files, foray was built without the hdf5 feature".to_string(),
+    ))
+}
+
+#[cfg(feature = "hdf5")]
+mod h5 {
+    //! Compound types of the `data` table. HDF5 matches the fields of compound types by name,
+    //! so only the fields that are used are declared
+
+    use std::path::Path;
+
+    use hdf5::types::{VarLenArray, VarLenAscii, VarLenUnicode};
+
+    use super::{Acquisition, EncodingCounters};
+    use crate::err::FormatError;
+
+    #[derive(hdf5::H5Type, Clone, Copy, Debug)]
+    #[repr(C)]
+    struct RawEncodingCounters {
+        kspace_encode_step_1: u16,
+        kspace_encode_step_2: u16,
+        average: u16,
+        slice: u16,
+        contrast: u16,
+        phase: u16,
+        repetition: u16,
+        set: u16,
+        segment: u16,
+    }
+
+    #[derive(hdf5::H5Type, Clone, Copy, Debug)]
+    #[repr(C)]
+    struct RawAcquisitionHeader {
+        flags: u64,
+        number_of_samples: u16,
+        active_channels: u16,
+        encoding_space_ref: u16,
+        idx: RawEncodingCounters,
+    }
+
+    #[derive(hdf5::H5Type, Clone, Debug)]
+    #[repr(C)]
+    struct RawAcquisition {
+        head: RawAcquisitionHeader,
+        /// Real and imaginary parts of the samples, interleaved
+        data: VarLenArray<f32>,
+    }
+
+    fn invalid(e: hdf5::Error) -> FormatError {
+        FormatError::Invalid(e.to_string())
+    }
+
+    pub(super) fn load(
+        path: &Path,
+        group: &str,
+    ) -> Result<(String, Vec<Acquisition>), FormatError> {
+        // HDF5's own error for a missing file is vague
+        std::fs::metadata(path)?;
+        let group = hdf5::File::open(path)
+            .and_then(|file| file.group(group))
+            .map_err(invalid)?;
+
+        // The C library writes the header as an ASCII string, h5py as UTF-8
+        let xml = group.dataset("xml").map_err(invalid)?;
+        let xml = match xml.read_raw::<VarLenUnicode>() {
+            Ok(xml) => xml.first().map(|s| s.as_str().to_string()),
+            Err(_) => xml
+                .read_raw::<VarLenAscii>()
+                .map_err(invalid)?
+                .first()
+                .map(|s| s.as_str().to_string()),
+        }
+        .ok_or_else(|| FormatError::Invalid("file has an empty header".to_string()))?;
+
+        let acquisitions = group
+            .dataset("data")
+            .and_then(|data| data.read_raw::<RawAcquisition>())
+            .map_err(invalid)?
+            .into_iter()
+            .map(|raw| {
+                let head = raw.head;
+                let idx = head.idx;
+                Acquisition {
+                    flags: head.flags,
+                    encoding_space_ref: head.encoding_space_ref,
+                    idx: EncodingCounters {
+                        kspace_encode_step_1: idx.kspace_encode_step_1,
+                        kspace_encode_step_2: idx.kspace_encode_step_2,
+                        average: idx.average,
+                        slice: idx.slice,
+                        contrast: idx.contrast,
+                        phase: idx.phase,
+                        repetition: idx.repetition,
+                        set: idx.set,
+                        segment: idx.segment,
+                    },
+                    active_channels: head.active_channels.into(),
+                    number_of_samples: head.number_of_samples.into(),
+                    data: crate::complex(raw.data.iter().map(|v| f64::from(*v)).collect()),
+                }
+            })
+            .collect();
+        Ok((xml, acquisitions))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    const XML: &str = r#"<?xml version="1.0"?>
+<ismrmrdHeader xmlns="http://www.ismrm.org/ISMRMRD">
+  <acquisitionSystemInformation>
+    <systemFieldStrength_T>2.89</systemFieldStrength_T>
+    <receiverChannels>2</receiverChannels>
+  </acquisitionSystemInformation>
+  <encoding>
+    <encodedSpace>
+      <matrixSize><x>4</x><y>3</y><z>1</z></matrixSize>
+      <fieldOfView_mm><x>512</x><y>256</y><z>5</z></fieldOfView_mm>
+    </encodedSpace>
+    <reconSpace>
+      <matrixSize><x>2</x><y>3</y><z>1</z></matrixSize>
+      <fieldOfView_mm><x>256</x><y>256</y><z>5</z></fieldOfView_mm>
+    </reconSpace>
+    <encodingLimits>
+      <kspace_encoding_step_1>
+        <minimum>0</minimum><maximum>2</maximum><center>1</center>
+      </kspace_encoding_step_1>
+      <slice><minimum>0</minimum><maximum>0</maximum><center>0</center></slice>
+    </encodingLimits>
+    <trajectory>cartesian</trajectory>
+  </encoding>
+  <sequenceParameters>
+    <TR>4.5</TR>
+    <TE>2.1</TE>
+    <TE>3.2</TE>
+  </sequenceParameters>
+</ismrmrdHeader>"#;
+
+    #[test]
+    fn header() {
+        let header = parse_header(XML).unwrap();
+        assert_eq!(header.encoded_matrix, [4, 3, 1]);
+        assert_eq!(header.encoded_fov, [512.0, 256.0, 5.0]);
+        assert_eq!(header.recon_matrix, [2, 3, 1]);
+        assert_eq!(header.trajectory, "cartesian");
+        assert_eq!(
+            header.limits["kspace_encoding_step_1"],
+            Limit {
+                minimum: 0,
+                maximum: 2,
+                center: 1
+            }
+        );
+        assert_eq!(header.receiver_channels, Some(2));
+        assert_eq!(header.field_strength, Some(2.89));
+        assert_eq!(header.tr, [4.5]);
+        assert_eq!(header.te, [2.1, 3.2]);
+        assert!(header.ti.is_empty());
+        assert!(parse_header("<ismrmrdHeader/>").is_err());
+        assert!(parse_header("<ismrmrdHeader><encoding").is_err());
+    }
+
+    fn acquisition(step_1: u16, flags: u64, value: f64) -> Acquisition {
+        Acquisition {
+            flags,
+            encoding_space_ref: 0,
+            idx: EncodingCounters {
+                kspace_encode_step_1: step_1,
+                ..Default::default()
+            },
+            active_channels: 2,
+            number_of_samples: 2,
+            // Coil 1, then coil 2
+            data: [value, value + 1.0, -value, -value - 1.0]
+                .map(|re| Complex64::new(re, 1.0))
+                .to_vec(),
+        }
+    }
+
+    #[test]
+    fn readouts_in_kspace() {
+        let header = parse_header(XML).unwrap();
+        let acquisitions = [
+            acquisition(0, flag::IS_NOISE_MEASUREMENT, 100.0),
+            acquisition(1, 0, 1.0),
+            // An average of the same line
+            acquisition(1, 0, 3.0),
+            acquisition(2, flag::IS_NAVIGATION_DATA, 5.0),
+            // Phase correction of the averaged line
+            acquisition(1, flag::IS_PHASECORR_DATA, 50.0),
+        ];
+        let exclude = Exclude {
+            noise: true,
+            navigators: true,
+        };
+        let k = kspace(&header, &acquisitions, exclude).unwrap();
+        // The limit covers 3 lines, though only one is acquired
+        assert_eq!(k.shape(), [1, 1, 1, 2, 1, 3, 2]);
+        let line = |coil, step_1| {
+            (0..2)
+                .map(|sample| k[[0, 0, 0, coil, 0, step_1, sample].as_slice()])
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(
+            line(0, 1),
+            [Complex64::new(2.0, 1.0), Complex64::new(3.0, 1.0)]
+        );
+        assert_eq!(
+            line(1, 1),
+            [Complex64::new(-2.0, 1.0), Complex64::new(-3.0, 1.0)]
+        );
+        assert_eq!(line(0, 0), [Complex64::default(); 2]);
+        assert_eq!(line(0, 2), [Complex64::default(); 2]);
+
+        let k = kspace(
+            &header,
+            &acquisitions,
+            Exclude {
+                noise: true,
+                navigators: false,
+            },
+        )
+        .unwrap();
+        assert_eq!(
+            k[[0, 0, 0, 0, 0, 2, 0].as_slice()],
+            Complex64::new(5.0, 1.0)
+        );
+
+        let mut smaller = acquisition(0, 0, 1.0);
+        smaller.number_of_samples = 1;
+        assert!(matches!(
+            kspace(&header, &[acquisitions[1].clone(), smaller], exclude),
+            Err(FormatError::Unsupported(_))
+        ));
+        assert!(kspace(&header, &acquisitions[..1], exclude).is_err());
+
+        // Noise of another length is left out, even if noise is kept
+        let mut longer_noise = acquisition(0, flag::IS_NOISE_MEASUREMENT, 100.0);
+        longer_noise.number_of_samples = 4;
+        longer_noise.data = vec![Complex64::default(); 8];
+        let keep_noise = Exclude {
+            noise: false,
+            navigators: true,
+        };
+        let k = kspace(
+            &header,
+            &[longer_noise, acquisitions[1].clone()],
+            keep_noise,
+        )
+        .unwrap();
+        assert_eq!(k.shape(), [1, 1, 1, 2, 1, 3, 2]);
+
+        let mut empty = acquisition(0, 0, 1.0);
+        (empty.number_of_samples, empty.data) = (0, vec![]);
+        assert!(matches!(
+            kspace(&header, &[empty], exclude),
+            Err(FormatError::Invalid(_))
+        ));
+    }
+}
@@ -0,0 +1,112 @@
+//! Reading and writing arrays in the file formats used by other tools
+//!
+//! - [`npy`]: numpy's `.npy` files, which can be memory mapped
+//! - [`npz`]: numpy's `.npz` archives of `.npy` files
+//! - [`cfl`]: BART's `.cfl`/`.hdr` pairs
+//! - [`dicom`]: series of DICOM images, as exported by scanners
+//! - [`ismrmrd`]: raw MR data, ISMRMRD `.h5` files
+//! - [`nifti`]: single file NIfTI-1 and NIfTI-2 images, `.nii` and `.nii.gz`
+//! - [`mat`]: MATLAB's `.mat` files, version 7.3 needs the `hdf5` feature
+pub mod cfl;
+pub mod dicom;
+pub mod err;
+pub mod ismrmrd;
+pub mod mat;
+pub mod nifti;
+pub mod npy;
+pub mod npz;
+
+use foray_data_model::node::{ForayArray, PortData, PortType};
+use ndarray::{ArrayD, IxDyn, ShapeBuilder};
+
+use crate::err::FormatError;
+
+/// Build an array from elements stored in memory order, the first axis varying fastest if
+/// `fortran_order`
+fn from_memory_order<A>(
+    shape: &[usize],
+    data: Vec<A>,
+    fortran_order: bool,
+) -> Result<ArrayD<A>, FormatError> {
+    let shape = IxDyn(shape);
+    match fortran_order {
+        true => ArrayD::from_shape_vec(shape.f(), data),
+        false => ArrayD::from_shape_vec(shape, data),
+    }
+    .map_err(|e| FormatError::Invalid(e.to_string()))
+}
+
+/// Decode each `N` byte element of `bytes` with `from_le_bytes`
+pub(crate) fn elements<const N: usize, T>(
+    bytes: &[u8],
+    big_endian: bool,
+    from_le_bytes: impl Fn([u8; N]) -> T,
+) -> Vec<T> {
+    bytes
+        .chunks_exact(N)
+        .map(|chunk| {
+            let mut element: [u8; N] = chunk.try_into().expect("chunks are N bytes");
+            if big_endian {
+                element.reverse();
+            }
+            from_le_bytes(element)
+        })
+        .collect()
+}
+
+/// Pair up interleaved real and imaginary parts
+pub(crate) fn complex(parts: Vec<f64>) -> Vec<num_complex::Complex64> {
+    parts
+        .chunks_exact(2)
+        .map(|c| num_complex::Complex64::new(c[0], c[1]))
+        .collect()
+}
+
+/// Elements of an array in Fortran order, the first axis varying fastest
+fn fortran_order_iter<A>(array: &ArrayD<A>) -> impl Iterator<Item = &A> {
+    // Iterating the transpose in logical order visits the last of its axes fastest
+    array.t().into_iter()
+}
+
+/// Data of a port as an array, values are 0 dimensional arrays
+pub fn port_array(data: &PortData) -> Result<ForayArray, FormatError> {
+    use ndarray::arr0;
+    match data {
+        PortData::Integer(v) => Ok(ForayArray::Integer(arr0(*v).into_dyn())),
+        PortData::Float(v) => Ok(ForayArray::Float(arr0(*v).into_dyn())),
+        PortData::Complex((re, im)) => Ok(ForayArray::Complex(
+            arr0(num_complex::Complex64::new(*re, *im)).into_dyn(),
+        )),
+        PortData::Boolean(v) => Ok(ForayArray::Boolean(arr0(*v).into_dyn())),
+        PortData::Array(array) => Ok(array.clone()),
+        data => Err(FormatError::Unsupported(format!(
+            "{:?} can't be saved as an array",
+            PortType::from(data)
+        ))),
+    }
+}
+
+pub(crate) fn shape(array: &ForayArray) -> &[usize] {
+    match array {
+        ForayArray::Integer(a) => a.shape(),
+        ForayArray::Float(a) => a.shape(),
+        ForayArray::Complex(a) => a.shape(),
+        ForayArray::Boolean(a) => a.shape(),
+        ForayArray::String(a) => a.shape(),
+        ForayArray::Object(a) => a.shape(),
+    }
+}
+
+/// Elements of an array as complex numbers, for formats that only store complex data
+pub fn complex_array(array: &ForayArray) -> Result<ArrayD<num_complex::Complex64>, FormatError> {
+    use num_complex::Complex64;
+    match array {
+        ForayArray::Complex(a) => Ok(a.clone()),
+        ForayArray::Float(a) => Ok(a.mapv(|v| Complex64::new(v, 0.0))),
+        ForayArray::Integer(a) => Ok(a.mapv(|v| Complex64::new(v as f64, 0.0))),
+        array => Err(FormatError::Unsupported(format!(
+            "{:?} can't be saved as complex numbers",
+            PortType::from(array)
+        ))),
+    }
+}
@@ -0,0 +1,1060 @@
+//! MATLAB's `.mat` files. Version 5 files, also written by MATLAB's `-v6` and `-v7`, are a
+//! 128 byte header followed by a tagged data element for each variable, which `-v7` compresses.
+//! Version 7.3 files are HDF5 files, which are read with the `hdf5` feature.
+//!
+//! MATLAB stores arrays in column-major order, with at least two dimensions.
+//! Arrays of one element are read as single values, char arrays as strings along their second
+//! dimension, structs as objects and cell arrays as arrays of objects.
+//! See <https://www.mathworks.com/help/pdf_doc/matlab/matfile_format.pdf>
+
+use std::{
+    fs::File,
+    io::{self, BufReader, BufWriter, Read, Seek, SeekFrom, Write},
+    path::Path,
+};
+
+use flate2::{Compression, read::ZlibDecoder, write::ZlibEncoder};
+use foray_data_model::node::{Dict, ForayArray, PortData};
+use ndarray::{ArrayD, Axis};
+use num_complex::Complex64;
+
+use crate::{elements, err::FormatError, fortran_order_iter, from_memory_order, port_array, shape};
+
+/// Types of data elements
+mod mi {
+    pub const INT8: u32 = 1;
+    pub const UINT8: u32 = 2;
+    pub const INT16: u32 = 3;
+    pub const UINT16: u32 = 4;
+    pub const INT32: u32 = 5;
+    pub const UINT32: u32 = 6;
+    pub const SINGLE: u32 = 7;
+    pub const DOUBLE: u32 = 9;
+    pub const INT64: u32 = 12;
+    pub const UINT64: u32 = 13;
+    pub const MATRIX: u32 = 14;
+    pub const COMPRESSED: u32 = 15;
+    pub const UTF8: u32 = 16;
+    pub const UTF16: u32 = 17;
+    pub const UTF32: u32 = 18;
+}
+
+/// Classes of arrays, and the flags stored with them
+mod class {
+    pub const CELL: u32 = 1;
+    pub const STRUCT: u32 = 2;
+    pub const CHAR: u32 = 4;
+    pub const SPARSE: u32 = 5;
+    pub const DOUBLE: u32 = 6;
+    pub const SINGLE: u32 = 7;
+    pub const UINT8: u32 = 9;
+    pub const INT32: u32 = 12;
+    pub const UINT64: u32 = 15;
+
+    pub const LOGICAL: u32 = 0x200;
+    pub const COMPLEX: u32 = 0x800;
+}
+
+const VERSION_5: u16 = 0x0100;
+const VERSION_7_3: u16 = 0x0200;
+
+/// Longest name MATLAB allows for variables and struct fields
+const MAX_NAME_LEN: usize = 63;
+
+/// The version of a file, and whether its elements are big endian, from its 128 byte header
+fn version(header: &[u8]) -> Result<(u16, bool), FormatError> {
+    if header.len() < 128 || !header.starts_with(b"MATLAB") {
+        return Err(FormatError::Invalid(
+            "not a MAT-file, or one older than version 5".to_string(),
+        ));
+    }
+    let big_endian = match &header[126..128] {
+        b"IM" => false,
+        b"MI" => true,
+        _ => return Err(FormatError::Invalid("invalid endian indicator".to_string())),
+    };
+    let version = [header[124], header[125]];
+    let version = match big_endian {
+        true => u16::from_be_bytes(version),
+        false => u16::from_le_bytes(version),
+    };
+    Ok((version, big_endian))
+}
+
+fn u32_at(bytes: &[u8], big_endian: bool) -> u32 {
+    let bytes = bytes[..4].try_into().expect("4 bytes");
+    match big_endian {
+        true => u32::from_be_bytes(bytes),
+        false => u32::from_le_bytes(bytes),
+    }
+}
+
+/// Data elements in turn, each an 8 byte tag with its type and size, followed by its data
+/// padded to 8 bytes. Elements of up to 4 bytes are packed into the tag
+struct Elements<'a> {
+    bytes: &'a [u8],
+    big_endian: bool,
+}
+
+impl<'a> Elements<'a> {
+    /// Type and data of the next element
+    fn next(&mut self) -> Result<Option<(u32, &'a [u8])>, FormatError> {
+        if self.bytes.len() < 8 {
+            return Ok(None);
+        }
+        let first = u32_at(self.bytes, self.big_endian);
+        let (data_type, data, len) = match first >> 16 {
+            0 => {
+                let size = u32_at(&self.bytes[4..], self.big_endian) as usize;
+                let end = 8 + size;
+                if end > self.bytes.len() {
+                    return Err(FormatError::Invalid(format!(
+                        "element of {size} bytes runs past the end of the file"
+                    )));
+                }
+                // Compressed elements aren't padded
+                let len = match first {
+                    mi::COMPRESSED => end,
+                    _ => end.next_multiple_of(8).min(self.bytes.len()),
+                };
+                (first, &self.bytes[8..end], len)
+            }
+            size @ 0..=4 => (first & 0xFFFF, &self.bytes[4..4 + size as usize], 8),
+            size => {
+                return Err(FormatError::Invalid(format!(
+                    "small element of {size} bytes"
+                )));
+            }
+        };
+        self.bytes = &self.bytes[len..];
+        Ok(Some((data_type, data)))
+    }
+
+    /// The next element, which must be there
+    fn expect(&mut self, what: &str) -> Result<(u32, &'a [u8]), FormatError> {
+        self.next()?
+            .ok_or_else(|| FormatError::Invalid(format!("matrix has no {what}")))
+    }
+}
+
+/// Elements of any numeric type as floats, which hold every value of MATLAB's 32 bit integers
+fn numbers(data_type: u32, data: &[u8], big_endian: bool) -> Result<Vec<f64>, FormatError> {
+    let be = big_endian;
+    Ok(match data_type {
+        mi::INT8 => data.iter().map(|v| *v as i8 as f64).collect(),
+        mi::UINT8 => data.iter().map(|v| *v as f64).collect(),
+        mi::INT16 => elements(data, be, |b| i16::from_le_bytes(b) as f64),
+        mi::UINT16 => elements(data, be, |b| u16::from_le_bytes(b) as f64),
+        mi::INT32 => elements(data, be, |b| i32::from_le_bytes(b) as f64),
+        mi::UINT32 => elements(data, be, |b| u32::from_le_bytes(b) as f64),
+        mi::INT64 => elements(data, be, |b| i64::from_le_bytes(b) as f64),
+        mi::UINT64 => elements(data, be, |b| u64::from_le_bytes(b) as f64),
+        mi::SINGLE => elements(data, be, |b| f32::from_le_bytes(b) as f64),
+        mi::DOUBLE => elements(data, be, f64::from_le_bytes),
+        data_type => {
+            return Err(FormatError::Invalid(format!(
+                "element type {data_type} isn't numeric"
+            )));
+        }
+    })
+}
+
+/// A UTF-16 code unit as a character, MATLAB's chars are code units
+fn utf16(unit: u16) -> char {
+    char::from_u32(unit.into()).unwrap_or(char::REPLACEMENT_CHARACTER)
+}
+
+fn chars(data_type: u32, data: &[u8], big_endian: bool) -> Result<Vec<char>, FormatError> {
+    Ok(match data_type {
+        mi::UTF8 => String::from_utf8_lossy(data).chars().collect(),
+        mi::INT8 | mi::UINT8 => data.iter().map(|v| char::from(*v)).collect(),
+        mi::UINT16 | mi::UTF16 => elements(data, big_endian, u16::from_le_bytes)
+            .into_iter()
+            .map(utf16)
+            .collect(),
+        mi::UINT32 | mi::UTF32 => elements(data, big_endian, u32::from_le_bytes)
+            .into_iter()
+            .map(|c| char::from_u32(c).unwrap_or(char::REPLACEMENT_CHARACTER))
+            .collect(),
+        data_type => {
+            return Err(FormatError::Invalid(format!(
+                "element type {data_type} isn't text"
+            )));
+        }
+    })
+}
+
+fn check_len(dims: &[usize], len: usize) -> Result<(), FormatError> {
+    let expected = dims.iter().product::<usize>();
+    match len == expected {
+        true => Ok(()),
+        false => Err(FormatError::Invalid(format!(
+            "expected {expected} elements, found {len}"
+        ))),
+    }
+}
+
+/// Arrays of one element as single values
+fn single_or_array(array: ForayArray) -> PortData {
+    match &array {
+        ForayArray::Integer(a) if a.len() == 1 => PortData::Integer(a.iter().copied().sum()),
+        ForayArray::Float(a) if a.len() == 1 => PortData::Float(a.iter().copied().sum()),
+        ForayArray::Complex(a) if a.len() == 1 => {
+            let v = a.iter().copied().sum::<Complex64>();
+            PortData::Complex((v.re, v.im))
+        }
+        ForayArray::Boolean(a) if a.len() == 1 => PortData::Boolean(a.iter().any(|v| *v)),
+        _ => PortData::Array(array),
+    }
+}
+
+/// How the elements of a numeric class are read
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum Numeric {
+    Float,
+    Integer,
+    Logical,
+}
+
+/// A numeric array, from its elements in column-major order
+fn numeric_data(
+    kind: Numeric,
+    dims: &[usize],
+    real: Vec<f64>,
+    imag: Option<Vec<f64>>,
+) -> Result<PortData, FormatError> {
+    check_len(dims, real.len())?;
+    let array = match (kind, imag) {
+        (_, Some(imag)) => {
+            check_len(dims, imag.len())?;
+            let values = real
+                .into_iter()
+                .zip(imag)
+                .map(|(re, im)| Complex64::new(re, im))
+                .collect();
+            ForayArray::Complex(from_memory_order(dims, values, true)?)
+        }
+        (Numeric::Float, None) => ForayArray::Float(from_memory_order(dims, real, true)?),
+        (Numeric::Logical, None) => ForayArray::Boolean(from_memory_order(
+            dims,
+            real.into_iter().map(|v| v != 0.0).collect(),
+            true,
+        )?),
+        (Numeric::Integer, None) => {
+            let values = real
+                .into_iter()
+                .map(|v| match v >= i32::MIN as f64 && v <= i32::MAX as f64 {
+                    true => Ok(v as i32),
+                    false => Err(FormatError::Unsupported(format!(
+                        "{v} doesn't fit in 32 bits"
+                    ))),
+                })
+                .collect::<Result<_, _>>()?;
+            ForayArray::Integer(from_memory_order(dims, values, true)?)
+        }
+    };
+    Ok(single_or_array(array))
+}
+
+/// A char array as strings along its second dimension, a single string for a row
+fn char_data(dims: &[usize], chars: Vec<char>) -> Result<PortData, FormatError> {
+    check_len(dims, chars.len())?;
+    if dims.len() < 2 || dims == [0, 0] {
+        return Ok(PortData::String(chars.into_iter().collect()));
+    }
+    let mut shape = dims.to_vec();
+    shape.remove(1);
+    let strings = from_memory_order(dims, chars, true)?
+        .lanes(Axis(1))
+        .into_iter()
+        .map(|row| row.iter().collect::<String>())
+        .collect::<Vec<_>>();
+    match shape.iter().all(|l| *l == 1) {
+        true => Ok(PortData::String(strings.into_iter().collect())),
+        false => Ok(PortData::Array(ForayArray::String(from_memory_order(
+            &shape, strings, false,
+        )?))),
+    }
+}
+
+/// Name and data of a matrix element
+fn matrix(data: &[u8], big_endian: bool) -> Result<(String, PortData), FormatError> {
+    // Empty cells and fields are written as matrices without any data
+    if data.is_empty() {
+        return Ok((
+            String::new(),
+            PortData::Array(ForayArray::Float(ArrayD::zeros(vec![0, 0]))),
+        ));
+    }
+    let mut sub = Elements {
+        bytes: data,
+        big_endian,
+    };
+    let (_, flags) = sub.expect("array flags")?;
+    if flags.len() < 4 {
+        return Err(FormatError::Invalid(
+            "array flags are too short".to_string(),
+        ));
+    }
+    let flags = u32_at(flags, big_endian);
+    let (_, dims) = sub.expect("dimensions")?;
+    let dims = elements(dims, big_endian, i32::from_le_bytes)
+        .into_iter()
+        .map(|d| {
+            usize::try_from(d).map_err(|_| FormatError::Invalid(format!("negative dimension {d}")))
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+    let (_, name) = sub.expect("name")?;
+    let name = String::from_utf8_lossy(name).into_owned();
+    let len = dims.iter().product::<usize>();
+
+    let nested = |sub: &mut Elements, what| {
+        let (_, data) = sub.expect(what)?;
+        matrix(data, big_endian).map(|(_, data)| data)
+    };
+    let data = match flags & 0xFF {
+        class::CELL => {
+            let cells = (0..len)
+                .map(|_| nested(&mut sub, "cell"))
+                .collect::<Result<_, _>>()?;
+            PortData::Array(ForayArray::Object(from_memory_order(&dims, cells, true)?))
+        }
+        class::STRUCT => {
+            let (_, name_len) = sub.expect("field name length")?;
+            let name_len = elements(name_len, big_endian, i32::from_le_bytes)
+                .first()
+                .map_or(0, |l| (*l).max(0) as usize);
+            let (_, names) = sub.expect("field names")?;
+            let fields = match name_len {
+                0 => vec![],
+                _ => names
+                    .chunks_exact(name_len)
+                    .map(|n| {
+                        String::from_utf8_lossy(n)
+                            .trim_end_matches('\0')
+                            .to_string()
+                    })
+                    .collect(),
+            };
+            let mut structs = (0..len)
+                .map(|_| {
+                    fields
+                        .iter()
+                        .map(|field| Ok((field.clone(), nested(&mut sub, "field")?)))
+                        .collect::<Result<Dict<_, _>, FormatError>>()
+                        .map(PortData::Object)
+                })
+                .collect::<Result<Vec<_>, _>>()?;
+            match len {
+                1 => structs.remove(0),
+                _ => PortData::Array(ForayArray::Object(from_memory_order(&dims, structs, true)?)),
+            }
+        }
+        class::CHAR => {
+            let (data_type, data) = sub.expect("characters")?;
+            char_data(&dims, chars(data_type, data, big_endian)?)?
+        }
+        class @ class::DOUBLE..=class::UINT64 => {
+            let kind = match (flags & class::LOGICAL != 0, class) {
+                (true, _) => Numeric::Logical,
+                (false, class::DOUBLE | class::SINGLE) => Numeric::Float,
+                (false, _) => Numeric::Integer,
+            };
+            let (data_type, real) = sub.expect("real part")?;
+            let real = numbers(data_type, real, big_endian)?;
+            let imag = match flags & class::COMPLEX != 0 {
+                true => {
+                    let (data_type, imag) = sub.expect("imaginary part")?;
+                    Some(numbers(data_type, imag, big_endian)?)
+                }
+                false => None,
+            };
+            numeric_data(kind, &dims, real, imag)?
+        }
+        class::SPARSE => {
+            return Err(FormatError::Unsupported(format!(
+                "{name} is a sparse matrix"
+            )));
+        }
+        class => {
+            return Err(FormatError::Unsupported(format!(
+                "{name} is a MATLAB object, of class {class}"
+            )));
+        }
+    };
+    Ok((name, data))
+}
+
+fn inflate(data: &[u8]) -> Result<Vec<u8>, FormatError> {
+    let mut inflated = vec![];
+    ZlibDecoder::new(data).read_to_end(&mut inflated)?;
+    Ok(inflated)
+}
+
+/// Every variable of a version 5 file with its name, in the order they're stored
+pub fn read(reader: &mut impl Read) -> Result<Vec<(String, PortData)>, FormatError> {
+    let mut bytes = vec![];
+    reader.read_to_end(&mut bytes)?;
+    let big_endian = match version(&bytes)? {
+        (VERSION_5, big_endian) => big_endian,
+        (VERSION_7_3, _) => {
+            return Err(FormatError::Unsupported(
+                "MAT-file version 7.3 can only be loaded from a file".to_string(),
+            ));
+        }
+        (version, _) => {
+            return Err(FormatError::Unsupported(format!(
+                "MAT-file version {version:#x}"
+            )));
+        }
+    };
+    let mut elements = Elements {
+        bytes: &bytes[128..],
+        big_endian,
+    };
+    let mut variables = vec![];
+    while let Some((data_type, data)) = elements.next()? {
+        let variable = match data_type {
+            mi::MATRIX => matrix(data, big_endian)?,
+            mi::COMPRESSED => {
+                let inflated = inflate(data)?;
+                let mut inflated = Elements {
+                    bytes: &inflated,
+                    big_endian,
+                };
+                match inflated.next()? {
+                    Some((mi::MATRIX, data)) => matrix(data, big_endian)?,
+                    _ => {
+                        return Err(FormatError::Invalid(
+                            "compressed element isn't a matrix".to_string(),
+                        ));
+                    }
+                }
+            }
+            // Other elements at the top level aren't variables
+            _ => continue,
+        };
+        variables.push(variable);
+    }
+    Ok(variables)
+}
+
+/// Name of a matrix element, from the start of its data
+fn matrix_name(data: &[u8], big_endian: bool) -> Option<String> {
+    let mut sub = Elements {
+        bytes: data,
+        big_endian,
+    };
+    sub.next().ok()??;
+    sub.next().ok()??;
+    let (_, name) = sub.next().ok()??;
+    Some(String::from_utf8_lossy(name).into_owned())
+}
+
+/// Names of the variables in a file, in the order they're stored, without reading their data
+pub fn names(path: &Path) -> Result<Vec<String>, FormatError> {
+    /// Bytes at the start of a matrix that hold its name, after its flags and dimensions
+    const NAME_BYTES: u64 = 1024;
+
+    let mut file = BufReader::new(File::open(path)?);
+    let mut header = [0; 128];
+    file.read_exact(&mut header)?;
+    let big_endian = match version(&header)? {
+        (VERSION_7_3, _) => return h5_names(path),
+        (_, big_endian) => big_endian,
+    };
+    let mut names = vec![];
+    loop {
+        let mut tag = [0; 8];
+        match file.read_exact(&mut tag) {
+            Err(e) if e.kind() == io::ErrorKind::UnexpectedEof => break,
+            result => result?,
+        }
+        let data_type = u32_at(&tag, big_endian);
+        if data_type >> 16 != 0 {
+            // Small elements aren't variables
+            continue;
+        }
+        let size = u32_at(&tag[4..], big_endian) as u64;
+        let start = file.stream_position()?;
+        let mut data = vec![];
+        let name = match data_type {
+            mi::MATRIX => {
+                (&mut file)
+                    .take(size.min(NAME_BYTES))
+                    .read_to_end(&mut data)?;
+                matrix_name(&data, big_endian)
+            }
+            mi::COMPRESSED => {
+                // Only the start of the matrix is inflated
+                ZlibDecoder::new((&mut file).take(size))
+                    .take(8 + NAME_BYTES)
+                    .read_to_end(&mut data)?;
+                match data.len() >= 8 && u32_at(&data, big_endian) == mi::MATRIX {
+                    true => matrix_name(&data[8..], big_endian),
+                    false => None,
+                }
+            }
+            _ => None,
+        };
+        names.extend(name);
+        let end = match data_type {
+            mi::COMPRESSED => start + size,
+            _ => start + size.next_multiple_of(8),
+        };
+        file.seek(SeekFrom::Start(end))?;
+    }
+    Ok(names)
+}
+
+#[cfg(feature = "hdf5")]
+fn h5_names(path: &Path) -> Result<Vec<String>, FormatError> {
+    h5::names(path)
+}
+
+#[cfg(not(feature = "hdf5"))]
+fn h5_names(_path: &Path) -> Result<Vec<String>, FormatError> {
+    Err(without_hdf5())
+}
+
+#[cfg(not(feature = "hdf5"))]
+fn without_hdf5() -> FormatError {
+    FormatError::Unsupported(
+        "MAT-file version 7.3, foray was built without the hdf5 feature".to_string(),
+    )
+}
+
+/// Every variable of a file with its name, in the order they're stored
+pub fn load(path: &Path) -> Result<Vec<(String, PortData)>, FormatError> {
+    let mut file = BufReader::new(File::open(path)?);
+    let mut header = [0; 128];
+    file.read_exact(&mut header)?;
+    match version(&header)? {
+        #[cfg(feature = "hdf5")]
+        (VERSION_7_3, _) => h5::load(path),
+        #[cfg(not(feature = "hdf5"))]
+        (VERSION_7_3, _) => Err(without_hdf5()),
+        _ => {
+            file.rewind()?;
+            read(&mut file)
+        }
+    }
+}
+
+/// Names of variables and struct fields start with a letter, followed by letters, digits and
+/// underscores
+fn check_name(name: &str) -> Result<(), FormatError> {
+    let mut chars = name.chars();
+    let valid = chars.next().is_some_and(|c| c.is_ascii_alphabetic())
+        && chars.all(|c| c.is_ascii_alphanumeric() || c == '_')
+        && name.len() <= MAX_NAME_LEN;
+    match valid {
+        true => Ok(()),
+        false => Err(FormatError::Unsupported(format!(
+            "'{name}' isn't a valid MATLAB name"
+        ))),
+    }
+}
+
+/// Append an element, packing data of up to 4 bytes into its tag
+fn push_element(out: &mut Vec<u8>, data_type: u32, data: &[u8]) -> Result<(), FormatError> {
+    let size = u32::try_from(data.len()).map_err(|_| {
+        FormatError::Unsupported(
+            "variables of 4 GB or more, which need MAT-file version 7.3".to_string(),
+        )
+    })?;
+    match size {
+        0..=4 => {
+            out.extend((size << 16 | data_type).to_le_bytes());
+            out.extend(data);
+            out.extend(&[0; 4][..4 - data.len()]);
+        }
+        _ => {
+            out.extend(data_type.to_le_bytes());
+            out.extend(size.to_le_bytes());
+            out.extend(data);
+            out.extend(&[0; 8][..(8 - data.len() % 8) % 8]);
+        }
+    }
+    Ok(())
+}
+
+fn push_header(
+    out: &mut Vec<u8>,
+    flags: u32,
+    dims: &[usize],
+    name: &str,
+) -> Result<(), FormatError> {
+    let flags = [flags, 0].map(u32::to_le_bytes).concat();
+    push_element(out, mi::UINT32, &flags)?;
+    let dims = dims
+        .iter()
+        .map(|d| {
+            i32::try_from(*d)
+                .map(i32::to_le_bytes)
+                .map_err(|_| FormatError::Unsupported(format!("dimension of length {d}")))
+        })
+        .collect::<Result<Vec<_>, _>>()?
+        .concat();
+    push_element(out, mi::INT32, &dims)?;
+    push_element(out, mi::INT8, name.as_bytes())
+}
+
+/// MATLAB's dimensions of an array, rows for 1D arrays and 1 by 1 for single values
+fn matlab_dims(shape: &[usize]) -> Vec<usize> {
+    match shape {
+        [] => vec![1, 1],
+        [len] => vec![1, *len],
+        shape => shape.to_vec(),
+    }
+}
+
+fn bytes<T, const N: usize>(a: &ArrayD<T>, to_le_bytes: impl Fn(&T) -> [u8; N]) -> Vec<u8> {
+    fortran_order_iter(a).flat_map(to_le_bytes).collect()
+}
+
+fn push_char(out: &mut Vec<u8>, name: &str, s: &str) -> Result<(), FormatError> {
+    let units = s.encode_utf16().collect::<Vec<_>>();
+    let dims = match units.len() {
+        0 => [0, 0],
+        len => [1, len],
+    };
+    push_header(out, class::CHAR, &dims, name)?;
+    push_element(
+        out,
+        mi::UINT16,
+        &units
+            .iter()
+            .flat_map(|u| u.to_le_bytes())
+            .collect::<Vec<_>>(),
+    )
+}
+
+/// The elements of an object array, if they all have the same fields
+fn struct_elements(a: &ArrayD<PortData>) -> Option<Vec<&Dict<String, PortData>>> {
+    let structs = fortran_order_iter(a)
+        .map(|data| match data {
+            PortData::Object(fields) => Some(fields),
+            _ => None,
+        })
+        .collect::<Option<Vec<_>>>()?;
+    let first = structs.first()?;
+    structs
+        .iter()
+        .all(|s| s.keys().eq(first.keys()))
+        .then_some(structs)
+}
+
+fn push_struct(
+    out: &mut Vec<u8>,
+    name: &str,
+    dims: &[usize],
+    structs: &[&Dict<String, PortData>],
+) -> Result<(), FormatError> {
+    let fields = structs
+        .first()
+        .map(|s| s.keys().collect::<Vec<_>>())
+        .unwrap_or_default();
+    for field in &fields {
+        check_name(field)?;
+    }
+    push_header(out, class::STRUCT, dims, name)?;
+    let name_len = fields.iter().map(|f| f.len()).max().unwrap_or(0) + 1;
+    push_element(out, mi::INT32, &(name_len as i32).to_le_bytes())?;
+    let mut names = vec![0; name_len * fields.len()];
+    for (field, name) in fields.iter().zip(names.chunks_exact_mut(name_len)) {
+        name[..field.len()].copy_from_slice(field.as_bytes());
+    }
+    push_element(out, mi::INT8, &names)?;
+    for s in structs {
+        for field in &fields {
+            push_matrix(out, "", &s[*field])?;
+        }
+    }
+    Ok(())
+}
+
+fn push_array(out: &mut Vec<u8>, name: &str, array: &ForayArray) -> Result<(), FormatError> {
+    let dims = matlab_dims(shape(array));
+    match array {
+        ForayArray::Integer(a) => {
+            push_header(out, class::INT32, &dims, name)?;
+            push_element(out, mi::INT32, &bytes(a, |v| v.to_le_bytes()))
+        }
+        ForayArray::Float(a) => {
+            push_header(out, class::DOUBLE, &dims, name)?;
+            push_element(out, mi::DOUBLE, &bytes(a, |v| v.to_le_bytes()))
+        }
+        ForayArray::Complex(a) => {
+            push_header(out, class::DOUBLE | class::COMPLEX, &dims, name)?;
+            push_element(out, mi::DOUBLE, &bytes(a, |v| v.re.to_le_bytes()))?;
+            push_element(out, mi::DOUBLE, &bytes(a, |v| v.im.to_le_bytes()))
+        }
+        ForayArray::Boolean(a) => {
+            push_header(out, class::UINT8 | class::LOGICAL, &dims, name)?;
+            push_element(out, mi::UINT8, &bytes(a, |v| [*v as u8]))
+        }
+        // Strings of different lengths don't fit in a char array
+        ForayArray::String(a) => {
+            push_header(out, class::CELL, &dims, name)?;
+            fortran_order_iter(a).try_for_each(|s| {
+                let mut cell = vec![];
+                push_char(&mut cell, "", s)?;
+                push_element(out, mi::MATRIX, &cell)
+            })
+        }
+        ForayArray::Object(a) => match struct_elements(a) {
+            Some(structs) => push_struct(out, name, &dims, &structs),
+            None => {
+                push_header(out, class::CELL, &dims, name)?;
+                fortran_order_iter(a).try_for_each(|data| push_matrix(out, "", data))
+            }
+        },
+    }
+}
+
+/// Append a matrix element holding `data`
+fn push_matrix(out: &mut Vec<u8>, name: &str, data: &PortData) -> Result<(), FormatError> {
+    let mut matrix = vec![];
+    match data {
+        PortData::String(s) => push_char(&mut matrix, name, s)?,
+        PortData::Object(fields) => push_struct(&mut matrix, name, &[1, 1], &[fields])?,
+        PortData::Array(array) => push_array(&mut matrix, name, array)?,
+        data => push_array(&mut matrix, name, &port_array(data)?)?,
+    }
+    push_element(out, mi::MATRIX, &matrix)
+}
+
+/// Write a version 5 file holding each variable, compressed like MATLAB's `-v7` if `compressed`
+pub fn write(
+    writer: &mut impl Write,
+    variables: &[(&str, &PortData)],
+    compressed: bool,
+) -> Result<(), FormatError> {
+    let mut header = format!(
+        "MATLAB 5.0 MAT-file, Platform: {}, Created by: foray",
+        std::env::consts::OS
+    )
+    .into_bytes();
+    header.resize(116, b' ');
+    // No subsystem data
+    header.extend([0; 8]);
+    header.extend(VERSION_5.to_le_bytes());
+    header.extend(b"IM");
+    writer.write_all(&header)?;
+
+    for (name, data) in variables {
+        check_name(name)?;
+        let mut element = vec![];
+        push_matrix(&mut element, name, data)?;
+        if compressed {
+            let mut encoder = ZlibEncoder::new(vec![], Compression::default());
+            encoder.write_all(&element)?;
+            let element = encoder.finish()?;
+            let size = u32::try_from(element.len()).map_err(|_| {
+                FormatError::Unsupported(
+                    "variables of 4 GB or more, which need MAT-file version 7.3".to_string(),
+                )
+            })?;
+            writer.write_all(&mi::COMPRESSED.to_le_bytes())?;
+            writer.write_all(&size.to_le_bytes())?;
+            writer.write_all(&element)?;
+        } else {
+            writer.write_all(&element)?;
+        }
+    }
+    Ok(())
+}
+
+pub fn save(
+    path: &Path,
+    variables: &[(&str, &PortData)],
+    compressed: bool,
+) -> Result<(), FormatError> {
+    let mut writer = BufWriter::new(File::create(path)?);
+    write(&mut writer, variables, compressed)?;
+    writer.flush()?;
+    Ok(())
+}
+
+#[cfg(feature = "hdf5")]
+mod h5 {
+    //! Version 7.3 files are HDF5 files with a 512 byte header. Each variable is a dataset with
+    //! a `MATLAB_class` attribute, or a group of fields for a struct. Datasets have MATLAB's
+    //! dimensions reversed, so their elements are in MATLAB's column-major order
+
+    use std::path::Path;
+
+    use foray_data_model::node::{Dict, PortData};
+    use hdf5::{
+        Group,
+        types::{FixedAscii, TypeDescriptor},
+    };
+
+    use super::{Numeric, char_data, numeric_data, utf16};
+    use crate::err::FormatError;
+
+    /// Complex elements are compounds of their parts
+    #[derive(hdf5::H5Type, Clone, Copy, Debug)]
+    #[repr(C)]
+    struct Parts {
+        real: f64,
+        imag: f64,
+    }
+
+    fn invalid(e: hdf5::Error) -> FormatError {
+        FormatError::Invalid(e.to_string())
+    }
+
+    /// Variables or fields, leaving out MATLAB's own `#refs#` and `#subsystem#` groups
+    fn members(group: &Group) -> Result<Vec<String>, FormatError> {
+        Ok(group
+            .member_names()
+            .map_err(invalid)?
+            .into_iter()
+            .filter(|name| !name.starts_with('#'))
+            .collect())
+    }
+
+    pub(super) fn names(path: &Path) -> Result<Vec<String>, FormatError> {
+        members(&hdf5::File::open(path).map_err(invalid)?)
+    }
+
+    pub(super) fn load(path: &Path) -> Result<Vec<(String, PortData)>, FormatError> {
+        let file = hdf5::File::open(path).map_err(invalid)?;
+        members(&file)?
+            .into_iter()
+            .map(|name| {
+                let data = member(&file, &name)?;
+                Ok((name, data))
+            })
+            .collect()
+    }
+
+    fn member(group: &Group, name: &str) -> Result<PortData, FormatError> {
+        // A struct is a group of its fields. Cells, and the fields of struct arrays, are
+        // datasets of references, which aren't read
+        if let Ok(fields) = group.group(name) {
+            return members(&fields)?
+                .into_iter()
+                .map(|field| {
+                    let data = member(&fields, &field)?;
+                    Ok((field, data))
+                })
+                .collect::<Result<Dict<_, _>, FormatError>>()
+                .map(PortData::Object);
+        }
+        let dataset = group.dataset(name).map_err(invalid)?;
+        let class = dataset
+            .attr("MATLAB_class")
+            .and_then(|class| class.read_scalar::<FixedAscii<32>>())
+            .map_err(invalid)?;
+        let mut dims = dataset.shape();
+        dims.reverse();
+        // Empty arrays hold their dimensions instead of elements
+        if dataset.attr("MATLAB_empty").is_ok() {
+            return match class.as_str() {
+                "char" => Ok(PortData::String(String::new())),
+                _ => numeric_data(Numeric::Float, &[0, 0], vec![], None),
+            };
+        }
+        let kind = match class.as_str() {
+            "char" => {
+                let units = dataset.read_raw::<u16>().map_err(invalid)?;
+                return char_data(&dims, units.into_iter().map(utf16).collect());
+            }
+            "double" | "single" => Numeric::Float,
+            "logical" => Numeric::Logical,
+            "int8" | "uint8" | "int16" | "uint16" | "int32" | "uint32" | "int64" | "uint64" => {
+                Numeric::Integer
+            }
+            class => {
+                return Err(FormatError::Unsupported(format!(
+                    "{name} is a MATLAB {class}, which isn't read from version 7.3 files"
+                )));
+            }
+        };
+        let descriptor = dataset
+            .dtype()
+            .and_then(|dtype| dtype.to_descriptor())
+            .map_err(invalid)?;
+        match descriptor {
+            TypeDescriptor::Compound(_) => {
+                let parts = dataset.read_raw::<Parts>().map_err(invalid)?;
+                numeric_data(
+                    kind,
+                    &dims,
+                    parts.iter().map(|p| p.real).collect(),
+                    Some(parts.iter().map(|p| p.imag).collect()),
+                )
+            }
+            _ => numeric_data(
+                kind,
+                &dims,
+                dataset.read_raw::<f64>().map_err(invalid)?,
+                None,
+            ),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use ndarray::array;
+
+    use super::*;
+
+    fn round_trip(variables: &[(&str, &PortData)], compressed: bool) -> Vec<(String, PortData)> {
+        let mut bytes = vec![];
+        write(&mut bytes, variables, compressed).unwrap();
+        read(&mut bytes.as_slice()).unwrap()
+    }
+
+    #[test]
+    fn round_trips() {
+        let object = PortData::Object(
+            [
+                ("te".to_string(), PortData::Float(2.5)),
+                ("name".to_string(), PortData::String("t1 ✓".into())),
+                (
+                    "flags".to_string(),
+                    PortData::Array(ForayArray::Boolean(array![[true, false]].into_dyn())),
+                ),
+            ]
+            .into(),
+        );
+        let structs = PortData::Array(ForayArray::Object(
+            array![[object.clone()], [object.clone()]].into_dyn(),
+        ));
+        let cells = PortData::Array(ForayArray::Object(
+            array![[PortData::Integer(1), PortData::String("a".into())]].into_dyn(),
+        ));
+        let variables = [
+            ("i", PortData::Integer(-3)),
+            ("x", PortData::Float(0.5)),
+            ("z", PortData::Complex((1.0, -2.0))),
+            ("b", PortData::Boolean(true)),
+            ("s", PortData::String("hello".into())),
+            ("empty", PortData::String(String::new())),
+            (
+                "a",
+                PortData::Array(ForayArray::Float(ArrayD::from_shape_fn(
+                    vec![2, 3, 4],
+                    |i| (i[0] * 100 + i[1] * 10 + i[2]) as f64,
+                ))),
+            ),
+            (
+                "c",
+                PortData::Array(ForayArray::Complex(
+                    array![[Complex64::new(1.0, 2.0), Complex64::new(3.0, 4.0)]].into_dyn(),
+                )),
+            ),
+            (
+                "labels",
+                PortData::Array(ForayArray::String(
+                    array![["t1", "flair"]].mapv(String::from).into_dyn(),
+                )),
+            ),
+            ("header", object),
+            ("structs", structs),
+            ("cells", cells),
+        ];
+        let variables = variables
+            .iter()
+            .map(|(name, data)| (*name, data))
+            .collect::<Vec<_>>();
+        for compressed in [false, true] {
+            let read = round_trip(&variables, compressed);
+            assert_eq!(read.len(), variables.len());
+            for ((name, data), (read_name, read_data)) in variables.iter().zip(&read) {
+                assert_eq!(name, read_name);
+                // Strings in a string array are read back as cells
+                match (name, read_data) {
+                    (&"labels", PortData::Array(ForayArray::Object(cells))) => assert_eq!(
+                        cells,
+                        array![[
+                            PortData::String("t1".into()),
+                            PortData::String("flair".into())
+                        ]]
+                        .into_dyn()
+                    ),
+                    _ => assert_eq!(*data, read_data),
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn column_major() {
+        // [1 2 3; 4 5 6] as MATLAB stores it, with a name padded to 8 bytes
+        let mut matrix = vec![];
+        push_header(&mut matrix, class::DOUBLE, &[2, 3], "m").unwrap();
+        push_element(
+            &mut matrix,
+            mi::DOUBLE,
+            &[1.0f64, 4.0, 2.0, 5.0, 3.0, 6.0]
+                .iter()
+                .flat_map(|v| v.to_le_bytes())
+                .collect::<Vec<_>>(),
+        )
+        .unwrap();
+        let mut bytes = vec![];
+        write(&mut bytes, &[], false).unwrap();
+        push_element(&mut bytes, mi::MATRIX, &matrix).unwrap();
+        assert_eq!(
+            read(&mut bytes.as_slice()).unwrap(),
+            [(
+                "m".to_string(),
+                PortData::Array(ForayArray::Float(
+                    array![[1.0, 2.0, 3.0], [4.0, 5.0, 6.0]].into_dyn()
+                ))
+            )]
+        );
+
+        // ['ab'; 'cd'] is stored 'acbd', each row is a string
+        let mut matrix = vec![];
+        push_header(&mut matrix, class::CHAR, &[2, 2], "c").unwrap();
+        push_element(&mut matrix, mi::UTF8, b"acbd").unwrap();
+        assert_eq!(matrix_name(&matrix, false), Some("c".to_string()));
+        assert_eq!(
+            super::matrix(&matrix, false).unwrap().1,
+            PortData::Array(ForayArray::String(
+                array!["ab", "cd"].mapv(String::from).into_dyn()
+            ))
+        );
+    }
+
+    #[test]
+    fn names_and_invalid_files() {
+        let path = std::env::temp_dir().join(format!("foray_mat_{}.mat", std::process::id()));
+        let a = PortData::Float(1.0);
+        for compressed in [false, true] {
+            save(&path, &[("first", &a), ("second", &a)], compressed).unwrap();
+            assert_eq!(names(&path).unwrap(), ["first", "second"]);
+        }
+        std::fs::remove_file(&path).unwrap();
+
+        let mut bytes = vec![];
+        assert!(matches!(
+            write(&mut bytes, &[("2d", &a)], false),
+            Err(FormatError::Unsupported(_))
+        ));
+        assert!(matches!(
+            read(&mut b"not a mat file".as_slice()),
+            Err(FormatError::Invalid(_))
+        ));
+        let mut matrix = vec![];
+        push_header(&mut matrix, class::SPARSE, &[2, 2], "s").unwrap();
+        assert!(matches!(
+            super::matrix(&matrix, false),
+            Err(FormatError::Unsupported(_))
+        ));
+        let mut matrix = vec![];
+        push_header(&mut matrix, class::DOUBLE, &[1, 1], "big").unwrap();
+        push_element(&mut matrix, mi::INT64, &(1i64 << 40).to_le_bytes()).unwrap();
+        assert_eq!(
+            super::matrix(&matrix, false).unwrap().1,
+            PortData::Float((1i64 << 40) as f64)
+        );
+    }
+}
@@ -0,0 +1,810 @@
+//! Single file NIfTI-1 and NIfTI-2 images, `.nii`, optionally gzipped, `.nii.gz`: a header
+//! followed by the voxels, with the first axis varying fastest.
+//! See <https://nifti.nimh.nih.gov/nifti-1> and <https://nifti.nimh.nih.gov/nifti-2>
+
+use std::{
+    fs::File,
+    io::{BufReader, BufWriter, Read, Write},
+    path::Path,
+};
+
+use flate2::{Compression, read::GzDecoder, write::GzEncoder};
+use foray_data_model::node::ForayArray;
+use ndarray::ArrayD;
+use num_complex::Complex64;
+
+use crate::{complex, elements, err::FormatError, fortran_order_iter, from_memory_order, shape};
+
+const NIFTI1_HEADER_LEN: usize = 348;
+const NIFTI2_HEADER_LEN: usize = 540;
+const NIFTI2_MAGIC: &[u8] = b"n+2\0\r\n\x1a\n";
+/// Extensions flag after the header, voxels start after it
+const EXTENSION_LEN: usize = 4;
+
+/// NIfTI codes of the types voxels can be stored as
+pub mod datatype {
+    /// Not a type voxels are stored as, when writing the type is chosen from the array
+    pub const UNKNOWN: i16 = 0;
+    pub const UINT8: i16 = 2;
+    pub const INT16: i16 = 4;
+    pub const INT32: i16 = 8;
+    pub const FLOAT32: i16 = 16;
+    pub const COMPLEX64: i16 = 32;
+    pub const FLOAT64: i16 = 64;
+    pub const INT8: i16 = 256;
+    pub const UINT16: i16 = 512;
+    pub const UINT32: i16 = 768;
+    pub const INT64: i16 = 1024;
+    pub const UINT64: i16 = 1280;
+    pub const COMPLEX128: i16 = 1792;
+
+    /// Datatypes by their numpy style names
+    pub const NAMES: [(i16, &str); 12] = [
+        (UINT8, "uint8"),
+        (INT16, "int16"),
+        (INT32, "int32"),
+        (FLOAT32, "float32"),
+        (COMPLEX64, "complex64"),
+        (FLOAT64, "float64"),
+        (INT8, "int8"),
+        (UINT16, "uint16"),
+        (UINT32, "uint32"),
+        (INT64, "int64"),
+        (UINT64, "uint64"),
+        (COMPLEX128, "complex128"),
+    ];
+
+    pub fn name(datatype: i16) -> Option<&'static str> {
+        NAMES.iter().find(|(d, _)| *d == datatype).map(|(_, n)| *n)
+    }
+
+    pub fn from_name(name: &str) -> Option<i16> {
+        NAMES.iter().find(|(_, n)| *n == name).map(|(d, _)| *d)
+    }
+}
+
+/// Fields of a NIfTI header that describe the image, others are left empty when writing.
+/// NIfTI-1 stores the floating point fields as `f32`
+#[derive(Clone, Debug, PartialEq)]
+pub struct Header {
+    /// 1 or 2, written as NIfTI-2 if the shape doesn't fit NIfTI-1
+    pub version: u8,
+    /// Length of each axis, at most 7
+    pub shape: Vec<usize>,
+    pub datatype: i16,
+    /// `pixdim[0]` is the qfac of the qform, `pixdim[1..]` are the voxel sizes
+    pub pixdim: [f64; 8],
+    pub vox_offset: u64,
+    /// Voxel values are scaled by `value * scl_slope + scl_inter`, unless `scl_slope` is 0
+    pub scl_slope: f64,
+    pub scl_inter: f64,
+    pub xyzt_units: u8,
+    pub description: String,
+    pub qform_code: i16,
+    pub sform_code: i16,
+    /// `quatern_b`, `quatern_c`, `quatern_d`
+    pub quatern: [f64; 3],
+    /// `qoffset_x`, `qoffset_y`, `qoffset_z`
+    pub qoffset: [f64; 3],
+    /// `srow_x`, `srow_y`, `srow_z`, the rows of the affine from voxel to world coordinates
+    pub srow: [[f64; 4]; 3],
+    /// The header and voxels were stored big endian
+    pub big_endian: bool,
+}
+
+impl Header {
+    /// Header for an image of voxels of `datatype`, with unit voxel sizes and no orientation
+    pub fn new(shape: &[usize], datatype: i16) -> Self {
+        Header {
+            version: 1,
+            shape: shape.to_vec(),
+            datatype,
+            pixdim: [1.0; 8],
+            vox_offset: (NIFTI1_HEADER_LEN + EXTENSION_LEN) as u64,
+            scl_slope: 0.0,
+            scl_inter: 0.0,
+            xyzt_units: 0,
+            description: String::new(),
+            qform_code: 0,
+            sform_code: 0,
+            quatern: [0.0; 3],
+            qoffset: [0.0; 3],
+            srow: [
+                [1.0, 0.0, 0.0, 0.0],
+                [0.0, 1.0, 0.0, 0.0],
+                [0.0, 0.0, 1.0, 0.0],
+            ],
+            big_endian: false,
+        }
+    }
+
+    /// Size of a voxel in bytes
+    fn voxel_size(&self) -> Result<usize, FormatError> {
+        voxel_size(self.datatype)
+    }
+
+    /// Size of all voxels in bytes, checked, since the shape comes from the file
+    fn voxels_len(&self) -> Result<usize, FormatError> {
+        self.shape
+            .iter()
+            .try_fold(self.voxel_size()?, |len, length| len.checked_mul(*length))
+            .ok_or_else(|| {
+                FormatError::Invalid(format!("{:?} voxels don't fit in memory", self.shape))
+            })
+    }
+
+    fn has_scaling(&self) -> bool {
+        self.scl_slope != 0.0 && (self.scl_slope != 1.0 || self.scl_inter != 0.0)
+    }
+
+    /// Voxel sizes of the image's axes
+    pub fn spacing(&self) -> Vec<f64> {
+        self.pixdim[1..=self.shape.len().min(7)].to_vec()
+    }
+
+    /// Affine from voxel indices to world coordinates, from the sform if it's set, otherwise
+    /// from the qform if it's set, otherwise only scaling by the voxel sizes
+    pub fn affine(&self) -> [[f64; 4]; 4] {
+        let last_row = [0.0, 0.0, 0.0, 1.0];
+        if self.sform_code > 0 {
+            return [self.srow[0], self.srow[1], self.srow[2], last_row];
+        }
+        let [dx, dy, dz] = [1, 2, 3].map(|i| match self.pixdim[i] {
+            0.0 => 1.0,
+            d => d.abs(),
+        });
+        if self.qform_code <= 0 {
+            return [
+                [dx, 0.0, 0.0, 0.0],
+                [0.0, dy, 0.0, 0.0],
+                [0.0, 0.0, dz, 0.0],
+                last_row,
+            ];
+        }
+        let [b, c, d] = self.quatern;
+        let a = (1.0 - (b * b + c * c + d * d)).max(0.0).sqrt();
+        let qfac = if self.pixdim[0] < 0.0 { -1.0 } else { 1.0 };
+        let rotation = [
+            [
+                a * a + b * b - c * c - d * d,
+                2.0 * (b * c - a * d),
+                2.0 * (b * d + a * c),
+            ],
+            [
+                2.0 * (b * c + a * d),
+                a * a + c * c - b * b - d * d,
+                2.0 * (c * d - a * b),
+            ],
+            [
+                2.0 * (b * d - a * c),
+                2.0 * (c * d + a * b),
+                a * a + d * d - c * c - b * b,
+            ],
+        ];
+        let scale = [dx, dy, dz * qfac];
+        let row = |i: usize| {
+            let r: [f64; 3] = rotation[i];
+            [
+                r[0] * scale[0],
+                r[1] * scale[1],
+                r[2] * scale[2],
+                self.qoffset[i],
+            ]
+        };
+        [row(0), row(1), row(2), last_row]
+    }
+
+    /// Set the sform to `affine`, and the voxel sizes to the lengths of its columns.
+    /// The qform is cleared, since it can't represent every affine
+    pub fn set_affine(&mut self, affine: [[f64; 4]; 4]) {
+        self.srow = [affine[0], affine[1], affine[2]];
+        if self.sform_code <= 0 {
+            // Aligned to something
+            self.sform_code = 2;
+        }
+        self.qform_code = 0;
+        for (axis, pixdim) in self.pixdim[1..4].iter_mut().enumerate() {
+            *pixdim = (0..3)
+                .map(|row| affine[row][axis].powi(2))
+                .sum::<f64>()
+                .sqrt();
+        }
+    }
+}
+
+fn voxel_size(datatype: i16) -> Result<usize, FormatError> {
+    use datatype::*;
+    match datatype {
+        UINT8 | INT8 => Ok(1),
+        INT16 | UINT16 => Ok(2),
+        INT32 | UINT32 | FLOAT32 => Ok(4),
+        INT64 | UINT64 | FLOAT64 | COMPLEX64 => Ok(8),
+        COMPLEX128 => Ok(16),
+        datatype => Err(FormatError::Unsupported(format!(
+            "NIfTI datatype {datatype}"
+        ))),
+    }
+}
+
+/// Fixed offset fields of a header
+struct Fields<'a> {
+    bytes: &'a [u8],
+    big_endian: bool,
+}
+
+impl Fields<'_> {
+    fn get<const N: usize>(&self, offset: usize) -> [u8; N] {
+        let mut field: [u8; N] = self.bytes[offset..offset + N].try_into().expect("N bytes");
+        if self.big_endian {
+            field.reverse();
+        }
+        field
+    }
+    fn i16(&self, offset: usize) -> i16 {
+        i16::from_le_bytes(self.get(offset))
+    }
+    fn i32(&self, offset: usize) -> i32 {
+        i32::from_le_bytes(self.get(offset))
+    }
+    fn i64(&self, offset: usize) -> i64 {
+        i64::from_le_bytes(self.get(offset))
+    }
+    fn f32(&self, offset: usize) -> f64 {
+        f32::from_le_bytes(self.get(offset)) as f64
+    }
+    fn f64(&self, offset: usize) -> f64 {
+        f64::from_le_bytes(self.get(offset))
+    }
+    fn f32s<const N: usize>(&self, offset: usize) -> [f64; N] {
+        std::array::from_fn(|i| self.f32(offset + 4 * i))
+    }
+    fn f64s<const N: usize>(&self, offset: usize) -> [f64; N] {
+        std::array::from_fn(|i| self.f64(offset + 8 * i))
+    }
+    fn string(&self, offset: usize, len: usize) -> String {
+        String::from_utf8_lossy(&self.bytes[offset..offset + len])
+            .trim_end_matches('\0')
+            .to_string()
+    }
+}
+
+/// Axis lengths from the `dim` field, whose first element is the number of axes
+fn parse_shape(dim: [i64; 8]) -> Result<Vec<usize>, FormatError> {
+    let ndim = dim[0];
+    if !(1..=7).contains(&ndim) {
+        return Err(FormatError::Invalid(format!("{ndim} dimensions")));
+    }
+    dim[1..=ndim as usize]
+        .iter()
+        .map(|length| {
+            usize::try_from(*length)
+                .map_err(|_| FormatError::Invalid(format!("axis of length {length}")))
+        })
+        .collect()
+}
+
+/// Read the header of a `.nii` file, leaving `reader` at the start of the voxels
+pub fn read_header(reader: &mut impl Read) -> Result<Header, FormatError> {
+    let mut sizeof_hdr = [0; 4];
+    reader.read_exact(&mut sizeof_hdr)?;
+    let sizeof_hdr = i32::from_le_bytes(sizeof_hdr);
+    let (len, big_endian) = [NIFTI1_HEADER_LEN, NIFTI2_HEADER_LEN]
+        .into_iter()
+        .find_map(|len| match sizeof_hdr {
+            s if s == len as i32 => Some((len, false)),
+            s if s.swap_bytes() == len as i32 => Some((len, true)),
+            _ => None,
+        })
+        .ok_or_else(|| FormatError::Invalid("not a NIfTI file".to_string()))?;
+    let mut bytes = vec![0; len];
+    reader.read_exact(&mut bytes[4..])?;
+    let fields = Fields {
+        bytes: &bytes,
+        big_endian,
+    };
+
+    let header = match len {
+        NIFTI1_HEADER_LEN => {
+            match &bytes[344..348] {
+                b"n+1\0" => {}
+                b"ni1\0" => {
+                    return Err(FormatError::Unsupported(
+                        "NIfTI images split into .hdr and .img files".to_string(),
+                    ));
+                }
+                _ => return Err(FormatError::Invalid("not a NIfTI-1 file".to_string())),
+            }
+            Header {
+                version: 1,
+                shape: parse_shape(std::array::from_fn(|i| fields.i16(40 + 2 * i) as i64))?,
+                datatype: fields.i16(70),
+                pixdim: fields.f32s(76),
+                vox_offset: fields.f32(108) as u64,
+                scl_slope: fields.f32(112),
+                scl_inter: fields.f32(116),
+                xyzt_units: bytes[123],
+                description: fields.string(148, 80),
+                qform_code: fields.i16(252),
+                sform_code: fields.i16(254),
+                quatern: fields.f32s(256),
+                qoffset: fields.f32s(268),
+                srow: [fields.f32s(280), fields.f32s(296), fields.f32s(312)],
+                big_endian,
+            }
+        }
+        _ => {
+            match &bytes[4..12] {
+                NIFTI2_MAGIC => {}
+                b"ni2\0\r\n\x1a\n" => {
+                    return Err(FormatError::Unsupported(
+                        "NIfTI images split into .hdr and .img files".to_string(),
+                    ));
+                }
+                _ => return Err(FormatError::Invalid("not a NIfTI-2 file".to_string())),
+            }
+            Header {
+                version: 2,
+                shape: parse_shape(std::array::from_fn(|i| fields.i64(16 + 8 * i)))?,
+                datatype: fields.i16(12),
+                pixdim: fields.f64s(104),
+                vox_offset: u64::try_from(fields.i64(168))
+                    .map_err(|_| FormatError::Invalid("negative vox_offset".to_string()))?,
+                scl_slope: fields.f64(176),
+                scl_inter: fields.f64(184),
+                xyzt_units: fields.i32(500) as u8,
+                description: fields.string(240, 80),
+                qform_code: fields.i32(344) as i16,
+                sform_code: fields.i32(348) as i16,
+                quatern: fields.f64s(352),
+                qoffset: fields.f64s(376),
+                srow: [fields.f64s(400), fields.f64s(432), fields.f64s(464)],
+                big_endian,
+            }
+        }
+    };
+
+    // Skip extensions
+    let skip = (header.vox_offset as usize).saturating_sub(len);
+    std::io::copy(&mut reader.take(skip as u64), &mut std::io::sink())?;
+    Ok(header)
+}
+
+/// Write a header, as NIfTI-2 if its version is 2 or the shape doesn't fit NIfTI-1
+pub fn write_header(writer: &mut impl Write, header: &Header) -> Result<(), FormatError> {
+    if header.shape.len() > 7 {
+        return Err(FormatError::Unsupported(format!(
+            "{} dimensions, NIfTI images have at most 7",
+            header.shape.len()
+        )));
+    }
+    // A 0 dimensional array is stored as a single voxel
+    let shape = match header.shape.as_slice() {
+        [] => &[1][..],
+        shape => shape,
+    };
+    let fits_nifti1 = shape.iter().all(|length| *length <= i16::MAX as usize);
+    let description = header.description.as_bytes();
+    let description = &description[..description.len().min(79)];
+    let bitpix = (voxel_size(header.datatype)? * 8) as i16;
+
+    fn put(bytes: &mut [u8], offset: usize, field: &[u8]) {
+        bytes[offset..offset + field.len()].copy_from_slice(field)
+    }
+    let mut bytes;
+    if header.version < 2 && fits_nifti1 {
+        bytes = vec![0; NIFTI1_HEADER_LEN + EXTENSION_LEN];
+        put(&mut bytes, 0, &(NIFTI1_HEADER_LEN as i32).to_le_bytes());
+        put(&mut bytes, 40, &(shape.len() as i16).to_le_bytes());
+        for i in 1..8 {
+            let length = shape.get(i - 1).copied().unwrap_or(1) as i16;
+            put(&mut bytes, 40 + 2 * i, &length.to_le_bytes());
+        }
+        put(&mut bytes, 70, &header.datatype.to_le_bytes());
+        put(&mut bytes, 72, &bitpix.to_le_bytes());
+        let f32s = |values: &[f64]| -> Vec<u8> {
+            values
+                .iter()
+                .flat_map(|v| (*v as f32).to_le_bytes())
+                .collect()
+        };
+        put(&mut bytes, 76, &f32s(&header.pixdim));
+        let vox_offset = (NIFTI1_HEADER_LEN + EXTENSION_LEN) as f64;
+        put(&mut bytes, 108, &f32s(&[vox_offset]));
+        put(
+            &mut bytes,
+            112,
+            &f32s(&[header.scl_slope, header.scl_inter]),
+        );
+        put(&mut bytes, 123, &[header.xyzt_units]);
+        put(&mut bytes, 148, description);
+        put(&mut bytes, 252, &header.qform_code.to_le_bytes());
+        put(&mut bytes, 254, &header.sform_code.to_le_bytes());
+        put(&mut bytes, 256, &f32s(&header.quatern));
+        put(&mut bytes, 268, &f32s(&header.qoffset));
+        put(&mut bytes, 280, &f32s(header.srow.as_flattened()));
+        put(&mut bytes, 344, b"n+1\0");
+    } else {
+        bytes = vec![0; NIFTI2_HEADER_LEN + EXTENSION_LEN];
+        let f64s =
+            |values: &[f64]| -> Vec<u8> { values.iter().flat_map(|v| v.to_le_bytes()).collect() };
+        put(&mut bytes, 0, &(NIFTI2_HEADER_LEN as i32).to_le_bytes());
+        put(&mut bytes, 4, NIFTI2_MAGIC);
+        put(&mut bytes, 12, &header.datatype.to_le_bytes());
+        put(&mut bytes, 14, &bitpix.to_le_bytes());
+        put(&mut bytes, 16, &(shape.len() as i64).to_le_bytes());
+        for i in 1..8 {
+            let length = shape.get(i - 1).copied().unwrap_or(1) as i64;
+            put(&mut bytes, 16 + 8 * i, &length.to_le_bytes());
+        }
+        put(&mut bytes, 104, &f64s(&header.pixdim));
+        let vox_offset = (NIFTI2_HEADER_LEN + EXTENSION_LEN) as i64;
+        put(&mut bytes, 168, &vox_offset.to_le_bytes());
+        put(
+            &mut bytes,
+            176,
+            &f64s(&[header.scl_slope, header.scl_inter]),
+        );
+        put(&mut bytes, 240, description);
+        put(&mut bytes, 344, &(header.qform_code as i32).to_le_bytes());
+        put(&mut bytes, 348, &(header.sform_code as i32).to_le_bytes());
+        put(&mut bytes, 352, &f64s(&header.quatern));
+        put(&mut bytes, 376, &f64s(&header.qoffset));
+        put(&mut bytes, 400, &f64s(header.srow.as_flattened()));
+        put(&mut bytes, 500, &(header.xyzt_units as i32).to_le_bytes());
+    }
+    writer.write_all(&bytes)?;
+    Ok(())
+}
+/// Decode the voxels of an image described by `header`.
+/// Scaled integers are converted to floats
+pub fn decode(header: &Header, bytes: &[u8]) -> Result<ForayArray, FormatError> {
+    use datatype::*;
+    let len = header.voxels_len()?;
+    if bytes.len() < len {
+        return Err(FormatError::Invalid(format!(
+            "expected {len} bytes of voxels, found {}",
+            bytes.len()
+        )));
+    }
+    let bytes = &bytes[..len];
+    let be = header.big_endian;
+
+    let values: Vec<f64> = match header.datatype {
+        COMPLEX64 | COMPLEX128 => {
+            let parts = match header.datatype {
+                COMPLEX64 => elements(bytes, be, |b| f32::from_le_bytes(b) as f64),
+                _ => elements(bytes, be, f64::from_le_bytes),
+            };
+            let mut voxels = from_memory_order(&header.shape, complex(parts), true)?;
+            if header.has_scaling() {
+                voxels.mapv_inplace(|v| v * header.scl_slope + header.scl_inter);
+            }
+            return Ok(ForayArray::Complex(voxels));
+        }
+        UINT8 => elements(bytes, be, |b: [u8; 1]| b[0] as f64),
+        INT8 => elements(bytes, be, |b| i8::from_le_bytes(b) as f64),
+        INT16 => elements(bytes, be, |b| i16::from_le_bytes(b) as f64),
+        UINT16 => elements(bytes, be, |b| u16::from_le_bytes(b) as f64),
+        INT32 => elements(bytes, be, |b| i32::from_le_bytes(b) as f64),
+        UINT32 => elements(bytes, be, |b| u32::from_le_bytes(b) as f64),
+        INT64 => elements(bytes, be, |b| i64::from_le_bytes(b) as f64),
+        UINT64 => elements(bytes, be, |b| u64::from_le_bytes(b) as f64),
+        FLOAT32 => elements(bytes, be, |b| f32::from_le_bytes(b) as f64),
+        FLOAT64 => elements(bytes, be, f64::from_le_bytes),
+        datatype => {
+            return Err(FormatError::Unsupported(format!(
+                "NIfTI datatype {datatype}"
+            )));
+        }
+    };
+    let is_integer = !matches!(header.datatype, FLOAT32 | FLOAT64);
+    let fits_integer = values
+        .iter()
+        .all(|v| *v >= i32::MIN as f64 && *v <= i32::MAX as f64);
+    if is_integer && !header.has_scaling() && fits_integer {
+        let values = values.into_iter().map(|v| v as i32).collect();
+        return Ok(ForayArray::Integer(from_memory_order(
+            &header.shape,
+            values,
+            true,
+        )?));
+    }
+    let mut voxels = from_memory_order(&header.shape, values, true)?;
+    if header.has_scaling() {
+        voxels.mapv_inplace(|v| v * header.scl_slope + header.scl_inter);
+    }
+    Ok(ForayArray::Float(voxels))
+}
+
+pub fn read(reader: &mut impl Read) -> Result<(Header, ForayArray), FormatError> {
+    let header = read_header(reader)?;
+    // Only as many bytes as the file has are allocated, a short file is reported by `decode`
+    let mut bytes = vec![];
+    reader
+        .take(header.voxels_len()? as u64)
+        .read_to_end(&mut bytes)?;
+    let voxels = decode(&header, &bytes)?;
+    Ok((header, voxels))
+}
+
+/// Datatype voxels of `array` are saved as, unless the header asks for another
+pub fn array_datatype(array: &ForayArray) -> Result<i16, FormatError> {
+    match array {
+        ForayArray::Integer(_) => Ok(datatype::INT32),
+        ForayArray::Float(_) => Ok(datatype::FLOAT64),
+        ForayArray::Complex(_) => Ok(datatype::COMPLEX128),
+        ForayArray::Boolean(_) => Ok(datatype::UINT8),
+        ForayArray::String(_) | ForayArray::Object(_) => Err(FormatError::Unsupported(
+            "arrays of strings or objects can't be saved as NIfTI".to_string(),
+        )),
+    }
+}
+
+/// Whether voxels of `datatype` can hold the elements of `array`.
+/// Floats can be stored as `float32`, losing precision
+fn stores(datatype: i16, array: &ForayArray) -> bool {
+    use datatype::*;
+    let range = match datatype {
+        UINT8 => Some((u8::MIN as i64, u8::MAX as i64)),
+        INT8 => Some((i8::MIN as i64, i8::MAX as i64)),
+        INT16 => Some((i16::MIN as i64, i16::MAX as i64)),
+        UINT16 => Some((u16::MIN as i64, u16::MAX as i64)),
+        INT32 | INT64 => Some((i32::MIN as i64, i32::MAX as i64)),
+        UINT32 | UINT64 => Some((0, i32::MAX as i64)),
+        _ => None,
+    };
+    if let (ForayArray::Integer(a), Some((min, max))) = (array, range) {
+        return a.iter().all(|v| (min..=max).contains(&(*v as i64)));
+    }
+    matches!(
+        (array, datatype),
+        (
+            ForayArray::Integer(_) | ForayArray::Float(_),
+            FLOAT32 | FLOAT64
+        ) | (ForayArray::Complex(_), COMPLEX64 | COMPLEX128)
+            | (ForayArray::Boolean(_), UINT8 | INT8)
+    )
+}
+
+/// Write `array` with `header`, whose shape is replaced by the array's.
+/// Voxels are stored as the header's datatype if it can hold the array's elements, so that a
+/// read image is written back with the type it had, otherwise as [`array_datatype`]
+pub fn write(
+    writer: &mut impl Write,
+    header: &Header,
+    array: &ForayArray,
+) -> Result<(), FormatError> {
+    use datatype::*;
+    let datatype = match stores(header.datatype, array) {
+        true => header.datatype,
+        false => array_datatype(array)?,
+    };
+    let header = Header {
+        shape: shape(array).to_vec(),
+        datatype,
+        scl_slope: 0.0,
+        scl_inter: 0.0,
+        ..header.clone()
+    };
+    write_header(writer, &header)?;
+
+    fn voxels<A, const N: usize>(
+        writer: &mut impl Write,
+        array: &ArrayD<A>,
+        to_le_bytes: impl Fn(&A) -> [u8; N],
+    ) -> std::io::Result<()> {
+        fortran_order_iter(array).try_for_each(|v| writer.write_all(&to_le_bytes(v)))
+    }
+    if let ForayArray::Complex(a) = array {
+        match datatype {
+            COMPLEX64 => voxels(writer, a, |v: &Complex64| {
+                let mut bytes = [0; 8];
+                bytes[..4].copy_from_slice(&(v.re as f32).to_le_bytes());
+                bytes[4..].copy_from_slice(&(v.im as f32).to_le_bytes());
+                bytes
+            }),
+            _ => voxels(writer, a, |v: &Complex64| {
+                let mut bytes = [0; 16];
+                bytes[..8].copy_from_slice(&v.re.to_le_bytes());
+                bytes[8..].copy_from_slice(&v.im.to_le_bytes());
+                bytes
+            }),
+        }?;
+        return Ok(());
+    }
+    // Integers and booleans are exact as floats, and were checked to fit the datatype
+    let values: ArrayD<f64> = match array {
+        ForayArray::Integer(a) => a.mapv(f64::from),
+        ForayArray::Float(a) => a.clone(),
+        ForayArray::Boolean(a) => a.mapv(|v| v as u8 as f64),
+        _ => unreachable!("checked by array_datatype"),
+    };
+    match datatype {
+        UINT8 => voxels(writer, &values, |v| [*v as u8]),
+        INT8 => voxels(writer, &values, |v| (*v as i8).to_le_bytes()),
+        INT16 => voxels(writer, &values, |v| (*v as i16).to_le_bytes()),
+        UINT16 => voxels(writer, &values, |v| (*v as u16).to_le_bytes()),
+        INT32 => voxels(writer, &values, |v| (*v as i32).to_le_bytes()),
+        UINT32 => voxels(writer, &values, |v| (*v as u32).to_le_bytes()),
+        INT64 => voxels(writer, &values, |v| (*v as i64).to_le_bytes()),
+        UINT64 => voxels(writer, &values, |v| (*v as u64).to_le_bytes()),
+        FLOAT32 => voxels(writer, &values, |v| (*v as f32).to_le_bytes()),
+        _ => voxels(writer, &values, |v| v.to_le_bytes()),
+    }?;
+    Ok(())
+}
+
+fn is_gzip(path: &Path) -> bool {
+    path.extension().is_some_and(|ext| ext == "gz")
+}
+
+/// Read a `.nii` file, or a gzipped `.nii.gz` file
+pub fn load(path: &Path) -> Result<(Header, ForayArray), FormatError> {
+    let file = BufReader::new(File::open(path)?);
+    match is_gzip(path) {
+        true => read(&mut GzDecoder::new(file)),
+        false => read(&mut { file }),
+    }
+}
+
+/// Write a `.nii` file, gzipped if the path ends with `.gz`
+pub fn save(path: &Path, header: &Header, array: &ForayArray) -> Result<(), FormatError> {
+    let file = BufWriter::new(File::create(path)?);
+    match is_gzip(path) {
+        true => {
+            let mut writer = GzEncoder::new(file, Compression::default());
+            write(&mut writer, header, array)?;
+            writer.finish()?.flush()?;
+        }
+        false => {
+            let mut writer = file;
+            write(&mut writer, header, array)?;
+            writer.flush()?;
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use ndarray::array;
+
+    use super::*;
+
+    fn round_trip(array: ForayArray) -> (Header, ForayArray) {
+        let mut bytes = vec![];
+        write(&mut bytes, &Header::new(&[], 0), &array).unwrap();
+        assert_eq!(bytes.len() % 4, 0);
+        read(&mut bytes.as_slice()).unwrap()
+    }
+
+    #[test]
+    fn round_trips() {
+        let a = ForayArray::Float(
+            array![[[1.0, 2.0], [3.0, 4.0]], [[5.0, 6.0], [7.0, 8.0]]].into_dyn(),
+        );
+        assert_eq!(round_trip(a.clone()).1, a);
+        let a = ForayArray::Integer(array![[1, 2, 3], [4, 5, 6]].into_dyn());
+        let (header, voxels) = round_trip(a.clone());
+        assert_eq!(voxels, a);
+        assert_eq!(header.shape, vec![2, 3]);
+        assert_eq!(header.datatype, datatype::INT32);
+        let a = ForayArray::Complex(array![Complex64::new(1.0, -1.0)].into_dyn());
+        assert_eq!(round_trip(a.clone()).1, a);
+    }
+
+    #[test]
+    fn scaling_and_voxel_order() {
+        let mut header = Header::new(&[2, 2], datatype::INT16);
+        header.scl_slope = 0.5;
+        header.scl_inter = 1.0;
+        let mut bytes = vec![];
+        write_header(&mut bytes, &header).unwrap();
+        // The first axis varies fastest
+        for v in [0i16, 2, 4, 6] {
+            bytes.extend(v.to_le_bytes());
+        }
+        let (header, voxels) = read(&mut bytes.as_slice()).unwrap();
+        assert_eq!(header.scl_slope, 0.5);
+        assert_eq!(
+            voxels,
+            ForayArray::Float(array![[1.0, 3.0], [2.0, 4.0]].into_dyn())
+        );
+    }
+
+    #[test]
+    fn invalid_shapes() {
+        let read_shape = |shape: &[usize]| {
+            let mut bytes = vec![];
+            write_header(&mut bytes, &Header::new(shape, datatype::FLOAT64)).unwrap();
+            read(&mut bytes.as_slice()).unwrap_err()
+        };
+        assert_eq!(
+            read_shape(&[1 << 40, 1 << 40]),
+            FormatError::Invalid(
+                "[1099511627776, 1099511627776] voxels don't fit in memory".into()
+            )
+        );
+        assert_eq!(
+            read_shape(&[1 << 40]),
+            FormatError::Invalid("expected 8796093022208 bytes of voxels, found 0".into())
+        );
+        let mut header = vec![];
+        write_header(&mut header, &Header::new(&[1 << 40], datatype::FLOAT64)).unwrap();
+        // A negative NIfTI-2 axis length
+        header[24..32].copy_from_slice(&(-2i64).to_le_bytes());
+        assert_eq!(
+            read_header(&mut header.as_slice()).unwrap_err(),
+            FormatError::Invalid("axis of length -2".into())
+        );
+    }
+
+    #[test]
+    fn datatype_is_kept() {
+        let a = ForayArray::Integer(array![[1, -2], [300, 4]].into_dyn());
+        let mut bytes = vec![];
+        write(&mut bytes, &Header::new(&[], datatype::INT16), &a).unwrap();
+        let (header, voxels) = read(&mut bytes.as_slice()).unwrap();
+        assert_eq!(header.datatype, datatype::INT16);
+        assert_eq!(voxels, a);
+
+        // Values that don't fit are stored as the array's type
+        let mut bytes = vec![];
+        write(&mut bytes, &Header::new(&[], datatype::UINT8), &a).unwrap();
+        assert_eq!(
+            read_header(&mut bytes.as_slice()).unwrap().datatype,
+            datatype::INT32
+        );
+    }
+
+    #[test]
+    fn nifti2() {
+        let a = ForayArray::Float(array![[1.0, 2.0], [3.0, 4.0]].into_dyn());
+        let mut header = Header::new(&[], datatype::FLOAT32);
+        header.version = 2;
+        header.pixdim = [1.0, 0.5, 2.0, 1.0, 1.0, 1.0, 1.0, 1.0];
+        header.description = "nifti 2".to_string();
+        let mut bytes = vec![];
+        write(&mut bytes, &header, &a).unwrap();
+        assert_eq!(&bytes[4..12], NIFTI2_MAGIC);
+        let (read_header, voxels) = read(&mut bytes.as_slice()).unwrap();
+        assert_eq!(voxels, a);
+        assert_eq!(
+            read_header,
+            Header {
+                shape: vec![2, 2],
+                vox_offset: 544,
+                ..header
+            }
+        );
+    }
+
+    #[test]
+    fn affines() {
+        let mut header = Header::new(&[2, 2, 2], datatype::FLOAT64);
+        header.pixdim = [-1.0, 2.0, 3.0, 4.0, 1.0, 1.0, 1.0, 1.0];
+        assert_eq!(header.affine()[1], [0.0, 3.0, 0.0, 0.0]);
+        // A rotation of 180 degrees around z, with a flipped third axis
+        header.qform_code = 1;
+        header.quatern = [0.0, 0.0, 1.0];
+        header.qoffset = [10.0, 20.0, 30.0];
+        assert_eq!(
+            header.affine(),
+            [
+                [-2.0, 0.0, 0.0, 10.0],
+                [0.0, -3.0, 0.0, 20.0],
+                [0.0, 0.0, -4.0, 30.0],
+                [0.0, 0.0, 0.0, 1.0],
+            ]
+        );
+        let affine = [
+            [0.0, 2.0, 0.0, 1.0],
+            [-3.0, 0.0, 0.0, 2.0],
+            [0.0, 0.0, 4.0, 3.0],
+            [0.0, 0.0, 0.0, 1.0],
+        ];
+        header.set_affine(affine);
+        assert_eq!(header.affine(), affine);
+        assert_eq!(header.spacing(), vec![3.0, 2.0, 4.0]);
+    }
+}
@@ -0,0 +1,619 @@
+//! numpy's `.npy` format: a short text header describing the array, followed by its elements.
+//! See <https://numpy.org/doc/stable/reference/generated/numpy.lib.format.html>
+
+use std::{
+    fs::File,
+    io::{BufReader, BufWriter, Read, Seek, Write},
+    path::Path,
+};
+
+use crate::{complex, elements, err::FormatError, from_memory_order, shape};
+use foray_data_model::node::ForayArray;
+use memmap2::Mmap;
+use ndarray::{ArrayView, ArrayViewD, IxDyn, ShapeBuilder};
+
+const MAGIC: &[u8] = b"\x93NUMPY";
+
+/// Type of an array's elements, parsed from the `descr` of its header, e.g. `<f8`
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Dtype {
+    /// numpy's kind character, e.g. `f` for floating point
+    pub kind: char,
+    /// Size of an element in bytes, or in characters for unicode strings
+    pub size: usize,
+    pub big_endian: bool,
+}
+
+impl Dtype {
+    fn parse(descr: &str) -> Result<Self, FormatError> {
+        let unsupported = || FormatError::Unsupported(format!("element type '{descr}'"));
+        let mut chars = descr.chars();
+        let big_endian = match chars.next() {
+            Some('>') => true,
+            Some('<' | '|' | '=') => cfg!(target_endian = "big"),
+            _ => return Err(unsupported()),
+        };
+        let kind = chars.next().ok_or_else(unsupported)?;
+        let size = chars.as_str().parse().map_err(|_| unsupported())?;
+        Ok(Dtype {
+            kind,
+            size,
+            big_endian,
+        })
+    }
+
+    /// The little endian type numpy uses for the elements of `array`.
+    /// Strings are stored with the length of the longest
+    fn of(array: &ForayArray) -> Result<Self, FormatError> {
+        let (kind, size) = match array {
+            ForayArray::Integer(_) => ('i', 4),
+            ForayArray::Float(_) => ('f', 8),
+            ForayArray::Complex(_) => ('c', 16),
+            ForayArray::Boolean(_) => ('b', 1),
+            ForayArray::String(a) => (
+                'U',
+                a.iter()
+                    .map(|s| s.chars().count())
+                    .max()
+                    .unwrap_or(0)
+                    .max(1),
+            ),
+            ForayArray::Object(_) => {
+                return Err(FormatError::Unsupported(
+                    "arrays of objects can't be saved as .npy".to_string(),
+                ));
+            }
+        };
+        Ok(Dtype {
+            kind,
+            size,
+            big_endian: false,
+        })
+    }
+
+    /// numpy's description of the type, e.g. `<f8`
+    fn descr(&self) -> String {
+        let byte_order = match (self.itemsize(), self.big_endian) {
+            (1, _) => '|',
+            (_, true) => '>',
+            (_, false) => '<',
+        };
+        format!("{byte_order}{}{}", self.kind, self.size)
+    }
+
+    /// Size of an element in bytes
+    pub fn itemsize(&self) -> usize {
+        match self.kind {
+            // UTF-32
+            'U' => self.size * 4,
+            _ => self.size,
+        }
+    }
+}
+
+/// Description of the array stored in a `.npy` file
+#[derive(Clone, Debug, PartialEq)]
+pub struct Header {
+    pub dtype: Dtype,
+    /// Elements are stored with the first axis varying fastest
+    pub fortran_order: bool,
+    pub shape: Vec<usize>,
+}
+
+impl Header {
+    /// Header of `array` as it's written, in C order
+    pub fn of(array: &ForayArray) -> Result<Self, FormatError> {
+        Ok(Header {
+            dtype: Dtype::of(array)?,
+            fortran_order: false,
+            shape: shape(array).to_vec(),
+        })
+    }
+
+    /// Number of bytes taken by the array's elements, checked, since the shape comes from the file
+    pub fn data_len(&self) -> Result<usize, FormatError> {
+        self.shape
+            .iter()
+            .try_fold(self.dtype.itemsize(), |len, length| {
+                len.checked_mul(*length)
+            })
+            .ok_or_else(|| {
+                FormatError::Invalid(format!(
+                    "an array of shape {:?} doesn't fit in memory",
+                    self.shape
+                ))
+            })
+    }
+}
+
+/// Read the header of a `.npy` file, leaving `reader` at the start of the array's elements
+pub fn read_header(reader: &mut impl Read) -> Result<Header, FormatError> {
+    let mut magic = [0; 8];
+    reader.read_exact(&mut magic)?;
+    if &magic[..6] != MAGIC {
+        return Err(FormatError::Invalid("not a .npy file".to_string()));
+    }
+    let header_len = match magic[6] {
+        1 => {
+            let mut len = [0; 2];
+            reader.read_exact(&mut len)?;
+            u16::from_le_bytes(len) as usize
+        }
+        2 | 3 => {
+            let mut len = [0; 4];
+            reader.read_exact(&mut len)?;
+            u32::from_le_bytes(len) as usize
+        }
+        version => {
+            return Err(FormatError::Unsupported(format!(
+                ".npy format version {version}"
+            )));
+        }
+    };
+    let mut header = vec![0; header_len];
+    reader.read_exact(&mut header)?;
+    let header = String::from_utf8_lossy(&header);
+
+    let descr = dict_value(&header, "descr")?
+        .strip_prefix('\'')
+        .and_then(|v| v.split('\'').next())
+        .ok_or_else(|| FormatError::Invalid("descr should be a string".to_string()))?;
+    let fortran_order = dict_value(&header, "fortran_order")?.starts_with("True");
+    let shape = dict_value(&header, "shape")?
+        .strip_prefix('(')
+        .and_then(|v| v.split(')').next())
+        .ok_or_else(|| FormatError::Invalid("shape should be a tuple".to_string()))?
+        .split(',')
+        .map(str::trim)
+        .filter(|length| !length.is_empty())
+        .map(|length| {
+            length
+                .trim_end_matches('L')
+                .parse()
+                .map_err(|_| FormatError::Invalid(format!("invalid axis length '{length}'")))
+        })
+        .collect::<Result<_, _>>()?;
+    Ok(Header {
+        dtype: Dtype::parse(descr)?,
+        fortran_order,
+        shape,
+    })
+}
+
+/// Text following `key` and its colon, in the python dict literal of a header
+fn dict_value<'a>(header: &'a str, key: &str) -> Result<&'a str, FormatError> {
+    let missing = || FormatError::Invalid(format!("header has no '{key}'"));
+    let quoted = format!("'{key}'");
+    let start = header.find(&quoted).ok_or_else(missing)? + quoted.len();
+    header[start..]
+        .trim_start()
+        .strip_prefix(':')
+        .map(str::trim_start)
+        .ok_or_else(missing)
+}
+
+/// Decode the elements of an array described by `header`
+pub fn decode(header: &Header, bytes: &[u8]) -> Result<ForayArray, FormatError> {
+    let Dtype {
+        kind,
+        size,
+        big_endian,
+    } = header.dtype;
+    let unsupported = || FormatError::Unsupported(format!("element type {kind}{size}"));
+    let shape = &header.shape;
+    let fortran_order = header.fortran_order;
+    let data_len = header.data_len()?;
+    if bytes.len() < data_len {
+        return Err(FormatError::Invalid(format!(
+            "expected {data_len} bytes of data, found {}",
+            bytes.len()
+        )));
+    }
+    let bytes = &bytes[..data_len];
+
+    let integers = |values: Vec<i64>| {
+        let values = values
+            .into_iter()
+            .map(|v| {
+                i32::try_from(v)
+                    .map_err(|_| FormatError::Unsupported(format!("{v} doesn't fit in 32 bits")))
+            })
+            .collect::<Result<_, _>>()?;
+        Ok(ForayArray::Integer(from_memory_order(
+            shape,
+            values,
+            fortran_order,
+        )?))
+    };
+    let strings = |values: Vec<String>| {
+        Ok(ForayArray::String(from_memory_order(
+            shape,
+            values,
+            fortran_order,
+        )?))
+    };
+    match (kind, size) {
+        ('S' | 'U', 0) => strings(vec![String::new(); shape.iter().product()]),
+        // Strings shorter than the array's longest are padded with zeros
+        ('S', size) => strings(
+            bytes
+                .chunks_exact(size)
+                .map(|s| {
+                    String::from_utf8_lossy(s)
+                        .trim_end_matches('\0')
+                        .to_string()
+                })
+                .collect(),
+        ),
+        ('U', size) => strings(
+            bytes
+                .chunks_exact(size * 4)
+                .map(|s| {
+                    elements(s, big_endian, u32::from_le_bytes)
+                        .into_iter()
+                        .take_while(|c| *c != 0)
+                        .map(|c| char::from_u32(c).unwrap_or(char::REPLACEMENT_CHARACTER))
+                        .collect()
+                })
+                .collect(),
+        ),
+        ('b', 1) => Ok(ForayArray::Boolean(from_memory_order(
+            shape,
+            bytes.iter().map(|b| *b != 0).collect(),
+            fortran_order,
+        )?)),
+        ('i', 1) => integers(elements(bytes, big_endian, |b: [u8; 1]| {
+            i8::from_le_bytes(b) as i64
+        })),
+        ('i', 2) => integers(elements(bytes, big_endian, |b| {
+            i16::from_le_bytes(b) as i64
+        })),
+        ('i', 4) => integers(elements(bytes, big_endian, |b| {
+            i32::from_le_bytes(b) as i64
+        })),
+        ('i', 8) => integers(elements(bytes, big_endian, i64::from_le_bytes)),
+        ('u', 1) => integers(elements(bytes, big_endian, |b: [u8; 1]| b[0] as i64)),
+        ('u', 2) => integers(elements(bytes, big_endian, |b| {
+            u16::from_le_bytes(b) as i64
+        })),
+        ('u', 4) => integers(elements(bytes, big_endian, |b| {
+            u32::from_le_bytes(b) as i64
+        })),
+        ('u', 8) => integers(
+            elements(bytes, big_endian, u64::from_le_bytes)
+                .into_iter()
+                .map(|v| i64::try_from(v).unwrap_or(i64::MAX))
+                .collect(),
+        ),
+        ('f', 4) => Ok(ForayArray::Float(from_memory_order(
+            shape,
+            elements(bytes, big_endian, |b| f32::from_le_bytes(b) as f64),
+            fortran_order,
+        )?)),
+        ('f', 8) => Ok(ForayArray::Float(from_memory_order(
+            shape,
+            elements(bytes, big_endian, f64::from_le_bytes),
+            fortran_order,
+        )?)),
+        ('c', 8) => Ok(ForayArray::Complex(from_memory_order(
+            shape,
+            complex(elements(bytes, big_endian, |b| {
+                f32::from_le_bytes(b) as f64
+            })),
+            fortran_order,
+        )?)),
+        ('c', 16) => Ok(ForayArray::Complex(from_memory_order(
+            shape,
+            complex(elements(bytes, big_endian, f64::from_le_bytes)),
+            fortran_order,
+        )?)),
+        _ => Err(unsupported()),
+    }
+}
+
+/// Decode the elements of a view of their bytes, with a last axis over the bytes of each
+/// element, like a slice of [`Mapped::bytes`]
+pub fn decode_view(dtype: Dtype, bytes: ArrayViewD<u8>) -> Result<ForayArray, FormatError> {
+    match bytes.shape().split_last() {
+        Some((itemsize, shape)) if *itemsize == dtype.itemsize() => {
+            let header = Header {
+                dtype,
+                fortran_order: false,
+                shape: shape.to_vec(),
+            };
+            let bytes = bytes.as_standard_layout();
+            decode(
+                &header,
+                bytes.as_slice().expect("standard layout is contiguous"),
+            )
+        }
+        _ => Err(FormatError::Invalid(format!(
+            "the last axis should have the {} bytes of an element",
+            dtype.itemsize()
+        ))),
+    }
+}
+
+/// A `.npy` file mapped into memory. Elements are only read from the file when they're
+/// decoded, so part of an array that doesn't fit in memory can still be read
+pub struct Mapped {
+    pub header: Header,
+    map: Mmap,
+    data_start: usize,
+    data_len: usize,
+}
+
+impl Mapped {
+    /// The array's elements as bytes, with a last axis over the bytes of each element.
+    /// Slicing the other axes before decoding selects the elements that are read
+    pub fn bytes(&self) -> ArrayViewD<'_, u8> {
+        let Header {
+            dtype,
+            fortran_order,
+            shape,
+        } = &self.header;
+        // Strides in bytes of the axes in memory order, the element's bytes are contiguous
+        let mut strides = vec![0; shape.len() + 1];
+        strides[shape.len()] = 1;
+        let mut stride = dtype.itemsize();
+        let mut memory_order = (0..shape.len()).collect::<Vec<_>>();
+        if !fortran_order {
+            memory_order.reverse();
+        }
+        for axis in memory_order {
+            strides[axis] = stride;
+            stride *= shape[axis];
+        }
+        let mut dims = shape.clone();
+        dims.push(dtype.itemsize());
+        let data = &self.map[self.data_start..self.data_start + self.data_len];
+        ArrayView::from_shape(IxDyn(&dims).strides(IxDyn(&strides)), data)
+            .expect("strides of the header's shape are within the data")
+    }
+
+    /// Decode every element
+    pub fn decode(&self) -> Result<ForayArray, FormatError> {
+        decode(
+            &self.header,
+            &self.map[self.data_start..self.data_start + self.data_len],
+        )
+    }
+}
+
+/// Map a `.npy` file into memory, reading only its header
+pub fn map(path: &Path) -> Result<Mapped, FormatError> {
+    let file = File::open(path)?;
+    let mut reader = BufReader::new(&file);
+    let header = read_header(&mut reader)?;
+    let data_start = reader.stream_position()? as usize;
+    // SAFETY: like numpy's read only memmap, the file must not be truncated or written to while
+    // it's mapped
+    let map = unsafe { Mmap::map(&file)? };
+    let data_len = header.data_len()?;
+    if map.len().saturating_sub(data_start) < data_len {
+        return Err(FormatError::Invalid(format!(
+            "expected {data_len} bytes of data, found {}",
+            map.len().saturating_sub(data_start)
+        )));
+    }
+    Ok(Mapped {
+        header,
+        map,
+        data_start,
+        data_len,
+    })
+}
+
+pub fn read(reader: &mut impl Read) -> Result<ForayArray, FormatError> {
+    let header = read_header(reader)?;
+    // Only as many bytes as the file has are allocated, a short file is reported by `decode`
+    let mut bytes = vec![];
+    reader
+        .take(header.data_len()? as u64)
+        .read_to_end(&mut bytes)?;
+    decode(&header, &bytes)
+}
+
+pub fn write(writer: &mut impl Write, array: &ForayArray) -> Result<(), FormatError> {
+    let dtype = Dtype::of(array)?;
+    let descr = dtype.descr();
+    let shape = match shape(array) {
+        [length] => format!("({length},)"),
+        shape => format!(
+            "({})",
+            shape
+                .iter()
+                .map(|l| l.to_string())
+                .collect::<Vec<_>>()
+                .join(", ")
+        ),
+    };
+    let mut header = format!("{{'descr': '{descr}', 'fortran_order': False, 'shape': {shape}, }}");
+    // The header ends with a newline, and the data starts on a 64 byte boundary
+    let version: u8 = if header.len() + 1 + 10 > u16::MAX as usize {
+        2
+    } else {
+        1
+    };
+    let prefix_len = if version == 1 { 10 } else { 12 };
+    let padding = (64 - (prefix_len + header.len() + 1) % 64) % 64;
+    header.push_str(&" ".repeat(padding));
+    header.push('\n');
+
+    writer.write_all(MAGIC)?;
+    writer.write_all(&[version, 0])?;
+    match version {
+        1 => writer.write_all(&(header.len() as u16).to_le_bytes())?,
+        _ => writer.write_all(&(header.len() as u32).to_le_bytes())?,
+    }
+    writer.write_all(header.as_bytes())?;
+
+    // Elements in C order, the logical order of an array
+    match array {
+        ForayArray::Integer(a) => a
+            .iter()
+            .try_for_each(|v| writer.write_all(&v.to_le_bytes())),
+        ForayArray::Float(a) => a
+            .iter()
+            .try_for_each(|v| writer.write_all(&v.to_le_bytes())),
+        ForayArray::Complex(a) => a.iter().try_for_each(|v| {
+            writer.write_all(&v.re.to_le_bytes())?;
+            writer.write_all(&v.im.to_le_bytes())
+        }),
+        ForayArray::Boolean(a) => a.iter().try_for_each(|v| writer.write_all(&[*v as u8])),
+        // UTF-32, padded with zeros to the longest string
+        ForayArray::String(a) => a.iter().try_for_each(|s| {
+            let padding = dtype.size - s.chars().count();
+            s.chars()
+                .map(u32::from)
+                .chain(std::iter::repeat_n(0, padding))
+                .try_for_each(|c| writer.write_all(&c.to_le_bytes()))
+        }),
+        ForayArray::Object(_) => unreachable!("checked by Dtype::of"),
+    }?;
+    Ok(())
+}
+
+pub fn load(path: &Path) -> Result<ForayArray, FormatError> {
+    read(&mut BufReader::new(File::open(path)?))
+}
+
+pub fn save(path: &Path, array: &ForayArray) -> Result<(), FormatError> {
+    let mut writer = BufWriter::new(File::create(path)?);
+    write(&mut writer, array)?;
+    writer.flush()?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use ndarray::{ArrayD, Axis, Slice, array};
+    use num_complex::Complex64;
+
+    use super::*;
+
+    fn round_trip(array: ForayArray) {
+        let mut bytes = vec![];
+        write(&mut bytes, &array).unwrap();
+        assert_eq!(read(&mut bytes.as_slice()).unwrap(), array);
+    }
+
+    #[test]
+    fn round_trips() {
+        round_trip(ForayArray::Float(
+            array![[1.0, 2.0, 3.0], [4.0, 5.0, 6.0]].into_dyn(),
+        ));
+        round_trip(ForayArray::Integer(array![1, -2, 3].into_dyn()));
+        round_trip(ForayArray::Complex(
+            array![Complex64::new(1.0, -1.0), Complex64::new(0.5, 2.0)].into_dyn(),
+        ));
+        round_trip(ForayArray::Boolean(array![[true], [false]].into_dyn()));
+        round_trip(ForayArray::Float(ArrayD::from_elem(vec![], 7.0)));
+        round_trip(ForayArray::String(
+            array!["", "t1", "ünïcode"].mapv(String::from).into_dyn(),
+        ));
+    }
+
+    #[test]
+    fn header_alignment() {
+        let mut bytes = vec![];
+        write(&mut bytes, &ForayArray::Integer(array![1, 2].into_dyn())).unwrap();
+        let data_start = bytes.len() - 2 * 4;
+        assert_eq!(data_start % 64, 0);
+        assert_eq!(bytes[data_start - 1], b'\n');
+    }
+
+    /// A `.npy` file of `np.asfortranarray(np.array([[1, 2], [3, 4]], dtype='>i2'))`
+    fn fortran_order_file() -> Vec<u8> {
+        let header = "{'descr': '>i2', 'fortran_order': True, 'shape': (2, 2), }";
+        let mut bytes = MAGIC.to_vec();
+        bytes.extend([1, 0]);
+        bytes.extend((header.len() as u16).to_le_bytes());
+        bytes.extend(header.as_bytes());
+        for v in [1i16, 3, 2, 4] {
+            bytes.extend(v.to_be_bytes());
+        }
+        bytes
+    }
+
+    #[test]
+    fn fortran_order_and_other_types() {
+        assert_eq!(
+            read(&mut fortran_order_file().as_slice()).unwrap(),
+            ForayArray::Integer(array![[1, 2], [3, 4]].into_dyn())
+        );
+        // np.array(['ab', 'c'], dtype='>U2'), and the same as bytes
+        let header = |descr: &str| Header {
+            dtype: Dtype::parse(descr).unwrap(),
+            fortran_order: false,
+            shape: vec![2],
+        };
+        let utf32 = [b'a', b'b', b'c', 0].map(u32::from);
+        let strings = ForayArray::String(array!["ab", "c"].mapv(String::from).into_dyn());
+        assert_eq!(
+            decode(
+                &header(">U2"),
+                &utf32
+                    .iter()
+                    .flat_map(|c| c.to_be_bytes())
+                    .collect::<Vec<_>>()
+            )
+            .unwrap(),
+            strings
+        );
+        assert_eq!(decode(&header("|S2"), b"abc\0").unwrap(), strings);
+    }
+
+    #[test]
+    fn lengths_from_the_header() {
+        let header_only = |shape: &str| {
+            let header = format!("{{'descr': '<f8', 'fortran_order': False, 'shape': {shape}, }}");
+            let mut bytes = MAGIC.to_vec();
+            bytes.extend([1, 0]);
+            bytes.extend((header.len() as u16).to_le_bytes());
+            bytes.extend(header.as_bytes());
+            read(&mut bytes.as_slice()).unwrap_err()
+        };
+        assert_eq!(
+            header_only("(1099511627776, 1099511627776)"),
+            FormatError::Invalid(
+                "an array of shape [1099511627776, 1099511627776] doesn't fit in memory".into()
+            )
+        );
+        assert_eq!(
+            header_only("(1099511627776,)"),
+            FormatError::Invalid("expected 8796093022208 bytes of data, found 0".into())
+        );
+    }
+
+    #[test]
+    fn mapped_slices() {
+        let path = std::env::temp_dir().join(format!("foray_mapped_{}.npy", std::process::id()));
+        let a = ArrayD::from_shape_fn(vec![3, 4], |i| (i[0] * 10 + i[1]) as i32);
+        save(&path, &ForayArray::Integer(a.clone())).unwrap();
+        let mapped = map(&path).unwrap();
+        assert_eq!(mapped.decode().unwrap(), ForayArray::Integer(a));
+        let bytes = mapped.bytes();
+        assert_eq!(bytes.shape(), [3, 4, 4]);
+        assert_eq!(
+            decode_view(mapped.header.dtype, bytes.index_axis(Axis(1), 2)).unwrap(),
+            ForayArray::Integer(array![2, 12, 22].into_dyn())
+        );
+        // The bytes of an element can't be sliced
+        assert!(
+            decode_view(
+                mapped.header.dtype,
+                bytes.slice_axis(Axis(2), Slice::from(..2))
+            )
+            .is_err()
+        );
+
+        std::fs::write(&path, fortran_order_file()).unwrap();
+        let mapped = map(&path).unwrap();
+        assert_eq!(
+            decode_view(mapped.header.dtype, mapped.bytes().index_axis(Axis(0), 1)).unwrap(),
+            ForayArray::Integer(array![3, 4].into_dyn())
+        );
+        std::fs::remove_file(&path).unwrap();
+    }
+}
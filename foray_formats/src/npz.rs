@@ -0,0 +1,110 @@
+//! numpy's `.npz` format: a zip archive with a `.npy` file for each array, named after the array.
+//! `np.savez_compressed` compresses them with deflate, `np.savez` stores them as they are.
+//! See <https://numpy.org/doc/stable/reference/generated/numpy.savez.html>
+
+use std::{
+    fs::File,
+    io::{BufReader, BufWriter, Read, Seek, Write},
+    path::Path,
+};
+
+use foray_data_model::node::ForayArray;
+use zip::{CompressionMethod, ZipArchive, ZipWriter, write::SimpleFileOptions};
+
+use crate::{err::FormatError, npy};
+
+/// Names of the arrays in an archive, in the order they're stored
+pub fn read_names(reader: impl Read + Seek) -> Result<Vec<String>, FormatError> {
+    Ok(ZipArchive::new(reader)?
+        .file_names()
+        .filter_map(|name| name.strip_suffix(".npy"))
+        .map(str::to_string)
+        .collect())
+}
+
+/// Every array in an archive with its name, in the order they're stored
+pub fn read(reader: impl Read + Seek) -> Result<Vec<(String, ForayArray)>, FormatError> {
+    let mut archive = ZipArchive::new(reader)?;
+    let mut arrays = vec![];
+    for i in 0..archive.len() {
+        let mut file = archive.by_index(i)?;
+        if let Some(name) = file.name().strip_suffix(".npy") {
+            let name = name.to_string();
+            arrays.push((name, npy::read(&mut file)?));
+        }
+    }
+    Ok(arrays)
+}
+
+/// Write each array as `<name>.npy`, compressed if `compressed`
+pub fn write(
+    writer: impl Write + Seek,
+    arrays: &[(&str, &ForayArray)],
+    compressed: bool,
+) -> Result<(), FormatError> {
+    let method = match compressed {
+        true => CompressionMethod::Deflated,
+        false => CompressionMethod::Stored,
+    };
+    let mut zip = ZipWriter::new(writer);
+    for (name, array) in arrays {
+        // Files of 4 GiB or more need zip64, the margin covers the .npy header
+        let large = npy::Header::of(array)?.data_len()? > (u32::MAX - u16::MAX as u32) as usize;
+        let options = SimpleFileOptions::default()
+            .compression_method(method)
+            .large_file(large);
+        zip.start_file(format!("{name}.npy"), options)?;
+        npy::write(&mut zip, array)?;
+    }
+    zip.finish()?;
+    Ok(())
+}
+
+pub fn names(path: &Path) -> Result<Vec<String>, FormatError> {
+    read_names(BufReader::new(File::open(path)?))
+}
+
+pub fn load(path: &Path) -> Result<Vec<(String, ForayArray)>, FormatError> {
+    read(BufReader::new(File::open(path)?))
+}
+
+pub fn save(
+    path: &Path,
+    arrays: &[(&str, &ForayArray)],
+    compressed: bool,
+) -> Result<(), FormatError> {
+    let mut writer = BufWriter::new(File::create(path)?);
+    write(&mut writer, arrays, compressed)?;
+    writer.flush()?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use std::io::Cursor;
+
+    use ndarray::array;
+
+    use super::*;
+
+    #[test]
+    fn round_trips() {
+        let a = ForayArray::Float(array![[1.0, 2.0], [3.0, 4.0]].into_dyn());
+        let labels = ForayArray::String(array!["t1", "flair"].mapv(String::from).into_dyn());
+        for compressed in [false, true] {
+            let mut bytes = Cursor::new(vec![]);
+            write(&mut bytes, &[("a", &a), ("labels", &labels)], compressed).unwrap();
+            bytes.rewind().unwrap();
+            assert_eq!(read_names(&mut bytes).unwrap(), ["a", "labels"]);
+            bytes.rewind().unwrap();
+            assert_eq!(
+                read(&mut bytes).unwrap(),
+                [
+                    ("a".to_string(), a.clone()),
+                    ("labels".to_string(), labels.clone())
+                ]
+            );
+        }
+        assert!(read(Cursor::new(b"not a zip".to_vec())).is_err());
+    }
+}
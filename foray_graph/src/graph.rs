@@ -1,6 +1,8 @@
 use std::{collections::HashMap, sync::Arc};
 
 use derive_more::Display;
+use foray_command::err::CommandError;
+use foray_plugin::err::PluginError;
 use foray_py::err::PyNodeConfigError;
 use itertools::Itertools;
 use serde::{Deserialize, Serialize};
@@ -18,6 +20,8 @@ pub type PortName = String;
 pub enum ForayNodeError {
     PyNodeConifgError(PyNodeConfigError),
     NodeError(NodeError),
+    Plugin(PluginError),
+    Command(CommandError),
 }
 
 pub trait GraphNode<PortType, WireData>
@@ -264,6 +268,40 @@ where
         self.edges.push((from, to));
     }
 
+    /// Rename a port of a node, keeping its edges and output data
+    pub fn rename_port(&mut self, nx: NodeIndex, io: IO, old_name: &str, new_name: &str) {
+        self.edges.iter_mut().for_each(|(from, to)| {
+            let port = match io {
+                IO::In => to,
+                IO::Out => from,
+            };
+            if port.node == nx && port.name == old_name {
+                port.name = new_name.to_string();
+            }
+        });
+        if io == IO::Out
+            && let Some(data) = self.wire_data.remove(&(nx, old_name.to_string()))
+        {
+            self.wire_data.insert((nx, new_name.to_string()), data);
+        }
+    }
+
+    /// Is there any edge associated with the given port
+    pub fn is_connected(&self, port: &PortRef) -> bool {
+        self.edges
+            .iter()
+            .any(|(from, to)| port == from || port == to)
+    }
+
+    /// Find the edges associated with the given port
+    pub fn port_edges(&self, port: &PortRef) -> Vec<(PortRef, PortRef)> {
+        self.edges
+            .iter()
+            .filter(|(from, to)| port == from || port == to)
+            .cloned()
+            .collect()
+    }
+
     /// Remove any edges associated with the given port
     pub fn remove_edge(&mut self, port: &PortRef) {
         self.edges.retain(|(from, to)| port != from && port != to)
@@ -326,6 +364,21 @@ where
             .collect()
     }
 
+    /// Find every node downstream of `nx`, not including `nx`
+    pub fn descendants(&self, nx: &NodeIndex) -> Vec<NodeIndex> {
+        let mut found = vec![];
+        let mut to_visit = vec![*nx];
+        while let Some(next) = to_visit.pop() {
+            for child in self.outgoing_edges(&next).into_iter().map(|p| p.node) {
+                if !found.contains(&child) {
+                    found.push(child);
+                    to_visit.push(child);
+                }
+            }
+        }
+        found
+    }
+
     /// Topological sort using Kahn's algorithm
     /// returns a list of NodeIndices
     pub fn topological_sort(&self) -> Vec<NodeIndex> {
@@ -509,6 +562,57 @@ mod test {
         assert_eq!(*g.get_wire_data(&n2, "out").unwrap().read().unwrap(), 7);
         assert_eq!(*g.get_wire_data(&n3, "out").unwrap().read().unwrap(), 7);
     }
+
+    #[test]
+    fn rename_port() {
+        let mut g: Graph<Node, (), u32> = Graph::new();
+
+        let n1 = g.node(Node::Constant(ConstantNode { value: 7 }));
+        let n2 = g.node(Node::Identity(IdentityNode {}));
+        let n3 = g.node(Node::Identity(IdentityNode {}));
+
+        g.connect((n1, "out"), (n2, "in"));
+        g.connect((n1, "out"), (n3, "in"));
+        g.update_wire_data(n1, [("out".to_string(), 7)].into());
+
+        g.rename_port(n1, IO::Out, "out", "value");
+        g.rename_port(n2, IO::In, "in", "x");
+
+        assert_eq!(
+            g.get_parent(&n2, "x".into()).map(|p| p.name),
+            Some("value".to_string())
+        );
+        assert_eq!(
+            g.get_parent(&n3, "in".into()).map(|p| p.name),
+            Some("value".to_string())
+        );
+        assert_eq!(g.get_parent(&n2, "in".into()), None);
+        assert_eq!(*g.get_wire_data(&n1, "value").unwrap().read().unwrap(), 7);
+        assert!(g.get_wire_data(&n1, "out").is_none());
+    }
+
+    #[test]
+    fn descendants() {
+        let mut g: Graph<Node, (), u32> = Graph::new();
+
+        let n1 = g.node(Node::Constant(ConstantNode { value: 7 }));
+        let n2 = g.node(Node::Identity(IdentityNode {}));
+        let n3 = g.node(Node::Identity(IdentityNode {}));
+        let n4 = g.node(Node::Identity(IdentityNode {}));
+        let n5 = g.node(Node::Identity(IdentityNode {}));
+
+        g.connect((n1, "out"), (n2, "in"));
+        g.connect((n2, "out"), (n3, "in"));
+        g.connect((n1, "out"), (n4, "in"));
+
+        assert_eq!(g.descendants(&n2), vec![n3]);
+        assert_eq!(
+            g.descendants(&n1).into_iter().sorted().collect_vec(),
+            vec![n2, n3, n4]
+        );
+        assert!(g.descendants(&n5).is_empty());
+    }
+
     //TODO: test unconnected nodes, making sure we don't try to run nodes without the necessary
     //inputs
 }
@@ -0,0 +1,41 @@
+//! Nodes implemented in rust, by foray or by any crate that is linked into it.
+//!
+//! Implement [`NativeNode`] for a type, and add it to the add node tree with [`register_node!`]:
+//! ```ignore
+//! struct Negate;
+//!
+//! impl NativeNode for Negate {
+//!     fn id(&self) -> &'static str {
+//!         "my_nodes.negate"
+//!     }
+//!     fn path(&self) -> Vec<String> {
+//!         vec!["my_nodes".into(), "negate".into()]
+//!     }
+//!     fn inputs(&self, _parameters: Parameters) -> Dict<PortName, PortType> {
+//!         [("a".to_string(), PortType::Float)].into()
+//!     }
+//!     fn outputs(&self, _parameters: Parameters) -> Dict<PortName, PortType> {
+//!         [("out".to_string(), PortType::Float)].into()
+//!     }
+//!     fn compute(
+//!         &self,
+//!         inputs: Inputs,
+//!         _parameters: Parameters,
+//!     ) -> Result<Dict<PortName, PortData>, ForayNodeError> {
+//!         match &*inputs.get("a")? {
+//!             PortData::Float(a) => Ok([("out".to_string(), PortData::Float(-a))].into()),
+//!             _ => Err(inputs.unexpected("a")),
+//!         }
+//!     }
+//! }
+//!
+//! register_node!(Negate);
+//! ```
+pub mod node;
+pub mod registry;
+
+pub use node::{ConfiguredNode, Inputs, NativeNode, Parameters};
+pub use registry::{NativeNodeRegistration, NativeNodeTemplate, native_nodes};
+
+#[doc(hidden)]
+pub use inventory;
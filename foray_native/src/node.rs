@@ -0,0 +1,125 @@
+use foray_data_model::{
+    WireDataContainer, WireDataReference,
+    node::{Dict, NodeError, PortData, PortType, UIParameter},
+};
+use foray_graph::graph::{ForayNodeError, GraphNode, PortName};
+
+/// A node implemented in rust.
+///
+/// Nodes don't hold any state, parameter values are stored with each node in the network and
+/// passed to every method, so one value of the type is shared by every node in the network
+pub trait NativeNode: Send + Sync {
+    /// Identifies the node in saved networks, so should never change, e.g. `"my_nodes.negate"`
+    fn id(&self) -> &'static str;
+    /// Location in the add node tree, ending with the node's name
+    fn path(&self) -> Vec<String>;
+    /// One line summary
+    fn description(&self) -> Option<String> {
+        None
+    }
+    /// Parameters shown in the side bar, with their default values
+    fn parameters(&self) -> Dict<String, UIParameter> {
+        Dict::new()
+    }
+    fn inputs(&self, parameters: Parameters) -> Dict<PortName, PortType>;
+    fn outputs(&self, parameters: Parameters) -> Dict<PortName, PortType>;
+    fn compute(
+        &self,
+        inputs: Inputs,
+        parameters: Parameters,
+    ) -> Result<Dict<PortName, PortData>, ForayNodeError>;
+}
+
+/// A native node, with the values of its parameters
+pub struct ConfiguredNode<'a> {
+    pub node: &'a dyn NativeNode,
+    pub parameters: Dict<String, PortData>,
+}
+
+impl GraphNode<PortType, PortData> for ConfiguredNode<'_> {
+    fn inputs(&self) -> Dict<PortName, PortType> {
+        self.node.inputs(Parameters(&self.parameters))
+    }
+
+    fn outputs(&self) -> Dict<PortName, PortType> {
+        self.node.outputs(Parameters(&self.parameters))
+    }
+
+    fn compute(
+        self,
+        inputs: Dict<PortName, WireDataContainer<PortData>>,
+    ) -> Result<Dict<PortName, PortData>, ForayNodeError> {
+        self.node
+            .compute(Inputs(inputs), Parameters(&self.parameters))
+    }
+}
+
+/// Data connected to a node's inputs
+pub struct Inputs(pub Dict<PortName, WireDataContainer<PortData>>);
+
+impl Inputs {
+    /// Data of an input, which stays locked while it's borrowed
+    pub fn get(&self, name: &str) -> Result<WireDataReference<'_, PortData>, ForayNodeError> {
+        self.0
+            .get(name)
+            .map(|data| data.read().unwrap())
+            .ok_or_else(|| {
+                ForayNodeError::NodeError(NodeError::Input(format!("{name} is not connected")))
+            })
+    }
+
+    /// Data of an input, if it's connected
+    pub fn get_optional(&self, name: &str) -> Option<WireDataReference<'_, PortData>> {
+        self.0.get(name).map(|data| data.read().unwrap())
+    }
+
+    /// Error for an input whose data doesn't have the expected type
+    pub fn unexpected(&self, name: &str) -> ForayNodeError {
+        let found = self
+            .get_optional(name)
+            .map(|data| format!("{:?}", PortType::from(&*data)))
+            .unwrap_or_default();
+        ForayNodeError::NodeError(NodeError::Input(format!(
+            "{name} has an unexpected type {found}"
+        )))
+    }
+}
+
+/// Values of a node's parameters, with typed accessors
+#[derive(Clone, Copy, Debug)]
+pub struct Parameters<'a>(pub &'a Dict<String, PortData>);
+
+impl Parameters<'_> {
+    pub fn float(&self, name: &str) -> Result<f64, ForayNodeError> {
+        match self.0.get(name) {
+            Some(PortData::Float(v)) => Ok(*v),
+            Some(PortData::Integer(v)) => Ok(*v as f64),
+            _ => Err(parameter_error(name, "a number")),
+        }
+    }
+
+    /// Number parameter, rounded to the nearest integer
+    pub fn integer(&self, name: &str) -> Result<i64, ForayNodeError> {
+        self.float(name).map(|v| v.round() as i64)
+    }
+
+    pub fn boolean(&self, name: &str) -> Result<bool, ForayNodeError> {
+        match self.0.get(name) {
+            Some(PortData::Boolean(v)) => Ok(*v),
+            _ => Err(parameter_error(name, "a boolean")),
+        }
+    }
+
+    pub fn string(&self, name: &str) -> Result<&str, ForayNodeError> {
+        match self.0.get(name) {
+            Some(PortData::String(v)) => Ok(v),
+            _ => Err(parameter_error(name, "a string")),
+        }
+    }
+}
+
+fn parameter_error(name: &str, expected: &str) -> ForayNodeError {
+    ForayNodeError::NodeError(NodeError::Compute(format!(
+        "parameter {name} should be {expected}"
+    )))
+}
@@ -0,0 +1,123 @@
+use foray_data_model::node::NodeError;
+use foray_graph::graph::ForayNodeError;
+use serde::{Deserialize, Serialize};
+
+use crate::node::NativeNode;
+
+/// A node added to the add node tree with [`register_node!`](crate::register_node)
+pub struct NativeNodeRegistration {
+    pub node: &'static dyn NativeNode,
+}
+
+inventory::collect!(NativeNodeRegistration);
+
+/// Make a native node available in foray.
+/// The crate that calls this only has to be linked into the foray binary
+#[macro_export]
+macro_rules! register_node {
+    ($node:expr) => {
+        $crate::inventory::submit! {
+            $crate::NativeNodeRegistration { node: &$node }
+        }
+    };
+}
+
+/// Every registered native node
+pub fn native_nodes() -> impl Iterator<Item = &'static dyn NativeNode> {
+    inventory::iter::<NativeNodeRegistration>
+        .into_iter()
+        .map(|registration| registration.node)
+}
+
+/// Template of a native node, which is only saved as the node's id
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq, Eq, PartialOrd)]
+pub struct NativeNodeTemplate {
+    pub id: String,
+}
+
+impl NativeNodeTemplate {
+    pub fn new(node: &dyn NativeNode) -> Self {
+        NativeNodeTemplate {
+            id: node.id().to_string(),
+        }
+    }
+
+    /// The registered node, which is missing if the crate that defines it isn't linked
+    pub fn node(&self) -> Result<&'static dyn NativeNode, ForayNodeError> {
+        native_nodes()
+            .find(|node| node.id() == self.id)
+            .ok_or_else(|| {
+                ForayNodeError::NodeError(NodeError::Compute(format!(
+                    "native node {} is not available",
+                    self.id
+                )))
+            })
+    }
+
+    pub fn name(&self) -> String {
+        self.node()
+            .ok()
+            .and_then(|node| node.path().pop())
+            .unwrap_or_else(|| self.id.clone())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use foray_data_model::node::{Dict, PortData, PortType};
+    use foray_graph::graph::{ForayNodeError, GraphNode, PortName};
+
+    use crate::{ConfiguredNode, Inputs, NativeNode, NativeNodeTemplate, Parameters};
+
+    struct Constant;
+
+    impl NativeNode for Constant {
+        fn id(&self) -> &'static str {
+            "test.constant"
+        }
+        fn path(&self) -> Vec<String> {
+            vec!["test".into(), "constant".into()]
+        }
+        fn inputs(&self, _parameters: Parameters) -> Dict<PortName, PortType> {
+            Dict::new()
+        }
+        fn outputs(&self, _parameters: Parameters) -> Dict<PortName, PortType> {
+            [("out".to_string(), PortType::Float)].into()
+        }
+        fn compute(
+            &self,
+            _inputs: Inputs,
+            parameters: Parameters,
+        ) -> Result<Dict<PortName, PortData>, ForayNodeError> {
+            Ok([(
+                "out".to_string(),
+                PortData::Float(parameters.float("value")?),
+            )]
+            .into())
+        }
+    }
+
+    crate::register_node!(Constant);
+
+    #[test]
+    fn registered() {
+        let template = NativeNodeTemplate {
+            id: "test.constant".to_string(),
+        };
+        let node = template.node().unwrap();
+        assert_eq!(template.name(), "constant");
+
+        let outputs = ConfiguredNode {
+            node,
+            parameters: [("value".to_string(), PortData::Float(2.0))].into(),
+        }
+        .compute(Dict::new())
+        .unwrap();
+        assert_eq!(outputs["out"], PortData::Float(2.0));
+
+        let missing = NativeNodeTemplate {
+            id: "test.missing".to_string(),
+        };
+        assert!(missing.node().is_err());
+    }
+}
@@ -1,5 +1,5 @@
 import numpy as np
-from foray import ForayConfig, NumberField, Port, Slider
+from foray import ForayConfig, NumberField, Port, Slider, progress
 import time
 
 
@@ -24,11 +24,8 @@ def compute(inputs, p):
     if out is None:
         out = False
 
-    print(0)
-    time.sleep(1.0)
-    print(1)
-    time.sleep(1.0)
-    print(2)
-    time.sleep(1.0)
-    print(3)
+    steps = 3
+    for step in range(steps):
+        progress(step / steps, f"step {step + 1} of {steps}")
+        time.sleep(1.0)
     return {"out": out}
@@ -1,5 +1,5 @@
 import os
-from foray import CheckBox, ForayConfig, NumberField, Slider, TextDisplay, FilePicker
+from foray import CheckBox, ForayConfig, NumberField, Slider, TextDisplay, FilePicker, TextInput, CodeEditor, DirectoryPicker, Dropdown
 
 
 def config():
@@ -10,6 +10,10 @@ def config():
             "checkbox": CheckBox(True),
             "text display": TextDisplay("hello world!!"),
             "file": FilePicker(),
+            "text input": TextInput("edit me"),
+            "code editor": CodeEditor("x = 1\ny = x + 1"),
+            "directory": DirectoryPicker(),
+            "dropdown": Dropdown(["first", "second", "third"]),
         }
     )
 
@@ -1,3 +1,9 @@
+"""Centered 2D fourier transform.
+
+Computes `fftshift(fft2(a))`, so the zero frequency is in the center of the output.
+Use `ifft` to transform back.
+"""
+
 import numpy as np
 from foray import ForayConfig, Port
 
@@ -15,6 +21,9 @@ def config():
                 "out": Port.array(Port.complex, [None, None]),
             }
         )
+        .tags("fourier", "frequency", "k-space")
+        .port_doc("a", "image to transform")
+        .port_doc("out", "centered spectrum of `a`")
     )
 
 
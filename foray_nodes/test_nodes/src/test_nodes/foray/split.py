@@ -0,0 +1,34 @@
+"""Split a stack of images into separate outputs.
+
+The number of outputs is set by the `count` parameter, and the output `out{i}` is the
+`i`th image along the first axis of `a`.
+"""
+
+from foray import ForayConfig, NumberField, Port
+
+
+def output_count(parameters):
+    return max(1, int(parameters.get("count", 2)))
+
+
+def config(parameters):
+    return (
+        ForayConfig()
+        .inputs(
+            {
+                "a": Port.array(Port.complex, [None, None, None]),
+            }
+        )
+        .outputs(
+            {
+                f"out{i}": Port.array(Port.complex, [None, None])
+                for i in range(output_count(parameters))
+            }
+        )
+        .parameters({"count": NumberField(2)})
+    )
+
+
+def compute(input, p):
+    a = input["a"]
+    return {f"out{i}": a[i] for i in range(output_count(p))}
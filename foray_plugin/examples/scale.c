@@ -0,0 +1,68 @@
+// Example plugin, with a node that multiplies an image by a factor.
+//
+// Build with:
+//     cc -shared -fPIC -I foray_plugin/include foray_plugin/examples/scale.c -o nodes/scale.so
+// and put the library anywhere in the workspace's nodes/ directory.
+#include <stdio.h>
+
+#include "foray_plugin.h"
+
+static int32_t scale(const ForayArrayView *inputs,
+                     const double *parameters,
+                     const ForayOutputs *outputs,
+                     char *error,
+                     size_t error_len) {
+  const ForayArrayView *image = &inputs[0];
+  double factor = parameters[0];
+  if (factor < 0.0) {
+    snprintf(error, error_len, "factor must be positive, got %f", factor);
+    return 1;
+  }
+
+  double *out = outputs->allocate(outputs->context, 0, image->ndim, image->shape);
+  if (out == NULL) {
+    snprintf(error, error_len, "could not allocate the output");
+    return 1;
+  }
+  const double *data = image->data;
+  size_t rows = image->shape[0], cols = image->shape[1];
+  for (size_t i = 0; i < rows; i++) {
+    for (size_t j = 0; j < cols; j++) {
+      // Inputs aren't necessarily contiguous, outputs always are
+      out[i * cols + j] = factor * data[i * image->strides[0] + j * image->strides[1]];
+    }
+  }
+  return 0;
+}
+
+static const ForayPortDesc inputs[] = {
+    {.name = "image", .element_type = ForayElementType_Float, .ndim = 2},
+};
+static const ForayPortDesc outputs[] = {
+    {.name = "out", .element_type = ForayElementType_Float, .ndim = 2},
+};
+static const ForayParameterDesc parameters[] = {
+    {.name = "factor", .kind = ForayParameterKind_Slider, .min = 0.0, .max = 10.0, .default_value = 1.0},
+};
+
+static const ForayNodeDesc nodes[] = {
+    {
+        .name = "scale",
+        .description = "Multiply an image by a factor",
+        .inputs = inputs,
+        .n_inputs = 1,
+        .outputs = outputs,
+        .n_outputs = 1,
+        .parameters = parameters,
+        .n_parameters = 1,
+        .compute = scale,
+    },
+};
+
+static const ForayPlugin plugin = {
+    .abi_version = FORAY_PLUGIN_ABI_VERSION,
+    .nodes = nodes,
+    .n_nodes = 1,
+};
+
+const ForayPlugin *foray_plugin(void) { return &plugin; }
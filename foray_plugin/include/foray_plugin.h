@@ -0,0 +1,139 @@
+#ifndef FORAY_PLUGIN_H
+#define FORAY_PLUGIN_H
+
+/* Generated from foray_plugin/src/abi.rs by cbindgen, do not edit */
+
+#include <stddef.h>
+#include <stdint.h>
+#include <stdbool.h>
+
+// Version of the layout of the types in this file.
+// Plugins built against a different version are not loaded
+#define FORAY_PLUGIN_ABI_VERSION 1
+
+// Type of each element of a port's data
+enum ForayElementType
+#ifdef __cplusplus
+  : uint32_t
+#endif // __cplusplus
+ {
+  // `int32_t`
+  ForayElementType_Integer = 0,
+  // `double`
+  ForayElementType_Float = 1,
+  // Two `double`s, real then imaginary
+  ForayElementType_Complex = 2,
+  // `bool`
+  ForayElementType_Boolean = 3,
+};
+#ifndef __cplusplus
+typedef uint32_t ForayElementType;
+#endif // __cplusplus
+
+// How a parameter is edited in the side bar
+enum ForayParameterKind
+#ifdef __cplusplus
+  : uint32_t
+#endif // __cplusplus
+ {
+  ForayParameterKind_NumberField = 0,
+  // Slider from `min` to `max`
+  ForayParameterKind_Slider = 1,
+  // `default_value` is 0 or 1
+  ForayParameterKind_CheckBox = 2,
+};
+#ifndef __cplusplus
+typedef uint32_t ForayParameterKind;
+#endif // __cplusplus
+
+// An input or output port.
+// Ports with `ndim == 0` hold a single value, others hold arrays with `ndim` dimensions
+typedef struct ForayPortDesc {
+  const char *name;
+  // A `ForayElementType`, plugins with other values are not loaded
+  uint32_t element_type;
+  size_t ndim;
+} ForayPortDesc;
+
+typedef struct ForayParameterDesc {
+  const char *name;
+  // A `ForayParameterKind`, plugins with other values are not loaded
+  uint32_t kind;
+  double min;
+  double max;
+  double default_value;
+} ForayParameterDesc;
+
+// Read only view of an input's data.
+// `shape` and `strides` have `ndim` elements, strides are counted in elements, not bytes
+typedef struct ForayArrayView {
+  ForayElementType element_type;
+  const void *data;
+  size_t ndim;
+  const size_t *shape;
+  const ptrdiff_t *strides;
+} ForayArrayView;
+
+// Allocate the data of output number `output`, as a contiguous row major array of `shape`.
+// Returns a pointer to write the output's elements to, or null if `output` doesn't exist, `ndim`
+// isn't the number of dimensions of an array output, or the array is too large to allocate.
+// Allocating the same output again discards its previous data
+typedef void *(*ForayAllocateFn)(void *context, size_t output, size_t ndim, const size_t *shape);
+
+// Passed to `compute`, for allocating outputs
+typedef struct ForayOutputs {
+  void *context;
+  ForayAllocateFn allocate;
+} ForayOutputs;
+
+// Compute a node's outputs.
+// `inputs` and `parameters` are in the order of the node's description.
+// Every output must be allocated with `outputs->allocate` and filled.
+// Returns 0 on success. On failure, a nul terminated message can be written to `error`,
+// which has room for `error_len` bytes. Must not unwind, e.g. throw a C++ exception
+typedef int32_t (*ForayComputeFn)(const struct ForayArrayView *inputs,
+                                  const double *parameters,
+                                  const struct ForayOutputs *outputs,
+                                  char *error,
+                                  size_t error_len);
+
+typedef struct ForayNodeDesc {
+  const char *name;
+  // One line summary, may be null
+  const char *description;
+  const struct ForayPortDesc *inputs;
+  size_t n_inputs;
+  const struct ForayPortDesc *outputs;
+  size_t n_outputs;
+  const struct ForayParameterDesc *parameters;
+  size_t n_parameters;
+  ForayComputeFn compute;
+} ForayNodeDesc;
+
+// Description of every node in a plugin.
+// Must stay valid for as long as the library is loaded
+typedef struct ForayPlugin {
+  // Must be `FORAY_PLUGIN_ABI_VERSION`
+  uint32_t abi_version;
+  const struct ForayNodeDesc *nodes;
+  size_t n_nodes;
+} ForayPlugin;
+
+// `const ForayPlugin *foray_plugin(void)`, exported by every plugin
+typedef const struct ForayPlugin *(*ForayPluginFn)(void);
+
+#endif  /* FORAY_PLUGIN_H */
+
+#ifndef FORAY_PLUGIN_FN
+#define FORAY_PLUGIN_FN
+#ifdef __cplusplus
+extern "C" {
+#endif // __cplusplus
+
+/* Every plugin exports this function, returning a description of its nodes */
+const ForayPlugin *foray_plugin(void);
+
+#ifdef __cplusplus
+}  // extern "C"
+#endif // __cplusplus
+#endif  /* FORAY_PLUGIN_FN */
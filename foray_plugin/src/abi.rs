@@ -0,0 +1,184 @@
+//! Types shared with plugins over the C ABI.
+//!
+//! `include/foray_plugin.h` is generated from this file, so any change here must increase
+//! `FORAY_PLUGIN_ABI_VERSION`, and the header must be regenerated with
+//! `FORAY_UPDATE_HEADER=1 cargo test -p foray_plugin header`
+use std::ffi::{c_char, c_void};
+
+/// Version of the layout of the types in this file.
+/// Plugins built against a different version are not loaded
+pub const FORAY_PLUGIN_ABI_VERSION: u32 = 1;
+
+/// Name of the function every plugin exports, see `ForayPluginFn`
+pub(crate) const FORAY_PLUGIN_SYMBOL: &[u8] = b"foray_plugin\0";
+
+/// Type of each element of a port's data
+#[repr(u32)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ForayElementType {
+    /// `int32_t`
+    Integer = 0,
+    /// `double`
+    Float = 1,
+    /// Two `double`s, real then imaginary
+    Complex = 2,
+    /// `bool`
+    Boolean = 3,
+}
+
+impl TryFrom<u32> for ForayElementType {
+    type Error = u32;
+
+    fn try_from(value: u32) -> Result<Self, Self::Error> {
+        match value {
+            0 => Ok(ForayElementType::Integer),
+            1 => Ok(ForayElementType::Float),
+            2 => Ok(ForayElementType::Complex),
+            3 => Ok(ForayElementType::Boolean),
+            _ => Err(value),
+        }
+    }
+}
+
+/// An input or output port.
+/// Ports with `ndim == 0` hold a single value, others hold arrays with `ndim` dimensions
+#[repr(C)]
+#[derive(Debug)]
+pub struct ForayPortDesc {
+    pub name: *const c_char,
+    /// A `ForayElementType`, plugins with other values are not loaded
+    pub element_type: u32,
+    pub ndim: usize,
+}
+
+/// How a parameter is edited in the side bar
+#[repr(u32)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ForayParameterKind {
+    NumberField = 0,
+    /// Slider from `min` to `max`
+    Slider = 1,
+    /// `default_value` is 0 or 1
+    CheckBox = 2,
+}
+
+impl TryFrom<u32> for ForayParameterKind {
+    type Error = u32;
+
+    fn try_from(value: u32) -> Result<Self, Self::Error> {
+        match value {
+            0 => Ok(ForayParameterKind::NumberField),
+            1 => Ok(ForayParameterKind::Slider),
+            2 => Ok(ForayParameterKind::CheckBox),
+            _ => Err(value),
+        }
+    }
+}
+
+#[repr(C)]
+#[derive(Debug)]
+pub struct ForayParameterDesc {
+    pub name: *const c_char,
+    /// A `ForayParameterKind`, plugins with other values are not loaded
+    pub kind: u32,
+    pub min: f64,
+    pub max: f64,
+    pub default_value: f64,
+}
+
+/// Read only view of an input's data.
+/// `shape` and `strides` have `ndim` elements, strides are counted in elements, not bytes
+#[repr(C)]
+#[derive(Debug)]
+pub struct ForayArrayView {
+    pub element_type: ForayElementType,
+    pub data: *const c_void,
+    pub ndim: usize,
+    pub shape: *const usize,
+    pub strides: *const isize,
+}
+
+/// Allocate the data of output number `output`, as a contiguous row major array of `shape`.
+/// Returns a pointer to write the output's elements to, or null if `output` doesn't exist, `ndim`
+/// isn't the number of dimensions of an array output, or the array is too large to allocate.
+/// Allocating the same output again discards its previous data
+pub type ForayAllocateFn = unsafe extern "C" fn(
+    context: *mut c_void,
+    output: usize,
+    ndim: usize,
+    shape: *const usize,
+) -> *mut c_void;
+
+/// Passed to `compute`, for allocating outputs
+#[repr(C)]
+#[derive(Debug)]
+pub struct ForayOutputs {
+    pub context: *mut c_void,
+    pub allocate: ForayAllocateFn,
+}
+
+/// Compute a node's outputs.
+/// `inputs` and `parameters` are in the order of the node's description.
+/// Every output must be allocated with `outputs->allocate` and filled.
+/// Returns 0 on success. On failure, a nul terminated message can be written to `error`,
+/// which has room for `error_len` bytes. Must not unwind, e.g. throw a C++ exception
+pub type ForayComputeFn = unsafe extern "C" fn(
+    inputs: *const ForayArrayView,
+    parameters: *const f64,
+    outputs: *const ForayOutputs,
+    error: *mut c_char,
+    error_len: usize,
+) -> i32;
+
+#[repr(C)]
+#[derive(Debug)]
+pub struct ForayNodeDesc {
+    pub name: *const c_char,
+    /// One line summary, may be null
+    pub description: *const c_char,
+    pub inputs: *const ForayPortDesc,
+    pub n_inputs: usize,
+    pub outputs: *const ForayPortDesc,
+    pub n_outputs: usize,
+    pub parameters: *const ForayParameterDesc,
+    pub n_parameters: usize,
+    pub compute: ForayComputeFn,
+}
+
+/// Description of every node in a plugin.
+/// Must stay valid for as long as the library is loaded
+#[repr(C)]
+#[derive(Debug)]
+pub struct ForayPlugin {
+    /// Must be `FORAY_PLUGIN_ABI_VERSION`
+    pub abi_version: u32,
+    pub nodes: *const ForayNodeDesc,
+    pub n_nodes: usize,
+}
+
+/// `const ForayPlugin *foray_plugin(void)`, exported by every plugin
+pub type ForayPluginFn = unsafe extern "C" fn() -> *const ForayPlugin;
+
+#[cfg(test)]
+mod test {
+    use std::{env, fs, path::Path};
+
+    #[test]
+    fn header() {
+        let crate_dir = env!("CARGO_MANIFEST_DIR");
+        let mut generated = vec![];
+        cbindgen::generate(crate_dir)
+            .expect("abi.rs should generate a header")
+            .write(&mut generated);
+
+        let path = Path::new(crate_dir).join("include/foray_plugin.h");
+        if env::var_os("FORAY_UPDATE_HEADER").is_some() {
+            fs::write(&path, &generated).unwrap();
+        }
+        assert!(
+            fs::read(&path).is_ok_and(|header| header == generated),
+            "include/foray_plugin.h is out of date, \
+            regenerate it with `FORAY_UPDATE_HEADER=1 cargo test -p foray_plugin header`"
+        );
+    }
+}
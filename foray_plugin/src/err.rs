@@ -0,0 +1,38 @@
+use std::fmt::Display;
+
+use serde::{Deserialize, Serialize};
+
+use crate::abi::FORAY_PLUGIN_ABI_VERSION;
+
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Serialize, Deserialize)]
+pub enum PluginError {
+    /// The library could not be loaded, or doesn't export `foray_plugin`
+    Load(String),
+    /// The library was built against a different version of `foray_plugin.h`
+    AbiVersion(u32),
+    /// The library doesn't define a node, e.g. it was removed since a network was saved
+    MissingNode(String),
+    /// A node's description is invalid
+    Description(String),
+    /// The node's inputs don't match its ports
+    Input(String),
+    /// The node's compute function reported an error
+    Compute(String),
+}
+
+impl Display for PluginError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            PluginError::Load(e) => write!(f, "Could not load plugin: {e}"),
+            PluginError::AbiVersion(found) => write!(
+                f,
+                "Plugin was built for ABI version {found}, expected {FORAY_PLUGIN_ABI_VERSION}. \
+                Rebuild it against the current foray_plugin.h"
+            ),
+            PluginError::MissingNode(name) => write!(f, "Plugin does not define node {name}"),
+            PluginError::Description(e) => write!(f, "Invalid plugin description: {e}"),
+            PluginError::Input(e) => write!(f, "Invalid input: {e}"),
+            PluginError::Compute(e) => write!(f, "{e}"),
+        }
+    }
+}
@@ -0,0 +1,10 @@
+//! Nodes written in C, C++, Fortran, or any language that can export a C interface,
+//! compiled into shared libraries and loaded at runtime.
+//!
+//! A plugin exports `foray_plugin`, which describes its nodes, see `include/foray_plugin.h`
+pub mod abi;
+pub mod err;
+mod library;
+pub mod node;
+
+pub use library::reload_library;
@@ -0,0 +1,132 @@
+use std::{
+    collections::HashMap,
+    env,
+    ffi::{CStr, c_char},
+    fs,
+    path::{Path, PathBuf},
+    process, slice,
+    sync::{
+        Arc, LazyLock, Mutex,
+        atomic::{AtomicUsize, Ordering},
+    },
+};
+
+use log::trace;
+
+use crate::{
+    abi::{
+        FORAY_PLUGIN_ABI_VERSION, FORAY_PLUGIN_SYMBOL, ForayNodeDesc, ForayPlugin, ForayPluginFn,
+    },
+    err::PluginError,
+};
+
+/// Libraries that have been loaded, by the path of the original library file
+static LIBRARIES: LazyLock<Mutex<HashMap<PathBuf, Arc<Library>>>> = LazyLock::new(Default::default);
+
+/// A loaded plugin library
+pub(crate) struct Library {
+    plugin: *const ForayPlugin,
+    // Unloads the library when dropped, so must outlive `plugin`, which points into it
+    _library: libloading::Library,
+}
+
+// SAFETY: a plugin's description is never modified, and plugins are documented to allow
+// computing from multiple threads at once
+unsafe impl Send for Library {}
+unsafe impl Sync for Library {}
+
+impl Library {
+    fn load(path: &Path) -> Result<Self, PluginError> {
+        let load_error = |e: &dyn std::fmt::Display| PluginError::Load(format!("{path:?}: {e}"));
+
+        // Load a copy of the library, so the original can be rebuilt while it's in use, and so
+        // the dynamic loader doesn't give back a previously loaded version of the same file
+        let copy = loaded_copy_path(path).map_err(|e| load_error(&e))?;
+        fs::copy(path, &copy).map_err(|e| load_error(&e))?;
+        trace!("loading plugin {path:?} from {copy:?}");
+        let library = unsafe { libloading::Library::new(&copy) }.map_err(|e| load_error(&e))?;
+        // The library stays loaded once its file is removed, except on windows where the
+        // file can't be removed while it's loaded
+        let _ = fs::remove_file(&copy);
+
+        let plugin = unsafe {
+            let plugin_fn = library
+                .get::<ForayPluginFn>(FORAY_PLUGIN_SYMBOL)
+                .map_err(|e| load_error(&e))?;
+            plugin_fn()
+        };
+        match unsafe { plugin.as_ref() } {
+            None => Err(PluginError::Description(
+                "foray_plugin() returned null".to_string(),
+            )),
+            Some(p) if p.abi_version != FORAY_PLUGIN_ABI_VERSION => {
+                Err(PluginError::AbiVersion(p.abi_version))
+            }
+            Some(_) => Ok(Library {
+                plugin,
+                _library: library,
+            }),
+        }
+    }
+
+    pub(crate) fn nodes(&self) -> &[ForayNodeDesc] {
+        // SAFETY: `plugin` was checked to be non-null when loaded, and stays valid while the
+        // library is loaded
+        let plugin = unsafe { &*self.plugin };
+        unsafe { c_slice(plugin.nodes, plugin.n_nodes) }
+    }
+
+    pub(crate) fn node(&self, name: &str) -> Result<&ForayNodeDesc, PluginError> {
+        self.nodes()
+            .iter()
+            .find(|desc| unsafe { c_string(desc.name) }.as_deref() == Some(name))
+            .ok_or_else(|| PluginError::MissingNode(name.to_string()))
+    }
+}
+
+/// Get a loaded library, loading it if it hasn't been yet
+pub(crate) fn library(path: &Path) -> Result<Arc<Library>, PluginError> {
+    let mut libraries = LIBRARIES.lock().unwrap();
+    if let Some(library) = libraries.get(path) {
+        return Ok(library.clone());
+    }
+    let library = Arc::new(Library::load(path)?);
+    libraries.insert(path.to_path_buf(), library.clone());
+    Ok(library)
+}
+
+/// Forget a loaded library, so that it is loaded again from disk the next time it's used.
+/// Nodes that are already computing keep using the old version
+pub fn reload_library(path: &Path) {
+    LIBRARIES.lock().unwrap().remove(path);
+}
+
+/// A unique path in the temp directory, to load a copy of the library from
+fn loaded_copy_path(path: &Path) -> std::io::Result<PathBuf> {
+    static LOADED_COUNT: AtomicUsize = AtomicUsize::new(0);
+
+    let dir = env::temp_dir().join("foray_plugins");
+    fs::create_dir_all(&dir)?;
+    let file_name = path.file_name().unwrap_or_default().to_string_lossy();
+    let count = LOADED_COUNT.fetch_add(1, Ordering::Relaxed);
+    Ok(dir.join(format!("{}-{count}-{file_name}", process::id())))
+}
+
+/// # Safety
+/// `ptr` must be null, or point to a nul terminated string
+pub(crate) unsafe fn c_string(ptr: *const c_char) -> Option<String> {
+    (!ptr.is_null()).then(|| {
+        unsafe { CStr::from_ptr(ptr) }
+            .to_string_lossy()
+            .into_owned()
+    })
+}
+
+/// # Safety
+/// `ptr` must be null, or point to `len` valid elements
+pub(crate) unsafe fn c_slice<'a, T>(ptr: *const T, len: usize) -> &'a [T] {
+    match ptr.is_null() {
+        true => &[],
+        false => unsafe { slice::from_raw_parts(ptr, len) },
+    }
+}
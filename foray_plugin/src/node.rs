@@ -0,0 +1,424 @@
+use std::{
+    borrow::Cow,
+    ffi::{c_char, c_void},
+    fs,
+    path::{Path, PathBuf},
+    ptr,
+};
+
+use foray_data_model::{
+    WireDataContainer,
+    node::{Dict, ForayArray, PortData, PortType, UIParameter},
+};
+use log::warn;
+use ndarray::{ArrayD, IxDyn};
+use num_complex::Complex64;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    abi::{
+        ForayArrayView, ForayElementType, ForayNodeDesc, ForayOutputs, ForayParameterDesc,
+        ForayParameterKind, ForayPortDesc,
+    },
+    err::PluginError,
+    library::{c_slice, c_string, library},
+};
+
+/// Size of the buffer a node's compute function can write an error message to
+const ERROR_LEN: usize = 1024;
+
+/// Template of a node defined by a plugin library
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq, PartialOrd)]
+pub struct PluginNodeTemplate {
+    pub name: String,
+    /// Shared library that defines the node
+    pub library: PathBuf,
+    pub description: Option<String>,
+    pub inputs: Dict<String, PortType>,
+    pub outputs: Dict<String, PortType>,
+    pub parameters: Dict<String, UIParameter>,
+}
+
+impl PluginNodeTemplate {
+    fn from_desc(library: &Path, desc: &ForayNodeDesc) -> Result<Self, PluginError> {
+        let ports = |ports: &[ForayPortDesc]| {
+            ports
+                .iter()
+                .map(|port| Ok((port_name(port)?, port_type(port)?)))
+                .collect::<Result<Dict<_, _>, PluginError>>()
+        };
+        let parameters = unsafe { c_slice(desc.parameters, desc.n_parameters) }
+            .iter()
+            .map(|parameter| Ok((parameter_name(parameter)?, ui_parameter(parameter)?)))
+            .collect::<Result<_, PluginError>>()?;
+
+        Ok(PluginNodeTemplate {
+            name: unsafe { c_string(desc.name) }
+                .ok_or_else(|| PluginError::Description("node without a name".to_string()))?,
+            library: library.to_path_buf(),
+            description: unsafe { c_string(desc.description) },
+            inputs: ports(unsafe { c_slice(desc.inputs, desc.n_inputs) })?,
+            outputs: ports(unsafe { c_slice(desc.outputs, desc.n_outputs) })?,
+            parameters,
+        })
+    }
+
+    /// Run the node's compute function, from the currently loaded version of its library
+    pub fn compute(
+        &self,
+        inputs: Dict<String, WireDataContainer<PortData>>,
+        parameters: &Dict<String, PortData>,
+    ) -> Result<Dict<String, PortData>, PluginError> {
+        let library = library(&self.library)?;
+        let desc = library.node(&self.name)?;
+        let input_descs = unsafe { c_slice(desc.inputs, desc.n_inputs) };
+        let output_descs = unsafe { c_slice(desc.outputs, desc.n_outputs) };
+        let parameter_descs = unsafe { c_slice(desc.parameters, desc.n_parameters) };
+
+        //// Inputs, in the order of the node's description
+        // Inputs stay locked until the compute function returns
+        let guards = input_descs
+            .iter()
+            .map(|port| {
+                let name = port_name(port)?;
+                inputs
+                    .get(&name)
+                    .map(|wire| wire.read().unwrap())
+                    .ok_or_else(|| PluginError::Input(format!("{name} is not connected")))
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+        let arrays = guards
+            .iter()
+            .zip(input_descs)
+            .map(|(data, port)| {
+                input_array(data).ok_or_else(|| {
+                    PluginError::Input(format!(
+                        "{} can't be passed to a plugin: {:?}",
+                        port_name(port).unwrap_or_default(),
+                        PortType::from(&**data)
+                    ))
+                })
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+        let views = arrays
+            .iter()
+            .zip(input_descs)
+            .map(|(array, port)| {
+                let view = array_view(array);
+                if view.element_type == element_type(port)? && view.ndim == port.ndim {
+                    Ok(view)
+                } else {
+                    Err(PluginError::Input(format!(
+                        "{} expected {:?}, got {:?}",
+                        port_name(port)?,
+                        port_type(port)?,
+                        PortType::from(&**array)
+                    )))
+                }
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+
+        //// Parameters, in the order of the node's description
+        let parameter_values: Vec<f64> = parameter_descs
+            .iter()
+            .map(|parameter| {
+                let name = parameter_name(parameter).unwrap_or_default();
+                match parameters.get(&name) {
+                    Some(PortData::Float(v)) => *v,
+                    Some(PortData::Integer(v)) => *v as f64,
+                    Some(PortData::Boolean(v)) => f64::from(u8::from(*v)),
+                    _ => parameter.default_value,
+                }
+            })
+            .collect();
+
+        //// Compute
+        let mut buffers = OutputBuffers {
+            element_types: output_descs
+                .iter()
+                .map(element_type)
+                .collect::<Result<_, _>>()?,
+            ndims: output_descs.iter().map(|port| port.ndim).collect(),
+            arrays: vec![None; output_descs.len()],
+        };
+        let outputs = ForayOutputs {
+            context: (&mut buffers as *mut OutputBuffers).cast(),
+            allocate: allocate_output,
+        };
+        let mut error = [0 as c_char; ERROR_LEN];
+        let status = unsafe {
+            (desc.compute)(
+                views.as_ptr(),
+                parameter_values.as_ptr(),
+                &outputs,
+                error.as_mut_ptr(),
+                error.len(),
+            )
+        };
+        if status != 0 {
+            // Make sure the message is terminated, even if the plugin filled the buffer
+            error[ERROR_LEN - 1] = 0;
+            let message = unsafe { c_string(error.as_ptr()) }.unwrap_or_default();
+            return Err(PluginError::Compute(match message.is_empty() {
+                true => format!("{} failed with status {status}", self.name),
+                false => message,
+            }));
+        }
+
+        //// Outputs
+        output_descs
+            .iter()
+            .zip(buffers.arrays)
+            .map(|(port, array)| {
+                let name = port_name(port)?;
+                let array = array
+                    .ok_or_else(|| PluginError::Compute(format!("output {name} was not set")))?;
+                let data = match port.ndim {
+                    0 => into_scalar(array)
+                        .ok_or_else(|| PluginError::Compute(format!("output {name} is empty")))?,
+                    _ => PortData::Array(array),
+                };
+                Ok((name, data))
+            })
+            .collect()
+    }
+}
+
+/// Load the nodes defined by a plugin library
+pub fn load_plugin(path: &Path) -> Result<Vec<PluginNodeTemplate>, PluginError> {
+    library(path)?
+        .nodes()
+        .iter()
+        .map(|desc| PluginNodeTemplate::from_desc(path, desc))
+        .collect()
+}
+
+/// Find and load plugin libraries in `dirs` and their subdirectories
+pub fn find_plugins(
+    dirs: &[PathBuf],
+) -> Vec<(PathBuf, Result<Vec<PluginNodeTemplate>, PluginError>)> {
+    fn libraries(dir: &Path) -> Vec<PathBuf> {
+        let Ok(entries) = fs::read_dir(dir) else {
+            return vec![];
+        };
+        entries
+            .flatten()
+            .map(|entry| entry.path())
+            .flat_map(|path| match path.is_dir() {
+                true => libraries(&path),
+                false => match is_plugin_file(&path) {
+                    true => vec![path],
+                    false => vec![],
+                },
+            })
+            .collect()
+    }
+
+    dirs.iter()
+        .flat_map(|dir| libraries(dir))
+        .map(|path| {
+            let nodes = load_plugin(&path);
+            if let Err(e) = &nodes {
+                warn!("{path:?}: {e}");
+            }
+            (path, nodes)
+        })
+        .collect()
+}
+
+/// Whether a file could be a plugin library.
+/// Python extension modules, e.g. `module.cpython-312-x86_64-linux-gnu.so`, are not
+pub fn is_plugin_file(path: &Path) -> bool {
+    path.extension() == Some(std::env::consts::DLL_EXTENSION.as_ref())
+        && path
+            .file_stem()
+            .is_some_and(|stem| !stem.to_string_lossy().contains('.'))
+}
+
+/// Outputs allocated by a node's compute function
+struct OutputBuffers {
+    element_types: Vec<ForayElementType>,
+    /// Number of dimensions of each output, 0 for values
+    ndims: Vec<usize>,
+    arrays: Vec<Option<ForayArray>>,
+}
+
+/// `ForayAllocateFn` given to plugins, `context` is an `OutputBuffers`.
+/// Returns null instead of panicking, as it's called from the plugin's code
+unsafe extern "C" fn allocate_output(
+    context: *mut c_void,
+    output: usize,
+    ndim: usize,
+    shape: *const usize,
+) -> *mut c_void {
+    let buffers = unsafe { &mut *context.cast::<OutputBuffers>() };
+    let Some(element_type) = buffers.element_types.get(output) else {
+        return ptr::null_mut();
+    };
+    // Values can be allocated with any shape, but arrays must match their port
+    let port_ndim = buffers.ndims[output];
+    if port_ndim != 0 && ndim != port_ndim {
+        return ptr::null_mut();
+    }
+    let shape = unsafe { c_slice(shape, ndim) };
+    let allocated = match element_type {
+        ForayElementType::Integer => zeroed(shape, 0, ForayArray::Integer),
+        ForayElementType::Float => zeroed(shape, 0.0, ForayArray::Float),
+        ForayElementType::Complex => zeroed(shape, Complex64::new(0.0, 0.0), ForayArray::Complex),
+        ForayElementType::Boolean => zeroed(shape, false, ForayArray::Boolean),
+    };
+    let Some((array, data)) = allocated else {
+        return ptr::null_mut();
+    };
+    buffers.arrays[output] = Some(array);
+    data
+}
+
+/// Array of `shape` filled with `zero`, and a pointer to its data.
+/// `None` if its size overflows, or it can't be allocated
+fn zeroed<A: Clone>(
+    shape: &[usize],
+    zero: A,
+    foray_array: fn(ArrayD<A>) -> ForayArray,
+) -> Option<(ForayArray, *mut c_void)> {
+    let len = shape
+        .iter()
+        .try_fold(1usize, |len, &n| len.checked_mul(n))?;
+    let mut data = Vec::new();
+    data.try_reserve_exact(len).ok()?;
+    data.resize(len, zero);
+    let mut array = ArrayD::from_shape_vec(IxDyn(shape), data).ok()?;
+    // Arrays keep their data where it is when moved, so the returned pointer stays valid
+    let data = array.as_mut_ptr().cast();
+    Some((foray_array(array), data))
+}
+
+/// Data of an input as an array, values are passed to plugins as 0 dimensional arrays
+fn input_array(data: &PortData) -> Option<Cow<'_, ForayArray>> {
+    let scalar = |array| Some(Cow::Owned(array));
+    let shape = IxDyn(&[]);
+    match data {
+        PortData::Integer(v) => scalar(ForayArray::Integer(ArrayD::from_elem(shape, *v))),
+        PortData::Float(v) => scalar(ForayArray::Float(ArrayD::from_elem(shape, *v))),
+        PortData::Complex((re, im)) => scalar(ForayArray::Complex(ArrayD::from_elem(
+            shape,
+            Complex64::new(*re, *im),
+        ))),
+        PortData::Boolean(v) => scalar(ForayArray::Boolean(ArrayD::from_elem(shape, *v))),
+        PortData::Array(
+            array @ (ForayArray::Integer(_)
+            | ForayArray::Float(_)
+            | ForayArray::Complex(_)
+            | ForayArray::Boolean(_)),
+        ) => Some(Cow::Borrowed(array)),
+        PortData::String(_) | PortData::Array(_) | PortData::Object(_) => None,
+    }
+}
+
+fn array_view(array: &ForayArray) -> ForayArrayView {
+    fn view<A>(array: &ArrayD<A>, element_type: ForayElementType) -> ForayArrayView {
+        ForayArrayView {
+            element_type,
+            data: array.as_ptr().cast(),
+            ndim: array.ndim(),
+            shape: array.shape().as_ptr(),
+            strides: array.strides().as_ptr(),
+        }
+    }
+    match array {
+        ForayArray::Integer(a) => view(a, ForayElementType::Integer),
+        ForayArray::Float(a) => view(a, ForayElementType::Float),
+        ForayArray::Complex(a) => view(a, ForayElementType::Complex),
+        ForayArray::Boolean(a) => view(a, ForayElementType::Boolean),
+        ForayArray::String(_) | ForayArray::Object(_) => {
+            unreachable!("only numeric arrays are passed to plugins")
+        }
+    }
+}
+
+fn into_scalar(array: ForayArray) -> Option<PortData> {
+    match array {
+        ForayArray::Integer(a) => a.first().map(|v| PortData::Integer(*v)),
+        ForayArray::Float(a) => a.first().map(|v| PortData::Float(*v)),
+        ForayArray::Complex(a) => a.first().map(|v| PortData::Complex((v.re, v.im))),
+        ForayArray::Boolean(a) => a.first().map(|v| PortData::Boolean(*v)),
+        ForayArray::String(_) | ForayArray::Object(_) => None,
+    }
+}
+
+fn port_name(port: &ForayPortDesc) -> Result<String, PluginError> {
+    unsafe { c_string(port.name) }
+        .ok_or_else(|| PluginError::Description("port without a name".to_string()))
+}
+
+fn parameter_name(parameter: &ForayParameterDesc) -> Result<String, PluginError> {
+    unsafe { c_string(parameter.name) }
+        .ok_or_else(|| PluginError::Description("parameter without a name".to_string()))
+}
+
+/// Element type of a port, which is read from the plugin as an integer so that unknown values
+/// can be rejected
+fn element_type(port: &ForayPortDesc) -> Result<ForayElementType, PluginError> {
+    ForayElementType::try_from(port.element_type).map_err(|value| {
+        PluginError::Description(format!(
+            "port {} has unknown element type {value}",
+            port_name(port).unwrap_or_default()
+        ))
+    })
+}
+
+fn port_type(port: &ForayPortDesc) -> Result<PortType, PluginError> {
+    let element = match element_type(port)? {
+        ForayElementType::Integer => PortType::Integer,
+        ForayElementType::Float => PortType::Float,
+        ForayElementType::Complex => PortType::Complex,
+        ForayElementType::Boolean => PortType::Boolean,
+    };
+    Ok(match port.ndim {
+        0 => element,
+        ndim => PortType::Array(Box::new(element), vec![None; ndim]),
+    })
+}
+
+fn ui_parameter(parameter: &ForayParameterDesc) -> Result<UIParameter, PluginError> {
+    let kind = ForayParameterKind::try_from(parameter.kind).map_err(|value| {
+        PluginError::Description(format!(
+            "parameter {} has unknown kind {value}",
+            parameter_name(parameter).unwrap_or_default()
+        ))
+    })?;
+    Ok(match kind {
+        ForayParameterKind::NumberField => UIParameter::NumberField(parameter.default_value),
+        ForayParameterKind::Slider => {
+            UIParameter::Slider(parameter.min, parameter.max, parameter.default_value)
+        }
+        ForayParameterKind::CheckBox => UIParameter::CheckBox(parameter.default_value != 0.0),
+    })
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn allocate() {
+        let mut buffers = OutputBuffers {
+            element_types: vec![ForayElementType::Float],
+            ndims: vec![2],
+            arrays: vec![None],
+        };
+        let context = (&mut buffers as *mut OutputBuffers).cast();
+        for shape in [[usize::MAX, 2], [usize::MAX / 4, 1]] {
+            let data = unsafe { allocate_output(context, 0, 2, shape.as_ptr()) };
+            assert!(data.is_null());
+        }
+        let data = unsafe { allocate_output(context, 0, 2, [2, 3].as_ptr()) };
+        assert!(!data.is_null());
+        assert!(unsafe { allocate_output(context, 1, 2, [2, 3].as_ptr()) }.is_null());
+        assert!(unsafe { allocate_output(context, 0, 3, [2, 3, 1].as_ptr()) }.is_null());
+        match &buffers.arrays[0] {
+            Some(ForayArray::Float(array)) => assert_eq!(array.shape(), [2, 3]),
+            array => panic!("expected a float array, got {array:?}"),
+        }
+    }
+}
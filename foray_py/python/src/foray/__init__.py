@@ -1,5 +1,22 @@
+import threading
 from enum import StrEnum
 
+try:
+    # Compiled bindings to run networks from python, see `foray_bindings`.
+    # Only available when that package is installed
+    from foray_bindings import Network
+except ModuleNotFoundError as e:
+    if e.name != "foray_bindings":
+        raise
+
+    def __getattr__(name: str):
+        if name == "Network":
+            raise ImportError(
+                "foray.Network needs the compiled bindings, "
+                "install them with `pip install <foray repository>/foray_bindings`"
+            )
+        raise AttributeError(f"module {__name__!r} has no attribute {name!r}")
+
 type PortType = PrimitivePortType | ArrayType | dict
 
 type ArrayShape = list[int | None]
@@ -12,6 +29,7 @@ class PrimitivePortType(StrEnum):
     complex = "Complex"
     boolean = "Boolean"
     string = "String"
+    any = "Any"
 
 
 class Port:
@@ -20,6 +38,7 @@ class Port:
     complex = PrimitivePortType.complex
     boolean = PrimitivePortType.boolean
     string = PrimitivePortType.integer
+    any = PrimitivePortType.any
 
     @staticmethod
     def array(port_type: PortType, port_shape: ArrayShape):
@@ -49,6 +68,24 @@ def FilePicker():
     return ("FilePicker", {"path": ""})
 
 
+def TextInput(default_value: str = ""):
+    return ("TextInput", {"default": default_value})
+
+
+def CodeEditor(default_value: str = ""):
+    return ("CodeEditor", {"default": default_value})
+
+
+def DirectoryPicker():
+    return ("DirectoryPicker", {"path": ""})
+
+
+def Dropdown(options: list[str], default_value: str | None = None):
+    if default_value is None:
+        default_value = options[0] if options else ""
+    return ("Dropdown", {"options": options, "default": default_value})
+
+
 class ForayConfig(dict):
     def inputs(self, input_ports: dict[str, PortType]):
         self["inputs"] = input_ports
@@ -61,3 +98,69 @@ class ForayConfig(dict):
     def parameters(self, parameters: dict[str, ParameterType]):
         self["parameters"] = parameters
         return self
+
+    def description(self, description: str):
+        """One line summary, shown when adding nodes.
+        Defaults to the first paragraph of the module's docstring"""
+        self["description"] = description
+        return self
+
+    def docs(self, docs: str):
+        """Markdown documentation, shown in the node's help pane.
+        Defaults to the module's docstring"""
+        self["docs"] = docs
+        return self
+
+    def tags(self, *tags: str):
+        """Extra words to match when searching for nodes"""
+        self["tags"] = list(tags)
+        return self
+
+    def icon(self, glyph: str):
+        """Glyph shown next to the node's name, e.g. a nerd font icon"""
+        self["icon"] = glyph
+        return self
+
+    def version(self, version: int):
+        """Version of the node's config and parameters, increase it when they change"""
+        self["version"] = version
+        return self
+
+    def renamed_ports(self, renames: dict[str, str]):
+        """Ports that were renamed, from old name to new name.
+        Connections of networks saved with the old name are moved to the new name"""
+        self["renamed_ports"] = renames
+        return self
+
+    def renamed_parameters(self, renames: dict[str, str]):
+        """Parameters that were renamed, from old name to new name.
+        Values of networks saved with the old name are moved to the new name"""
+        self["renamed_parameters"] = renames
+        return self
+
+    def port_doc(self, port: str, description: str = "", units: str = ""):
+        """Describe an input or output port"""
+        self.setdefault("port_docs", {})[port] = {
+            "description": description,
+            "units": units,
+        }
+        return self
+
+
+class _ProgressState(threading.local):
+    # Set by foray while a node's `compute` function is running on this thread
+    callback = None
+
+
+_progress_state = _ProgressState()
+
+
+def progress(fraction: float, message: str = ""):
+    """Report progress from inside a node's `compute` function.
+
+    `fraction` is the completed portion of work, from 0.0 to 1.0.
+    Does nothing when the node is not being run by foray.
+    """
+    callback = _progress_state.callback
+    if callback is not None:
+        callback(float(fraction), str(message))
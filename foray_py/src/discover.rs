@@ -1,11 +1,19 @@
-use std::path::PathBuf;
+use std::{
+    ffi::OsStr,
+    fmt::Display,
+    fs,
+    path::{Path, PathBuf},
+};
 
+use log::{trace, warn};
 use pyo3::{
     Python,
     ffi::c_str,
     types::{PyAnyMethods, PyModule},
 };
 
+use crate::err::{RuntimeErr, py_err_traceback};
+
 /// Information about a python package that is provides nodes
 /// Unprocessed, strait from python
 pub struct RawNodePackageInfo {
@@ -19,63 +27,245 @@ pub struct RawNodePackageInfo {
     pub node_py_paths: Vec<String>,
 }
 
-pub fn get_foray_py_packages() -> Vec<RawNodePackageInfo> {
+/// Why the nodes of a package could not be discovered
+#[derive(Clone, Debug, PartialEq)]
+pub struct DiscoverError {
+    /// The package that declared the entry point
+    pub package_name: String,
+    pub entry_point: String,
+    /// Name of the module that could not be found, if the entry point failed to import because
+    /// of a missing module
+    pub missing_module: Option<String>,
+    pub error: RuntimeErr,
+    /// Suggested fix, if the cause can be guessed
+    pub hint: Option<String>,
+}
+
+impl Display for DiscoverError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "Could not load nodes of {} (entry point `{}`)\n{}",
+            self.package_name, self.entry_point, self.error.error
+        )?;
+        if let Some(hint) = &self.hint {
+            write!(f, "\n\n{hint}")?;
+        }
+        Ok(())
+    }
+}
+
+/// Find the nodes of every package with a `foray` entry point in the current python environment.
+/// A package that fails to load doesn't prevent other packages from being discovered
+pub fn get_foray_py_packages() -> Vec<Result<RawNodePackageInfo, DiscoverError>> {
+    type Loaded = (String, String, PathBuf, Vec<String>);
+    type Failed = (String, String, String, String, Option<String>);
+
     Python::with_gil(|py| {
-        let snippet = PyModule::from_code(
+        let result = PyModule::from_code(
             py,
             c_str!(
                 r#"
 from importlib.metadata import entry_points
 import pkgutil
+import traceback
 
 
 def get_node_paths():
-    # get all 'foray' entry points as configured in `pyproject.toml`s
-    # (module, entry_point/node_module)
-    foray_modules = [(ep.load(), ep.value) for ep in entry_points(group="foray")]
-
     def recurse_module(module):
         return pkgutil.walk_packages(module.__path__, module.__name__ + ".")
 
-    return [
-        (
-            m.__name__.split(".")[0],  # get root module
-            entry_point,
-            # Usually there is just 1 element in this list. Unsure when there is more
-            m.__path__[0],
-            # get all submodules
-            [
-                name
-                for _, name, ispkg in recurse_module(m)
-                if not ispkg  # discard packages, e.g. __init__.py
-            ],
-        )
-        for (m, entry_point) in foray_modules
-    ]
+    def load(ep):
+        try:
+            m = ep.load()
+            loaded = (
+                m.__name__.split(".")[0],  # get root module
+                ep.value,
+                # Usually there is just 1 element in this list. Unsure when there is more
+                m.__path__[0],
+                # get all submodules
+                [
+                    name
+                    for _, name, ispkg in recurse_module(m)
+                    if not ispkg  # discard packages, e.g. __init__.py
+                ],
+            )
+            return (loaded, None)
+        except Exception as e:
+            package_name = ep.dist.name if ep.dist is not None else ep.value.split(".")[0]
+            missing_module = e.name if isinstance(e, ModuleNotFoundError) else None
+            failed = (
+                package_name,
+                ep.value,
+                f"{type(e).__name__}: {e}",
+                traceback.format_exc(),
+                missing_module,
+            )
+            return (None, failed)
+
+    # get all 'foray' entry points as configured in `pyproject.toml`s
+    return [load(ep) for ep in entry_points(group="foray")]
 "#
             ),
             c_str!("dicover.py"),
             c_str!("discover"),
         )
-        .unwrap();
-        let result: Vec<(String, String, PathBuf, Vec<String>)> = snippet
-            .getattr("get_node_paths")
-            .unwrap()
-            .call0()
-            .unwrap()
-            .extract()
-            .unwrap();
+        .and_then(|snippet| snippet.getattr("get_node_paths")?.call0()?.extract());
+
+        let result: Vec<(Option<Loaded>, Option<Failed>)> = match result {
+            Ok(r) => r,
+            Err(e) => {
+                return vec![Err(DiscoverError {
+                    package_name: "foray".to_string(),
+                    entry_point: "foray".to_string(),
+                    missing_module: None,
+                    error: py_err_traceback(e),
+                    hint: None,
+                })];
+            }
+        };
 
         result
             .into_iter()
-            .map(
-                |(package_name, entry_point, abs_path, node_py_paths)| RawNodePackageInfo {
-                    package_name,
-                    entry_point,
-                    abs_path,
-                    node_py_paths,
-                },
-            )
+            .filter_map(|(loaded, failed)| match (loaded, failed) {
+                (Some((package_name, entry_point, abs_path, node_py_paths)), _) => {
+                    Some(Ok(RawNodePackageInfo {
+                        package_name,
+                        entry_point,
+                        abs_path,
+                        node_py_paths,
+                    }))
+                }
+                (_, Some((package_name, entry_point, error, traceback, missing_module))) => {
+                    let hint = missing_module.as_ref().and_then(|_| moved_venv_hint(py));
+                    Some(Err(DiscoverError {
+                        package_name,
+                        entry_point,
+                        missing_module,
+                        error: RuntimeErr { error, traceback },
+                        hint,
+                    }))
+                }
+                (None, None) => None,
+            })
             .collect()
     })
 }
+
+/// Python path entries are absolute, including those pointing to editable installs.
+/// When a workspace or its virtual environment is moved, they point to directories
+/// that no longer exist
+fn moved_venv_hint(py: Python<'_>) -> Option<String> {
+    let sys_path: Vec<PathBuf> = PyModule::import(py, "sys")
+        .and_then(|sys| sys.getattr("path")?.extract())
+        .ok()?;
+    let missing: Vec<String> = sys_path
+        .iter()
+        .filter(|p| !p.as_os_str().is_empty() && !p.exists())
+        .map(|p| format!("  {}", p.display()))
+        .collect();
+    match missing.is_empty() {
+        true => None,
+        false => Some(format!(
+            "These python path entries don't exist:\n{}\n\
+            If the workspace or its virtual environment was moved, recreate the environment \
+            (e.g. `uv sync`) so installed packages point to their new location",
+            missing.join("\n")
+        )),
+    }
+}
+
+/// Find nodes in plain directories of python files, that aren't installed as a package.
+///
+/// Each directory is treated as a python namespace package, named after the directory,
+/// and its parent directory is added to `sys.path` so the nodes can be imported.
+/// Any `.py` file that defines both `config` and `compute` functions is a node
+pub fn get_directory_packages(dirs: &[PathBuf]) -> Vec<RawNodePackageInfo> {
+    dirs.iter()
+        .filter(|dir| {
+            if !dir.is_dir() {
+                trace!("Skipping node directory {dir:?}, it does not exist");
+            }
+            dir.is_dir()
+        })
+        .filter_map(|dir| {
+            let package_name = dir.file_name()?.to_str()?.to_string();
+            if !is_identifier(&package_name) {
+                warn!(
+                    "Skipping node directory {dir:?}, its name is not a valid python module name"
+                );
+                return None;
+            }
+            let parent = dir.parent()?;
+            if let Err(e) = add_to_sys_path(parent) {
+                warn!("Could not add {parent:?} to python path\n{e}");
+                return None;
+            }
+
+            let mut node_py_paths: Vec<String> = find_node_files(dir)
+                .into_iter()
+                .filter_map(|file| {
+                    let relative = file.strip_prefix(parent).ok()?.with_extension("");
+                    let modules: Option<Vec<&str>> = relative
+                        .components()
+                        .map(|c| c.as_os_str().to_str())
+                        .collect();
+                    Some(modules?.join("."))
+                })
+                .collect();
+            node_py_paths.sort();
+
+            Some(RawNodePackageInfo {
+                entry_point: package_name.clone(),
+                package_name,
+                abs_path: dir.clone(),
+                node_py_paths,
+            })
+        })
+        .collect()
+}
+
+/// Recursively find python files that look like nodes, without importing them
+fn find_node_files(dir: &Path) -> Vec<PathBuf> {
+    let Ok(entries) = fs::read_dir(dir) else {
+        return vec![];
+    };
+    entries
+        .filter_map(|entry| entry.ok())
+        .map(|entry| entry.path())
+        .flat_map(|path| {
+            let name = path.file_name().and_then(OsStr::to_str).unwrap_or_default();
+            if name.starts_with('.') || name == "__pycache__" {
+                vec![]
+            } else if path.is_dir() && is_identifier(name) {
+                find_node_files(&path)
+            } else if path.extension() == Some(OsStr::new("py"))
+                && is_identifier(name.trim_end_matches(".py"))
+                && fs::read_to_string(&path)
+                    .is_ok_and(|s| s.contains("def config(") && s.contains("def compute("))
+            {
+                vec![path]
+            } else {
+                vec![]
+            }
+        })
+        .collect()
+}
+
+fn is_identifier(name: &str) -> bool {
+    let mut chars = name.chars();
+    chars.next().is_some_and(|c| c.is_alphabetic() || c == '_')
+        && chars.all(|c| c.is_alphanumeric() || c == '_')
+}
+
+fn add_to_sys_path(dir: &Path) -> pyo3::PyResult<()> {
+    // sys.path entries should be strings
+    let dir = dir.to_string_lossy().to_string();
+    Python::with_gil(|py| {
+        let sys_path = PyModule::import(py, "sys")?.getattr("path")?;
+        if !sys_path.contains(&dir)? {
+            sys_path.call_method1("append", (dir,))?;
+        }
+        Ok(())
+    })
+}
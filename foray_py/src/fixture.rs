@@ -0,0 +1,310 @@
+use std::{
+    fs,
+    path::{Path, PathBuf},
+};
+
+use foray_data_model::node::{Dict, ForayArray, PortData, PortType};
+use pyo3::{
+    Python,
+    types::{PyAnyMethods, PyModule},
+};
+
+use crate::{
+    err::PyNodeConfigError,
+    npz::load_npz,
+    py_node::{PyNodeTemplate, py_compute_unlocked},
+    reproducer::{INPUTS_FILE, PARAMETERS_FILE},
+};
+
+/// Directory, next to a node's module, that holds the node's fixtures
+pub const FIXTURE_DIR: &str = "fixtures";
+pub const OUTPUTS_FILE: &str = "outputs.npz";
+pub const TOLERANCE_FILE: &str = "tolerance.json";
+
+/// Allowed difference between an expected and a computed value, with the same meaning as
+/// numpy's `allclose`: `|actual - expected| <= atol + rtol * |expected|`
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Tolerance {
+    pub rtol: f64,
+    pub atol: f64,
+}
+
+impl Default for Tolerance {
+    fn default() -> Self {
+        // numpy's defaults
+        Self {
+            rtol: 1e-5,
+            atol: 1e-8,
+        }
+    }
+}
+
+impl Tolerance {
+    fn close(&self, actual: f64, expected: f64) -> bool {
+        (actual - expected).abs() <= self.atol + self.rtol * expected.abs()
+            || (actual.is_nan() && expected.is_nan())
+    }
+}
+
+/// A single recorded compute call of a node, and the outputs it is expected to produce.
+///
+/// Fixtures are stored next to the node's module, e.g. for `my_nodes/filters/blur.py`:
+/// ```text
+/// my_nodes/filters/fixtures/blur/<case>/
+///     inputs.npz
+///     parameters.json
+///     outputs.npz
+///     tolerance.json  (optional, e.g. {"rtol": 1e-3, "atol": 0.0})
+/// ```
+/// The layout matches a saved reproducer, with the addition of `outputs.npz`
+#[derive(Clone, Debug)]
+pub struct Fixture {
+    pub case: String,
+    pub dir: PathBuf,
+}
+
+#[derive(Clone, Debug)]
+pub enum FixtureOutcome {
+    Pass,
+    /// Outputs were computed, but didn't match the expected outputs
+    Fail(Vec<String>),
+    /// The fixture couldn't be loaded, or the node failed to compute
+    Error(PyNodeConfigError),
+}
+
+#[derive(Clone, Debug)]
+pub struct FixtureResult {
+    pub node: String,
+    pub case: String,
+    pub outcome: FixtureOutcome,
+}
+
+/// Find all fixtures of a python node, sorted by case name
+pub fn find_fixtures(template: &PyNodeTemplate) -> Result<Vec<Fixture>, PyNodeConfigError> {
+    let dir = module_dir(&template.py_path)?
+        .join(FIXTURE_DIR)
+        .join(&template.name);
+    if !dir.is_dir() {
+        return Ok(vec![]);
+    }
+
+    let mut fixtures: Vec<Fixture> = fs::read_dir(&dir)?
+        .filter_map(|entry| entry.ok())
+        .map(|entry| entry.path())
+        .filter(|path| path.join(OUTPUTS_FILE).is_file())
+        .map(|dir| Fixture {
+            case: dir
+                .file_name()
+                .map(|n| n.to_string_lossy().to_string())
+                .unwrap_or_default(),
+            dir,
+        })
+        .collect();
+    fixtures.sort_by(|a, b| a.case.cmp(&b.case));
+    Ok(fixtures)
+}
+
+/// Run a node's compute function with a fixture's inputs and parameters,
+/// and compare the outputs against the fixture's expected outputs.
+/// A `tolerance.json` in the fixture takes precedence over `default_tolerance`
+pub fn run_fixture(
+    template: &PyNodeTemplate,
+    fixture: &Fixture,
+    default_tolerance: Tolerance,
+) -> FixtureResult {
+    let outcome = match check_fixture(template, fixture, default_tolerance) {
+        Ok(mismatches) if mismatches.is_empty() => FixtureOutcome::Pass,
+        Ok(mismatches) => FixtureOutcome::Fail(mismatches),
+        Err(e) => FixtureOutcome::Error(e),
+    };
+    FixtureResult {
+        node: template.name.clone(),
+        case: fixture.case.clone(),
+        outcome,
+    }
+}
+
+fn check_fixture(
+    template: &PyNodeTemplate,
+    fixture: &Fixture,
+    default_tolerance: Tolerance,
+) -> Result<Vec<String>, PyNodeConfigError> {
+    let inputs = match fixture.dir.join(INPUTS_FILE) {
+        path if path.is_file() => load_npz(&path)?,
+        // Nodes without inputs don't need an inputs file
+        _ => Dict::new(),
+    };
+    let parameters = match fixture.dir.join(PARAMETERS_FILE) {
+        path if path.is_file() => load_json(&path)?,
+        _ => Dict::new(),
+    };
+    let expected = load_npz(&fixture.dir.join(OUTPUTS_FILE))?;
+    let tolerance = match fixture.dir.join(TOLERANCE_FILE) {
+        path if path.is_file() => load_tolerance(&path, default_tolerance)?,
+        _ => default_tolerance,
+    };
+
+    let actual = py_compute_unlocked(template, inputs, parameters)?;
+    Ok(compare_outputs(&expected, &actual, tolerance))
+}
+
+/// Compare each expected output to the computed one, returning a description of each mismatch
+pub fn compare_outputs(
+    expected: &Dict<String, PortData>,
+    actual: &Dict<String, PortData>,
+    tolerance: Tolerance,
+) -> Vec<String> {
+    expected
+        .iter()
+        .filter_map(|(name, expected)| match actual.get(name) {
+            None => Some(format!("{name}: missing from outputs")),
+            Some(actual) => compare(expected, actual, tolerance)
+                .err()
+                .map(|e| format!("{name}: {e}")),
+        })
+        .collect()
+}
+
+/// Check that `actual` matches `expected`, numbers are compared within `tolerance`.
+/// Integers and floats are compared as numbers, as values saved to `.npz` don't always
+/// keep their exact type
+pub fn compare(expected: &PortData, actual: &PortData, tolerance: Tolerance) -> Result<(), String> {
+    use PortData as P;
+    let close = |a: f64, e: f64| -> Result<(), String> {
+        match tolerance.close(a, e) {
+            true => Ok(()),
+            false => Err(format!("expected {e}, got {a}")),
+        }
+    };
+    match (expected, actual) {
+        (P::Integer(e), P::Integer(a)) if e == a => Ok(()),
+        (P::Integer(e), P::Integer(a)) => Err(format!("expected {e}, got {a}")),
+        (P::Float(e), P::Float(a)) => close(*a, *e),
+        (P::Integer(e), P::Float(a)) => close(*a, *e as f64),
+        (P::Float(e), P::Integer(a)) => close(*a as f64, *e),
+        (P::Complex(e), P::Complex(a)) => close(a.0, e.0).and_then(|_| close(a.1, e.1)),
+        (P::Boolean(e), P::Boolean(a)) if e == a => Ok(()),
+        (P::String(e), P::String(a)) if e == a => Ok(()),
+        (P::Array(e), P::Array(a)) => compare_arrays(e, a, tolerance),
+        (P::Object(e), P::Object(a)) => match compare_outputs(e, a, tolerance).into_iter().next() {
+            Some(mismatch) => Err(mismatch),
+            None => Ok(()),
+        },
+        (e, a) => Err(format!("expected {e:?}, got {a:?}")),
+    }
+}
+
+fn compare_arrays(
+    expected: &ForayArray,
+    actual: &ForayArray,
+    tolerance: Tolerance,
+) -> Result<(), String> {
+    use ForayArray as A;
+    fn check_shape(e: &[usize], a: &[usize]) -> Result<(), String> {
+        match e == a {
+            true => Ok(()),
+            false => Err(format!("expected shape {e:?}, got {a:?}")),
+        }
+    }
+    // Report the largest difference, rather than the first one
+    fn check_close(
+        differences: impl Iterator<Item = (f64, bool)>,
+        count: usize,
+    ) -> Result<(), String> {
+        let (mismatched, max_difference) = differences
+            .filter(|(_, close)| !close)
+            .fold((0, 0.0_f64), |(n, max), (diff, _)| (n + 1, max.max(diff)));
+        match mismatched {
+            0 => Ok(()),
+            _ => Err(format!(
+                "{mismatched}/{count} elements differ, max difference {max_difference:e}"
+            )),
+        }
+    }
+
+    match (expected, actual) {
+        (A::Float(e), A::Float(a)) => {
+            check_shape(e.shape(), a.shape())?;
+            check_close(
+                e.iter()
+                    .zip(a.iter())
+                    .map(|(e, a)| ((a - e).abs(), tolerance.close(*a, *e))),
+                e.len(),
+            )
+        }
+        (A::Integer(e), A::Integer(a)) => {
+            check_shape(e.shape(), a.shape())?;
+            check_close(
+                e.iter()
+                    .zip(a.iter())
+                    .map(|(e, a)| ((*a as f64 - *e as f64).abs(), a == e)),
+                e.len(),
+            )
+        }
+        (A::Complex(e), A::Complex(a)) => {
+            check_shape(e.shape(), a.shape())?;
+            check_close(
+                e.iter().zip(a.iter()).map(|(e, a)| {
+                    let diff = (a - e).norm();
+                    (diff, diff <= tolerance.atol + tolerance.rtol * e.norm())
+                }),
+                e.len(),
+            )
+        }
+        (A::Boolean(e), A::Boolean(a)) => {
+            check_shape(e.shape(), a.shape())?;
+            check_close(e.iter().zip(a.iter()).map(|(e, a)| (1.0, a == e)), e.len())
+        }
+        (A::String(e), A::String(a)) => {
+            check_shape(e.shape(), a.shape())?;
+            check_close(e.iter().zip(a.iter()).map(|(e, a)| (1.0, a == e)), e.len())
+        }
+        (A::Object(e), A::Object(a)) => {
+            check_shape(e.shape(), a.shape())?;
+            e.iter()
+                .zip(a.iter())
+                .try_for_each(|(e, a)| compare(e, a, tolerance))
+        }
+        (e, a) => Err(format!(
+            "expected array of {:?}, got {:?}",
+            PortType::from(e),
+            PortType::from(a)
+        )),
+    }
+}
+
+/// Directory containing a python module, without importing it
+fn module_dir(py_path: &str) -> Result<PathBuf, PyNodeConfigError> {
+    Python::with_gil(|py| {
+        let origin: PathBuf = PyModule::import(py, "importlib.util")?
+            .getattr("find_spec")?
+            .call1((py_path,))?
+            .getattr("origin")?
+            .extract()?;
+        Ok(origin.parent().map(Path::to_path_buf).unwrap_or_default())
+    })
+}
+
+fn load_json(path: &Path) -> Result<Dict<String, PortData>, PyNodeConfigError> {
+    let contents = fs::read_to_string(path)?;
+    Python::with_gil(|py| {
+        Ok(PyModule::import(py, "json")?
+            .getattr("loads")?
+            .call1((contents,))?
+            .extract()?)
+    })
+}
+
+fn load_tolerance(path: &Path, default: Tolerance) -> Result<Tolerance, PyNodeConfigError> {
+    let contents = fs::read_to_string(path)?;
+    Python::with_gil(|py| {
+        let values: Dict<String, f64> = PyModule::import(py, "json")?
+            .getattr("loads")?
+            .call1((contents,))?
+            .extract()?;
+        Ok(Tolerance {
+            rtol: values.get("rtol").copied().unwrap_or(default.rtol),
+            atol: values.get("atol").copied().unwrap_or(default.atol),
+        })
+    })
+}
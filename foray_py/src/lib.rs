@@ -1,3 +1,9 @@
 pub mod discover;
 pub mod err;
+pub mod fixture;
+pub mod npz;
+mod progress;
 pub mod py_node;
+pub mod reload;
+pub mod reproducer;
+pub mod snippet;
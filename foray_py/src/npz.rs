@@ -0,0 +1,48 @@
+use std::path::Path;
+
+use foray_data_model::node::{Dict, PortData};
+use pyo3::{
+    PyResult, Python,
+    types::{IntoPyDict, PyAnyMethods, PyDict, PyModule},
+};
+
+use crate::err::PyNodeConfigError;
+
+/// Save port values to a numpy `.npz` archive, one entry per port.
+/// Values that aren't arrays are stored as 0-d arrays
+pub fn save_npz(path: &Path, values: Dict<String, PortData>) -> Result<(), PyNodeConfigError> {
+    Python::with_gil(|py| {
+        let numpy = PyModule::import(py, "numpy")?;
+        let arrays = PyDict::new(py);
+        for (name, value) in values {
+            arrays.set_item(name, value)?;
+        }
+        numpy.getattr("savez")?.call((path,), Some(&arrays))?;
+        Ok(())
+    })
+}
+
+/// Load port values from a numpy `.npz` archive.
+/// 0-d arrays are unpacked back into plain values
+pub fn load_npz(path: &Path) -> Result<Dict<String, PortData>, PyNodeConfigError> {
+    Python::with_gil(|py| {
+        let numpy = PyModule::import(py, "numpy")?;
+        let archive = numpy
+            .getattr("load")?
+            .call((path,), Some(&[("allow_pickle", true)].into_py_dict(py)?))?;
+        let names: Vec<String> = archive.getattr("files")?.extract()?;
+
+        let values = names
+            .into_iter()
+            .map(|name| {
+                let mut value = archive.get_item(&name)?;
+                if value.getattr("ndim")?.extract::<usize>()? == 0 {
+                    value = value.call_method0("item")?;
+                }
+                Ok((name, value.extract::<PortData>()?))
+            })
+            .collect::<PyResult<Dict<_, _>>>();
+        archive.call_method0("close")?;
+        Ok(values?)
+    })
+}
@@ -0,0 +1,64 @@
+use std::sync::Arc;
+
+use foray_data_model::context::{ComputeContext, Progress};
+use log::{trace, warn};
+use pyo3::{
+    Bound, PyAny, PyResult, Python,
+    types::{PyAnyMethods, PyCFunction, PyDict, PyModule, PyTuple},
+};
+
+/// Makes `foray.progress(fraction, message)` deliver updates to the current thread's
+/// [`ComputeContext`] for as long as the hook is alive.
+///
+/// The `foray` python module keeps a thread local `_progress_state.callback`, which is set here,
+/// and cleared again when the hook is dropped.
+pub(crate) struct ProgressHook<'py> {
+    state: Option<Bound<'py, PyAny>>,
+}
+
+impl<'py> ProgressHook<'py> {
+    pub(crate) fn install(py: Python<'py>) -> Self {
+        let Some(reporter) = ComputeContext::current().and_then(|c| c.progress) else {
+            return Self { state: None };
+        };
+        let state = match PyModule::import(py, "foray").and_then(|m| m.getattr("_progress_state")) {
+            Ok(state) => state,
+            Err(_) => {
+                trace!("installed `foray` module does not support progress, updates are ignored");
+                return Self { state: None };
+            }
+        };
+
+        // Only hold a weak reference, python code could keep the callback alive
+        // longer than the compute call it belongs to
+        let reporter = Arc::downgrade(&reporter);
+        let callback = PyCFunction::new_closure(
+            py,
+            None,
+            None,
+            move |args: &Bound<'_, PyTuple>, _kwargs: Option<&Bound<'_, PyDict>>| -> PyResult<()> {
+                let (fraction, message): (f32, String) = args.extract()?;
+                if let Some(reporter) = reporter.upgrade() {
+                    reporter(Progress::new(fraction, message));
+                }
+                Ok(())
+            },
+        );
+
+        match callback.and_then(|callback| state.setattr("callback", callback)) {
+            Ok(()) => Self { state: Some(state) },
+            Err(e) => {
+                warn!("Could not install progress callback: {e}");
+                Self { state: None }
+            }
+        }
+    }
+}
+
+impl Drop for ProgressHook<'_> {
+    fn drop(&mut self) {
+        if let Some(state) = &self.state {
+            let _ = state.setattr("callback", state.py().None());
+        }
+    }
+}
@@ -3,15 +3,18 @@ use foray_data_model::{
     node::{Dict, ParameterError, PortData, PortError, PortType, UIParameter},
 };
 
-use log::trace;
+use log::{trace, warn};
 use pyo3::{
-    Bound, PyAny, Python,
+    Bound, FromPyObject, PyAny, Python,
     types::{PyAnyMethods, PyModule},
 };
 
 use serde::{Deserialize, Serialize};
 
-use crate::err::{PyNodeConfigError, py_err_traceback};
+use crate::{
+    err::{PyNodeConfigError, py_err_traceback},
+    progress::ProgressHook,
+};
 
 /// Template that will be stored for each available node type
 #[derive(Clone, Debug, Serialize, Deserialize, PartialEq, PartialOrd)]
@@ -26,6 +29,14 @@ pub struct PyConfig {
     pub inputs: Result<Dict<String, PortType>, PortError>,
     pub outputs: Result<Dict<String, PortType>, PortError>,
     pub parameters: Result<Dict<String, UIParameter>, ParameterError>,
+    /// Metadata and migrations are boxed, so that node templates stay small
+    #[serde(default)]
+    pub metadata: Box<NodeMetadata>,
+    #[serde(default)]
+    pub migrations: Box<Migrations>,
+    /// `config` takes the node's parameter values, and is re-evaluated when they change
+    #[serde(default)]
+    pub dynamic: bool,
 }
 impl Default for PyConfig {
     fn default() -> Self {
@@ -33,10 +44,44 @@ impl Default for PyConfig {
             inputs: Ok(Default::default()),
             outputs: Ok(Default::default()),
             parameters: Ok(Default::default()),
+            metadata: Default::default(),
+            migrations: Default::default(),
+            dynamic: false,
         }
     }
 }
 
+/// How to carry a node's configuration forward, when its ports or parameters are renamed.
+/// Maps are from old name to new name
+#[derive(Clone, Debug, Default, Serialize, Deserialize, PartialEq, PartialOrd)]
+pub struct Migrations {
+    pub renamed_ports: Dict<String, String>,
+    pub renamed_parameters: Dict<String, String>,
+}
+
+/// Descriptive information about a node, used for display and search.
+/// Every field is optional in a node's config
+#[derive(Clone, Debug, Default, Serialize, Deserialize, PartialEq, PartialOrd)]
+pub struct NodeMetadata {
+    /// One line summary, defaults to the first paragraph of the module docstring
+    pub description: Option<String>,
+    /// Markdown documentation, defaults to the module docstring
+    pub docs: Option<String>,
+    pub tags: Vec<String>,
+    /// Glyph shown next to the node's name
+    pub icon: Option<String>,
+    /// Version of the node's config, 0 if the node isn't versioned
+    pub version: u32,
+    /// Descriptions of input and output ports
+    pub ports: Dict<String, PortDoc>,
+}
+
+#[derive(Clone, Debug, Default, Serialize, Deserialize, PartialEq, PartialOrd)]
+pub struct PortDoc {
+    pub description: Option<String>,
+    pub units: Option<String>,
+}
+
 impl PyNodeTemplate {
     pub fn new(py_path: String) -> Self {
         trace!("loading node: {py_path:?}");
@@ -75,6 +120,38 @@ impl PyNodeTemplate {
             Err(e) => vec![e.clone()],
         }
     }
+    pub fn metadata(&self) -> NodeMetadata {
+        match &self.config {
+            Ok(c) => (*c.metadata).clone(),
+            Err(_) => NodeMetadata::default(),
+        }
+    }
+    pub fn migrations(&self) -> Migrations {
+        match &self.config {
+            Ok(c) => (*c.migrations).clone(),
+            Err(_) => Migrations::default(),
+        }
+    }
+    /// Does the node's config depend on its parameter values
+    pub fn is_dynamic(&self) -> bool {
+        self.config.as_ref().is_ok_and(|c| c.dynamic)
+    }
+    /// Re-evaluate a dynamic node's config for the given parameter values.
+    /// Nodes that aren't dynamic are returned unchanged
+    pub fn configure(&self, parameters: &Dict<String, PortData>) -> Self {
+        if !self.is_dynamic() {
+            return self.clone();
+        }
+        trace!("configuring node: {:?} {parameters:?}", self.py_path);
+        let config = Python::with_gil(|py| {
+            let node_module = PyModule::import(py, &self.py_path)?;
+            evaluate_config(&node_module, Some(parameters.clone()))
+        });
+        PyNodeTemplate {
+            config,
+            ..self.clone()
+        }
+    }
 }
 impl PyConfig {
     pub fn errors(&self) -> Vec<PyNodeConfigError> {
@@ -113,11 +190,43 @@ pub fn py_compute_unlocked(
 ) -> Result<Dict<String, PortData>, PyNodeConfigError> {
     Python::with_gil(|py| {
         let node_module = PyModule::import(py, &template.py_path)?;
-        node_module
-            .getattr("compute")?
-            .call((populated_inputs, populated_parameters), None)?
-            .extract::<Dict<String, PortData>>()
-            .map_err(|py_err| PyNodeConfigError::ConfigReturn(py_err.to_string()))
+        call_compute(&node_module, populated_inputs, populated_parameters)
+    })
+}
+
+/// Call the node's `compute` function, reporting its progress
+pub(crate) fn call_compute(
+    node_module: &Bound<'_, PyModule>,
+    populated_inputs: Dict<String, PortData>,
+    populated_parameters: Dict<String, PortData>,
+) -> Result<Dict<String, PortData>, PyNodeConfigError> {
+    let _progress_hook = ProgressHook::install(node_module.py());
+    node_module
+        .getattr("compute")?
+        .call((populated_inputs, populated_parameters), None)?
+        .extract::<Dict<String, PortData>>()
+        .map_err(|py_err| PyNodeConfigError::ConfigReturn(py_err.to_string()))
+}
+
+/// Call a node's optional `migrate(from_version, parameters)` function, which returns
+/// parameters saved with an older version of the node, updated for the current version.
+/// Returns `None` if the node doesn't define `migrate`
+pub fn py_migrate(
+    template: &PyNodeTemplate,
+    from_version: u32,
+    parameters: Dict<String, PortData>,
+) -> Result<Option<Dict<String, PortData>>, PyNodeConfigError> {
+    Python::with_gil(|py| {
+        let node_module = PyModule::import(py, &template.py_path)?;
+        let Ok(migrate) = node_module.getattr("migrate") else {
+            return Ok(None);
+        };
+        Ok(Some(
+            migrate
+                .call1((from_version, parameters))?
+                .extract::<Dict<String, PortData>>()
+                .map_err(|py_err| PyNodeConfigError::ConfigReturn(py_err.to_string()))?,
+        ))
     })
 }
 
@@ -129,21 +238,60 @@ fn load_node(py_path: &str) -> Result<PyConfig, PyNodeConfigError> {
         let import_mod = PyModule::import(py, "importlib")?;
         let _ = import_mod.getattr("reload").unwrap().call1((&node_module,));
 
-        let config_dict = node_module
-            .getattr("config")
-            .map_err(|_| PyNodeConfigError::NoConfig)?
-            .call0()
-            .map_err(|py_err| PyNodeConfigError::Runtime(py_err_traceback(py_err)))?
-            .extract::<Dict<String, Bound<'_, PyAny>>>()
-            .map_err(|py_err| PyNodeConfigError::Runtime(py_err_traceback(py_err)))?;
-
-        let (inputs, outputs) = load_ports(&config_dict);
-        let parameters = load_parameters(&config_dict);
-        Ok(PyConfig {
-            inputs,
-            outputs,
-            parameters,
-        })
+        evaluate_config(&node_module, None)
+    })
+}
+
+/// Call the node's `config` function.
+/// A dynamic node (one whose `config` takes an argument) is passed `parameters`, or when they
+/// aren't known yet, the default values of the parameters it declares
+pub(crate) fn evaluate_config(
+    node_module: &Bound<'_, PyModule>,
+    parameters: Option<Dict<String, PortData>>,
+) -> Result<PyConfig, PyNodeConfigError> {
+    let py = node_module.py();
+    let config_fn = node_module
+        .getattr("config")
+        .map_err(|_| PyNodeConfigError::NoConfig)?;
+    let dynamic = PyModule::import(py, "inspect")?
+        .getattr("signature")?
+        .call1((&config_fn,))?
+        .getattr("parameters")?
+        .len()?
+        > 0;
+
+    let config_dict = match dynamic {
+        true => config_fn.call1((parameters.clone().unwrap_or_default(),)),
+        false => config_fn.call0(),
+    }
+    .map_err(|py_err| PyNodeConfigError::Runtime(py_err_traceback(py_err)))?
+    .extract::<Dict<String, Bound<'_, PyAny>>>()
+    .map_err(|py_err| PyNodeConfigError::Runtime(py_err_traceback(py_err)))?;
+
+    let (inputs, outputs) = load_ports(&config_dict);
+    let parameter_types = load_parameters(&config_dict);
+
+    if dynamic && parameters.is_none() {
+        let defaults = parameter_types
+            .iter()
+            .flatten()
+            .map(|(k, v)| (k.clone(), v.default_value()))
+            .collect();
+        return evaluate_config(node_module, Some(defaults));
+    }
+
+    let metadata = load_metadata(&config_dict, node_module);
+    let migrations = Migrations {
+        renamed_ports: optional_field(&config_dict, "renamed_ports").unwrap_or_default(),
+        renamed_parameters: optional_field(&config_dict, "renamed_parameters").unwrap_or_default(),
+    };
+    Ok(PyConfig {
+        inputs,
+        outputs,
+        parameters: parameter_types,
+        metadata: Box::new(metadata),
+        migrations: Box::new(migrations),
+        dynamic,
     })
 }
 
@@ -173,3 +321,56 @@ fn load_parameters(
         None => Ok(Dict::new()),
     }
 }
+
+/// Extract an optional config field, ignoring it with a warning if it is invalid
+fn optional_field<'py, T: FromPyObject<'py>>(
+    config_dict: &Dict<String, Bound<'py, PyAny>>,
+    key: &str,
+) -> Option<T> {
+    let value = config_dict.get(key)?;
+    value
+        .extract()
+        .inspect_err(|_| warn!("Ignoring invalid node {key}: {value}"))
+        .ok()
+}
+
+/// Metadata is only informational, invalid fields are ignored rather than failing the node
+fn load_metadata(
+    config_dict: &Dict<String, Bound<'_, PyAny>>,
+    node_module: &Bound<'_, PyModule>,
+) -> NodeMetadata {
+    // `inspect.getdoc` removes docstring indentation
+    let docstring: Option<String> = PyModule::import(node_module.py(), "inspect")
+        .and_then(|inspect| inspect.getattr("getdoc")?.call1((node_module,))?.extract())
+        .ok()
+        .flatten();
+
+    let ports = optional_field::<Dict<String, Dict<String, String>>>(config_dict, "port_docs")
+        .unwrap_or_default()
+        .into_iter()
+        .map(|(port, doc)| {
+            let non_empty = |key: &str| doc.get(key).filter(|v| !v.is_empty()).cloned();
+            (
+                port,
+                PortDoc {
+                    description: non_empty("description"),
+                    units: non_empty("units"),
+                },
+            )
+        })
+        .collect();
+
+    NodeMetadata {
+        description: optional_field(config_dict, "description").or_else(|| {
+            docstring
+                .as_ref()
+                .and_then(|d| d.split("\n\n").next())
+                .map(|summary| summary.replace('\n', " "))
+        }),
+        docs: optional_field(config_dict, "docs").or(docstring),
+        tags: optional_field(config_dict, "tags").unwrap_or_default(),
+        icon: optional_field(config_dict, "icon"),
+        version: optional_field(config_dict, "version").unwrap_or_default(),
+        ports,
+    }
+}
@@ -0,0 +1,161 @@
+use std::path::PathBuf;
+
+use log::{trace, warn};
+use pyo3::{
+    Python,
+    ffi::c_str,
+    types::{PyAnyMethods, PyModule},
+};
+
+use crate::err::PyNodeConfigError;
+
+/// Find which of `node_py_paths` are affected by changes to `changed_files`: nodes defined in
+/// a changed file, and nodes that import a changed module, directly or through other modules.
+///
+/// Affected modules that aren't nodes themselves (e.g. shared helpers) are reloaded here, each
+/// after the modules it imports, so that nodes importing them see the changes when they are
+/// reloaded.
+/// If the changes can't be traced, every node is considered affected
+pub fn changed_node_modules(changed_files: &[PathBuf], node_py_paths: &[String]) -> Vec<String> {
+    Python::with_gil(|py| {
+        let result = PyModule::from_code(
+            py,
+            c_str!(
+                r#"
+import ast
+import importlib
+import importlib.util
+import os
+import sys
+import traceback
+
+
+def module_file(module):
+    file = getattr(module, "__file__", None)
+    return os.path.realpath(file) if file else None
+
+
+def imported_modules(module):
+    """Names of the modules that `module` imports from"""
+    try:
+        with open(module.__file__) as f:
+            tree = ast.parse(f.read())
+    except Exception:
+        return set()
+
+    names = set()
+    for node in ast.walk(tree):
+        if isinstance(node, ast.Import):
+            names.update(alias.name for alias in node.names)
+        elif isinstance(node, ast.ImportFrom):
+            try:
+                base = importlib.util.resolve_name(
+                    "." * node.level + (node.module or ""), module.__package__
+                )
+            except Exception:
+                continue
+            names.add(base)
+            # `from pkg import helper` may import a submodule
+            names.update(f"{base}.{alias.name}" for alias in node.names)
+    return names
+
+
+def user_modules():
+    """Modules loaded from files outside of python's installation and the environment"""
+    prefixes = tuple(
+        {
+            os.path.join(os.path.realpath(prefix), "")
+            for prefix in (sys.prefix, sys.base_prefix, sys.exec_prefix, sys.base_exec_prefix)
+        }
+    )
+    modules = {}
+    for name, module in list(sys.modules.items()):
+        file = module_file(module)
+        if file and not file.startswith(prefixes):
+            modules[name] = module
+    return modules
+
+
+def dependency_order(names, imports):
+    """`names`, each after the modules it imports. Modules in a cycle are in name order"""
+    ordered = []
+    visited = set()
+
+    def visit(name):
+        if name in visited:
+            return
+        visited.add(name)
+        for imported in sorted(imports.get(name, ())):
+            if imported in names:
+                visit(imported)
+        ordered.append(name)
+
+    for name in sorted(names):
+        visit(name)
+    return ordered
+
+
+def changed_node_modules(changed_files, node_py_paths):
+    changed_files = {os.path.realpath(f) for f in changed_files}
+    changed = {
+        name
+        for name, module in list(sys.modules.items())
+        if module_file(module) in changed_files
+    }
+
+    modules = user_modules()
+    imports = {
+        name: imported_modules(module) & modules.keys() for name, module in modules.items()
+    }
+    importers = {}
+    for name, imported in imports.items():
+        for dependency in imported:
+            importers.setdefault(dependency, set()).add(name)
+
+    # Everything that imports a changed module, directly or through other modules
+    affected = set()
+    pending = list(changed)
+    while pending:
+        name = pending.pop()
+        if name not in affected:
+            affected.add(name)
+            pending.extend(importers.get(name, ()))
+
+    # Nodes are reloaded when their template is rebuilt, everything else is reloaded here
+    failed = []
+    for name in dependency_order(affected - set(node_py_paths), imports):
+        try:
+            importlib.reload(sys.modules[name])
+        except Exception:
+            failed.append((name, traceback.format_exc()))
+    return ([py_path for py_path in node_py_paths if py_path in affected], failed)
+"#
+            ),
+            c_str!("reload.py"),
+            c_str!("reload"),
+        )
+        .and_then(|snippet| {
+            snippet
+                .getattr("changed_node_modules")?
+                .call1((changed_files.to_vec(), node_py_paths.to_vec()))?
+                .extract::<(Vec<String>, Vec<(String, String)>)>()
+        });
+
+        match result {
+            Ok((affected, failed)) => {
+                for (module, traceback) in failed {
+                    warn!("Could not reload {module}\n{traceback}");
+                }
+                trace!("Nodes affected by changes to {changed_files:?}: {affected:?}");
+                affected
+            }
+            Err(e) => {
+                warn!(
+                    "Could not determine which nodes changed, reloading all\n{}",
+                    PyNodeConfigError::from(e)
+                );
+                node_py_paths.to_vec()
+            }
+        }
+    })
+}
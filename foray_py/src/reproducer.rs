@@ -0,0 +1,98 @@
+use std::{
+    fs,
+    path::{Path, PathBuf},
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use foray_data_model::node::{Dict, PortData};
+use pyo3::{
+    Python,
+    types::{IntoPyDict, PyAnyMethods, PyModule},
+};
+
+use crate::{err::PyNodeConfigError, npz::save_npz, py_node::PyNodeTemplate};
+
+pub const INPUTS_FILE: &str = "inputs.npz";
+pub const PARAMETERS_FILE: &str = "parameters.json";
+pub const SCRIPT_FILE: &str = "reproduce.py";
+pub const ERROR_FILE: &str = "error.txt";
+
+/// Write a standalone reproducer of a python node's compute call into a new directory
+/// inside of `root_dir`, returning the created directory.
+///
+/// The directory contains:
+/// - `inputs.npz`: the node's input port values
+/// - `parameters.json`: the node's parameter values
+/// - `reproduce.py`: a script that imports the node and calls `compute` with them
+/// - `error.txt`: the error that occurred, if one is given
+pub fn write_reproducer(
+    root_dir: &Path,
+    template: &PyNodeTemplate,
+    inputs: Dict<String, PortData>,
+    parameters: Dict<String, PortData>,
+    error: Option<String>,
+) -> Result<PathBuf, PyNodeConfigError> {
+    let timestamp = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or_default();
+    let dir = root_dir.join(format!("{}-{timestamp}", template.name));
+    fs::create_dir_all(&dir)?;
+
+    save_npz(&dir.join(INPUTS_FILE), inputs)?;
+    fs::write(dir.join(PARAMETERS_FILE), parameters_json(parameters)?)?;
+    fs::write(dir.join(SCRIPT_FILE), reproducer_script(template))?;
+    if let Some(error) = error {
+        fs::write(dir.join(ERROR_FILE), error)?;
+    }
+
+    Ok(dir)
+}
+
+fn parameters_json(parameters: Dict<String, PortData>) -> Result<String, PyNodeConfigError> {
+    Python::with_gil(|py| {
+        let json = PyModule::import(py, "json")?;
+        Ok(json
+            .getattr("dumps")?
+            .call((parameters,), Some(&[("indent", 2)].into_py_dict(py)?))?
+            .extract()?)
+    })
+}
+
+fn reproducer_script(template: &PyNodeTemplate) -> String {
+    format!(
+        r#"# Reproducer for the foray node `{name}`
+# Calls the node's `compute` function with the inputs and parameters saved alongside this file.
+# Run it with the python environment of your foray workspace, e.g. `.venv/bin/python reproduce.py`
+import json
+from importlib import import_module
+from pathlib import Path
+
+import numpy as np
+
+HERE = Path(__file__).parent
+
+
+def load_inputs():
+    with np.load(HERE / "{INPUTS_FILE}", allow_pickle=True) as data:
+        # Values that aren't arrays are stored as 0-d arrays
+        return {{
+            name: data[name].item() if data[name].ndim == 0 else data[name]
+            for name in data.files
+        }}
+
+
+def load_parameters():
+    return json.loads((HERE / "{PARAMETERS_FILE}").read_text())
+
+
+if __name__ == "__main__":
+    node = import_module("{py_path}")
+    outputs = node.compute(load_inputs(), load_parameters())
+    for name, value in outputs.items():
+        print(name, getattr(value, "shape", value))
+"#,
+        name = template.name,
+        py_path = template.py_path,
+    )
+}
@@ -0,0 +1,108 @@
+//! Python nodes whose source code is one of their parameters.
+//! The code is edited in foray and saved in the network, rather than in an installed package
+
+use std::ffi::CString;
+
+use foray_data_model::{
+    WireDataContainer,
+    node::{Dict, PortData, UIParameter},
+};
+use log::trace;
+use pyo3::{Bound, Python, prelude::PyAnyMethods, types::PyModule};
+
+use crate::{
+    err::{PyNodeConfigError, RuntimeErr},
+    py_node::{PyNodeTemplate, call_compute, evaluate_config},
+};
+
+/// Name of the parameter holding a snippet's source code
+pub const SNIPPET_CODE: &str = "code";
+/// Module name snippets are compiled as, which appears in their tracebacks.
+/// The snippet of each node gets its own module, see [`snippet_module_name`]
+pub const SNIPPET_MODULE: &str = "foray_snippet";
+
+/// Module name of the snippet of node `nx`, so that snippets don't share their globals
+pub fn snippet_module_name(nx: u32) -> String {
+    format!("{SNIPPET_MODULE}_{nx}")
+}
+
+/// Code of newly added snippets, declaring ports the same way as nodes in packages do
+pub const DEFAULT_SNIPPET: &str = r#"from foray import ForayConfig, Port
+
+
+def config():
+    return ForayConfig().inputs({"a": Port.any}).outputs({"out": Port.any})
+
+
+def compute(inputs, parameters):
+    return {"out": inputs["a"]}
+"#;
+
+impl PyNodeTemplate {
+    /// Template of a snippet node, configured by compiling `code` as the module `module_name`.
+    /// `parameters` are the values of the parameters the snippet declares, if they are known
+    pub fn snippet(module_name: &str, code: &str, parameters: Dict<String, PortData>) -> Self {
+        trace!("configuring snippet {module_name}");
+        let config = Python::with_gil(|py| {
+            let node_module = snippet_module(py, module_name, code)?;
+            evaluate_config(&node_module, (!parameters.is_empty()).then_some(parameters))
+        });
+        PyNodeTemplate {
+            name: "python snippet".to_string(),
+            py_path: module_name.to_string(),
+            config,
+        }
+    }
+
+    /// Parameters of a snippet node, its code followed by the parameters the snippet declares
+    pub fn snippet_parameters(&self) -> Dict<String, UIParameter> {
+        let mut parameters = self.parameters().unwrap_or_default();
+        parameters.insert(
+            SNIPPET_CODE.to_string(),
+            UIParameter::CodeEditor(DEFAULT_SNIPPET.to_string()),
+        );
+        parameters
+    }
+}
+
+/// Compile a snippet's code, which is one of its parameters, and call its `compute` function.
+/// The snippet is passed the values of the parameters it declares, without its code
+pub fn snippet_compute(
+    snippet: &PyNodeTemplate,
+    populated_inputs: Dict<String, WireDataContainer<PortData>>,
+    mut populated_parameters: Dict<String, PortData>,
+) -> Result<Dict<String, PortData>, PyNodeConfigError> {
+    let py_inputs: Dict<String, PortData> = populated_inputs
+        .into_iter()
+        .map(|(k, v)| (k, v.read().unwrap().clone()))
+        .collect();
+    let code = match populated_parameters.remove(SNIPPET_CODE) {
+        Some(PortData::String(code)) => code,
+        _ => String::new(),
+    };
+    Python::with_gil(|py| {
+        let node_module = snippet_module(py, &snippet.py_path, &code)?;
+        call_compute(&node_module, py_inputs, populated_parameters)
+    })
+}
+
+/// Compile a snippet as a module, syntax errors are reported with their traceback
+fn snippet_module<'py>(
+    py: Python<'py>,
+    module_name: &str,
+    code: &str,
+) -> Result<Bound<'py, PyModule>, PyNodeConfigError> {
+    let code = CString::new(code).map_err(|_| {
+        PyNodeConfigError::Runtime(RuntimeErr {
+            error: "snippet contains a null character".to_string(),
+            traceback: String::new(),
+        })
+    })?;
+    // Compiling into a module that is already imported would keep the globals of earlier code
+    PyModule::import(py, "sys")?
+        .getattr("modules")?
+        .call_method1("pop", (module_name, py.None()))?;
+    let file_name = CString::new(format!("<{module_name}>")).expect("no null characters");
+    let module_name = CString::new(module_name).expect("no null characters");
+    Ok(PyModule::from_code(py, &code, &file_name, &module_name)?)
+}
@@ -0,0 +1,53 @@
+use foray_data_model::node::{Dict, ForayArray, PortData};
+use foray_py::fixture::{Tolerance, compare, compare_outputs};
+use numpy::{IxDyn, ndarray::ArrayD};
+
+fn float_array(values: Vec<f64>) -> PortData {
+    PortData::Array(ForayArray::Float(
+        ArrayD::from_shape_vec(IxDyn(&[values.len()]), values).unwrap(),
+    ))
+}
+
+#[test]
+fn numbers_within_tolerance() {
+    let tolerance = Tolerance {
+        rtol: 0.0,
+        atol: 0.1,
+    };
+    assert!(compare(&PortData::Float(1.0), &PortData::Float(1.05), tolerance).is_ok());
+    assert!(compare(&PortData::Float(1.0), &PortData::Float(1.2), tolerance).is_err());
+    assert!(compare(&PortData::Integer(1), &PortData::Float(1.05), tolerance).is_ok());
+    assert!(compare(&PortData::Integer(1), &PortData::Integer(2), tolerance).is_err());
+}
+
+#[test]
+fn arrays_within_tolerance() {
+    let tolerance = Tolerance {
+        rtol: 1e-3,
+        atol: 0.0,
+    };
+    let expected = float_array(vec![1.0, 2.0, 1000.0]);
+    assert!(compare(&expected, &float_array(vec![1.0, 2.0, 1000.5]), tolerance).is_ok());
+    assert_eq!(
+        compare(&expected, &float_array(vec![1.1, 2.0, 1002.0]), tolerance),
+        Err("2/3 elements differ, max difference 2e0".to_string())
+    );
+    assert_eq!(
+        compare(&expected, &float_array(vec![1.0, 2.0]), tolerance),
+        Err("expected shape [3], got [2]".to_string())
+    );
+}
+
+#[test]
+fn missing_outputs() {
+    let expected: Dict<String, PortData> = [
+        ("a".to_string(), PortData::Integer(1)),
+        ("b".to_string(), PortData::Integer(2)),
+    ]
+    .into();
+    let actual: Dict<String, PortData> = [("a".to_string(), PortData::Integer(1))].into();
+    assert_eq!(
+        compare_outputs(&expected, &actual, Tolerance::default()),
+        vec!["b: missing from outputs".to_string()]
+    );
+}
@@ -1,7 +1,10 @@
-use crate::config::Config;
+use crate::config::{Config, ConfigError};
+use crate::interface::environment::EnvironmentLog;
 use crate::interface::theme_config::{AppThemeMessage, GuiColorMessage};
+use crate::python_env::{self, PythonEnvError};
 use crate::style::theme::AppTheme;
-use crate::workspace::{Workspace, WorkspaceMessage};
+use crate::venv::{env_commands_task, recreate_commands, sync_commands, EnvEvent, Installer};
+use crate::workspace::{Workspace, WorkspaceError, WorkspaceMessage};
 
 use derive_more::Debug;
 use iced::advanced::graphics::core::Element;
@@ -19,7 +22,7 @@ use iced::{
     Alignment::Center,
 };
 
-use log::warn;
+use log::{error, warn};
 use std::path::PathBuf;
 
 pub struct App {
@@ -35,11 +38,38 @@ pub struct App {
     pub show_palette_ui: bool,
 
     markdown: Vec<markdown::Item>,
+    /// Workspace without a usable python environment, and the network to open once it has one
+    pending_workspace: Option<(PathBuf, Option<PathBuf>)>,
+    /// Why the pending workspace's environment can't be used, if it exists
+    environment_problem: Option<PythonEnvError>,
+    /// Output of creating the pending workspace's environment
+    environment_log: EnvironmentLog,
+    /// Config file that kept the last workspace from opening
+    config_error: Option<ConfigError>,
 }
 impl App {
     pub fn new(working_dir: PathBuf, cli_network_path: Option<PathBuf>) -> Self {
-        let workspace = match Workspace::new(working_dir, cli_network_path, None) {
+        // Only offer to create an environment in a directory that looks like a workspace.
+        // An invalid config is reported when opening the workspace below
+        let mut pending_workspace =
+            (matches!(Workspace::is_valid_workspace(&working_dir), Ok(false))
+                && working_dir.join("pyproject.toml").is_file())
+            .then(|| (working_dir.clone(), cli_network_path.clone()));
+        let mut environment_problem = None;
+        let mut config_error = None;
+        let workspace = match Workspace::new(working_dir.clone(), cli_network_path.clone(), None) {
             Ok(workspace) => Some(workspace),
+            Err(WorkspaceError::Python(e)) => {
+                warn!("{e}");
+                pending_workspace = Some((working_dir, cli_network_path));
+                environment_problem = Some(e);
+                None
+            }
+            Err(WorkspaceError::Config(e)) => {
+                error!("{e}");
+                config_error = Some(e);
+                None
+            }
             Err(e) => {
                 warn!("Workspace Initialialization Error: {:?}", e);
                 None
@@ -47,6 +77,10 @@ impl App {
         };
         App {
             workspace,
+            pending_workspace,
+            environment_problem,
+            environment_log: Default::default(),
+            config_error,
 
             debug: false,
             show_palette_ui: false,
@@ -77,6 +111,10 @@ pub enum Message {
     FocusNext,
     FocusPrevious,
 
+    /// Create, or recreate, the python environment of the pending workspace, then open it
+    CreateEnvironment,
+    EnvironmentEvent(EnvEvent),
+
     WorkspaceMessage(crate::workspace::WorkspaceMessage),
     OpenWindow(window::Id),
 }
@@ -88,12 +126,11 @@ impl App {
         //}
         match message {
             Message::WorkspaceMessage(m) => {
-                return self
-                    .workspace
-                    .as_mut()
-                    .expect("workspace should exist")
-                    .update(m, self.modifiers)
-                    .map(Message::WorkspaceMessage);
+                let workspace = self.workspace.as_mut().expect("workspace should exist");
+                let task = workspace.update(m, self.modifiers);
+                // Selection and parameter changes can come from any message
+                workspace.sync_code_editor();
+                return task.map(Message::WorkspaceMessage);
             }
             Message::ThemeValueChange(tm, tv) => self.app_theme.update(tm, tv),
             Message::ToggleDebug => {
@@ -113,17 +150,7 @@ impl App {
                 return Task::perform(select_workspace_dialog(), Message::EndWorkspaceSelect)
             }
             Message::EndWorkspaceSelect(Some(workspace_path)) => {
-                self.workspace = match Workspace::is_valid_workspace(&workspace_path) {
-                    true => match Workspace::new(workspace_path, None, self.main_window_id) {
-                        Ok(workspace) => Some(workspace),
-                        Err(_e) => {
-                            warn!("Current directory is not a valid workspace");
-                            None
-                        }
-                    },
-                    false => None,
-                };
-                return Task::done(Message::WorkspaceMessage(WorkspaceMessage::ComputeAll));
+                return self.open_workspace(workspace_path, None);
             }
             Message::EndWorkspaceSelect(None) => {}
             Message::StartNetworkSelect => {
@@ -138,24 +165,57 @@ impl App {
                         .unwrap()
                         .to_path_buf();
 
-                    self.workspace = match Workspace::is_valid_workspace(&workspace_path) {
-                        true => match Workspace::new(
-                            workspace_path,
-                            Some(network_path),
-                            self.main_window_id,
-                        ) {
-                            Ok(workspace) => Some(workspace),
-                            Err(_e) => {
-                                warn!("Current directory is not a valid workspace");
-                                None
-                            }
-                        },
-                        false => None,
-                    };
-                    return Task::done(Message::WorkspaceMessage(WorkspaceMessage::ComputeAll));
+                    return self.open_workspace(workspace_path, Some(network_path));
                 }
                 None => {}
             },
+            Message::CreateEnvironment => {
+                if let Some((workspace_path, _)) = &self.pending_workspace {
+                    if !self.environment_log.running {
+                        self.environment_log = EnvironmentLog::start();
+                        let installer = Installer::detect();
+                        let config = match Config::load(workspace_path) {
+                            Ok(config) => config,
+                            Err(e) => {
+                                error!("{e}");
+                                self.config_error = Some(e);
+                                return Task::none();
+                            }
+                        };
+                        let venv_dir = config.venv_dir(workspace_path);
+                        let commands = match &self.environment_problem {
+                            Some(problem) => recreate_commands(
+                                &installer,
+                                workspace_path,
+                                &venv_dir,
+                                problem.embedded(),
+                            ),
+                            None => sync_commands(
+                                &installer,
+                                workspace_path,
+                                &venv_dir,
+                                config.python(workspace_path).as_deref(),
+                                python_env::embedded_version(),
+                            ),
+                        };
+                        return env_commands_task(
+                            workspace_path.clone(),
+                            venv_dir,
+                            commands,
+                            Message::EnvironmentEvent,
+                        );
+                    }
+                }
+            }
+            Message::EnvironmentEvent(event) => {
+                let succeeded = matches!(event, EnvEvent::Finished(Ok(())));
+                self.environment_log.update(event);
+                if let (true, Some((workspace_path, network_path))) =
+                    (succeeded, self.pending_workspace.clone())
+                {
+                    return self.open_workspace(workspace_path, network_path);
+                }
+            }
             Message::LinkClicked(link) => {
                 let _ = open::that_in_background(link.to_string());
             }
@@ -172,6 +232,90 @@ impl App {
         Task::none()
     }
 
+    /// Open a workspace, or offer to create its python environment if it doesn't have a usable one
+    fn open_workspace(
+        &mut self,
+        workspace_path: PathBuf,
+        network_path: Option<PathBuf>,
+    ) -> Task<Message> {
+        self.config_error = None;
+        match Workspace::is_valid_workspace(&workspace_path) {
+            Ok(true) => {}
+            Ok(false) => {
+                warn!("{workspace_path:?} has no python environment");
+                self.workspace = None;
+                self.pending_workspace = Some((workspace_path, network_path));
+                self.environment_problem = None;
+                self.environment_log = Default::default();
+                return Task::none();
+            }
+            Err(e) => {
+                error!("{e}");
+                self.workspace = None;
+                self.pending_workspace = None;
+                self.config_error = Some(e);
+                return Task::none();
+            }
+        }
+        self.pending_workspace = None;
+        self.environment_problem = None;
+        self.workspace = match Workspace::new(
+            workspace_path.clone(),
+            network_path.clone(),
+            self.main_window_id,
+        ) {
+            Ok(workspace) => Some(workspace),
+            Err(WorkspaceError::Python(e)) => {
+                warn!("{e}");
+                self.pending_workspace = Some((workspace_path, network_path));
+                self.environment_problem = Some(e);
+                return Task::none();
+            }
+            Err(WorkspaceError::Config(e)) => {
+                error!("{e}");
+                self.config_error = Some(e);
+                None
+            }
+            Err(_e) => {
+                warn!("Current directory is not a valid workspace");
+                None
+            }
+        };
+        Task::done(Message::WorkspaceMessage(WorkspaceMessage::ComputeAll))
+    }
+
+    /// Offer to create the python environment of a workspace that doesn't have one,
+    /// or to recreate one that doesn't match the embedded python
+    fn create_environment_view(&self) -> Element<'_, Message, Theme, Renderer> {
+        let Some((workspace_path, _)) = &self.pending_workspace else {
+            return space::vertical().height(0).into();
+        };
+        let installer = Installer::detect();
+        let (problem, action) = match &self.environment_problem {
+            Some(problem) => (
+                text(problem.to_string()).style(text::danger),
+                format!("Recreate environment with {}", installer.name()),
+            ),
+            None => (
+                text(format!(
+                    "{} has no python environment",
+                    workspace_path.display()
+                )),
+                format!("Create environment with {}", installer.name()),
+            ),
+        };
+        column![
+            problem,
+            button(text(action)).on_press_maybe(
+                (!self.environment_log.running).then_some(Message::CreateEnvironment)
+            ),
+            container(self.environment_log.view()).height(200),
+        ]
+        .align_x(Center)
+        .spacing(10)
+        .into()
+    }
+
     /// App View
     pub fn view(&'_ self) -> Element<'_, Message, Theme, Renderer> {
         let workspace_content = match &self.workspace {
@@ -190,6 +334,10 @@ impl App {
                     ]
                     .align_y(Center)
                     .spacing(20),
+                    self.create_environment_view(),
+                    self.config_error
+                        .as_ref()
+                        .map(|e| text(e.to_string()).style(text::danger)),
                     space::vertical(),
                     rule::horizontal(1),
                     container(
@@ -287,3 +435,12 @@ pub async fn file_dialog(default_path: PathBuf) -> Option<PathBuf> {
         .await
         .map(|fh| fh.into())
 }
+
+/// Open a generic dialog to choose a directory
+pub async fn directory_dialog(default_path: PathBuf) -> Option<PathBuf> {
+    rfd::AsyncFileDialog::new()
+        .set_directory(default_path)
+        .pick_folder()
+        .await
+        .map(|fh| fh.into())
+}
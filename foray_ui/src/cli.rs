@@ -0,0 +1,157 @@
+use crate::{
+    app::{subscriptions, theme, title, App},
+    config::Config,
+    headless::{run_headless, run_node_tests},
+    python_env,
+    venv::{install_commands, print_env_commands, sync_commands, Installer},
+};
+use clap::{Parser, Subcommand};
+use env_logger::Env;
+use foray_py::fixture::Tolerance;
+use iced::{application, Font};
+use std::{error::Error, fs, path::PathBuf};
+
+#[derive(Parser)]
+#[command(version, about, long_about = None, args_conflicts_with_subcommands = true)]
+struct Cli {
+    #[command(subcommand)]
+    command: Option<Command>,
+    /// network file
+    network: Option<PathBuf>,
+    /// Run the supplied network file without opening the graphical interface
+    #[arg(long)]
+    no_gui: bool,
+    /// When running without the graphical interface, save a reproducer for a failing python node
+    #[arg(long, requires = "no_gui")]
+    save_reproducers: bool,
+}
+
+#[derive(Subcommand)]
+enum Command {
+    /// Run python nodes against the fixtures stored next to their modules
+    Test {
+        /// workspace directory, defaults to the current directory
+        workspace: Option<PathBuf>,
+        /// Only test nodes whose python path contains this
+        #[arg(long)]
+        node: Option<String>,
+        /// Relative tolerance used when a fixture doesn't specify one
+        #[arg(long, default_value_t = Tolerance::default().rtol)]
+        rtol: f64,
+        /// Absolute tolerance used when a fixture doesn't specify one
+        #[arg(long, default_value_t = Tolerance::default().atol)]
+        atol: f64,
+    },
+    /// Create or update the workspace's python environment, with uv if it's available
+    Env {
+        #[command(subcommand)]
+        action: EnvAction,
+    },
+}
+
+#[derive(Subcommand)]
+enum EnvAction {
+    /// Create the environment if it doesn't exist, and install the workspace's pyproject.toml
+    Sync {
+        /// workspace directory, defaults to the current directory
+        workspace: Option<PathBuf>,
+    },
+    /// Install a package, by name or local path, like `./my_nodes`. With uv, it's also added to pyproject.toml
+    Install {
+        package: String,
+        /// workspace directory, defaults to the current directory
+        #[arg(long)]
+        workspace: Option<PathBuf>,
+    },
+}
+
+/// Run foray from the command line.
+///
+/// Binaries that link crates with extra native nodes call this from their `main`,
+/// see [`foray_native`]
+pub fn run() -> Result<(), Box<dyn Error>> {
+    env_logger::Builder::from_env(Env::default().default_filter_or("foray=warn")).init();
+
+    let cli = Cli::parse();
+
+    let workspace_dir = |workspace: Option<PathBuf>| match workspace {
+        Some(w) => fs::canonicalize(w),
+        None => std::env::current_dir(),
+    };
+    match cli.command {
+        Some(Command::Test {
+            workspace,
+            node,
+            rtol,
+            atol,
+        }) => {
+            let workspace = workspace_dir(workspace)?;
+            return run_node_tests(workspace, node, Tolerance { rtol, atol });
+        }
+        Some(Command::Env { action }) => {
+            let installer = Installer::detect();
+            let (workspace, package) = match action {
+                EnvAction::Sync { workspace } => (workspace_dir(workspace)?, None),
+                EnvAction::Install { package, workspace } => {
+                    (workspace_dir(workspace)?, Some(package))
+                }
+            };
+            let config = Config::load(&workspace)?;
+            let venv_dir = config.venv_dir(&workspace);
+            let commands = match package {
+                None => sync_commands(
+                    &installer,
+                    &workspace,
+                    &venv_dir,
+                    config.python(&workspace).as_deref(),
+                    python_env::embedded_version(),
+                ),
+                Some(package) => install_commands(&installer, &workspace, &venv_dir, &package),
+            };
+            return Ok(print_env_commands(&workspace, &venv_dir, &commands)?);
+        }
+        None => {}
+    }
+
+    let absolute_network = cli
+        .network
+        .map(|p| fs::canonicalize(&p).unwrap_or_else(|_| panic!("network does not exist {p:?}")));
+
+    let workspace_dir = match &absolute_network {
+        Some(n) => n.parent().unwrap().parent().unwrap().to_owned(),
+        None => std::env::current_dir().expect("current working directory should be availble"),
+    };
+
+    if cli.no_gui {
+        match absolute_network {
+            Some(network) => run_headless(network, cli.save_reproducers),
+            None => {
+                println!("No network file provided");
+                Ok(())
+            }
+        }
+    } else {
+        application(
+            move || App::new(workspace_dir.clone(), absolute_network.clone()),
+            App::update,
+            App::view,
+        )
+        .title(title)
+        .subscription(subscriptions)
+        .theme(theme)
+        .window(iced::window::Settings {
+            min_size: Some((400., 300.).into()),
+            ..Default::default()
+        })
+        .antialiasing(true)
+        .window_size((1000., 800.))
+        .decorations(true)
+        // .scale_factor(|_| 1.25)
+        .font(include_bytes!("../data/CaskaydiaCoveNerdFont.ttf").as_slice())
+        .font(include_bytes!("../data/CaskaydiaCove.ttf").as_slice())
+        .font(include_bytes!("../data/cour.ttf").as_slice())
+        .default_font(Font::with_name("CaskaydiaCove"))
+        .run()?;
+        Ok(())
+    }
+}
@@ -1,74 +1,219 @@
 use std::{
-    fs::{self, read_to_string},
-    path::PathBuf,
-    process::Command,
+    fmt::Display,
+    fs::read_to_string,
+    io::ErrorKind,
+    path::{Path, PathBuf},
 };
 
-use log::{error, info};
+use log::{error, info, warn};
 use serde::{Deserialize, Serialize};
 
-use crate::style::theme::AppTheme;
+use crate::{style::theme::AppTheme, venv::VENV_DIR};
 
-/// User configuration data that is saved/loaded from a config.toml file
-#[derive(Serialize, Deserialize, Debug)]
+/// User configuration file, relative to the home directory
+const USER_CONFIG_FILE: &str = ".config/gpi/config.toml";
+/// Workspace configuration file, relative to the workspace
+pub const WORKSPACE_CONFIG_FILE: &str = "foray.toml";
+
+/// Configuration read from the user's `~/.config/gpi/config.toml`,
+/// and the workspace's `foray.toml`, which takes precedence.
+/// Relative paths are relative to the workspace
+#[derive(Serialize, Deserialize, Debug, Default, Clone, PartialEq)]
+#[serde(default, deny_unknown_fields)]
 pub struct Config {
-    pub venv_dir: PathBuf,
+    /// Python virtual environment, `.venv` if not set.
+    /// It must be created with the python version foray embeds
+    pub venv: Option<PathBuf>,
+    /// Python interpreter the environment is created with, the embedded python version if not set.
+    /// A command like `python3.12` is looked up on `PATH`
+    pub python: Option<PathBuf>,
+    /// Replaced by `venv`, older user configs still contain it
+    #[serde(skip_serializing)]
+    pub venv_dir: Option<PathBuf>,
+    /// Directories of python nodes that aren't installed as packages.
+    /// Directories from both files are used
+    pub node_dirs: Vec<PathBuf>,
+    /// Network opened with the workspace, when no network is given
+    pub default_network: Option<PathBuf>,
+    /// Directory networks are saved to and opened from, `networks` if not set
+    pub networks_dir: Option<PathBuf>,
+    pub executor: ExecutorConfig,
+    pub cache: CacheConfig,
+}
+
+#[derive(Serialize, Deserialize, Debug, Default, Clone, PartialEq)]
+#[serde(default, deny_unknown_fields)]
+pub struct ExecutorConfig {
+    /// Most nodes computed at the same time, unlimited if not set.
+    /// Other nodes wait until a running node finishes
+    pub max_concurrent_nodes: Option<usize>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Default, Clone, PartialEq)]
+#[serde(default, deny_unknown_fields)]
+pub struct CacheConfig {
+    /// Where session data like the recent network and the add node menu state is kept.
+    /// The user's cache directory if not set, shared by all workspaces
+    pub dir: Option<PathBuf>,
+}
+
+/// A configuration file that can't be used
+#[derive(Clone, Debug, PartialEq)]
+pub struct ConfigError {
+    pub file: PathBuf,
+    pub message: String,
+}
+
+impl Display for ConfigError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "Invalid config {}:\n{}",
+            self.file.display(),
+            self.message
+        )
+    }
 }
 
+impl std::error::Error for ConfigError {}
+
+/// Node directory that is always loaded, relative to the workspace
+pub const DEFAULT_NODE_DIR: &str = "nodes";
+/// Directory of the workspace's networks, unless it's configured
+pub const DEFAULT_NETWORKS_DIR: &str = "networks";
+
 impl Config {
-    pub fn read_config() -> Self {
-        let user_dirs = directories::UserDirs::new()
-            .expect("Application configuration folder should be accessible");
-        let config_dir = user_dirs.home_dir().join(".config/gpi");
-        let config_file = config_dir.join("config.toml");
-
-        match read_to_string(&config_file).map(|s| toml::from_str::<Config>(&s)) {
-            Ok(Ok(c)) => {
-                info!("Loaded config: {config_file:?}");
-                c
+    /// Read and validate the user config and the workspace config layered over it.
+    /// Missing files are treated as empty
+    pub fn load(workspace_dir: &Path) -> Result<Self, ConfigError> {
+        let user_config = directories::UserDirs::new()
+            .map(|d| d.home_dir().join(USER_CONFIG_FILE))
+            .map(|file| Self::read(&file, workspace_dir))
+            .transpose()?
+            .unwrap_or_default();
+        let workspace_config =
+            Self::read(&workspace_dir.join(WORKSPACE_CONFIG_FILE), workspace_dir)?;
+        Ok(user_config.layer(workspace_config))
+    }
+
+    /// Read and validate one config file
+    fn read(file: &Path, workspace_dir: &Path) -> Result<Self, ConfigError> {
+        let config_error = |message: String| ConfigError {
+            file: file.to_path_buf(),
+            message,
+        };
+        let source = match read_to_string(file) {
+            Ok(source) => source,
+            Err(e) if e.kind() == ErrorKind::NotFound => return Ok(Config::default()),
+            Err(e) => return Err(config_error(e.to_string())),
+        };
+        let config = toml::from_str::<Config>(&source).map_err(|e| config_error(e.to_string()))?;
+        config.validate(workspace_dir).map_err(config_error)?;
+        info!("Loaded config: {file:?}");
+        if config.venv_dir.is_some() {
+            warn!("{file:?}: `venv_dir` is no longer used, set `venv` in {WORKSPACE_CONFIG_FILE}");
+        }
+        Ok(config)
+    }
+
+    /// Check settings that would otherwise fail later, or be silently ignored
+    fn validate(&self, workspace_dir: &Path) -> Result<(), String> {
+        if self.executor.max_concurrent_nodes == Some(0) {
+            return Err("executor.max_concurrent_nodes must be at least 1".to_string());
+        }
+        if let Some(network) = &self.default_network {
+            if network.extension().is_none_or(|ext| ext != "network") {
+                return Err(format!(
+                    "default_network must be a .network file, found {network:?}"
+                ));
             }
-            Ok(Err(e)) => {
-                panic!("Error reading config {config_file:?}:\n{e}");
+        }
+        if let Some(dir) = &self.networks_dir {
+            if workspace_dir.join(dir).is_file() {
+                return Err(format!(
+                    "networks_dir must be a directory, found the file {dir:?}"
+                ));
             }
-            _ => {
-                //// Could not read config, creating a default...
-                // TODO: Prompt for venv path
-                // TEMP: create default node location
-                let nodes_dir = user_dirs.home_dir().join("gpi_default");
-                let venv_dir = nodes_dir.join(".venv");
-
-                println!("No configuration file found at {config_file:?} creating default config");
-                if fs::read_dir(&nodes_dir).is_err() {
-                    println!("creating default node directory at {nodes_dir:?}");
-                    fs::create_dir(&nodes_dir)
-                        .unwrap_or_else(|_| panic!("couldn't create default folder{nodes_dir:?}"));
-
-                    let output = Command::new("python3")
-                        .arg("-m")
-                        .arg("venv")
-                        .arg(&venv_dir)
-                        .output()
-                        .unwrap_or_else(|e| panic!("failed to execute python process {e}"));
-                    info!("{output:?}");
-                }
-
-                println!("Creating default config file");
-                let config = Config { venv_dir };
-                let _ = std::fs::create_dir(config_dir);
-                std::fs::write(
-                    &config_file,
-                    toml::to_string_pretty(&config).unwrap_or_else(|e| {
-                        panic!("Could not parse config file {config_file:?}\n{e}")
-                    }),
-                )
-                .unwrap_or_else(|e| panic!("Could not write config file {config_file:?}\n{e}"));
-                config
+        }
+        if let Some(venv) = &self.venv {
+            if workspace_dir.join(venv).is_file() {
+                return Err(format!(
+                    "venv must be a virtual environment directory, found the file {venv:?}"
+                ));
             }
         }
+        Ok(())
+    }
+
+    /// Settings of `other` replace those of `self`, node directories of both are used
+    fn layer(self, other: Config) -> Config {
+        Config {
+            venv: other.venv.or(self.venv),
+            python: other.python.or(self.python),
+            venv_dir: other.venv_dir.or(self.venv_dir),
+            node_dirs: self.node_dirs.into_iter().chain(other.node_dirs).collect(),
+            default_network: other.default_network.or(self.default_network),
+            networks_dir: other.networks_dir.or(self.networks_dir),
+            executor: ExecutorConfig {
+                max_concurrent_nodes: other
+                    .executor
+                    .max_concurrent_nodes
+                    .or(self.executor.max_concurrent_nodes),
+            },
+            cache: CacheConfig {
+                dir: other.cache.dir.or(self.cache.dir),
+            },
+        }
+    }
+
+    /// The workspace's python virtual environment
+    pub fn venv_dir(&self, workspace_dir: &Path) -> PathBuf {
+        workspace_dir.join(self.venv.as_deref().unwrap_or(Path::new(VENV_DIR)))
+    }
+
+    /// Interpreter new environments are created with, if it's configured.
+    /// Paths are relative to the workspace, a bare command is left to be found on `PATH`
+    pub fn python(&self, workspace_dir: &Path) -> Option<PathBuf> {
+        let python = self.python.as_ref()?;
+        Some(match python.components().count() {
+            1 if python.is_relative() => python.clone(),
+            _ => workspace_dir.join(python),
+        })
+    }
+
+    /// Directory networks are saved to and opened from
+    pub fn networks_dir(&self, workspace_dir: &Path) -> PathBuf {
+        workspace_dir.join(
+            self.networks_dir
+                .as_deref()
+                .unwrap_or(Path::new(DEFAULT_NETWORKS_DIR)),
+        )
+    }
+
+    /// Directories to search for python nodes in, in addition to installed node packages.
+    /// Includes `<workspace>/nodes`, and any configured `node_dirs`
+    pub fn node_dirs(&self, workspace_dir: &Path) -> Vec<PathBuf> {
+        std::iter::once(Path::new(DEFAULT_NODE_DIR))
+            .chain(self.node_dirs.iter().map(PathBuf::as_path))
+            .map(|dir| workspace_dir.join(dir))
+            .collect()
+    }
+
+    /// Network opened with the workspace, if it's configured and exists
+    pub fn default_network(&self, workspace_dir: &Path) -> Option<PathBuf> {
+        let network = workspace_dir.join(self.default_network.as_ref()?);
+        if !network.is_file() {
+            warn!("default_network {network:?} does not exist");
+            return None;
+        }
+        Some(network)
+    }
+
+    /// Directory session data is kept in, if it's configured
+    pub fn cache_dir(&self, workspace_dir: &Path) -> Option<PathBuf> {
+        self.cache.dir.as_ref().map(|dir| workspace_dir.join(dir))
     }
-}
 
-impl Config {
     pub fn load_theme() -> AppTheme {
         let user_dirs =
             directories::UserDirs::new().expect("application configuration folder is accessible");
@@ -84,3 +229,82 @@ impl Config {
         }
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn parse(source: &str) -> Result<Config, String> {
+        let config = toml::from_str::<Config>(source).map_err(|e| e.to_string())?;
+        config.validate(Path::new("/nonexistent"))?;
+        Ok(config)
+    }
+
+    #[test]
+    fn layered() {
+        let user = parse(
+            r#"
+venv_dir = "/home/user/gpi_default/.venv"
+node_dirs = ["/data/shared/foray_nodes"]
+executor.max_concurrent_nodes = 8
+"#,
+        )
+        .unwrap();
+        let workspace = parse(
+            r#"
+venv = "../env"
+python = "python3.12"
+node_dirs = ["experiments"]
+networks_dir = "recon/networks"
+
+[executor]
+max_concurrent_nodes = 2
+"#,
+        )
+        .unwrap();
+        let config = user.layer(workspace);
+        let workspace_dir = Path::new("/ws");
+        assert_eq!(config.venv_dir(workspace_dir), Path::new("/ws/../env"));
+        assert_eq!(
+            config.node_dirs(workspace_dir),
+            ["/ws/nodes", "/data/shared/foray_nodes", "/ws/experiments"].map(PathBuf::from)
+        );
+        assert_eq!(config.executor.max_concurrent_nodes, Some(2));
+        assert_eq!(config.python(workspace_dir), Some("python3.12".into()));
+        assert_eq!(
+            config.networks_dir(workspace_dir),
+            Path::new("/ws/recon/networks")
+        );
+        let default = Config::default();
+        assert_eq!(default.venv_dir(workspace_dir), Path::new("/ws/.venv"));
+        assert_eq!(
+            default.networks_dir(workspace_dir),
+            Path::new("/ws/networks")
+        );
+        assert_eq!(default.python(workspace_dir), None);
+        let python = parse("python = \"tools/python\"").unwrap();
+        assert_eq!(
+            python.python(workspace_dir),
+            Some("/ws/tools/python".into())
+        );
+    }
+
+    #[test]
+    fn invalid() {
+        assert!(parse("node_dir = [\"nodes\"]")
+            .unwrap_err()
+            .contains("unknown field `node_dir`"));
+        assert_eq!(
+            parse("executor.max_concurrent_nodes = 0").unwrap_err(),
+            "executor.max_concurrent_nodes must be at least 1"
+        );
+        assert_eq!(
+            parse("default_network = \"recon.ron\"").unwrap_err(),
+            "default_network must be a .network file, found \"recon.ron\""
+        );
+        let workspace_dir = Path::new("/nonexistent");
+        assert!(Config::read(&workspace_dir.join(WORKSPACE_CONFIG_FILE), workspace_dir).is_ok());
+        // A file that exists but can't be read isn't silently ignored
+        assert!(Config::read(&std::env::temp_dir(), workspace_dir).is_err());
+    }
+}
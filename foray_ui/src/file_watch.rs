@@ -3,6 +3,8 @@ use std::path::Component;
 use std::path::PathBuf;
 use std::time::Duration;
 
+use foray_command::node::is_command_file;
+use foray_plugin::node::is_plugin_file;
 use futures::StreamExt;
 use iced::futures::channel::mpsc;
 use iced::futures::sink::SinkExt;
@@ -44,7 +46,9 @@ fn worker(root_dir: &PathBuf) -> impl Stream<Item = Vec<PathBuf>> {
                         .filter(|e| {
                             (e.kind.is_modify() || e.kind.is_create())
                                 && e.paths.iter().any(|p| {
-                                    p.extension() == Some(OsStr::new("py"))
+                                    (p.extension() == Some(OsStr::new("py"))
+                                        || is_plugin_file(p)
+                                        || is_command_file(p))
                                         && !p
                                             .components()
                                             .any(|s| s == Component::Normal(OsStr::new(".venv")))
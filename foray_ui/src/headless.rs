@@ -1,17 +1,45 @@
-use std::{error::Error, path::PathBuf};
+use std::{
+    error::Error,
+    path::PathBuf,
+    sync::{Arc, RwLock},
+    time::Instant,
+};
 
-use foray_graph::graph::Graph;
+use foray_data_model::{
+    context::ComputeContext,
+    node::{Dict, PortData, PortType},
+};
+use foray_graph::graph::{ForayNodeError, Graph};
+use foray_py::{
+    discover,
+    fixture::{find_fixtures, run_fixture, FixtureOutcome, Tolerance},
+    py_node::PyNodeTemplate,
+    reproducer::write_reproducer,
+};
 use log::trace;
 
-use crate::{network::Network, python_env};
+use crate::{
+    config::Config,
+    network::Network,
+    node_instance::{ForayNodeInstance, ForayNodeTemplate},
+    python_env,
+    workspace::REPRODUCER_DIR,
+};
 
-pub fn run_headless(network_path: PathBuf) -> Result<(), Box<dyn Error>> {
-    let venv_dir = network_path
+/// Run every node of a network once, in topological order.
+/// If `save_reproducers` is set, a reproducer is written for a failing python node
+pub fn run_headless(network_path: PathBuf, save_reproducers: bool) -> Result<(), Box<dyn Error>> {
+    let workspace_dir = network_path
         .parent()
         .expect("Network should be a file")
-        .join("../.venv");
+        .join("..");
+    let config = Config::load(&workspace_dir)?;
+    let venv_dir = config.venv_dir(&workspace_dir);
 
+    python_env::preflight(&venv_dir)?;
     python_env::setup_python(venv_dir);
+    // Make nodes from node directories importable
+    discover::get_directory_packages(&config.node_dirs(&workspace_dir));
 
     let network = match Network::load_network(&network_path) {
         Ok(n) => n,
@@ -19,13 +47,134 @@ pub fn run_headless(network_path: PathBuf) -> Result<(), Box<dyn Error>> {
     };
     let mut graph = network.graph;
 
-    // Propogate values
+    if let Err((nx, e)) = execute(&mut graph, &Dict::new(), |nx, node| {
+        progress_printer(nx, node.template.name())
+    }) {
+        let (node, inputs) = graph.get_compute(nx);
+        let name = node.template.name();
+        println!("[{name} #{nx}] failed:\n{e}");
+        if let (true, ForayNodeTemplate::PyNode(py_node)) = (save_reproducers, &node.template) {
+            let inputs = inputs
+                .into_iter()
+                .map(|(k, v)| (k, v.read().unwrap().clone()))
+                .collect();
+            match write_reproducer(
+                &workspace_dir.join(REPRODUCER_DIR),
+                py_node,
+                inputs,
+                node.parameters_values.clone(),
+                Some(e.to_string()),
+            ) {
+                Ok(dir) => println!("[{name} #{nx}] saved reproducer to {dir:?}"),
+                Err(re) => println!("[{name} #{nx}] could not save reproducer: {re}"),
+            }
+        }
+        return Err(format!("node {name} #{nx} failed").into());
+    }
+
+    Ok(())
+}
+
+/// Run every node of a graph once, in topological order.
+/// Values in `inputs`, keyed by node and input port, are used in place of whatever is
+/// connected to that port.
+/// Stops at the first node that fails, returning its index and error
+pub fn execute(
+    graph: &mut Graph<ForayNodeInstance, PortType, PortData>,
+    inputs: &Dict<(u32, String), PortData>,
+    context: impl Fn(u32, &ForayNodeInstance) -> ComputeContext,
+) -> Result<(), (u32, ForayNodeError)> {
     for nx in graph.topological_sort() {
         trace!("Executing node {nx}");
-        let (node, input_guarded) = graph.get_compute(nx);
-        let (_, output) = Graph::compute_node(nx, node, input_guarded);
-        graph.update_wire_data(nx, output.unwrap());
-    }
+        let (node, mut input_guarded) = graph.get_compute(nx);
+        input_guarded.extend(
+            inputs
+                .iter()
+                .filter(|((input_nx, _), _)| *input_nx == nx)
+                .map(|((_, port), value)| (port.clone(), Arc::new(RwLock::new(value.clone())))),
+        );
 
+        let context = context(nx, &node);
+        let (_, output) = context.scope(|| Graph::compute_node(nx, node, input_guarded));
+        graph.update_wire_data(nx, output.map_err(|e| (nx, e))?);
+    }
     Ok(())
 }
+
+/// Print progress updates sent by a node as it computes
+fn progress_printer(nx: u32, node_name: String) -> ComputeContext {
+    let start = Instant::now();
+    ComputeContext {
+        progress: Some(Arc::new(move |progress| {
+            println!("[{node_name} #{nx}] {}", progress.summary(start.elapsed()))
+        })),
+        cancellation: None,
+    }
+}
+
+/// Run the fixtures of every python node in a workspace's environment, printing a report.
+/// Only nodes whose name or python path contains `filter` are tested, if it is given
+pub fn run_node_tests(
+    workspace_dir: PathBuf,
+    filter: Option<String>,
+    tolerance: Tolerance,
+) -> Result<(), Box<dyn Error>> {
+    let config = Config::load(&workspace_dir)?;
+    let venv_dir = config.venv_dir(&workspace_dir);
+    python_env::preflight(&venv_dir)?;
+    python_env::setup_python(venv_dir);
+
+    let templates = discover::get_foray_py_packages()
+        .into_iter()
+        .filter_map(|package| {
+            package
+                .inspect_err(|e| println!("{e}\n{}", e.error.traceback))
+                .ok()
+        })
+        .chain(discover::get_directory_packages(
+            &config.node_dirs(&workspace_dir),
+        ))
+        .flat_map(|package| package.node_py_paths)
+        .filter(|py_path| {
+            filter
+                .as_ref()
+                .is_none_or(|filter| py_path.contains(filter.as_str()))
+        })
+        .map(PyNodeTemplate::new);
+
+    let mut results = vec![];
+    for template in templates {
+        let fixtures = match find_fixtures(&template) {
+            Ok(fixtures) => fixtures,
+            Err(e) => {
+                println!("{}: could not find fixtures\n{e}", template.py_path);
+                continue;
+            }
+        };
+        for fixture in fixtures {
+            let result = run_fixture(&template, &fixture, tolerance);
+            match &result.outcome {
+                FixtureOutcome::Pass => println!("PASS {} {}", result.node, result.case),
+                FixtureOutcome::Fail(mismatches) => {
+                    println!("FAIL {} {}", result.node, result.case);
+                    mismatches.iter().for_each(|m| println!("    {m}"));
+                }
+                FixtureOutcome::Error(e) => {
+                    println!("ERROR {} {}\n{e}", result.node, result.case)
+                }
+            }
+            results.push(result);
+        }
+    }
+
+    let passed = results
+        .iter()
+        .filter(|r| matches!(r.outcome, FixtureOutcome::Pass))
+        .count();
+    println!("\n{passed}/{} fixtures passed", results.len());
+
+    match passed == results.len() {
+        true => Ok(()),
+        false => Err(format!("{} fixtures failed", results.len() - passed).into()),
+    }
+}
@@ -2,7 +2,9 @@ use crate::node_instance::ForayNodeTemplate;
 use crate::project::{NodeTree, Project};
 use crate::style;
 use crate::style::container::rounded_box;
+use crate::style::icon::icon;
 use crate::workspace::WorkspaceMessage;
+use foray_py::discover::DiscoverError;
 use iced::padding::left;
 use iced::*;
 use itertools::Itertools;
@@ -11,27 +13,44 @@ use widget::{column, *};
 const ROW_HEIGHT: f32 = 25.0;
 
 /// Given a list of node trees,
-/// Render a nested list, that is expanded to `selected_tree_path`
+/// Render a nested list, that is expanded to `selected_tree_path`.
+/// When searching, a flat list of matching nodes is shown instead
 pub fn add_node_tree_panel<'b>(
     projects: &[Project],
+    broken_packages: &[DiscoverError],
     selected_tree_path: &[String],
+    search: &str,
 ) -> Element<'b, WorkspaceMessage> {
-    let node_list = column(
-        projects
-            .iter()
-            .flat_map(|prj| &prj.node_tree.children)
-            .sorted_by(|a, b| a.0.partial_cmp(b.0).unwrap()) // sort by key
-            .map(|(_, tree)| node_tree(tree, &[], selected_tree_path)),
-    );
+    let node_list = match search.trim() {
+        "" => column(
+            projects
+                .iter()
+                .flat_map(|prj| &prj.node_tree.children)
+                .sorted_by(|a, b| a.0.partial_cmp(b.0).unwrap()) // sort by key
+                .map(|(_, tree)| node_tree(tree, &[], selected_tree_path)),
+        ),
+        search => column(
+            projects
+                .iter()
+                .flat_map(|prj| prj.node_tree.leaves())
+                .filter(|(path, template)| matches_search(search, path, template))
+                .sorted_by(|a, b| a.0.cmp(&b.0))
+                .map(|(path, template)| search_result(path, template)),
+        ),
+    };
 
     container(column![
         // Header
         container(text("Add Node").size(16.))
             .center_x(Fill)
             .padding(5.),
+        text_input("search", search)
+            .on_input(WorkspaceMessage::AddNodeSearch)
+            .padding([2, 4]),
         rule::horizontal(3.0),
         // Contents
-        container(scrollable(node_list).spacing(2.)).padding(2.0)
+        container(scrollable(node_list).spacing(2.)).padding(2.0),
+        broken_package_list(broken_packages),
     ])
     .style(rounded_box)
     .width(300.)
@@ -39,6 +58,78 @@ pub fn add_node_tree_panel<'b>(
     .into()
 }
 
+/// Case insensitive match against a node's name, location, description and tags
+fn matches_search(search: &str, path: &[String], template: &ForayNodeTemplate) -> bool {
+    let search = search.to_lowercase();
+    let metadata = template.metadata();
+    path.iter()
+        .chain(metadata.description.iter())
+        .chain(metadata.tags.iter())
+        .any(|s| s.to_lowercase().contains(&search))
+}
+
+fn search_result<'b>(
+    path: Vec<String>,
+    template: &ForayNodeTemplate,
+) -> Element<'b, WorkspaceMessage> {
+    let metadata = template.metadata();
+    button(column![
+        row![
+            text(node_label(&template.name(), &metadata.icon)),
+            text(path[..path.len().saturating_sub(1)].join("/"))
+                .size(10)
+                .style(text::secondary),
+        ]
+        .spacing(8)
+        .align_y(Center),
+        text(metadata.description.unwrap_or_default()).size(10),
+    ])
+    .padding([2, 4])
+    .on_press(WorkspaceMessage::AddNode(template.clone()))
+    .width(Fill)
+    .style(style::button::list)
+    .into()
+}
+
+fn node_label(name: &str, icon: &Option<String>) -> String {
+    match icon {
+        Some(icon) => format!("{icon} {name}"),
+        None => name.to_string(),
+    }
+}
+
+/// Packages that failed to load, with their error shown on hover
+fn broken_package_list<'b>(broken_packages: &[DiscoverError]) -> Element<'b, WorkspaceMessage> {
+    if broken_packages.is_empty() {
+        return space::vertical().height(0).into();
+    }
+    column![
+        rule::horizontal(1.0),
+        column(broken_packages.iter().map(|e| {
+            let summary = row![
+                icon("").style(text::danger),
+                text(e.package_name.clone()).style(text::danger),
+                text(e.error.error.clone()).size(10).style(text::danger),
+            ]
+            .spacing(8)
+            .align_y(Center)
+            .height(ROW_HEIGHT);
+
+            let details = format!("{e}\n\n{}", e.error.traceback);
+            tooltip(
+                summary,
+                container(text(details).size(10))
+                    .padding(2)
+                    .style(rounded_box),
+                tooltip::Position::Bottom,
+            )
+            .into()
+        }))
+        .padding([0, 4])
+    ]
+    .into()
+}
+
 /// Recursively build a nested list
 pub fn node_tree<'b>(
     node: &NodeTree<ForayNodeTemplate>,
@@ -63,12 +154,25 @@ pub fn node_tree<'b>(
     };
     match &node.data {
         // Base case, File Row
-        Some(data) => tree_row(
-            text(node.name.clone()),
-            WorkspaceMessage::AddNode(data.clone()),
-        )
-        .align_y(Center)
-        .into(),
+        Some(data) => {
+            let metadata = data.metadata();
+            let row = tree_row(
+                text(node_label(&node.name, &metadata.icon)),
+                WorkspaceMessage::AddNode(data.clone()),
+            )
+            .align_y(Center);
+            match metadata.description {
+                Some(description) => tooltip(
+                    row,
+                    container(text(description).size(10))
+                        .padding(2)
+                        .style(rounded_box),
+                    tooltip::Position::Right,
+                )
+                .into(),
+                None => row.into(),
+            }
+        }
         // Folder Row
         None => {
             let next_path = &[tree_path, &[node.name.to_owned()]].concat();
@@ -0,0 +1,50 @@
+use iced::widget::text_editor;
+use iced::widget::text_editor::{Action, Content};
+use iced::Element;
+
+use crate::workspace::WorkspaceMessage;
+use crate::CODE_FONT;
+
+/// Editing state of a node's code parameter.
+/// The editor's cursor and selection live in its `Content`, so it's kept between frames, and
+/// rebuilt when a different node is selected or the code changes outside of the editor
+pub struct CodeEditor {
+    pub node: u32,
+    pub parameter: String,
+    /// Code the content was last synced with
+    pub code: String,
+    content: Content,
+}
+
+impl CodeEditor {
+    pub fn new(node: u32, parameter: String, code: String) -> Self {
+        CodeEditor {
+            node,
+            parameter,
+            content: Content::with_text(&code),
+            code,
+        }
+    }
+
+    /// Apply an editor action, returning the new code if it was edited
+    pub fn perform(&mut self, action: Action) -> Option<String> {
+        let is_edit = action.is_edit();
+        self.content.perform(action);
+        if !is_edit {
+            return None;
+        }
+        self.code = self.content.text();
+        Some(self.code.clone())
+    }
+
+    pub fn view(&self) -> Element<'_, WorkspaceMessage> {
+        let node = self.node;
+        let parameter = self.parameter.clone();
+        text_editor(&self.content)
+            .font(CODE_FONT)
+            .size(12)
+            .height(300)
+            .on_action(move |action| WorkspaceMessage::EditCode(node, parameter.clone(), action))
+            .into()
+    }
+}
@@ -0,0 +1,56 @@
+use iced::widget::{column, scrollable, text};
+use iced::{Element, Fill};
+
+use crate::venv::{EnvError, EnvEvent};
+use crate::CODE_FONT;
+
+/// Lines kept from the output of environment commands
+const MAX_LINES: usize = 500;
+
+/// Output of the last environment sync or package install
+#[derive(Debug, Default)]
+pub struct EnvironmentLog {
+    pub running: bool,
+    pub lines: Vec<String>,
+    pub error: Option<EnvError>,
+}
+
+impl EnvironmentLog {
+    pub fn start() -> Self {
+        EnvironmentLog {
+            running: true,
+            ..Default::default()
+        }
+    }
+
+    pub fn update(&mut self, event: EnvEvent) {
+        match event {
+            EnvEvent::Command(command) => self.lines.push(format!("$ {command}")),
+            EnvEvent::Output(line) => self.lines.push(line),
+            EnvEvent::Finished(result) => {
+                self.running = false;
+                self.error = result.err();
+            }
+        }
+        if self.lines.len() > MAX_LINES {
+            self.lines.drain(..self.lines.len() - MAX_LINES);
+        }
+    }
+
+    pub fn view<'a, M: 'a>(&'a self) -> Element<'a, M> {
+        let status = match (self.running, &self.error) {
+            (true, _) => text("running...").size(12),
+            (false, Some(e)) => text(e.to_string()).size(12).style(text::danger),
+            (false, None) if !self.lines.is_empty() => text("done").size(12).style(text::success),
+            (false, None) => text(""),
+        };
+        column![
+            status,
+            scrollable(text(self.lines.join("\n")).size(10).font(CODE_FONT))
+                .anchor_bottom()
+                .height(Fill)
+        ]
+        .spacing(4)
+        .into()
+    }
+}
@@ -1,8 +1,11 @@
 use std::fmt::Debug;
 use std::fmt::Display;
 pub mod add_node;
+pub mod code_editor;
+pub mod environment;
 pub mod node;
 pub mod node_canvas;
+pub mod node_help;
 pub mod port;
 pub mod side_bar;
 pub mod status;
@@ -2,12 +2,14 @@ use std::f32::consts::TAU;
 use std::time::Instant;
 
 use crate::interface::port::port_color_pair;
+use crate::network::NODE_RELOAD_FLASH_DUR;
 use crate::node_instance::visualiztion::{NDimVis, Visualization};
 use crate::node_instance::{ForayNodeInstance, ForayNodeTemplate};
 use crate::rust_nodes::RustNodeTemplate;
 use crate::style::theme::AppTheme;
 use crate::workspace::{Action, WorkspaceMessage};
 use crate::StableMap;
+use foray_data_model::context::Progress;
 use foray_data_model::node::{Dict, PortData, PortType};
 use foray_data_model::WireDataContainer;
 use foray_data_vis::series_vis::SeriesVis;
@@ -155,7 +157,7 @@ pub fn draw_node(
             true => app_theme.primary.strong_color().into(),
             false => app_theme.text.weak_color().into(),
         },
-        crate::node_instance::NodeStatus::Running { start } => {
+        crate::node_instance::NodeStatus::Running { start, .. } => {
             let duration = (Instant::now() - *start).as_secs_f32();
             let base_color = match is_selected {
                 true => app_theme.primary.strong_color(),
@@ -230,6 +232,90 @@ pub fn draw_node(
             draw_node_ports(frame, app_theme, node, node_id, action, cursor, stroke);
         }
     };
+
+    if let crate::node_instance::NodeStatus::Running {
+        start,
+        progress: Some(progress),
+    } = &node.status
+    {
+        draw_node_progress(frame, app_theme, node_bounding, *start, progress);
+    }
+
+    if let Some(reloaded) = node.last_reloaded {
+        draw_node_reload_flash(frame, app_theme, node_bounding, scale, reloaded);
+    }
+}
+
+/// Briefly outline a node whose template was just reloaded from disk
+pub fn draw_node_reload_flash(
+    // Draw directly into frame
+    frame: &mut iced::widget::canvas::Frame,
+    app_theme: &AppTheme,
+    node_bounding: Rectangle,
+    scale: f32,
+    reloaded: Instant,
+) {
+    let since_reload = (Instant::now() - reloaded).as_secs_f32();
+    if since_reload >= NODE_RELOAD_FLASH_DUR {
+        return;
+    }
+    let fade = 1.0 - since_reload / NODE_RELOAD_FLASH_DUR;
+    let flash_padding = 4.0;
+    let flash = Path::rounded_rectangle(
+        node_bounding.position() - iced::Vector::new(flash_padding, flash_padding),
+        iced::Size::new(
+            node_bounding.width + 2.0 * flash_padding,
+            node_bounding.height + 2.0 * flash_padding,
+        ),
+        (NODE_RADIUS + flash_padding).into(),
+    );
+    frame.stroke(
+        &flash,
+        Stroke::default()
+            .with_color(app_theme.primary.base_color.iced_color().scale_alpha(fade))
+            .with_width(3.0 * scale),
+    );
+}
+
+/// Draw a progress bar along the bottom of the node, with the node's message and ETA beside it
+pub fn draw_node_progress(
+    // Draw directly into frame
+    frame: &mut iced::widget::canvas::Frame,
+    app_theme: &AppTheme,
+    node_bounding: Rectangle,
+    start: Instant,
+    progress: &Progress,
+) {
+    let bar_height = 3.0;
+    let bar_inset = NODE_RADIUS;
+    let bar_width = node_bounding.width - 2.0 * bar_inset;
+    let bar_position = iced::Point::new(
+        node_bounding.x + bar_inset,
+        node_bounding.y + node_bounding.height - bar_height - 2.0,
+    );
+
+    frame.fill_rectangle(
+        bar_position,
+        iced::Size::new(bar_width, bar_height),
+        app_theme.background.strong_color().iced_color(),
+    );
+    frame.fill_rectangle(
+        bar_position,
+        iced::Size::new(bar_width * progress.fraction, bar_height),
+        app_theme.primary.strong_color().iced_color(),
+    );
+
+    frame.fill_text(Text {
+        content: progress.summary(start.elapsed()),
+        position: iced::Point::new(
+            node_bounding.x + node_bounding.width + 6.0,
+            node_bounding.center_y(),
+        ),
+        color: app_theme.text.weak_color().into(),
+        size: iced::Pixels(12.0),
+        align_y: iced::alignment::Vertical::Center,
+        ..Default::default()
+    });
 }
 
 pub fn draw_node_text(
@@ -0,0 +1,57 @@
+use foray_data_model::node::{Dict, PortType};
+use foray_graph::graph::GraphNode;
+use iced::{
+    widget::{column, container, markdown, rule, scrollable, text},
+    Element, Fill, Theme,
+};
+
+use crate::{
+    interface::port::port_tooltip, node_instance::ForayNodeInstance, style::theme::AppTheme,
+    workspace::WorkspaceMessage,
+};
+
+/// Documentation for a node: its description, ports and markdown docs
+pub fn node_help_pane<'a>(
+    node: &'a ForayNodeInstance,
+    docs: Option<&'a Vec<markdown::Item>>,
+    app_theme: &'a AppTheme,
+) -> Element<'a, WorkspaceMessage> {
+    let metadata = node.template.metadata();
+    let title = match (&metadata.icon, metadata.version) {
+        (Some(icon), 0) => format!("{icon} {}", node.template.name()),
+        (Some(icon), v) => format!("{icon} {} v{v}", node.template.name()),
+        (None, 0) => node.template.name(),
+        (None, v) => format!("{} v{v}", node.template.name()),
+    };
+
+    let ports = |heading, ports: Dict<String, PortType>| {
+        column![text(heading).size(14)]
+            .extend(ports.into_iter().map(|(name, port_type)| {
+                let doc = metadata.ports.get(&name).cloned();
+                port_tooltip(name, port_type, doc, app_theme)
+            }))
+            .spacing(4)
+    };
+
+    container(scrollable(
+        column![
+            container(text(title).size(20.)).center_x(Fill),
+            rule::horizontal(0),
+            text(metadata.description.clone().unwrap_or_default()),
+            ports("Inputs", node.inputs()),
+            ports("Outputs", node.outputs()),
+            rule::horizontal(0),
+            match docs {
+                Some(docs) => markdown::view(docs, Theme::from(app_theme.clone()))
+                    .map(WorkspaceMessage::OpenLink),
+                None => text("No documentation").size(10).into(),
+            }
+        ]
+        .spacing(8)
+        .padding([10, 10]),
+    ))
+    .width(300.)
+    .height(Fill)
+    .style(|theme: &Theme| container::background(theme.palette().background))
+    .into()
+}
@@ -1,4 +1,5 @@
 use foray_data_model::node::PortType;
+use foray_py::py_node::PortDoc;
 use iced::{
     alignment::Horizontal::Right,
     widget::{column, container, container::background, row, text},
@@ -54,6 +55,7 @@ pub fn port_color_pair(port_type: &PortType, app_theme: &AppTheme) -> (Color, Co
         PortType::Complex => app_theme.orange.color_pair(),
         PortType::Boolean => app_theme.cyan.color_pair(),
         PortType::String => app_theme.green.color_pair(),
+        PortType::Any => app_theme.text.color_pair(),
         PortType::Array(array_port_type, _) => port_color_pair(array_port_type, app_theme),
     }
 }
@@ -66,6 +68,7 @@ fn port_text(port_type: &PortType) -> String {
         PortType::Complex => "Complex",
         PortType::Boolean => "Boolean",
         PortType::String => "String",
+        PortType::Any => "Any",
         PortType::Array(_port_type, _items) => "Array",
         PortType::Object(_children) => "Object",
     }
@@ -83,18 +86,26 @@ fn port_type_label<'a, M: 'a>(
         .into()
 }
 
-/// Display summary of port information
+/// Display summary of port information, including its description and units if available
 pub fn port_tooltip<'a, M: 'a>(
     port_name: String,
     port_type: PortType,
+    doc: Option<PortDoc>,
     app_theme: &'a AppTheme,
 ) -> Element<'a, M> {
-    row![
-        text(port_name),
-        port_tooltip_recurse(port_type, app_theme, true)
-    ]
-    .align_y(Center)
-    .into()
+    let PortDoc { description, units } = doc.unwrap_or_default();
+    let name = match units {
+        Some(units) => format!("{port_name} [{units}]"),
+        None => port_name,
+    };
+    let summary =
+        row![text(name), port_tooltip_recurse(port_type, app_theme, true)].align_y(Center);
+    match description {
+        Some(description) => column![summary, text(description).size(10)]
+            .spacing(VERTICAL_SPACING)
+            .into(),
+        None => summary.into(),
+    }
 }
 
 const VERTICAL_SPACING: u32 = 2;
@@ -1,11 +1,13 @@
+use crate::interface::code_editor::CodeEditor;
 use crate::interface::status::node_status_widget;
 use crate::interface::SEPERATOR;
 use crate::node_instance::visualiztion::Visualization;
-use crate::node_instance::{ForayNodeInstance, ForayNodeTemplate};
+use crate::node_instance::{ForayNodeInstance, ForayNodeTemplate, NodeStatus};
 use crate::rust_nodes::RustNodeTemplate;
 use crate::style::button::{primary_icon, secondary_icon};
 use crate::style::icon::icon;
 use crate::workspace::{Workspace, WorkspaceMessage};
+use crate::CODE_FONT;
 use foray_data_model::node::{Dict, PortData, UIParameter};
 use foray_data_model::WireDataContainer;
 use foray_widgets::numeric_input::numeric_input;
@@ -84,15 +86,25 @@ pub fn side_bar(app: &Workspace) -> Element<'_, WorkspaceMessage> {
                 rule::horizontal(0),
                 node_status_widget(&node.status),
                 space::vertical().height(10.),
-                config_view(node, *selected_id, input_data).unwrap_or(text("...").into()),
+                config_view(node, *selected_id, input_data, app.code_editor.as_ref())
+                    .unwrap_or(text("...").into()),
                 // node.config_view(*selected_id, input_data)
                 //     .unwrap_or(text("...").into()),
                 space::vertical(),
                 //scrollable(out_port_display),
-                row![button(text("delete node"))
-                    .style(button::danger)
-                    .padding([1, 4])
-                    .on_press(WorkspaceMessage::DeleteSelectedNodes)]
+                row![
+                    button(text("help"))
+                        .style(button::secondary)
+                        .padding([1, 4])
+                        .on_press(WorkspaceMessage::ToggleNodeHelp),
+                    save_reproducer_button(node, *selected_id),
+                    cancel_button(node, *selected_id),
+                    button(text("delete node"))
+                        .style(button::danger)
+                        .padding([1, 4])
+                        .on_press(WorkspaceMessage::DeleteSelectedNodes)
+                ]
+                .spacing(4.)
             ]
             .align_x(Center)
             .height(Fill)
@@ -100,7 +112,7 @@ pub fn side_bar(app: &Workspace) -> Element<'_, WorkspaceMessage> {
             .padding([10., 5.])
             .into()
         } else {
-            text("").into()
+            environment_view(app)
         };
     container(
         column![
@@ -123,10 +135,65 @@ pub fn side_bar(app: &Workspace) -> Element<'_, WorkspaceMessage> {
     .into()
 }
 
+/// Sync the workspace's python environment, and install packages into it
+fn environment_view(app: &Workspace) -> Element<'_, WorkspaceMessage> {
+    let idle = !app.environment_log.running;
+    column![
+        container(text("python environment").size(16)).center_x(Fill),
+        rule::horizontal(0),
+        button(text("sync"))
+            .style(button::secondary)
+            .padding([1, 4])
+            .on_press_maybe(idle.then_some(WorkspaceMessage::SyncEnvironment)),
+        row![
+            text_input("package or ./path", &app.install_package)
+                .size(12)
+                .on_input(WorkspaceMessage::InstallPackageInput)
+                .on_submit_maybe(idle.then_some(WorkspaceMessage::InstallPackage)),
+            button(text("install"))
+                .style(button::secondary)
+                .padding([1, 4])
+                .on_press_maybe(idle.then_some(WorkspaceMessage::InstallPackage)),
+        ]
+        .spacing(4)
+        .align_y(Center),
+        app.environment_log.view(),
+    ]
+    .spacing(5.)
+    .padding([10., 5.])
+    .height(Fill)
+    .into()
+}
+
+/// Offer to save a standalone reproducer when a python node has failed
+fn save_reproducer_button<'a>(node: &ForayNodeInstance, id: u32) -> Element<'a, WorkspaceMessage> {
+    match (&node.template, &node.status) {
+        (ForayNodeTemplate::PyNode(_), NodeStatus::Error(_)) => button(text("save reproducer"))
+            .style(button::secondary)
+            .padding([1, 4])
+            .on_press(WorkspaceMessage::SaveReproducer(id))
+            .into(),
+        _ => space::horizontal().width(0).into(),
+    }
+}
+
+/// Offer to stop a command node while it runs, which kills its process
+fn cancel_button<'a>(node: &ForayNodeInstance, id: u32) -> Element<'a, WorkspaceMessage> {
+    match (&node.template, &node.status) {
+        (ForayNodeTemplate::Command(_), NodeStatus::Running { .. }) => button(text("cancel"))
+            .style(button::secondary)
+            .padding([1, 4])
+            .on_press(WorkspaceMessage::CancelCompute(id))
+            .into(),
+        _ => space::horizontal().width(0).into(),
+    }
+}
+
 pub fn config_view<'a>(
     node_instance: &'a ForayNodeInstance,
     id: u32,
     _input_data: Dict<String, WireDataContainer<PortData>>,
+    code_editor: Option<&'a CodeEditor>,
 ) -> Option<iced::Element<'a, WorkspaceMessage>> {
     match &node_instance.template {
         ForayNodeTemplate::RustNode(RustNodeTemplate::Display)
@@ -142,152 +209,222 @@ pub fn config_view<'a>(
                 None => None,
             }
         }
+        ForayNodeTemplate::RustNode(rust_node) => Some(parameters_view(
+            node_instance,
+            id,
+            rust_node.parameters(),
+            code_editor,
+        )),
         // TODO: data is awkwardly stored in two locations, defaults are in the widget_type,
         // current values are stored in node_instance.parameter_values. these values are less
         // structured (they could be any PortData type)
         //
-        ForayNodeTemplate::PyNode(pn) => {
-            if let Ok(parameters) = pn.parameters() {
-                Some(
-                    column(parameters.clone().into_iter().map(|(name, widget_type)| {
-                        let name_2 = name.clone();
-                        let name_3 = name.clone();
-                        let name_4 = name.clone();
-                        let message = move |widget_value| {
-                            WorkspaceMessage::UpdateNodeParameter(id, name.clone(), widget_value)
-                        };
-                        let message_2 = message.clone();
-                        //TODO: make widget type view
-                        let widget: Element<WorkspaceMessage> = match widget_type {
-                            UIParameter::CheckBox(_initial_v) => {
-                                row![
-                                    space::horizontal(),
-                                    toggler(
-                                        match node_instance
-                                            .parameters_values
-                                            .get(&name_2)
-                                            .expect("parameter should exist")
-                                        {
-                                            PortData::Boolean(v) => *v,
-                                            _ => panic!("unexpected port type"),
-                                        }
-                                    )
-                                    .on_toggle(
-                                        move |v| message(PortData::Boolean(v)) //, in_progress))
-                                    )
-                                    .width(60.0)
-                                ]
-                                .align_y(Center)
-                                .into()
-                            }
-                            UIParameter::NumberField(_v) => {
-                                let current_value =
-                                    match node_instance.parameters_values[&name_2.clone()] {
-                                        PortData::Float(v) => v,
-                                        _ => panic!("slider should be a float"),
-                                    };
-                                row![
-                                    space::horizontal(),
-                                    row![numeric_input(current_value as f32).on_input(
-                                        move |new_v| { message(PortData::Float(new_v as f64)) },
-                                    )]
-                                    .width(60.0)
-                                ]
-                                .align_y(Center)
-                                .into()
-                            }
-                            UIParameter::Slider(start, stop, _default_v) => {
-                                let current_value =
-                                    match node_instance.parameters_values[&name_2.clone()] {
-                                        PortData::Float(v) => v,
-                                        _ => panic!("slider should be a float"),
-                                    };
+        ForayNodeTemplate::PyNode(pn) => match pn.parameters() {
+            Ok(parameters) => Some(parameters_view(node_instance, id, parameters, code_editor)),
+            Err(_) => Some(text("").into()),
+        },
+        ForayNodeTemplate::Plugin(plugin) => Some(parameters_view(
+            node_instance,
+            id,
+            plugin.parameters.clone(),
+            code_editor,
+        )),
+        ForayNodeTemplate::Native(native) => native
+            .node()
+            .ok()
+            .map(|node| parameters_view(node_instance, id, node.parameters(), code_editor)),
+        ForayNodeTemplate::PySnippet(snippet) => Some(parameters_view(
+            node_instance,
+            id,
+            snippet.snippet_parameters(),
+            code_editor,
+        )),
+        ForayNodeTemplate::Command(command) => Some(parameters_view(
+            node_instance,
+            id,
+            command.parameters(),
+            code_editor,
+        )),
+    }
+}
 
-                                row![
-                                    row![
-                                        numeric_input(current_value as f32)
-                                            // iced_aw::typed_input::TypedInput::new(
-                                            //     "Placeholder",
-                                            //     &current_value
-                                            // )
-                                            .on_input(move |new_v| {
-                                                WorkspaceMessage::UpdateNodeParameter(
-                                                    id,
-                                                    name_2.clone(),
-                                                    PortData::Float(new_v as f64),
-                                                )
-                                            }) // iced_aw::typed_input(&current_value, move |new_v| {
-                                               //     Message::UpdateNodeParameter(
-                                               //         id,
-                                               //         name.clone(),
-                                               //         PortData::Float(new_v as f64),
-                                               //     )
-                                               // }),
-                                               // numeric_input::numeric_input(
-                                               // current_value,
-                                               // numeric_input::PartialUIValue::Complete,
-                                               // move |new_v, pv| {
-                                               //     message(PortData::Float(new_v as f64))
-                                               //     // UIParameter::Slider(start, stop, new_v as f64))
-                                               // },
-                                    ]
-                                    .width(60.0),
-                                    slider(start..=stop, current_value, move |new_v| {
-                                        message_2(PortData::Float(
-                                            (new_v * PRECISION).round() / PRECISION,
-                                        ))
-                                        // message(
-                                        //     Self::Slider(new_v, PartialUIValue::Complete))
-                                    })
-                                    .step(1.0 / PRECISION)
-                                ]
-                                .align_y(Center)
-                                .spacing(4.0)
-                                .into()
-                            }
-                            UIParameter::TextDisplay(content) => text(content).into(),
-                            UIParameter::FilePicker(_path) => {
-                                let current_path =
-                                    match &node_instance.parameters_values[&name_2.clone()] {
-                                        PortData::String(content) => content.clone(),
-                                        _ => panic!("filepicker value should be a string"),
-                                    };
-                                row![
-                                    text_input("path", &current_path)
-                                        .on_input(move |widget_value| {
-                                            WorkspaceMessage::UpdateNodeParameter(
-                                                id,
-                                                name_4.clone(),
-                                                PortData::String(widget_value.into()),
-                                            )
-                                        })
-                                        .align_x(Right),
-                                    button(icon(""))
-                                        .on_press(WorkspaceMessage::StartWidgetFilePicker(
-                                            id,
-                                            name_3.clone()
-                                        ))
-                                        .height(30.0)
-                                        .padding(4.0)
-                                ]
-                                .align_y(Center)
-                                .spacing(2.0)
-                                .into()
-                            }
-                        };
-                        row![text(name_3.clone()), widget,]
-                            .spacing(8.0)
-                            .align_y(Center)
-                            .width(Fill)
-                            .into()
-                    }))
-                    .spacing(8.)
-                    .width(Fill)
-                    .into(),
+/// Widgets to edit each of a node's parameters
+fn parameters_view<'a>(
+    node_instance: &'a ForayNodeInstance,
+    id: u32,
+    parameters: Dict<String, UIParameter>,
+    code_editor: Option<&'a CodeEditor>,
+) -> Element<'a, WorkspaceMessage> {
+    column(parameters.into_iter().map(|(name, widget_type)| {
+        let name_2 = name.clone();
+        let name_3 = name.clone();
+        let name_4 = name.clone();
+        let message = move |widget_value| {
+            WorkspaceMessage::UpdateNodeParameter(id, name.clone(), widget_value)
+        };
+        let message_2 = message.clone();
+        // Code is too wide to fit next to its name
+        let below_name = matches!(widget_type, UIParameter::CodeEditor(_));
+        //TODO: make widget type view
+        let widget: Element<WorkspaceMessage> = match widget_type {
+            UIParameter::CheckBox(_initial_v) => {
+                row![
+                    space::horizontal(),
+                    toggler(
+                        match node_instance
+                            .parameters_values
+                            .get(&name_2)
+                            .expect("parameter should exist")
+                        {
+                            PortData::Boolean(v) => *v,
+                            _ => panic!("unexpected port type"),
+                        }
+                    )
+                    .on_toggle(
+                        move |v| message(PortData::Boolean(v)) //, in_progress))
+                    )
+                    .width(60.0)
+                ]
+                .align_y(Center)
+                .into()
+            }
+            UIParameter::NumberField(_v) => {
+                let current_value = match node_instance.parameters_values[&name_2.clone()] {
+                    PortData::Float(v) => v,
+                    _ => panic!("slider should be a float"),
+                };
+                row![
+                    space::horizontal(),
+                    row![numeric_input(current_value as f32)
+                        .on_input(move |new_v| { message(PortData::Float(new_v as f64)) },)]
+                    .width(60.0)
+                ]
+                .align_y(Center)
+                .into()
+            }
+            UIParameter::Slider(start, stop, _default_v) => {
+                let current_value = match node_instance.parameters_values[&name_2.clone()] {
+                    PortData::Float(v) => v,
+                    _ => panic!("slider should be a float"),
+                };
+
+                row![
+                    row![
+                        numeric_input(current_value as f32)
+                            // iced_aw::typed_input::TypedInput::new(
+                            //     "Placeholder",
+                            //     &current_value
+                            // )
+                            .on_input(move |new_v| {
+                                WorkspaceMessage::UpdateNodeParameter(
+                                    id,
+                                    name_2.clone(),
+                                    PortData::Float(new_v as f64),
+                                )
+                            }) // iced_aw::typed_input(&current_value, move |new_v| {
+                               //     Message::UpdateNodeParameter(
+                               //         id,
+                               //         name.clone(),
+                               //         PortData::Float(new_v as f64),
+                               //     )
+                               // }),
+                               // numeric_input::numeric_input(
+                               // current_value,
+                               // numeric_input::PartialUIValue::Complete,
+                               // move |new_v, pv| {
+                               //     message(PortData::Float(new_v as f64))
+                               //     // UIParameter::Slider(start, stop, new_v as f64))
+                               // },
+                    ]
+                    .width(60.0),
+                    slider(start..=stop, current_value, move |new_v| {
+                        message_2(PortData::Float((new_v * PRECISION).round() / PRECISION))
+                        // message(
+                        //     Self::Slider(new_v, PartialUIValue::Complete))
+                    })
+                    .step(1.0 / PRECISION)
+                ]
+                .align_y(Center)
+                .spacing(4.0)
+                .into()
+            }
+            UIParameter::TextDisplay(content) => text(content).into(),
+            UIParameter::TextInput(_default) => {
+                let current_value = match &node_instance.parameters_values[&name_2.clone()] {
+                    PortData::String(content) => content.clone(),
+                    _ => panic!("text input value should be a string"),
+                };
+                text_input("", &current_value)
+                    .on_input(move |widget_value| message(PortData::String(widget_value)))
+                    .align_x(Right)
+                    .into()
+            }
+            UIParameter::CodeEditor(_default) => match code_editor
+                .filter(|editor| editor.node == id && editor.parameter == name_2)
+            {
+                Some(editor) => editor.view(),
+                // Only one code parameter of the selected node is editable
+                None => match &node_instance.parameters_values[&name_2.clone()] {
+                    PortData::String(code) => text(code.clone()).size(12).font(CODE_FONT).into(),
+                    _ => panic!("code editor value should be a string"),
+                },
+            },
+            UIParameter::FilePicker(_) | UIParameter::DirectoryPicker(_) => {
+                let current_path = match &node_instance.parameters_values[&name_2.clone()] {
+                    PortData::String(content) => content.clone(),
+                    _ => panic!("filepicker value should be a string"),
+                };
+                let pick = match widget_type {
+                    UIParameter::DirectoryPicker(_) => {
+                        WorkspaceMessage::StartWidgetDirectoryPicker(id, name_3.clone())
+                    }
+                    _ => WorkspaceMessage::StartWidgetFilePicker(id, name_3.clone()),
+                };
+                row![
+                    text_input("path", &current_path)
+                        .on_input(move |widget_value| {
+                            WorkspaceMessage::UpdateNodeParameter(
+                                id,
+                                name_4.clone(),
+                                PortData::String(widget_value.into()),
+                            )
+                        })
+                        .align_x(Right),
+                    button(icon("")).on_press(pick).height(30.0).padding(4.0)
+                ]
+                .align_y(Center)
+                .spacing(2.0)
+                .into()
+            }
+            UIParameter::Dropdown(options, default) => {
+                let selected = match &node_instance.parameters_values[&name_2.clone()] {
+                    PortData::String(selected) => selected.clone(),
+                    _ => panic!("dropdown value should be a string"),
+                };
+                // An empty value stands for the default
+                pick_list(
+                    options,
+                    Some(selected).filter(|s| !s.is_empty()),
+                    move |selected| message(PortData::String(selected)),
                 )
-            } else {
-                Some(text("").into())
+                .placeholder(default)
+                .width(Fill)
+                .into()
             }
+        };
+        if below_name {
+            return column![text(name_3.clone()), widget]
+                .spacing(4.0)
+                .width(Fill)
+                .into();
         }
-    }
+        row![text(name_3.clone()), widget,]
+            .spacing(8.0)
+            .align_y(Center)
+            .width(Fill)
+            .into()
+    }))
+    .spacing(8.)
+    .width(Fill)
+    .into()
 }
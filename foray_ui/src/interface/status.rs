@@ -1,3 +1,5 @@
+use foray_command::err::CommandError;
+use foray_data_model::node::NodeError;
 use foray_graph::graph::ForayNodeError;
 use iced::{
     widget::{column, container, container::rounded_box, row, text, tooltip, tooltip::Position},
@@ -5,12 +7,16 @@ use iced::{
     Element,
 };
 
-use crate::{node_instance::NodeStatus, style::icon::icon};
+use crate::{node_instance::NodeStatus, style::icon::icon, CODE_FONT};
 
 pub fn node_status_widget<'a, M: 'a>(status: &'a NodeStatus) -> Element<'a, M> {
     match status {
         NodeStatus::Idle { .. } => text("").into(),
-        NodeStatus::Running { start: _ } => text("").into(),
+        NodeStatus::Running {
+            start,
+            progress: Some(progress),
+        } => text(progress.summary(start.elapsed())).size(10).into(),
+        NodeStatus::Running { .. } => text("").into(),
         NodeStatus::Error(errs) => column(errs.iter().map(|e| {
             let (summary, detailed) = match e {
                 ForayNodeError::PyNodeConifgError(py_node_config_error) => {
@@ -22,7 +28,21 @@ pub fn node_status_widget<'a, M: 'a>(status: &'a NodeStatus) -> Element<'a, M> {
                         _ => (py_node_config_error.to_string(), None),
                     }
                 }
+                ForayNodeError::NodeError(NodeError::Parse {
+                    message,
+                    source,
+                    column,
+                }) => (
+                    message.clone(),
+                    Some(format!("{source}\n{}^", " ".repeat(*column))),
+                ),
                 ForayNodeError::NodeError(node_error) => (node_error.to_string(), None),
+                ForayNodeError::Plugin(plugin_error) => (plugin_error.to_string(), None),
+                ForayNodeError::Command(command_error @ CommandError::Exit { stderr, .. }) => (
+                    command_error.to_string(),
+                    (!stderr.trim().is_empty()).then(|| stderr.clone()),
+                ),
+                ForayNodeError::Command(command_error) => (command_error.to_string(), None),
             };
 
             let summary_row = row![
@@ -35,7 +55,9 @@ pub fn node_status_widget<'a, M: 'a>(status: &'a NodeStatus) -> Element<'a, M> {
             match detailed {
                 Some(d) => tooltip(
                     summary_row,
-                    container(text(d).size(10)).padding(2).style(rounded_box),
+                    container(text(d).size(10).font(CODE_FONT))
+                        .padding(2)
+                        .style(rounded_box),
                     Position::Right,
                 )
                 .into(),
@@ -1,13 +1,13 @@
 use std::iter::once;
 use std::time::Instant;
 
-use crate::network::NODE_FIRE_ANIMATION_DUR;
+use crate::network::{DroppedEdge, NODE_FIRE_ANIMATION_DUR};
 use crate::node_instance::NodeStatus;
 use crate::style::theme::AppTheme;
 use crate::workspace::Workspace;
 use crate::{math::Point, workspace::Action};
 use canvas::{Path, Stroke};
-use foray_graph::graph::{PortRef, IO};
+use foray_graph::graph::{GraphNode, PortRef, IO};
 use iced::mouse::Cursor;
 use iced::{widget::*, Size};
 use indexmap::IndexMap;
@@ -30,6 +30,25 @@ impl Workspace {
             };
             (port_center + points[&port.node].to_vector().into()).into()
         };
+        // The port of a dropped connection may not exist anymore,
+        // in which case the wire ends at the edge of the node
+        let dangling_position = |port: &PortRef| {
+            let position = points.get(&port.node)?;
+            let node = self.network.graph.get_node(port.node);
+            let port_exists = match port.io {
+                IO::In => node.inputs().contains_key(&port.name),
+                IO::Out => node.outputs().contains_key(&port.name),
+            };
+            if port_exists {
+                return Some(port_position(port));
+            }
+            let bounds = node.node_bounding_rect();
+            let node_edge = match port.io {
+                IO::In => iced::Point::new(bounds.center_x(), bounds.y),
+                IO::Out => iced::Point::new(bounds.center_x(), bounds.y + bounds.height),
+            };
+            Some(node_edge + position.to_vector().into())
+        };
 
         //// Handle currently active wire
         // TODO: test nodes with multiple out ports
@@ -91,6 +110,19 @@ impl Workspace {
                 );
                 ((port_position(input), port_position(output)), stroke)
             })
+            //// include connections that were dropped by a change in ports
+            .chain(
+                self.network
+                    .dropped_edges
+                    .iter()
+                    .filter(|dropped| dropped.to.0.node == wire_end_node)
+                    .filter_map(|DroppedEdge { from, to }| {
+                        Some((
+                            (dangling_position(&to.0)?, dangling_position(&from.0)?),
+                            dropped_wire_stroke(app_theme),
+                        ))
+                    }),
+            )
             //// include the active wire
             .chain(
                 once(match &wire_creation_state {
@@ -321,6 +353,13 @@ pub fn active_wire_stroke(t: &'_ AppTheme, is_tentative_connection: bool) -> Str
     }
 }
 
+/// A connection that was removed because its port went away
+pub fn dropped_wire_stroke(t: &'_ AppTheme) -> Stroke<'_> {
+    with_dashed_stroke(
+        default_wire_stroke(t, 0.0).with_color(t.danger.base_color.iced_color().scale_alpha(0.6)),
+    )
+}
+
 fn with_dashed_stroke(stroke: Stroke) -> Stroke {
     Stroke {
         line_dash: canvas::LineDash {
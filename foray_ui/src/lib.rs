@@ -9,6 +9,7 @@ pub const SYMBOL_FONT: Font = Font::with_name("CaskaydiaCove Nerd Font");
 pub const CODE_FONT: Font = Font::with_name("CaskaydiaCove Nerd Font");
 
 pub mod app;
+pub mod cli;
 pub mod config;
 pub mod file_watch;
 pub mod headless;
@@ -21,4 +22,5 @@ pub mod python_env;
 pub mod rust_nodes;
 pub mod style;
 pub mod user_data;
+pub mod venv;
 pub mod workspace;
@@ -1,16 +1,18 @@
 use std::{collections::HashSet, fs::read_to_string, iter::once, path::PathBuf, time::Instant};
 
-use foray_data_model::node::{PortData, PortType};
-use foray_graph::graph::{Graph, PortRef};
+use foray_data_model::node::{Dict, PortData, PortType};
+use foray_graph::graph::{ForayNodeError, Graph, GraphNode, PortRef, IO};
+use foray_native::Parameters;
+use foray_py::{py_node::PyNodeTemplate, snippet::snippet_module_name};
 use iced::keyboard::Modifiers;
 use indexmap::IndexMap;
-use log::warn;
+use log::{info, warn};
 use serde::{Deserialize, Serialize};
 
 use crate::{
     interface::node_canvas,
     math::Point,
-    node_instance::{ForayNodeInstance, NodeStatus},
+    node_instance::{ForayNodeInstance, ForayNodeTemplate, NodeStatus},
     workspace::Action,
 };
 
@@ -20,6 +22,7 @@ type UndoStash = Vec<(
 )>;
 
 pub const NODE_FIRE_ANIMATION_DUR: f32 = 1.0;
+pub const NODE_RELOAD_FLASH_DUR: f32 = 0.6;
 
 #[derive(Serialize, Deserialize, Default)]
 pub struct Network {
@@ -43,6 +46,17 @@ pub struct Network {
     pub redo_stack: UndoStash,
     #[serde(skip)]
     pub unsaved_changes: bool,
+    /// Connections that were removed because a port of a node with dynamic ports went away.
+    /// They are shown until the port comes back, and the connection is restored
+    #[serde(skip)]
+    pub dropped_edges: Vec<DroppedEdge>,
+}
+
+/// A connection removed by a change in a node's ports, with the port types at the time
+#[derive(Clone, Debug, PartialEq)]
+pub struct DroppedEdge {
+    pub from: (PortRef, PortType),
+    pub to: (PortRef, PortType),
 }
 #[derive(Debug)]
 pub enum NetworkLoadError {
@@ -55,6 +69,7 @@ impl Network {
         match read_to_string(path).map(|s| ron::from_str::<Network>(&s)) {
             Ok(Ok(mut network)) => {
                 network.file = Some(path.clone());
+                network.update_input_types();
                 Ok(network)
             }
             Ok(Err(e)) => {
@@ -74,6 +89,203 @@ impl Network {
         self.stash_state();
         self.graph.remove_edge(input);
         self.graph.add_edge_from_ref(output, input);
+        self.dropped_edges.retain(|dropped| dropped.to.0 != *input);
+        self.update_input_types();
+    }
+
+    /// Set the value of a node's parameter, updating the ports of nodes whose ports depend on
+    /// their parameters
+    pub fn set_parameter(&mut self, nx: u32, name: String, value: PortData) {
+        let node = self.graph.get_mut_node(nx);
+        let (old_inputs, old_outputs) = (node.inputs(), node.outputs());
+        node.parameters_values.insert(name, value);
+        self.reconfigure(nx);
+        self.update_port_edges(nx, old_inputs, old_outputs);
+    }
+
+    /// Update the ports of a node whose ports depend on its parameter values, as far as that's
+    /// quick enough for the UI thread, see [`Self::reconfigure`]. Edges of ports that no longer exist are dropped, and previously dropped edges are restored
+    /// if both of their ports are back
+    pub fn update_dynamic_ports(&mut self, nx: u32) {
+        let node = self.graph.get_node(nx);
+        let (old_inputs, old_outputs) = (node.inputs(), node.outputs());
+        self.reconfigure(nx);
+        self.update_port_edges(nx, old_inputs, old_outputs);
+    }
+
+    /// Update what a rust node keeps from its parameters. Snippets are only named after their
+    /// node here. Compiling snippets, and evaluating the config of python nodes with dynamic
+    /// ports, needs the GIL, which a running python node may hold, so that's left to
+    /// [`ForayNodeTemplate::configure`] in the background
+    fn reconfigure(&mut self, nx: u32) {
+        let node = self.graph.get_node(nx);
+        let template = match &node.template {
+            ForayNodeTemplate::RustNode(rust_node) => ForayNodeTemplate::RustNode(
+                rust_node.configure(Parameters(&node.parameters_values)),
+            ),
+            ForayNodeTemplate::PySnippet(snippet) => {
+                let py_path = snippet_module_name(nx);
+                if snippet.py_path != py_path {
+                    self.graph.get_mut_node(nx).template =
+                        ForayNodeTemplate::PySnippet(PyNodeTemplate {
+                            py_path,
+                            ..snippet.clone()
+                        });
+                }
+                return;
+            }
+            _ => return,
+        };
+        self.update_template(nx, template);
+    }
+
+    /// Configure a node's template for its current parameters on this thread, including what
+    /// the UI leaves to a background task, like scanning a DICOM directory.
+    /// For scripts, which run nodes right after setting their parameters
+    pub fn configure(&mut self, nx: u32) {
+        let node = self.graph.get_node(nx);
+        if node.template.configures_in_background() {
+            let template = node.template.configure(nx, &node.parameters_values);
+            self.set_configured_template(nx, template);
+        }
+    }
+
+    /// Replace the template of a node that was configured in the background for its current
+    /// parameters, updating the edges of its ports like [`Self::set_parameter`]
+    pub fn set_configured_template(&mut self, nx: u32, template: ForayNodeTemplate) {
+        let node = self.graph.get_node(nx);
+        let (old_inputs, old_outputs) = (node.inputs(), node.outputs());
+        self.update_template(nx, template);
+        self.update_port_edges(nx, old_inputs, old_outputs);
+    }
+
+    /// Set a node's newly configured template, and show its configuration errors
+    fn update_template(&mut self, nx: u32, template: ForayNodeTemplate) {
+        let errors: Vec<_> = match &template {
+            ForayNodeTemplate::PyNode(py_node) | ForayNodeTemplate::PySnippet(py_node) => py_node
+                .errors()
+                .into_iter()
+                .map(ForayNodeError::PyNodeConifgError)
+                .collect(),
+            _ => vec![],
+        };
+        let node = self.graph.get_mut_node(nx);
+        node.template = template;
+        // Parameters that appeared with the new config start at their default
+        for (key, value) in node.template.default_parameters() {
+            node.parameters_values.entry(key).or_insert(value);
+        }
+        if !errors.is_empty() {
+            node.status = NodeStatus::Error(errors);
+        } else if let NodeStatus::Error(_) = node.status {
+            node.status = NodeStatus::default();
+        }
+    }
+
+    /// Drop edges of a node's ports that went away or changed type, and restore previously
+    /// dropped edges whose ports are back
+    fn update_port_edges(
+        &mut self,
+        nx: u32,
+        old_inputs: Dict<String, PortType>,
+        old_outputs: Dict<String, PortType>,
+    ) {
+        //// Drop edges of ports that went away
+        let (new_inputs, new_outputs) = {
+            let node = self.graph.get_node(nx);
+            (node.inputs(), node.outputs())
+        };
+        let dropped_edges: Vec<_> = invalid_ports(nx, IO::In, old_inputs.clone(), &new_inputs)
+            .into_iter()
+            .chain(invalid_ports(
+                nx,
+                IO::Out,
+                old_outputs.clone(),
+                &new_outputs,
+            ))
+            .flat_map(|port| {
+                let edges = self.graph.port_edges(&port);
+                self.graph.remove_edge(&port);
+                edges
+            })
+            .collect();
+        // The node's own ports are gone, so use their types from before the change
+        let old_port_type = |port: &PortRef| match (port.node == nx, port.io) {
+            (true, IO::In) => old_inputs.get(&port.name).cloned(),
+            (true, IO::Out) => old_outputs.get(&port.name).cloned(),
+            (false, _) => self.port_type(port),
+        };
+        let dropped: Vec<DroppedEdge> = dropped_edges
+            .into_iter()
+            .filter_map(|(from, to)| {
+                Some(DroppedEdge {
+                    from: (from.clone(), old_port_type(&from)?),
+                    to: (to.clone(), old_port_type(&to)?),
+                })
+            })
+            .collect();
+
+        //// Restore edges whose ports are back
+        let (restored, still_dropped): (Vec<_>, Vec<_>) = std::mem::take(&mut self.dropped_edges)
+            .into_iter()
+            .partition(|dropped| {
+                self.port_type(&dropped.from.0)
+                    .is_some_and(|from| compatible_change(&dropped.from.1, &from))
+                    && self
+                        .port_type(&dropped.to.0)
+                        .is_some_and(|to| compatible_change(&dropped.to.1, &to))
+            });
+        self.dropped_edges = still_dropped;
+        for DroppedEdge { from, to } in restored {
+            if !self.graph.is_connected(&to.0) {
+                info!("Restoring connection {:?} -> {:?}", from.0, to.0);
+                self.graph.add_edge_from_ref(&from.0, &to.0);
+            }
+        }
+        for edge in &dropped {
+            warn!("Dropping connection {:?} -> {:?}", edge.from.0, edge.to.0);
+        }
+        self.dropped_edges.extend(dropped);
+        self.update_input_types();
+    }
+
+    /// Set the types of the outputs connected to each node's inputs, which the output types of
+    /// array nodes follow. Changes propagate downstream, but don't drop any connections
+    pub fn update_input_types(&mut self) {
+        let nodes = self.graph.nodes_ref();
+        // Each pass settles at least one more node along every path, so a pass per node is
+        // enough, and a cycle can't loop forever
+        for _ in 0..=nodes.len() {
+            let mut changed = false;
+            for nx in &nodes {
+                let input_types: Dict<_, _> = self
+                    .graph
+                    .incoming_edges(nx)
+                    .into_iter()
+                    .filter_map(|(from, to)| Some((to.name, self.port_type(&from)?)))
+                    .collect();
+                let node = self.graph.get_mut_node(*nx);
+                if node.input_types != input_types {
+                    node.input_types = input_types;
+                    changed = true;
+                }
+            }
+            if !changed {
+                break;
+            }
+        }
+    }
+
+    /// Type of a port, if it exists
+    fn port_type(&self, port: &PortRef) -> Option<PortType> {
+        if !self.graph.nodes_ref().contains(&port.node) {
+            return None;
+        }
+        let node = self.graph.get_node(port.node);
+        match port.io {
+            IO::In => node.inputs().remove(&port.name),
+            IO::Out => node.outputs().remove(&port.name),
+        }
     }
 
     /// Stash current app state, reset the redo stack, and mark unsaved changes
@@ -110,6 +322,7 @@ impl Network {
     pub fn remove_edge(&mut self, port: PortRef) {
         self.stash_state();
         self.graph.remove_edge(&port);
+        self.update_input_types();
     }
 
     pub(crate) fn select_node(
@@ -135,6 +348,8 @@ impl Network {
                     let new_node = self.graph.get_node(*id).template.clone().into();
                     // *Mutably* add new node to graph
                     let new_id = self.graph.node(new_node);
+                    // The copy starts with default parameters, which may have different ports
+                    self.update_dynamic_ports(new_id);
                     // *Mutably* add new position
                     self.shapes.shape_positions.insert(new_id, pos);
                     new_id
@@ -185,20 +400,56 @@ impl Network {
             .nodes_ref()
             .into_iter()
             .map(|nx| self.graph.get_node(nx))
-            .any(|node| match &node.status {
-                NodeStatus::Idle {
-                    last_finished: Some(finished_time),
-                } =>
-                //only animate for the first second after finishing
-                {
-                    (Instant::now() - *finished_time).as_secs_f32() < NODE_FIRE_ANIMATION_DUR
-                }
+            .any(|node| {
+                node.last_reloaded.is_some_and(|reloaded| {
+                    (Instant::now() - reloaded).as_secs_f32() < NODE_RELOAD_FLASH_DUR
+                }) || match &node.status {
+                    NodeStatus::Idle {
+                        last_finished: Some(finished_time),
+                    } =>
+                    //only animate for the first second after finishing
+                    {
+                        (Instant::now() - *finished_time).as_secs_f32() < NODE_FIRE_ANIMATION_DUR
+                    }
 
-                NodeStatus::Idle {
-                    last_finished: None,
-                } => false,
-                NodeStatus::Running { .. } => true,
-                NodeStatus::Error(_foray_node_errors) => false,
+                    NodeStatus::Idle {
+                        last_finished: None,
+                    } => false,
+                    NodeStatus::Running { .. } => true,
+                    NodeStatus::Error(_foray_node_errors) => false,
+                }
             })
     }
 }
+
+/// Ports of a node that no longer exist, or whose type changed in a way that matters to their
+/// connections, see [`compatible_change`]
+pub fn invalid_ports(
+    nx: u32,
+    io: IO,
+    old_ports: Dict<String, PortType>,
+    new_ports: &Dict<String, PortType>,
+) -> Vec<PortRef> {
+    old_ports
+        .into_iter()
+        .filter(|(old_name, old_type)| {
+            !new_ports
+                .get(old_name)
+                .is_some_and(|new_type| compatible_change(old_type, new_type))
+        })
+        .map(|(name, _)| PortRef { node: nx, name, io })
+        .collect()
+}
+
+/// Whether a port's connections stay valid when its type changes from `old` to `new`.
+/// Arrays can change shape, e.g. while a reshape node's shape is typed, and arrays whose
+/// element type is or becomes `Any` stay connected
+pub fn compatible_change(old: &PortType, new: &PortType) -> bool {
+    match (old, new) {
+        (PortType::Array(old, _), PortType::Array(new, _)) => {
+            old == new || **old == PortType::Any || **new == PortType::Any
+        }
+        (PortType::Array(..), PortType::Any) | (PortType::Any, PortType::Array(..)) => true,
+        (old, new) => old == new,
+    }
+}
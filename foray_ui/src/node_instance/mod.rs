@@ -6,12 +6,19 @@ pub mod visualiztion;
 use std::time::Instant;
 
 use derive_more::derive::Debug;
+use foray_command::node::CommandNodeTemplate;
 use foray_data_model::{
-    node::{Dict, PortData, PortType},
+    context::Progress,
+    node::{Dict, PortData, PortType, UIParameter},
     WireDataContainer,
 };
 use foray_graph::graph::{ForayNodeError, GraphNode, PortName};
-use foray_py::py_node::{py_compute, PyNodeTemplate};
+use foray_native::{ConfiguredNode, NativeNodeTemplate, Parameters};
+use foray_plugin::node::PluginNodeTemplate;
+use foray_py::{
+    py_node::{py_compute, NodeMetadata, PyNodeTemplate},
+    snippet::{snippet_compute, snippet_module_name, SNIPPET_CODE},
+};
 use serde::{Deserialize, Serialize};
 
 use crate::{node_instance::visualiztion::Visualization, rust_nodes::RustNodeTemplate};
@@ -20,32 +27,115 @@ use crate::{node_instance::visualiztion::Visualization, rust_nodes::RustNodeTemp
 pub enum ForayNodeTemplate {
     RustNode(RustNodeTemplate),
     PyNode(PyNodeTemplate),
+    /// Node defined by a native plugin library
+    Plugin(PluginNodeTemplate),
+    /// Node implemented in rust, by foray or another crate
+    Native(NativeNodeTemplate),
+    /// Python node whose code is one of its parameters, saved inline in the network
+    PySnippet(PyNodeTemplate),
+    /// Node running an external command-line tool, declared in a `*.command.toml` spec
+    Command(CommandNodeTemplate),
 }
 
 impl ForayNodeTemplate {
-    pub fn default_parameters(&self) -> Dict<String, PortData> {
+    /// Widgets of the node's parameters, with their default values
+    pub fn parameters(&self) -> Dict<String, UIParameter> {
         match &self {
-            ForayNodeTemplate::RustNode(_rust_node) => Default::default(),
-            ForayNodeTemplate::PyNode(py_node) => py_node
-                .parameters()
-                .unwrap_or_default()
-                .iter()
-                .map(|(k, v)| (k.clone(), v.default_value()))
-                .collect(),
+            ForayNodeTemplate::RustNode(rust_node) => rust_node.parameters(),
+            ForayNodeTemplate::PyNode(py_node) => py_node.parameters().unwrap_or_default(),
+            ForayNodeTemplate::Plugin(plugin) => plugin.parameters.clone(),
+            ForayNodeTemplate::Native(native) => native
+                .node()
+                .map(|node| node.parameters())
+                .unwrap_or_default(),
+            ForayNodeTemplate::PySnippet(snippet) => snippet.snippet_parameters(),
+            ForayNodeTemplate::Command(command) => command.parameters(),
         }
     }
+    pub fn default_parameters(&self) -> Dict<String, PortData> {
+        self.parameters()
+            .iter()
+            .map(|(k, v)| (k.clone(), v.default_value()))
+            .collect()
+    }
     pub fn name(&self) -> String {
         match &self {
             ForayNodeTemplate::RustNode(rust_node) => rust_node.to_string(),
-            ForayNodeTemplate::PyNode(py_node) => py_node.name.clone(),
+            ForayNodeTemplate::PyNode(py_node) | ForayNodeTemplate::PySnippet(py_node) => {
+                py_node.name.clone()
+            }
+            ForayNodeTemplate::Plugin(plugin) => plugin.name.clone(),
+            ForayNodeTemplate::Native(native) => native.name(),
+            ForayNodeTemplate::Command(command) => command.name().to_string(),
+        }
+    }
+    pub fn metadata(&self) -> NodeMetadata {
+        match &self {
+            ForayNodeTemplate::RustNode(_rust_node) => Default::default(),
+            ForayNodeTemplate::PyNode(py_node) | ForayNodeTemplate::PySnippet(py_node) => {
+                py_node.metadata()
+            }
+            ForayNodeTemplate::Plugin(plugin) => NodeMetadata {
+                description: plugin.description.clone(),
+                ..Default::default()
+            },
+            ForayNodeTemplate::Native(native) => NodeMetadata {
+                description: native.node().ok().and_then(|node| node.description()),
+                ..Default::default()
+            },
+            ForayNodeTemplate::Command(command) => NodeMetadata {
+                description: command.spec.description.clone(),
+                ..Default::default()
+            },
+        }
+    }
+    /// Whether [`Self::configure`] is too slow for the UI thread, like compiling a snippet,
+    /// evaluating a dynamic python config, or scanning a directory
+    pub fn configures_in_background(&self) -> bool {
+        match self {
+            ForayNodeTemplate::PySnippet(_) => true,
+            ForayNodeTemplate::PyNode(py_node) => py_node.is_dynamic(),
+            ForayNodeTemplate::RustNode(rust_node) => rust_node.reads_files(),
+            _ => false,
+        }
+    }
+    /// The template of node `nx` configured for its `parameters`, for templates that
+    /// [configure in the background](Self::configures_in_background)
+    pub fn configure(&self, nx: u32, parameters: &Dict<String, PortData>) -> Self {
+        match self {
+            ForayNodeTemplate::PyNode(py_node) => {
+                ForayNodeTemplate::PyNode(py_node.configure(parameters))
+            }
+            ForayNodeTemplate::PySnippet(_) => {
+                let mut parameters = parameters.clone();
+                let code = match parameters.remove(SNIPPET_CODE) {
+                    Some(PortData::String(code)) => code,
+                    _ => String::new(),
+                };
+                ForayNodeTemplate::PySnippet(PyNodeTemplate::snippet(
+                    &snippet_module_name(nx),
+                    &code,
+                    parameters,
+                ))
+            }
+            ForayNodeTemplate::RustNode(rust_node) => {
+                ForayNodeTemplate::RustNode(rust_node.configure_from_files(Parameters(parameters)))
+            }
+            _ => self.clone(),
         }
     }
 }
 
-#[derive(Clone, Debug, PartialEq, Eq, PartialOrd)]
+#[derive(Clone, Debug, PartialEq, PartialOrd)]
 pub enum NodeStatus {
-    Idle { last_finished: Option<Instant> },
-    Running { start: Instant },
+    Idle {
+        last_finished: Option<Instant>,
+    },
+    Running {
+        start: Instant,
+        /// Most recent progress update sent by the node, if any
+        progress: Option<Progress>,
+    },
     Error(Vec<ForayNodeError>),
 }
 
@@ -69,20 +159,47 @@ pub struct ForayNodeInstance {
     // If there are errors for any of NodeDefinition fields, the field will be empty,
     // The error will be noted in NodeStatus
     pub status: NodeStatus,
+    /// When the node's template was last hot reloaded from disk, used to flash the node
+    #[serde(skip)]
+    pub last_reloaded: Option<Instant>,
+    /// Types of the outputs connected to the node's inputs, kept up to date by the network.
+    /// The output types of array nodes follow them
+    #[serde(skip)]
+    pub input_types: Dict<PortName, PortType>,
 }
 
 impl GraphNode<PortType, PortData> for ForayNodeInstance {
     fn inputs(&self) -> Dict<PortName, PortType> {
         match &self.template {
-            ForayNodeTemplate::RustNode(rust_node) => rust_node.inputs(),
-            ForayNodeTemplate::PyNode(py_node) => py_node.inputs().unwrap_or_default(),
+            ForayNodeTemplate::RustNode(rust_node) => {
+                rust_node.inputs(Parameters(&self.parameters_values))
+            }
+            ForayNodeTemplate::PyNode(py_node) | ForayNodeTemplate::PySnippet(py_node) => {
+                py_node.inputs().unwrap_or_default()
+            }
+            ForayNodeTemplate::Plugin(plugin) => plugin.inputs.clone(),
+            ForayNodeTemplate::Command(command) => command.inputs(),
+            ForayNodeTemplate::Native(_) => self
+                .configured_native_node()
+                .map(|node| node.inputs())
+                .unwrap_or_default(),
         }
     }
 
     fn outputs(&self) -> Dict<PortName, PortType> {
         match &self.template {
-            ForayNodeTemplate::RustNode(rust_node) => rust_node.outputs(),
-            ForayNodeTemplate::PyNode(py_node) => py_node.outputs().unwrap_or_default(),
+            ForayNodeTemplate::RustNode(rust_node) => {
+                rust_node.outputs(Parameters(&self.parameters_values), &self.input_types)
+            }
+            ForayNodeTemplate::PyNode(py_node) | ForayNodeTemplate::PySnippet(py_node) => {
+                py_node.outputs().unwrap_or_default()
+            }
+            ForayNodeTemplate::Plugin(plugin) => plugin.outputs.clone(),
+            ForayNodeTemplate::Command(command) => command.outputs(),
+            ForayNodeTemplate::Native(_) => self
+                .configured_native_node()
+                .map(|node| node.outputs())
+                .unwrap_or_default(),
         }
     }
 
@@ -91,11 +208,41 @@ impl GraphNode<PortType, PortData> for ForayNodeInstance {
         inputs: Dict<PortName, WireDataContainer<PortData>>,
     ) -> Result<Dict<PortName, PortData>, ForayNodeError> {
         match self.template {
-            ForayNodeTemplate::RustNode(rust_node) => rust_node.compute(inputs),
+            ForayNodeTemplate::RustNode(rust_node) => {
+                rust_node.compute(inputs, Parameters(&self.parameters_values))
+            }
             ForayNodeTemplate::PyNode(py_node) => {
                 py_compute(&py_node, inputs, self.parameters_values)
                     .map_err(ForayNodeError::PyNodeConifgError)
             }
+            ForayNodeTemplate::PySnippet(snippet) => {
+                snippet_compute(&snippet, inputs, self.parameters_values)
+                    .map_err(ForayNodeError::PyNodeConifgError)
+            }
+            ForayNodeTemplate::Plugin(plugin) => plugin
+                .compute(inputs, &self.parameters_values)
+                .map_err(ForayNodeError::Plugin),
+            ForayNodeTemplate::Command(command) => command
+                .compute(inputs, &self.parameters_values)
+                .map_err(ForayNodeError::Command),
+            ForayNodeTemplate::Native(native) => ConfiguredNode {
+                node: native.node()?,
+                parameters: self.parameters_values,
+            }
+            .compute(inputs),
+        }
+    }
+}
+
+impl ForayNodeInstance {
+    /// Native node with this node's parameters, if it's available
+    fn configured_native_node(&self) -> Option<ConfiguredNode<'static>> {
+        match &self.template {
+            ForayNodeTemplate::Native(native) => Some(ConfiguredNode {
+                node: native.node().ok()?,
+                parameters: self.parameters_values.clone(),
+            }),
+            _ => None,
         }
     }
 }
@@ -115,8 +262,15 @@ impl From<ForayNodeTemplate> for ForayNodeInstance {
             parameters_values: template.default_parameters(),
             visualization: Default::default(),
             status: match &template {
-                ForayNodeTemplate::RustNode(_rust_node_template) => Default::default(),
-                ForayNodeTemplate::PyNode(py_node_template) => {
+                ForayNodeTemplate::RustNode(_)
+                | ForayNodeTemplate::Plugin(_)
+                | ForayNodeTemplate::Command(_) => Default::default(),
+                ForayNodeTemplate::Native(native) => match native.node() {
+                    Ok(_) => Default::default(),
+                    Err(e) => NodeStatus::Error(vec![e]),
+                },
+                ForayNodeTemplate::PyNode(py_node_template)
+                | ForayNodeTemplate::PySnippet(py_node_template) => {
                     let errors: Vec<_> = py_node_template
                         .errors()
                         .into_iter()
@@ -129,6 +283,8 @@ impl From<ForayNodeTemplate> for ForayNodeInstance {
                     }
                 }
             },
+            last_reloaded: None,
+            input_types: Dict::new(),
             template,
         }
     }
@@ -1,10 +1,19 @@
 use std::{collections::HashMap, iter::once, path::PathBuf};
 
+use foray_command::node::find_commands;
+use foray_data_model::node::Dict;
+use foray_native::{native_nodes, NativeNodeTemplate};
+use foray_plugin::node::find_plugins;
 use foray_py::{
-    discover::{self, RawNodePackageInfo},
+    discover::{self, DiscoverError, RawNodePackageInfo},
+    err::RuntimeErr,
     py_node::PyNodeTemplate,
+    snippet::{DEFAULT_SNIPPET, SNIPPET_MODULE},
 };
 
+use itertools::{Either, Itertools};
+use log::warn;
+
 use crate::{node_instance::ForayNodeTemplate, rust_nodes::RustNodeTemplate};
 
 #[derive(Debug)]
@@ -37,12 +46,157 @@ impl<D> NodeTree<D> {
         }
         current_node.data = Some(data);
     }
+
+    /// All data in the tree, with the path to reach it
+    pub fn leaves(&self) -> Vec<(Vec<String>, &D)> {
+        self.children
+            .values()
+            .flat_map(|child| {
+                let own = child.data.iter().map(|d| (vec![child.name.clone()], d));
+                let nested = child.leaves().into_iter().map(|(mut path, d)| {
+                    path.insert(0, child.name.clone());
+                    (path, d)
+                });
+                own.chain(nested).collect::<Vec<_>>()
+            })
+            .collect()
+    }
+}
+
+/// Get all python projects (node collections) from the current python environment,
+/// and from plain directories of nodes.
+/// Packages that could not be loaded are returned separately.
+/// Templates in `loaded` are reused, rather than importing their nodes again
+pub fn read_python_projects(
+    node_dirs: &[PathBuf],
+    loaded: &HashMap<String, PyNodeTemplate>,
+) -> (Vec<crate::project::Project>, Vec<DiscoverError>) {
+    let (packages, broken_packages): (Vec<_>, Vec<_>) = discover::get_foray_py_packages()
+        .into_iter()
+        .partition_result();
+    broken_packages
+        .iter()
+        .for_each(|e| warn!("{e}\n{}", e.error.traceback));
+
+    let projects = packages
+        .into_iter()
+        .chain(discover::get_directory_packages(node_dirs))
+        .map(|package_info| python_project(package_info, loaded))
+        .collect();
+    (projects, broken_packages)
+}
+
+/// Get a project for each plugin library in the node directories.
+/// Libraries that could not be loaded are returned separately
+pub fn plugin_projects(node_dirs: &[PathBuf]) -> (Vec<Project>, Vec<DiscoverError>) {
+    find_plugins(node_dirs)
+        .into_iter()
+        .partition_map(|(path, nodes)| {
+            let library_name = path
+                .file_stem()
+                .unwrap_or_default()
+                .to_string_lossy()
+                .to_string();
+            match nodes {
+                Ok(nodes) => {
+                    let mut tree = NodeTree::new(library_name.clone());
+                    nodes.into_iter().for_each(|node| {
+                        tree.insert(
+                            vec![library_name.as_str(), node.name.clone().as_str()],
+                            ForayNodeTemplate::Plugin(node),
+                        )
+                    });
+                    // Watch the library's directory, as rebuilding usually replaces the file
+                    Either::Left(Project {
+                        absolute_path: path.parent().unwrap_or(&path).to_path_buf(),
+                        node_tree: tree,
+                    })
+                }
+                Err(e) => Either::Right(DiscoverError {
+                    package_name: library_name,
+                    entry_point: path.display().to_string(),
+                    missing_module: None,
+                    error: RuntimeErr {
+                        error: e.to_string(),
+                        traceback: String::new(),
+                    },
+                    hint: None,
+                }),
+            }
+        })
+}
+
+/// Get a project for each directory of command specs in the node directories.
+/// Specs that could not be loaded are returned separately
+pub fn command_projects(node_dirs: &[PathBuf]) -> (Vec<Project>, Vec<DiscoverError>) {
+    let (commands, broken_commands): (Vec<_>, Vec<_>) = find_commands(node_dirs)
+        .into_iter()
+        .partition_map(|(path, node)| match node {
+            Ok(node) => Either::Left(node),
+            Err(e) => Either::Right(DiscoverError {
+                package_name: path
+                    .file_name()
+                    .unwrap_or_default()
+                    .to_string_lossy()
+                    .to_string(),
+                entry_point: path.display().to_string(),
+                missing_module: None,
+                error: RuntimeErr {
+                    error: e.to_string(),
+                    traceback: String::new(),
+                },
+                hint: None,
+            }),
+        });
+
+    let projects = commands
+        .into_iter()
+        .into_group_map_by(|node| node.path.parent().unwrap_or(&node.path).to_path_buf())
+        .into_iter()
+        .map(|(dir, nodes)| {
+            let dir_name = dir
+                .file_name()
+                .unwrap_or_default()
+                .to_string_lossy()
+                .to_string();
+            let mut tree = NodeTree::new(dir_name.clone());
+            nodes.into_iter().for_each(|node| {
+                let name = node.name().to_string();
+                tree.insert(
+                    vec![dir_name.as_str(), name.as_str()],
+                    ForayNodeTemplate::Command(node),
+                )
+            });
+            Project {
+                absolute_path: dir,
+                node_tree: tree,
+            }
+        })
+        .collect();
+    (projects, broken_commands)
 }
 
-/// Get all python projects (node collections) from the current python environment
-pub fn read_python_projects() -> Vec<crate::project::Project> {
-    let raw = discover::get_foray_py_packages();
-    raw.into_iter().map(python_project).collect()
+/// Get a project for each crate that registered native nodes, grouped by the first element of
+/// each node's path
+pub fn native_projects() -> Vec<Project> {
+    native_nodes()
+        .into_group_map_by(|node| node.path().first().cloned().unwrap_or_default())
+        .into_iter()
+        .map(|(name, nodes)| {
+            let mut tree = NodeTree::new(name);
+            nodes.into_iter().for_each(|node| {
+                let path = node.path();
+                tree.insert(
+                    path.iter().map(String::as_str).collect(),
+                    ForayNodeTemplate::Native(NativeNodeTemplate::new(node)),
+                )
+            });
+            Project {
+                absolute_path: Default::default(),
+                node_tree: tree,
+            }
+        })
+        .collect()
 }
 
 pub fn rust_project() -> crate::project::Project {
@@ -57,6 +211,89 @@ pub fn rust_project() -> crate::project::Project {
         vec!["display series"],
         ForayNodeTemplate::RustNode(RustNodeTemplate::DisplaySeries),
     );
+    [
+        RustNodeTemplate::Slice,
+        RustNodeTemplate::Transpose,
+        RustNodeTemplate::Reshape,
+        RustNodeTemplate::Squeeze,
+        RustNodeTemplate::ExpandDims,
+        RustNodeTemplate::Concatenate,
+        RustNodeTemplate::Stack,
+        RustNodeTemplate::Flip,
+        RustNodeTemplate::Roll,
+    ]
+    .into_iter()
+    .for_each(|node| {
+        let name = node.to_string().to_lowercase();
+        tree.insert(
+            vec!["array", name.as_str()],
+            ForayNodeTemplate::RustNode(node),
+        )
+    });
+
+    tree.insert(
+        vec!["expression"],
+        ForayNodeTemplate::RustNode(RustNodeTemplate::Expression(vec![])),
+    );
+    tree.insert(
+        vec!["fourier", "fft"],
+        ForayNodeTemplate::RustNode(RustNodeTemplate::Fft),
+    );
+    tree.insert(
+        vec!["fourier", "ifft"],
+        ForayNodeTemplate::RustNode(RustNodeTemplate::Ifft),
+    );
+    tree.insert(
+        vec!["io", "nifti", "read"],
+        ForayNodeTemplate::RustNode(RustNodeTemplate::ReadNifti),
+    );
+    tree.insert(
+        vec!["io", "nifti", "write"],
+        ForayNodeTemplate::RustNode(RustNodeTemplate::WriteNifti),
+    );
+    tree.insert(
+        vec!["io", "dicom", "read series"],
+        ForayNodeTemplate::RustNode(RustNodeTemplate::ReadDicom(vec![])),
+    );
+    // Without the HDF5 library ISMRMRD files can't be read, networks that have the node still
+    // load it and show why it fails
+    #[cfg(feature = "hdf5")]
+    tree.insert(
+        vec!["io", "ismrmrd", "read"],
+        ForayNodeTemplate::RustNode(RustNodeTemplate::ReadIsmrmrd),
+    );
+    tree.insert(
+        vec!["io", "numpy", "read npy"],
+        ForayNodeTemplate::RustNode(RustNodeTemplate::ReadNpy),
+    );
+    tree.insert(
+        vec!["io", "numpy", "write npy"],
+        ForayNodeTemplate::RustNode(RustNodeTemplate::WriteNpy),
+    );
+    tree.insert(
+        vec!["io", "numpy", "read npz"],
+        ForayNodeTemplate::RustNode(RustNodeTemplate::ReadNpz(vec![])),
+    );
+    tree.insert(
+        vec!["io", "numpy", "write npz"],
+        ForayNodeTemplate::RustNode(RustNodeTemplate::WriteNpz),
+    );
+    tree.insert(
+        vec!["io", "matlab", "read"],
+        ForayNodeTemplate::RustNode(RustNodeTemplate::ReadMat(vec![])),
+    );
+    tree.insert(
+        vec!["io", "matlab", "write"],
+        ForayNodeTemplate::RustNode(RustNodeTemplate::WriteMat),
+    );
+    tree.insert(
+        vec!["python snippet"],
+        ForayNodeTemplate::PySnippet(PyNodeTemplate::snippet(
+            SNIPPET_MODULE,
+            DEFAULT_SNIPPET,
+            Dict::new(),
+        )),
+    );
 
     Project {
         absolute_path: Default::default(),
@@ -69,7 +306,10 @@ pub struct Project {
     pub node_tree: NodeTree<ForayNodeTemplate>,
 }
 
-pub fn python_project(package_info: RawNodePackageInfo) -> Project {
+pub fn python_project(
+    package_info: RawNodePackageInfo,
+    loaded: &HashMap<String, PyNodeTemplate>,
+) -> Project {
     let mut tree = NodeTree::new(package_info.package_name.clone());
 
     let entry_point_modules: Vec<&str> = package_info.entry_point.split(".").collect();
@@ -85,10 +325,11 @@ pub fn python_project(package_info: RawNodePackageInfo) -> Project {
             )
             .collect();
 
-        tree.insert(
-            display_path.clone(),
-            ForayNodeTemplate::PyNode(PyNodeTemplate::new(py_path.to_string())),
-        )
+        let template = loaded
+            .get(py_path)
+            .cloned()
+            .unwrap_or_else(|| PyNodeTemplate::new(py_path.to_string()));
+        tree.insert(display_path.clone(), ForayNodeTemplate::PyNode(template))
     });
 
     Project {
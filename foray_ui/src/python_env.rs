@@ -1,6 +1,7 @@
 use std::{
     env,
-    ffi::{OsStr, OsString},
+    ffi::{CStr, OsStr, OsString},
+    fmt::Display,
     fs::{self},
     iter,
     path::{Path, PathBuf},
@@ -10,6 +11,178 @@ use std::{
 use log::{debug, warn};
 use pyo3::{types::PyAnyMethods, PyResult, Python};
 
+/// A python `major.minor` version, which extension modules and environments are tied to
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub struct PythonVersion {
+    pub major: u32,
+    pub minor: u32,
+}
+
+impl PythonVersion {
+    /// Parse the start of a version like `3.13.1 (main, ...)`
+    fn parse(version: &str) -> Option<Self> {
+        let mut parts = version.trim().split(['.', ' ']);
+        Some(PythonVersion {
+            major: parts.next()?.parse().ok()?,
+            minor: parts.next()?.parse().ok()?,
+        })
+    }
+
+    /// Parse the tag of an extension module file name,
+    /// like `_multiarray_umath.cpython-312-x86_64-linux-gnu.so` or `_multiarray_umath.cp312-win_amd64.pyd`
+    fn from_extension(file_name: &str) -> Option<Self> {
+        let tag = file_name.split('.').nth(1)?;
+        let digits = tag
+            .strip_prefix("cpython-")
+            .or_else(|| tag.strip_prefix("cp"))?;
+        let digits: String = digits.chars().take_while(char::is_ascii_digit).collect();
+        let (major, minor) = digits.split_at_checked(1)?;
+        Some(PythonVersion {
+            major: major.parse().ok()?,
+            minor: minor.parse().ok()?,
+        })
+    }
+}
+
+impl Display for PythonVersion {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}.{}", self.major, self.minor)
+    }
+}
+
+/// Version of the python interpreter linked into foray
+pub fn embedded_version() -> Option<PythonVersion> {
+    // SAFETY: `Py_GetVersion` returns a static string, and doesn't need the interpreter
+    // to be initialized
+    let version = unsafe { CStr::from_ptr(pyo3::ffi::Py_GetVersion()) };
+    PythonVersion::parse(&version.to_string_lossy())
+}
+
+/// Version of the python a virtual environment was created with, from its `pyvenv.cfg`,
+/// or the name of its `lib/python3.*` directory
+pub fn venv_version(venv_dir: &Path) -> Option<PythonVersion> {
+    let from_config = fs::read_to_string(venv_dir.join("pyvenv.cfg"))
+        .ok()
+        .and_then(|config| pyvenv_version(&config));
+    from_config.or_else(|| {
+        fs::read_dir(venv_dir.join("lib"))
+            .ok()?
+            .flatten()
+            .find_map(|entry| {
+                PythonVersion::parse(entry.file_name().to_str()?.strip_prefix("python")?)
+            })
+    })
+}
+
+/// `version` is written by `venv`, `version_info` by `uv` and `virtualenv`
+fn pyvenv_version(config: &str) -> Option<PythonVersion> {
+    config.lines().find_map(|line| {
+        let (key, value) = line.split_once('=')?;
+        match key.trim() {
+            "version" | "version_info" => PythonVersion::parse(value),
+            _ => None,
+        }
+    })
+}
+
+/// Compiled modules of packages that can't be imported by a different python version,
+/// relative to `site-packages`
+const EXTENSION_MODULES: [(&str, &str); 2] = [
+    ("numpy", "numpy/_core/_multiarray_umath.*"),
+    ("numpy", "numpy/core/_multiarray_umath.*"),
+];
+
+/// The environment can't be used by the embedded interpreter
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum PythonEnvError {
+    /// The environment was created with a different python version than foray embeds
+    VersionMismatch {
+        venv: PythonVersion,
+        embedded: PythonVersion,
+    },
+    /// A package in the environment was built for a different python version than foray embeds
+    ExtensionMismatch {
+        package: String,
+        built_for: PythonVersion,
+        embedded: PythonVersion,
+    },
+}
+
+impl PythonEnvError {
+    /// Python version the environment should be recreated with
+    pub fn embedded(&self) -> PythonVersion {
+        match self {
+            PythonEnvError::VersionMismatch { embedded, .. }
+            | PythonEnvError::ExtensionMismatch { embedded, .. } => *embedded,
+        }
+    }
+}
+
+impl Display for PythonEnvError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            PythonEnvError::VersionMismatch { venv, embedded } => write!(
+                f,
+                "The python environment is Python {venv}, but foray embeds Python {embedded}."
+            )?,
+            PythonEnvError::ExtensionMismatch {
+                package,
+                built_for,
+                embedded,
+            } => write!(
+                f,
+                "{package} in the python environment was built for Python {built_for}, \
+                 but foray embeds Python {embedded}."
+            )?,
+        }
+        let embedded = self.embedded();
+        write!(
+            f,
+            " Recreate it with `uv sync --python {embedded}`, \
+             or `python{embedded} -m venv --clear .venv` and reinstall its packages"
+        )
+    }
+}
+
+impl std::error::Error for PythonEnvError {}
+
+/// Check that the embedded interpreter can use a virtual environment, before python starts.
+/// A mismatch otherwise only shows up as obscure import errors from compiled packages
+pub fn preflight(venv_dir: &Path) -> Result<(), PythonEnvError> {
+    let Some(embedded) = embedded_version() else {
+        warn!("Could not determine the embedded python version");
+        return Ok(());
+    };
+    if let Some(venv) = venv_version(venv_dir) {
+        if venv != embedded {
+            return Err(PythonEnvError::VersionMismatch { venv, embedded });
+        }
+    }
+    for site_packages in site_paths(venv_dir)
+        .into_iter()
+        .filter(|path| path.ends_with("site-packages"))
+    {
+        for (package, pattern) in EXTENSION_MODULES {
+            let Ok(modules) = glob::glob(&site_packages.join(pattern).to_string_lossy()) else {
+                continue;
+            };
+            let built_for = modules
+                .flatten()
+                .find_map(|module| PythonVersion::from_extension(module.file_name()?.to_str()?));
+            if let Some(built_for) = built_for.filter(|&built_for| built_for != embedded) {
+                return Err(PythonEnvError::ExtensionMismatch {
+                    package: package.to_string(),
+                    built_for,
+                    embedded,
+                });
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Point the embedded interpreter at a virtual environment and start it.
+/// Run [`preflight`] first, the environment can't be changed once python has started
 pub fn setup_python(venv_dir: PathBuf) {
     println!("Using python environment: {venv_dir:?}");
 
@@ -21,60 +194,21 @@ pub fn setup_python(venv_dir: PathBuf) {
 
         // Set PYTHONPATH to appropriate paths in the venv directory
         // needed to address open pyo3 issue https://github.com/PyO3/pyo3/issues/1741
-        if let Ok(paths) = glob::glob(
-            venv_dir
-                .join("lib/python3*")
-                .to_str()
-                .unwrap_or_else(|| panic!("Paths must be valid unicode {:?}", venv_dir)),
-        ) {
-            let paths: Vec<_> = paths.filter_map(|p| p.ok()).collect();
-            if paths.len() > 1 {
-                warn!("Multiple python versions detected in venv {:?}, this has not been tested. Unexpected results may occur",venv_dir)
-            }
-            let paths_to_add: Vec<PathBuf> = paths
-                .into_iter()
-                .map(|path| path.join("site-packages"))
-                .flat_map(|path| {
-                    // When a package is installed as "editable"
-                    // a *.pth file is used to point to where the source code actually lives.
-                    // we find all these *.pth files and add them to path
-                    let editable_paths: Vec<PathBuf> =
-                        glob::glob(path.join("*.pth").to_str().unwrap())
-                            .unwrap()
-                            .filter_map(|p| p.ok())
-                            .filter(|path| path.file_name() != Some(OsStr::new("_virtualenv.pth")))
-                            .filter_map(|path| {
-                                let contents = fs::read_to_string(&path).unwrap();
-                                match PathBuf::from_str(&contents) {
-                                    Ok(p) => Some(p),
-                                    Err(_) => {
-                                        warn!(
-                                        "Unexpected `.pth` file contents in {path:?}: {contents}"
-                                    );
-                                        None
-                                    }
-                                }
-                            })
-                            .collect();
-
-                    [path].into_iter().chain(editable_paths).collect::<Vec<_>>()
+        let paths_to_add = site_paths(&venv_dir);
+        debug!("Adding paths to PYTHONPATH {paths_to_add:#?}");
+
+        // **Override** PYTHONPATH
+        env::set_var(
+            "PYTHONPATH",
+            paths_to_add
+                .iter()
+                .map(|p| {
+                    p.to_str()
+                        .unwrap_or_else(|| panic!("Paths must be valid unicode {:?}", &p))
                 })
-                .collect();
-            debug!("Adding paths to PYTHONPATH {paths_to_add:#?}");
-
-            // **Override** PYTHONPATH
-            env::set_var(
-                "PYTHONPATH",
-                paths_to_add
-                    .iter()
-                    .map(|p| {
-                        p.to_str()
-                            .unwrap_or_else(|| panic!("Paths must be valid unicode {:?}", &p))
-                    })
-                    .collect::<Vec<_>>()
-                    .join(":"),
-            );
-        }
+                .collect::<Vec<_>>()
+                .join(":"),
+        );
     }
 
     //// PYO3 init
@@ -91,6 +225,69 @@ pub fn setup_python(venv_dir: PathBuf) {
     });
 }
 
+/// `site-packages` directories of a venv, and the directories of packages installed into them
+/// in editable mode
+fn site_paths(venv_dir: &Path) -> Vec<PathBuf> {
+    let Ok(paths) = glob::glob(
+        venv_dir
+            .join("lib/python3*")
+            .to_str()
+            .unwrap_or_else(|| panic!("Paths must be valid unicode {:?}", venv_dir)),
+    ) else {
+        return vec![];
+    };
+    let paths: Vec<_> = paths.filter_map(|p| p.ok()).collect();
+    if paths.len() > 1 {
+        warn!("Multiple python versions detected in venv {:?}, this has not been tested. Unexpected results may occur",venv_dir)
+    }
+    paths
+        .into_iter()
+        .map(|path| path.join("site-packages"))
+        .flat_map(|path| {
+            // When a package is installed as "editable"
+            // a *.pth file is used to point to where the source code actually lives.
+            // we find all these *.pth files and add them to path
+            let editable_paths: Vec<PathBuf> = glob::glob(path.join("*.pth").to_str().unwrap())
+                .unwrap()
+                .filter_map(|p| p.ok())
+                .filter(|path| path.file_name() != Some(OsStr::new("_virtualenv.pth")))
+                .filter_map(|path| {
+                    let contents = fs::read_to_string(&path).unwrap();
+                    match PathBuf::from_str(contents.trim()) {
+                        Ok(p) => Some(p),
+                        Err(_) => {
+                            warn!("Unexpected `.pth` file contents in {path:?}: {contents}");
+                            None
+                        }
+                    }
+                })
+                .collect();
+
+            [path].into_iter().chain(editable_paths).collect::<Vec<_>>()
+        })
+        .collect()
+}
+
+/// Add site directories that appeared since python started to `sys.path`,
+/// so that packages installed while foray is running can be imported
+pub fn update_sys_path(venv_dir: &Path) {
+    let result = Python::with_gil(|py| -> PyResult<()> {
+        let sys_path = py.import("sys")?.getattr("path")?;
+        for path in site_paths(venv_dir) {
+            let path = path.to_string_lossy();
+            if !sys_path.contains(path.as_ref())? {
+                debug!("Adding {path} to sys.path");
+                sys_path.call_method1("append", (path.as_ref(),))?;
+            }
+        }
+        py.import("importlib")?.call_method0("invalidate_caches")?;
+        Ok(())
+    });
+    if let Err(e) = result {
+        warn!("Could not update sys.path: {e}");
+    }
+}
+
 /// Create a new env string that has the given value prepended
 fn prepend_env<P: AsRef<Path>>(env: &str, p: P) -> Result<OsString, env::JoinPathsError> {
     let new_path = p.as_ref();
@@ -101,3 +298,40 @@ fn prepend_env<P: AsRef<Path>>(env: &str, p: P) -> Result<OsString, env::JoinPat
         Ok(new_path.into())
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn versions() {
+        let v = |major, minor| Some(PythonVersion { major, minor });
+        assert_eq!(PythonVersion::parse("3.13.1 (main, Dec  3 2024)"), v(3, 13));
+        assert_eq!(
+            pyvenv_version(
+                "home = /usr/bin\ninclude-system-site-packages = false\nversion = 3.12.3\n"
+            ),
+            v(3, 12)
+        );
+        assert_eq!(
+            pyvenv_version("home = /usr/bin\nimplementation = CPython\nversion_info = 3.14.0\n"),
+            v(3, 14)
+        );
+        assert_eq!(
+            PythonVersion::from_extension("_multiarray_umath.cpython-312-x86_64-linux-gnu.so"),
+            v(3, 12)
+        );
+        assert_eq!(
+            PythonVersion::from_extension("_multiarray_umath.cpython-313t-darwin.so"),
+            v(3, 13)
+        );
+        assert_eq!(
+            PythonVersion::from_extension("_multiarray_umath.cp310-win_amd64.pyd"),
+            v(3, 10)
+        );
+        assert_eq!(
+            PythonVersion::from_extension("_multiarray_umath.abi3.so"),
+            None
+        );
+    }
+}
@@ -0,0 +1,812 @@
+//! Nodes that rearrange the elements of arrays, without changing their values.
+//! They work on arrays of any element type
+
+use foray_data_model::{
+    node::{Dict, ForayArray, NodeError, PortData, PortType, Shape},
+    WireDataReference,
+};
+use foray_graph::graph::{ForayNodeError, PortName};
+use foray_native::Parameters;
+use ndarray::{ArrayD, ArrayViewD, Axis, IxDyn, Slice};
+
+/// Most arrays that can be combined by concatenate and stack
+pub const MAX_COMBINED_INPUTS: usize = 26;
+
+/// Apply an expression that is generic over the element type to a [`ForayArray`]
+macro_rules! map_array {
+    ($array:expr, $a:ident => $f:expr) => {
+        match $array {
+            ForayArray::Integer($a) => ForayArray::Integer($f),
+            ForayArray::Float($a) => ForayArray::Float($f),
+            ForayArray::Complex($a) => ForayArray::Complex($f),
+            ForayArray::Boolean($a) => ForayArray::Boolean($f),
+            ForayArray::String($a) => ForayArray::String($f),
+            ForayArray::Object($a) => ForayArray::Object($f),
+        }
+    };
+}
+
+/// Views of arrays that must all have the element type `$variant`
+macro_rules! views_of {
+    ($arrays:expr, $variant:ident) => {
+        $arrays
+            .iter()
+            .map(|array| match array {
+                ForayArray::$variant(a) => Ok(a.view()),
+                _ => Err(compute_error(
+                    "arrays should all have the same element type",
+                )),
+            })
+            .collect::<Result<Vec<_>, _>>()?
+    };
+}
+
+/// Apply an expression to views of several arrays, which must all have the same element type
+macro_rules! map_arrays {
+    ($arrays:expr, $views:ident => $f:expr) => {
+        match $arrays[0] {
+            ForayArray::Integer(_) => {
+                let $views = views_of!($arrays, Integer);
+                ForayArray::Integer($f)
+            }
+            ForayArray::Float(_) => {
+                let $views = views_of!($arrays, Float);
+                ForayArray::Float($f)
+            }
+            ForayArray::Complex(_) => {
+                let $views = views_of!($arrays, Complex);
+                ForayArray::Complex($f)
+            }
+            ForayArray::Boolean(_) => {
+                let $views = views_of!($arrays, Boolean);
+                ForayArray::Boolean($f)
+            }
+            ForayArray::String(_) => {
+                let $views = views_of!($arrays, String);
+                ForayArray::String($f)
+            }
+            ForayArray::Object(_) => {
+                let $views = views_of!($arrays, Object);
+                ForayArray::Object($f)
+            }
+        }
+    };
+}
+
+pub(super) type ArrayInputs<'a> = Dict<String, WireDataReference<'a, PortData>>;
+
+/// One axis of a slice, numpy style
+#[derive(Clone, Debug, PartialEq)]
+pub enum SliceSpec {
+    /// Select one element, removing the axis
+    Index(isize),
+    /// `start:stop:step`, where missing positions cover the whole axis
+    Range {
+        start: Option<isize>,
+        stop: Option<isize>,
+        step: isize,
+    },
+}
+
+/// Parse a comma separated slice for each axis, e.g. `"1:-1, ::2, 0"`.
+/// Axes without a slice are kept whole
+pub fn parse_slices(spec: &str) -> Result<Vec<SliceSpec>, ForayNodeError> {
+    fn position(s: &str) -> Result<Option<isize>, ForayNodeError> {
+        match s.trim() {
+            "" => Ok(None),
+            s => s
+                .parse()
+                .map(Some)
+                .map_err(|_| compute_error(format!("'{s}' is not an integer"))),
+        }
+    }
+
+    if spec.trim().is_empty() {
+        return Ok(vec![]);
+    }
+    spec.split(',')
+        .map(|axis| {
+            let parts: Vec<&str> = axis.split(':').collect();
+            match parts.as_slice() {
+                [index] => position(index)?
+                    .map(SliceSpec::Index)
+                    .ok_or_else(|| compute_error("empty slice")),
+                [start, stop] => Ok(SliceSpec::Range {
+                    start: position(start)?,
+                    stop: position(stop)?,
+                    step: 1,
+                }),
+                [start, stop, step] => match position(step)?.unwrap_or(1) {
+                    0 => Err(compute_error("slice step can't be zero")),
+                    step => Ok(SliceSpec::Range {
+                        start: position(start)?,
+                        stop: position(stop)?,
+                        step,
+                    }),
+                },
+                _ => Err(compute_error(format!("'{axis}' is not a slice"))),
+            }
+        })
+        .collect()
+}
+
+/// Parse a comma separated list of integers, e.g. `"1, 0, -1"`
+pub(super) fn parse_list(spec: &str) -> Result<Vec<isize>, ForayNodeError> {
+    spec.split(',')
+        .map(str::trim)
+        .filter(|s| !s.is_empty())
+        .map(|s| {
+            s.parse()
+                .map_err(|_| compute_error(format!("'{s}' is not an integer")))
+        })
+        .collect()
+}
+
+/// Convert a numpy style slice into an ndarray slice of an axis with length `len`
+fn axis_slice(len: usize, start: Option<isize>, stop: Option<isize>, step: isize) -> Slice {
+    let len = len as isize;
+    // Negative positions count from the end, and positions past either end are clamped
+    let resolve =
+        |i: isize, low: isize, high: isize| (if i < 0 { i + len } else { i }).clamp(low, high);
+    if step > 0 {
+        let start = start.map_or(0, |i| resolve(i, 0, len));
+        let stop = stop.map_or(len, |i| resolve(i, 0, len)).max(start);
+        Slice::new(start, Some(stop), step)
+    } else {
+        // numpy steps backwards from start, ndarray from the end of the range
+        let start = start.map_or(len - 1, |i| resolve(i, -1, len - 1));
+        let stop = stop.map_or(-1, |i| resolve(i, -1, len - 1)).min(start);
+        Slice::new(stop + 1, Some(start + 1), step)
+    }
+}
+
+pub(super) fn resolve_axis(axis: isize, ndim: usize) -> Result<usize, ForayNodeError> {
+    let resolved = if axis < 0 { axis + ndim as isize } else { axis };
+    if (0..ndim as isize).contains(&resolved) {
+        Ok(resolved as usize)
+    } else {
+        Err(compute_error(format!(
+            "axis {axis} is out of bounds for an array with {ndim} dimensions"
+        )))
+    }
+}
+
+fn resolve_index(index: isize, len: usize) -> Result<usize, ForayNodeError> {
+    let resolved = if index < 0 {
+        index + len as isize
+    } else {
+        index
+    };
+    if (0..len as isize).contains(&resolved) {
+        Ok(resolved as usize)
+    } else {
+        Err(compute_error(format!(
+            "index {index} is out of bounds for an axis with length {len}"
+        )))
+    }
+}
+
+pub fn slice<A: Clone>(a: &ArrayD<A>, specs: &[SliceSpec]) -> Result<ArrayD<A>, ForayNodeError> {
+    Ok(slice_view(a.view(), specs)?.to_owned())
+}
+
+/// Slice a view without copying its elements
+pub fn slice_view<'a, A>(
+    a: ArrayViewD<'a, A>,
+    specs: &[SliceSpec],
+) -> Result<ArrayViewD<'a, A>, ForayNodeError> {
+    if specs.len() > a.ndim() {
+        return Err(compute_error(format!(
+            "{} slices given for an array with {} dimensions",
+            specs.len(),
+            a.ndim()
+        )));
+    }
+    let mut view = a;
+    for (i, spec) in specs.iter().enumerate() {
+        let len = view.len_of(Axis(i));
+        let slice = match spec {
+            SliceSpec::Index(index) => {
+                let index = resolve_index(*index, len)? as isize;
+                Slice::new(index, Some(index + 1), 1)
+            }
+            SliceSpec::Range { start, stop, step } => axis_slice(len, *start, *stop, *step),
+        };
+        view.slice_axis_inplace(Axis(i), slice);
+    }
+    // Remove indexed axes last to first, so the remaining axis numbers don't change
+    for (i, spec) in specs.iter().enumerate().rev() {
+        if let SliceSpec::Index(_) = spec {
+            view = view.index_axis_move(Axis(i), 0);
+        }
+    }
+    Ok(view)
+}
+
+/// Permute the axes of an array, reversing them if no axes are given
+pub fn transpose<A: Clone>(a: &ArrayD<A>, axes: &[isize]) -> Result<ArrayD<A>, ForayNodeError> {
+    if axes.is_empty() {
+        return Ok(a.view().reversed_axes().to_owned());
+    }
+    if axes.len() != a.ndim() {
+        return Err(compute_error(format!(
+            "{} axes given for an array with {} dimensions",
+            axes.len(),
+            a.ndim()
+        )));
+    }
+    let axes = axes
+        .iter()
+        .map(|axis| resolve_axis(*axis, a.ndim()))
+        .collect::<Result<Vec<_>, _>>()?;
+    if (0..a.ndim()).any(|axis| !axes.contains(&axis)) {
+        return Err(compute_error("axes should contain each axis once"));
+    }
+    Ok(a.view().permuted_axes(axes).to_owned())
+}
+
+/// Reshape an array in row major order. One dimension can be -1, which is inferred from the
+/// number of elements
+pub fn reshape<A: Clone>(a: &ArrayD<A>, shape: &[isize]) -> Result<ArrayD<A>, ForayNodeError> {
+    if shape.iter().filter(|d| **d == -1).count() > 1 {
+        return Err(compute_error("only one dimension can be -1"));
+    }
+    let known: usize = shape
+        .iter()
+        .filter(|d| **d >= 0)
+        .map(|d| *d as usize)
+        .product();
+    let dims = shape
+        .iter()
+        .map(|d| match d {
+            -1 if known > 0 && a.len().is_multiple_of(known) => Ok(a.len() / known),
+            -1 => Err(compute_error(format!(
+                "can't reshape {} elements into {shape:?}",
+                a.len()
+            ))),
+            d if *d < 0 => Err(compute_error(format!("invalid dimension {d}"))),
+            d => Ok(*d as usize),
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+    a.to_shape(IxDyn(&dims))
+        .map(|reshaped| reshaped.into_owned())
+        .map_err(|_| compute_error(format!("can't reshape {:?} into {dims:?}", a.shape())))
+}
+
+/// Remove axes with length 1. If no axes are given, every axis with length 1 is removed
+pub fn squeeze<A: Clone>(a: &ArrayD<A>, axes: &[isize]) -> Result<ArrayD<A>, ForayNodeError> {
+    let mut axes = if axes.is_empty() {
+        (0..a.ndim()).filter(|i| a.len_of(Axis(*i)) == 1).collect()
+    } else {
+        axes.iter()
+            .map(|axis| resolve_axis(*axis, a.ndim()))
+            .collect::<Result<Vec<_>, _>>()?
+    };
+    if let Some(axis) = axes.iter().find(|i| a.len_of(Axis(**i)) != 1) {
+        return Err(compute_error(format!(
+            "axis {axis} has length {}, only axes with length 1 can be removed",
+            a.len_of(Axis(*axis))
+        )));
+    }
+    axes.sort_unstable();
+    axes.dedup();
+    let mut view = a.view();
+    for axis in axes.into_iter().rev() {
+        view = view.index_axis_move(Axis(axis), 0);
+    }
+    Ok(view.to_owned())
+}
+
+/// Insert an axis with length 1 at `axis`, counting from the end if negative
+pub fn expand_dims<A: Clone>(a: &ArrayD<A>, axis: isize) -> Result<ArrayD<A>, ForayNodeError> {
+    let axis = resolve_axis(axis, a.ndim() + 1)?;
+    Ok(a.view().insert_axis(Axis(axis)).to_owned())
+}
+
+/// Reverse the order of elements along axes. If no axes are given, every axis is reversed
+pub fn flip<A: Clone>(a: &ArrayD<A>, axes: &[isize]) -> Result<ArrayD<A>, ForayNodeError> {
+    let axes = if axes.is_empty() {
+        (0..a.ndim()).collect()
+    } else {
+        axes.iter()
+            .map(|axis| resolve_axis(*axis, a.ndim()))
+            .collect::<Result<Vec<_>, _>>()?
+    };
+    let mut view = a.view();
+    for axis in axes {
+        view.invert_axis(Axis(axis));
+    }
+    Ok(view.to_owned())
+}
+
+/// Shift elements along an axis, wrapping those shifted past the end around to the start
+pub fn roll<A: Clone>(
+    a: &ArrayD<A>,
+    shift: isize,
+    axis: isize,
+) -> Result<ArrayD<A>, ForayNodeError> {
+    let axis = Axis(resolve_axis(axis, a.ndim())?);
+    let len = a.len_of(axis);
+    if len == 0 {
+        return Ok(a.clone());
+    }
+    let split = (len as isize - shift).rem_euclid(len as isize);
+    ndarray::concatenate(
+        axis,
+        &[
+            a.slice_axis(axis, Slice::from(split..)),
+            a.slice_axis(axis, Slice::from(..split)),
+        ],
+    )
+    .map_err(|e| compute_error(e.to_string()))
+}
+
+pub fn concatenate<A: Clone>(
+    arrays: &[ArrayViewD<A>],
+    axis: isize,
+) -> Result<ArrayD<A>, ForayNodeError> {
+    let axis = resolve_axis(axis, arrays[0].ndim())?;
+    ndarray::concatenate(Axis(axis), arrays).map_err(|e| compute_error(e.to_string()))
+}
+
+/// Join arrays with the same shape along a new axis
+pub fn stack<A: Clone>(arrays: &[ArrayViewD<A>], axis: isize) -> Result<ArrayD<A>, ForayNodeError> {
+    let axis = resolve_axis(axis, arrays[0].ndim() + 1)?;
+    ndarray::stack(Axis(axis), arrays).map_err(|e| compute_error(e.to_string()))
+}
+
+// Nodes
+
+/// Inputs of concatenate and stack, named `a`, `b`, ..., for the number of inputs parameter
+pub fn combined_inputs(parameters: Parameters) -> Dict<PortName, PortType> {
+    combined_input_names(parameters)
+        .map(|name| (name, PortType::Any))
+        .collect()
+}
+
+fn combined_input_names(parameters: Parameters) -> impl Iterator<Item = String> {
+    let count = parameters
+        .integer("inputs")
+        .unwrap_or(2)
+        .clamp(1, MAX_COMBINED_INPUTS as i64);
+    (0..count as u8).map(|i| ((b'a' + i) as char).to_string())
+}
+
+/// Type of the result of an operation on the array `a`, which keeps its element type.
+/// `shape` maps the shape of `a` to the shape of the result, or `None` if it isn't known
+fn derived_type(a: &PortType, shape: impl FnOnce(&Shape) -> Option<Shape>) -> PortType {
+    match a {
+        PortType::Array(element, a_shape) => match shape(a_shape) {
+            Some(shape) => PortType::Array(element.clone(), shape),
+            None => PortType::Any,
+        },
+        _ => PortType::Any,
+    }
+}
+
+/// Output type of slice, for the type of its input `a`
+pub fn slice_type(parameters: Parameters, a: &PortType) -> PortType {
+    match parameters.string("slices").and_then(parse_slices) {
+        Ok(specs) => derived_type(a, |shape| slice_shape(shape, &specs)),
+        Err(_) => PortType::Any,
+    }
+}
+
+fn slice_shape(shape: &Shape, specs: &[SliceSpec]) -> Option<Shape> {
+    if specs.len() > shape.len() {
+        return None;
+    }
+    let sliced = shape
+        .iter()
+        .enumerate()
+        .filter_map(|(i, len)| match specs.get(i) {
+            None => Some(*len),
+            Some(SliceSpec::Index(_)) => None,
+            Some(SliceSpec::Range { start, stop, step }) => Some(len.map(|len| {
+                let slice = axis_slice(len, *start, *stop, *step);
+                let end = slice.end.unwrap_or(len as isize);
+                ((end - slice.start).max(0) as usize).div_ceil(slice.step.unsigned_abs())
+            })),
+        })
+        .collect();
+    Some(sliced)
+}
+
+/// Output type of transpose. Without a known input, it has a dimension for each axis of the
+/// parameter
+pub fn transpose_type(parameters: Parameters, a: &PortType) -> PortType {
+    let Ok(axes) = parameters.string("axes").and_then(parse_list) else {
+        return PortType::Any;
+    };
+    match a {
+        PortType::Array(..) => derived_type(a, |shape| transpose_shape(shape, &axes)),
+        _ if !axes.is_empty() => PortType::Array(Box::new(PortType::Any), vec![None; axes.len()]),
+        _ => PortType::Any,
+    }
+}
+
+fn transpose_shape(shape: &Shape, axes: &[isize]) -> Option<Shape> {
+    if axes.is_empty() {
+        return Some(shape.iter().rev().copied().collect());
+    }
+    if axes.len() != shape.len() {
+        return None;
+    }
+    let axes = axes
+        .iter()
+        .map(|axis| resolve_axis(*axis, shape.len()).ok())
+        .collect::<Option<Vec<_>>>()?;
+    (0..shape.len())
+        .all(|axis| axes.contains(&axis))
+        .then(|| axes.iter().map(|axis| shape[*axis]).collect())
+}
+
+/// Output type of reshape, which has the parameter's shape when it's valid.
+/// A -1 dimension is known if every dimension of the input is
+pub fn reshape_type(parameters: Parameters, a: &PortType) -> PortType {
+    let Ok(shape) = parameters.string("shape").and_then(parse_list) else {
+        return PortType::Any;
+    };
+    let (element, len) = match a {
+        PortType::Array(element, a_shape) => (
+            (**element).clone(),
+            a_shape
+                .iter()
+                .try_fold(1usize, |len, d| len.checked_mul((*d)?)),
+        ),
+        _ => (PortType::Any, None),
+    };
+    let known: usize = shape
+        .iter()
+        .filter_map(|d| usize::try_from(*d).ok())
+        .product();
+    let shape = shape
+        .iter()
+        .map(|d| match d {
+            -1 => len
+                .filter(|len| known > 0 && len.is_multiple_of(known))
+                .map(|len| len / known),
+            d => usize::try_from(*d).ok(),
+        })
+        .collect();
+    PortType::Array(Box::new(element), shape)
+}
+
+/// Output type of squeeze, which is only known if the lengths of the removed axes are
+pub fn squeeze_type(parameters: Parameters, a: &PortType) -> PortType {
+    let Ok(axes) = parameters.string("axes").and_then(parse_list) else {
+        return PortType::Any;
+    };
+    derived_type(a, |shape| {
+        let axes = match axes.is_empty() {
+            true if shape.contains(&None) => return None,
+            true => (0..shape.len()).filter(|i| shape[*i] == Some(1)).collect(),
+            false => axes
+                .iter()
+                .map(|axis| resolve_axis(*axis, shape.len()).ok())
+                .collect::<Option<Vec<_>>>()?,
+        };
+        if axes.iter().any(|i| shape[*i].is_some_and(|len| len != 1)) {
+            return None;
+        }
+        Some(
+            (0..shape.len())
+                .filter(|i| !axes.contains(i))
+                .map(|i| shape[i])
+                .collect(),
+        )
+    })
+}
+
+pub fn expand_dims_type(parameters: Parameters, a: &PortType) -> PortType {
+    let Ok(axis) = parameters.integer("axis") else {
+        return PortType::Any;
+    };
+    derived_type(a, |shape| {
+        let axis = resolve_axis(axis as isize, shape.len() + 1).ok()?;
+        let mut shape = shape.clone();
+        shape.insert(axis, Some(1));
+        Some(shape)
+    })
+}
+
+/// Output type of flip and roll, which keep the shape of their input
+pub fn same_shape_type(a: &PortType) -> PortType {
+    derived_type(a, |shape| Some(shape.clone()))
+}
+
+/// Output type of concatenate, or of stack if `stacked`.
+/// It's only known if every input is connected to an array with the same number of dimensions
+pub fn combined_type(
+    parameters: Parameters,
+    inputs: &Dict<PortName, PortType>,
+    stacked: bool,
+) -> PortType {
+    let Ok(axis) = parameters.integer("axis") else {
+        return PortType::Any;
+    };
+    let Some(arrays) = combined_input_names(parameters)
+        .map(|name| match inputs.get(&name) {
+            Some(PortType::Array(element, shape)) => Some((&**element, shape)),
+            _ => None,
+        })
+        .collect::<Option<Vec<_>>>()
+    else {
+        return PortType::Any;
+    };
+    let (element, shape) = arrays[0];
+    if arrays.iter().any(|(_, s)| s.len() != shape.len()) {
+        return PortType::Any;
+    }
+    let element = match arrays.iter().all(|(e, _)| *e == element) {
+        true => element.clone(),
+        false => PortType::Any,
+    };
+    // Lengths of an axis that isn't combined are the same in every input
+    let mut shape: Shape = (0..shape.len())
+        .map(|i| arrays.iter().find_map(|(_, s)| s[i]))
+        .collect();
+    if stacked {
+        let Ok(axis) = resolve_axis(axis as isize, shape.len() + 1) else {
+            return PortType::Any;
+        };
+        shape.insert(axis, Some(arrays.len()));
+    } else {
+        let Ok(axis) = resolve_axis(axis as isize, shape.len()) else {
+            return PortType::Any;
+        };
+        shape[axis] = arrays.iter().map(|(_, s)| s[axis]).sum();
+    }
+    PortType::Array(Box::new(element), shape)
+}
+
+pub fn slice_node(
+    inputs: &ArrayInputs,
+    parameters: Parameters,
+) -> Result<Dict<String, PortData>, ForayNodeError> {
+    let specs = parse_slices(parameters.string("slices")?)?;
+    Ok(output(
+        map_array!(input_array(inputs, "a")?, a => slice(a, &specs)?),
+    ))
+}
+
+pub fn transpose_node(
+    inputs: &ArrayInputs,
+    parameters: Parameters,
+) -> Result<Dict<String, PortData>, ForayNodeError> {
+    let axes = parse_list(parameters.string("axes")?)?;
+    Ok(output(
+        map_array!(input_array(inputs, "a")?, a => transpose(a, &axes)?),
+    ))
+}
+
+pub fn reshape_node(
+    inputs: &ArrayInputs,
+    parameters: Parameters,
+) -> Result<Dict<String, PortData>, ForayNodeError> {
+    let shape = parse_list(parameters.string("shape")?)?;
+    Ok(output(
+        map_array!(input_array(inputs, "a")?, a => reshape(a, &shape)?),
+    ))
+}
+
+pub fn squeeze_node(
+    inputs: &ArrayInputs,
+    parameters: Parameters,
+) -> Result<Dict<String, PortData>, ForayNodeError> {
+    let axes = parse_list(parameters.string("axes")?)?;
+    Ok(output(
+        map_array!(input_array(inputs, "a")?, a => squeeze(a, &axes)?),
+    ))
+}
+
+pub fn expand_dims_node(
+    inputs: &ArrayInputs,
+    parameters: Parameters,
+) -> Result<Dict<String, PortData>, ForayNodeError> {
+    let axis = parameters.integer("axis")? as isize;
+    Ok(output(
+        map_array!(input_array(inputs, "a")?, a => expand_dims(a, axis)?),
+    ))
+}
+
+pub fn flip_node(
+    inputs: &ArrayInputs,
+    parameters: Parameters,
+) -> Result<Dict<String, PortData>, ForayNodeError> {
+    let axes = parse_list(parameters.string("axes")?)?;
+    Ok(output(
+        map_array!(input_array(inputs, "a")?, a => flip(a, &axes)?),
+    ))
+}
+
+pub fn roll_node(
+    inputs: &ArrayInputs,
+    parameters: Parameters,
+) -> Result<Dict<String, PortData>, ForayNodeError> {
+    let shift = parameters.integer("shift")? as isize;
+    let axis = parameters.integer("axis")? as isize;
+    Ok(output(
+        map_array!(input_array(inputs, "a")?, a => roll(a, shift, axis)?),
+    ))
+}
+
+pub fn concatenate_node(
+    inputs: &ArrayInputs,
+    parameters: Parameters,
+) -> Result<Dict<String, PortData>, ForayNodeError> {
+    let axis = parameters.integer("axis")? as isize;
+    let arrays = combined_arrays(inputs, parameters)?;
+    Ok(output(
+        map_arrays!(arrays, views => concatenate(&views, axis)?),
+    ))
+}
+
+pub fn stack_node(
+    inputs: &ArrayInputs,
+    parameters: Parameters,
+) -> Result<Dict<String, PortData>, ForayNodeError> {
+    let axis = parameters.integer("axis")? as isize;
+    let arrays = combined_arrays(inputs, parameters)?;
+    Ok(output(map_arrays!(arrays, views => stack(&views, axis)?)))
+}
+
+fn combined_arrays<'a>(
+    inputs: &'a ArrayInputs,
+    parameters: Parameters,
+) -> Result<Vec<&'a ForayArray>, ForayNodeError> {
+    combined_input_names(parameters)
+        .map(|name| input_array(inputs, &name))
+        .collect()
+}
+
+pub(super) fn input_array<'a>(
+    inputs: &'a ArrayInputs,
+    name: &str,
+) -> Result<&'a ForayArray, ForayNodeError> {
+    match inputs.get(name).map(|data| &**data) {
+        Some(PortData::Array(array)) => Ok(array),
+        Some(data) => Err(ForayNodeError::NodeError(NodeError::Input(format!(
+            "{name} should be an array, found {:?}",
+            PortType::from(data)
+        )))),
+        None => Err(ForayNodeError::NodeError(NodeError::Input(format!(
+            "{name} is not connected"
+        )))),
+    }
+}
+
+pub(super) fn output(array: ForayArray) -> Dict<String, PortData> {
+    [("out".to_string(), PortData::Array(array))].into()
+}
+
+pub(super) fn compute_error(message: impl Into<String>) -> ForayNodeError {
+    ForayNodeError::NodeError(NodeError::Compute(message.into()))
+}
+
+#[cfg(test)]
+mod test {
+    use ndarray::array;
+
+    use super::*;
+
+    fn range(shape: &[usize]) -> ArrayD<i32> {
+        let len = shape.iter().product::<usize>() as i32;
+        ArrayD::from_shape_vec(shape, (0..len).collect()).unwrap()
+    }
+
+    #[test]
+    fn slices() {
+        let a = range(&[3, 4]);
+        let s = |spec| slice(&a, &parse_slices(spec).unwrap()).unwrap();
+
+        assert_eq!(s(""), a);
+        assert_eq!(s("1"), array![4, 5, 6, 7].into_dyn());
+        assert_eq!(s(":, -1"), array![3, 7, 11].into_dyn());
+        assert_eq!(s("1:, ::2"), array![[4, 6], [8, 10]].into_dyn());
+        assert_eq!(s("::-1, 2"), array![10, 6, 2].into_dyn());
+        assert_eq!(s("0, 3:0:-2"), array![3, 1].into_dyn());
+        assert_eq!(s("5:, :"), range(&[0, 4]));
+
+        assert!(parse_slices("::0").is_err());
+        assert!(slice(&a, &parse_slices("3").unwrap()).is_err());
+        assert!(slice(&a, &parse_slices("0, 0, 0").unwrap()).is_err());
+    }
+
+    #[test]
+    fn axes() {
+        let a = range(&[2, 3, 1]);
+
+        assert_eq!(transpose(&a, &[]).unwrap().shape(), [1, 3, 2]);
+        assert_eq!(transpose(&a, &[1, 0, 2]).unwrap().shape(), [3, 2, 1]);
+        assert!(transpose(&a, &[0, 0, 1]).is_err());
+
+        assert_eq!(squeeze(&a, &[]).unwrap().shape(), [2, 3]);
+        assert_eq!(squeeze(&a, &[-1]).unwrap().shape(), [2, 3]);
+        assert!(squeeze(&a, &[0]).is_err());
+
+        assert_eq!(expand_dims(&a, 0).unwrap().shape(), [1, 2, 3, 1]);
+        assert_eq!(expand_dims(&a, -1).unwrap().shape(), [2, 3, 1, 1]);
+
+        assert_eq!(reshape(&a, &[3, -1]).unwrap(), range(&[3, 2]));
+        assert!(reshape(&a, &[4, -1]).is_err());
+        assert!(reshape(&a, &[-1, -1]).is_err());
+    }
+
+    #[test]
+    fn rearrange() {
+        let a = range(&[2, 3]);
+
+        assert_eq!(
+            flip(&a, &[1]).unwrap(),
+            array![[2, 1, 0], [5, 4, 3]].into_dyn()
+        );
+        assert_eq!(
+            flip(&a, &[]).unwrap(),
+            array![[5, 4, 3], [2, 1, 0]].into_dyn()
+        );
+
+        assert_eq!(
+            roll(&a, 1, 1).unwrap(),
+            array![[2, 0, 1], [5, 3, 4]].into_dyn()
+        );
+        assert_eq!(
+            roll(&a, -4, -1).unwrap(),
+            array![[1, 2, 0], [4, 5, 3]].into_dyn()
+        );
+
+        let views = [a.view(), a.view()];
+        assert_eq!(concatenate(&views, 0).unwrap().shape(), [4, 3]);
+        assert_eq!(stack(&views, 0).unwrap().shape(), [2, 2, 3]);
+        assert_eq!(stack(&views, -1).unwrap().shape(), [2, 3, 2]);
+    }
+
+    #[test]
+    fn types() {
+        let float =
+            |shape: &[Option<usize>]| PortType::Array(Box::new(PortType::Float), shape.into());
+        let a = float(&[Some(2), Some(3), None]);
+        let strings = |pairs: &[(&str, &str)]| -> Dict<String, PortData> {
+            pairs
+                .iter()
+                .map(|(k, v)| (k.to_string(), PortData::String(v.to_string())))
+                .collect()
+        };
+
+        let parameters = strings(&[("slices", "0, ::2")]);
+        assert_eq!(
+            slice_type(Parameters(&parameters), &a),
+            float(&[Some(2), None])
+        );
+        let parameters = strings(&[("axes", "2, 0, 1")]);
+        assert_eq!(
+            transpose_type(Parameters(&parameters), &a),
+            float(&[None, Some(2), Some(3)])
+        );
+        let parameters = strings(&[("shape", "-1, 2")]);
+        assert_eq!(
+            reshape_type(Parameters(&parameters), &float(&[Some(2), Some(3)])),
+            float(&[Some(3), Some(2)])
+        );
+        assert_eq!(
+            reshape_type(Parameters(&parameters), &a),
+            float(&[None, Some(2)])
+        );
+        assert_eq!(
+            reshape_type(Parameters(&parameters), &PortType::Any),
+            PortType::Array(Box::new(PortType::Any), vec![None, Some(2)])
+        );
+        assert_eq!(same_shape_type(&PortType::Any), PortType::Any);
+
+        let parameters = [("axis".to_string(), PortData::Integer(0))].into();
+        let inputs = [("a".to_string(), a.clone()), ("b".to_string(), a.clone())].into();
+        assert_eq!(
+            combined_type(Parameters(&parameters), &inputs, false),
+            float(&[Some(4), Some(3), None])
+        );
+        assert_eq!(
+            combined_type(Parameters(&parameters), &inputs, true),
+            float(&[Some(2), Some(2), Some(3), None])
+        );
+    }
+}
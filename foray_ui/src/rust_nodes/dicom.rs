@@ -0,0 +1,201 @@
+//! Node that reads a series of DICOM images from a directory into a volume
+
+use std::path::{Path, PathBuf};
+
+use foray_data_model::node::{Dict, ForayArray, PortData, PortType, UIParameter};
+use foray_formats::dicom::{self, tag, Series};
+use foray_graph::graph::ForayNodeError;
+use foray_native::Parameters;
+use ndarray::Array1;
+
+use super::array::compute_error;
+
+fn scan(dir: &Path) -> Result<Vec<Series>, ForayNodeError> {
+    dicom::scan(dir).map_err(|e| compute_error(format!("{}: {e}", dir.display())))
+}
+
+/// Labels to choose each series by, made unique with the series UID where they're the same
+fn labels(series: &[Series]) -> Vec<String> {
+    let labels = series.iter().map(Series::label).collect::<Vec<_>>();
+    labels
+        .iter()
+        .zip(series)
+        .map(
+            |(label, s)| match labels.iter().filter(|l| *l == label).count() {
+                1 => label.clone(),
+                _ => format!("{label} ({})", s.uid),
+            },
+        )
+        .collect()
+}
+
+fn directory(parameters: Parameters) -> Result<PathBuf, ForayNodeError> {
+    match parameters.string("directory")? {
+        "" => Err(compute_error("no directory selected")),
+        dir => Ok(PathBuf::from(dir)),
+    }
+}
+
+/// Series of the selected directory, none if it can't be scanned.
+/// Scanning reads every file, so it's done in the background when the directory changes
+pub fn scan_directory(parameters: Parameters) -> Vec<Series> {
+    directory(parameters)
+        .and_then(|dir| scan(&dir))
+        .unwrap_or_default()
+}
+
+/// The series found in the selected directory to choose from, the first is used if none is chosen
+pub fn dicom_parameters(series: &[Series]) -> Dict<String, UIParameter> {
+    let options = labels(series);
+    let default = options.first().cloned().unwrap_or_default();
+    [
+        (
+            "directory".to_string(),
+            UIParameter::DirectoryPicker(String::new()),
+        ),
+        (
+            "series".to_string(),
+            UIParameter::Dropdown(options, default),
+        ),
+    ]
+    .into()
+}
+
+/// Type of the tags object. Tags that are missing from the images are left out
+pub fn tags_type() -> PortType {
+    let vector = |len| PortType::Array(Box::new(PortType::Float), vec![Some(len)]);
+    PortType::Object(
+        [
+            ("modality", PortType::String),
+            ("series_description", PortType::String),
+            ("series_number", PortType::Integer),
+            ("series_uid", PortType::String),
+            ("study_uid", PortType::String),
+            ("study_date", PortType::String),
+            ("pixel_spacing", vector(2)),
+            ("slice_thickness", PortType::Float),
+            ("slice_spacing", PortType::Float),
+            ("orientation", vector(6)),
+            ("position", vector(3)),
+            ("rescale_slope", PortType::Float),
+            ("rescale_intercept", PortType::Float),
+            ("echo_time", PortType::Float),
+            ("repetition_time", PortType::Float),
+            ("flip_angle", PortType::Float),
+            ("field_strength", PortType::Float),
+        ]
+        .map(|(name, port_type)| (name.to_string(), port_type))
+        .into(),
+    )
+}
+
+/// Selected tags of the first image, and the spacing of the slices
+pub fn tags_object(volume: &dicom::Volume) -> PortData {
+    let header = &volume.header;
+    let strings = [
+        ("modality", tag::MODALITY),
+        ("series_description", tag::SERIES_DESCRIPTION),
+        ("series_uid", tag::SERIES_INSTANCE_UID),
+        ("study_uid", tag::STUDY_INSTANCE_UID),
+        ("study_date", tag::STUDY_DATE),
+    ]
+    .map(|(name, tag)| (name, header.string(tag).map(|s| PortData::String(s.into()))));
+    let floats = [
+        ("slice_thickness", tag::SLICE_THICKNESS),
+        ("rescale_slope", tag::RESCALE_SLOPE),
+        ("rescale_intercept", tag::RESCALE_INTERCEPT),
+        ("echo_time", tag::ECHO_TIME),
+        ("repetition_time", tag::REPETITION_TIME),
+        ("flip_angle", tag::FLIP_ANGLE),
+        ("field_strength", tag::MAGNETIC_FIELD_STRENGTH),
+    ]
+    .map(|(name, tag)| (name, header.number(tag).map(PortData::Float)));
+    let vectors = [
+        ("pixel_spacing", tag::PIXEL_SPACING),
+        ("orientation", tag::IMAGE_ORIENTATION_PATIENT),
+        ("position", tag::IMAGE_POSITION_PATIENT),
+    ]
+    .map(|(name, tag)| {
+        let vector = header
+            .numbers(tag)
+            .map(|v| PortData::Array(ForayArray::Float(Array1::from_vec(v).into_dyn())));
+        (name, vector)
+    });
+    let others = [
+        (
+            "series_number",
+            header
+                .number(tag::SERIES_NUMBER)
+                .map(|n| PortData::Integer(n as i32)),
+        ),
+        ("slice_spacing", volume.slice_spacing.map(PortData::Float)),
+    ];
+    PortData::Object(
+        strings
+            .into_iter()
+            .chain(floats)
+            .chain(vectors)
+            .chain(others)
+            .filter_map(|(name, data)| Some((name.to_string(), data?)))
+            .collect(),
+    )
+}
+
+/// Read the chosen one of the `series` found in the directory.
+/// If none were found, the directory is scanned again to report why
+pub fn read_dicom_node(
+    series: &[Series],
+    parameters: Parameters,
+) -> Result<Dict<String, PortData>, ForayNodeError> {
+    let dir = directory(parameters)?;
+    let scanned;
+    let series = match series {
+        [] => {
+            scanned = scan(&dir)?;
+            &scanned
+        }
+        series => series,
+    };
+    let labels = labels(series);
+    let selected = match parameters.string("series")? {
+        "" => series
+            .first()
+            .ok_or_else(|| compute_error(format!("{} has no DICOM images", dir.display())))?,
+        label => labels
+            .iter()
+            .position(|l| l == label)
+            .map(|i| &series[i])
+            .ok_or_else(|| compute_error(format!("series {label} is not in {}", dir.display())))?,
+    };
+    let volume = selected
+        .load()
+        .map_err(|e| compute_error(format!("series {}: {e}", selected.label())))?;
+    Ok([
+        ("tags".to_string(), tags_object(&volume)),
+        ("out".to_string(), PortData::Array(volume.array)),
+    ]
+    .into())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn unique_labels() {
+        let series = |uid: &str, number| Series {
+            uid: uid.to_string(),
+            number,
+            description: "t1".to_string(),
+            files: vec![],
+        };
+        assert_eq!(
+            labels(&[
+                series("1.1", Some(2)),
+                series("1.2", Some(3)),
+                series("1.3", Some(3))
+            ]),
+            ["2 t1", "3 t1 (1.2)", "3 t1 (1.3)"]
+        );
+    }
+}
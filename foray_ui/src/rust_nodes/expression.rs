@@ -0,0 +1,719 @@
+//! Array math typed into a node, e.g. `abs(a) * exp(1j * angle(b))` or `a[::2] - mean(a)`.
+//!
+//! Every free variable of the expression is an input of the node. `pi` and `e` are inputs too,
+//! which are the constants when nothing is connected to them. Values are real or complex arrays,
+//! scalars are arrays with no dimensions, and binary operations broadcast like numpy
+
+use foray_data_model::node::{Dict, ForayArray, NodeError, PortData, PortType};
+use foray_graph::graph::{ForayNodeError, PortName};
+use foray_native::Parameters;
+use ndarray::{arr0, ArrayD, Axis, Zip};
+use numpy::Complex64;
+use std::{collections::BTreeSet, f64::consts};
+
+use super::array::{compute_error, parse_slices, resolve_axis, slice, ArrayInputs, SliceSpec};
+
+/// Functions applied to each element
+const ELEMENTWISE: [&str; 14] = [
+    "abs", "angle", "real", "imag", "conj", "exp", "log", "log10", "sqrt", "sin", "cos", "tan",
+    "tanh", "sinc",
+];
+/// Functions that reduce all elements, or the elements along an axis given as a second argument
+const REDUCTIONS: [&str; 5] = ["sum", "mean", "prod", "min", "max"];
+
+#[derive(Clone, Debug, PartialEq)]
+enum Token {
+    Number(f64),
+    Imaginary(f64),
+    Identifier(String),
+    Symbol(char),
+    Power,
+    /// Contents of square brackets, parsed as slices
+    Index(String),
+    End,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum BinaryOp {
+    Add,
+    Subtract,
+    Multiply,
+    Divide,
+    Power,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+enum Expr {
+    Real(f64),
+    Imaginary(f64),
+    Variable(String),
+    Negate(Box<Expr>),
+    Binary(BinaryOp, Box<Expr>, Box<Expr>),
+    Call(String, Vec<Expr>),
+    Index(Box<Expr>, Vec<SliceSpec>),
+}
+
+/// A parsed expression
+#[derive(Clone, Debug, PartialEq)]
+pub struct Expression(Expr);
+
+impl Expression {
+    /// Parse an expression, errors point to the column where parsing failed
+    pub fn parse(source: &str) -> Result<Self, ForayNodeError> {
+        let mut parser = Parser {
+            source,
+            tokens: tokenize(source)?,
+            position: 0,
+        };
+        let expr = parser.additive()?;
+        match parser.peek() {
+            Token::End => Ok(Expression(expr)),
+            _ => Err(parser.error("unexpected input")),
+        }
+    }
+
+    /// Names of the variables in the expression, in sorted order
+    pub fn variables(&self) -> BTreeSet<String> {
+        fn collect(expr: &Expr, variables: &mut BTreeSet<String>) {
+            match expr {
+                Expr::Real(_) | Expr::Imaginary(_) => {}
+                Expr::Variable(name) => {
+                    variables.insert(name.clone());
+                }
+                Expr::Negate(e) | Expr::Index(e, _) => collect(e, variables),
+                Expr::Binary(_, a, b) => {
+                    collect(a, variables);
+                    collect(b, variables);
+                }
+                Expr::Call(_, args) => args.iter().for_each(|arg| collect(arg, variables)),
+            }
+        }
+        let mut variables = BTreeSet::new();
+        collect(&self.0, &mut variables);
+        variables
+    }
+
+    pub fn evaluate(&self, variables: &Dict<String, Value>) -> Result<Value, ForayNodeError> {
+        evaluate(&self.0, variables)
+    }
+}
+
+fn parse_error(source: &str, column: usize, message: impl Into<String>) -> ForayNodeError {
+    ForayNodeError::NodeError(NodeError::Parse {
+        message: message.into(),
+        source: source.to_string(),
+        column,
+    })
+}
+
+/// Split the source into tokens, each with the column it starts at
+fn tokenize(source: &str) -> Result<Vec<(Token, usize)>, ForayNodeError> {
+    let chars: Vec<char> = source.chars().collect();
+    let mut tokens = vec![];
+    let mut i = 0;
+    while i < chars.len() {
+        let start = i;
+        let c = chars[i];
+        let token = if c.is_whitespace() {
+            i += 1;
+            continue;
+        } else if c.is_ascii_digit()
+            || (c == '.' && chars.get(i + 1).is_some_and(char::is_ascii_digit))
+        {
+            while chars
+                .get(i)
+                .is_some_and(|c| c.is_ascii_digit() || *c == '.')
+            {
+                i += 1;
+            }
+            // Exponent, e.g. 1e-3
+            if chars.get(i).is_some_and(|c| *c == 'e' || *c == 'E') {
+                let sign = usize::from(chars.get(i + 1).is_some_and(|c| *c == '+' || *c == '-'));
+                if chars.get(i + 1 + sign).is_some_and(char::is_ascii_digit) {
+                    i += 1 + sign;
+                    while chars.get(i).is_some_and(char::is_ascii_digit) {
+                        i += 1;
+                    }
+                }
+            }
+            let text: String = chars[start..i].iter().collect();
+            let value: f64 = text
+                .parse()
+                .map_err(|_| parse_error(source, start, format!("invalid number '{text}'")))?;
+            if chars.get(i) == Some(&'j') {
+                i += 1;
+                Token::Imaginary(value)
+            } else {
+                Token::Number(value)
+            }
+        } else if c.is_alphabetic() || c == '_' {
+            while chars
+                .get(i)
+                .is_some_and(|c| c.is_alphanumeric() || *c == '_')
+            {
+                i += 1;
+            }
+            Token::Identifier(chars[start..i].iter().collect())
+        } else if c == '*' && chars.get(i + 1) == Some(&'*') {
+            i += 2;
+            Token::Power
+        } else if c == '[' {
+            let close = chars[i..]
+                .iter()
+                .position(|c| *c == ']')
+                .ok_or_else(|| parse_error(source, start, "'[' is never closed"))?;
+            i += close + 1;
+            Token::Index(chars[start + 1..i - 1].iter().collect())
+        } else if "+-*/(),".contains(c) {
+            i += 1;
+            Token::Symbol(c)
+        } else {
+            return Err(parse_error(source, start, format!("unexpected '{c}'")));
+        };
+        tokens.push((token, start));
+    }
+    tokens.push((Token::End, chars.len()));
+    Ok(tokens)
+}
+
+/// Recursive descent parser, with python's operator precedence
+struct Parser<'a> {
+    source: &'a str,
+    tokens: Vec<(Token, usize)>,
+    position: usize,
+}
+
+impl Parser<'_> {
+    fn peek(&self) -> &Token {
+        &self.tokens[self.position].0
+    }
+
+    fn column(&self) -> usize {
+        self.tokens[self.position].1
+    }
+
+    fn next(&mut self) -> Token {
+        let token = self.tokens[self.position].0.clone();
+        if token != Token::End {
+            self.position += 1;
+        }
+        token
+    }
+
+    fn error(&self, message: impl Into<String>) -> ForayNodeError {
+        parse_error(self.source, self.column(), message)
+    }
+
+    fn expect(&mut self, symbol: char) -> Result<(), ForayNodeError> {
+        match self.peek() {
+            Token::Symbol(c) if *c == symbol => {
+                self.next();
+                Ok(())
+            }
+            _ => Err(self.error(format!("expected '{symbol}'"))),
+        }
+    }
+
+    fn additive(&mut self) -> Result<Expr, ForayNodeError> {
+        let mut left = self.multiplicative()?;
+        loop {
+            let op = match self.peek() {
+                Token::Symbol('+') => BinaryOp::Add,
+                Token::Symbol('-') => BinaryOp::Subtract,
+                _ => return Ok(left),
+            };
+            self.next();
+            left = Expr::Binary(op, Box::new(left), Box::new(self.multiplicative()?));
+        }
+    }
+
+    fn multiplicative(&mut self) -> Result<Expr, ForayNodeError> {
+        let mut left = self.unary()?;
+        loop {
+            let op = match self.peek() {
+                Token::Symbol('*') => BinaryOp::Multiply,
+                Token::Symbol('/') => BinaryOp::Divide,
+                _ => return Ok(left),
+            };
+            self.next();
+            left = Expr::Binary(op, Box::new(left), Box::new(self.unary()?));
+        }
+    }
+
+    fn unary(&mut self) -> Result<Expr, ForayNodeError> {
+        match self.peek() {
+            Token::Symbol('-') => {
+                self.next();
+                Ok(Expr::Negate(Box::new(self.unary()?)))
+            }
+            Token::Symbol('+') => {
+                self.next();
+                self.unary()
+            }
+            _ => self.power(),
+        }
+    }
+
+    /// `**` binds tighter than a minus sign on its left, `-a**2` is `-(a**2)`
+    fn power(&mut self) -> Result<Expr, ForayNodeError> {
+        let base = self.postfix()?;
+        match self.peek() {
+            Token::Power => {
+                self.next();
+                let exponent = self.unary()?;
+                Ok(Expr::Binary(
+                    BinaryOp::Power,
+                    Box::new(base),
+                    Box::new(exponent),
+                ))
+            }
+            _ => Ok(base),
+        }
+    }
+
+    fn postfix(&mut self) -> Result<Expr, ForayNodeError> {
+        let mut expr = self.primary()?;
+        while let Token::Index(spec) = self.peek() {
+            let specs = parse_slices(spec).map_err(|e| self.error(e.to_string()))?;
+            self.next();
+            expr = Expr::Index(Box::new(expr), specs);
+        }
+        Ok(expr)
+    }
+
+    fn primary(&mut self) -> Result<Expr, ForayNodeError> {
+        let column = self.column();
+        match self.next() {
+            Token::Number(v) => Ok(Expr::Real(v)),
+            Token::Imaginary(v) => Ok(Expr::Imaginary(v)),
+            Token::Identifier(name) if *self.peek() == Token::Symbol('(') => {
+                self.next();
+                let mut args = vec![self.additive()?];
+                while *self.peek() == Token::Symbol(',') {
+                    self.next();
+                    args.push(self.additive()?);
+                }
+                self.expect(')')?;
+
+                let max_args = if ELEMENTWISE.contains(&name.as_str()) {
+                    1
+                } else if REDUCTIONS.contains(&name.as_str()) {
+                    2
+                } else {
+                    return Err(parse_error(
+                        self.source,
+                        column,
+                        format!("unknown function '{name}'"),
+                    ));
+                };
+                if args.len() > max_args {
+                    let expected = match max_args {
+                        1 => "one argument",
+                        _ => "one or two arguments",
+                    };
+                    return Err(parse_error(
+                        self.source,
+                        column,
+                        format!("{name} takes {expected}, found {}", args.len()),
+                    ));
+                }
+                Ok(Expr::Call(name, args))
+            }
+            Token::Identifier(name) => Ok(Expr::Variable(name)),
+            Token::Symbol('(') => {
+                let expr = self.additive()?;
+                self.expect(')')?;
+                Ok(expr)
+            }
+            Token::End => Err(parse_error(self.source, column, "expected a value")),
+            _ => Err(parse_error(self.source, column, "unexpected input")),
+        }
+    }
+}
+
+/// Real or complex array, scalars have no dimensions
+#[derive(Clone, Debug, PartialEq)]
+pub enum Value {
+    Real(ArrayD<f64>),
+    Complex(ArrayD<Complex64>),
+}
+
+impl Value {
+    fn into_complex(self) -> ArrayD<Complex64> {
+        match self {
+            Value::Real(a) => a.mapv(|v| Complex64::new(v, 0.0)),
+            Value::Complex(a) => a,
+        }
+    }
+
+    fn map(self, real: fn(f64) -> f64, complex: fn(Complex64) -> Complex64) -> Value {
+        match self {
+            Value::Real(a) => Value::Real(a.mapv(real)),
+            Value::Complex(a) => Value::Complex(a.mapv(complex)),
+        }
+    }
+
+    pub fn from_port_data(name: &str, data: &PortData) -> Result<Self, ForayNodeError> {
+        Ok(match data {
+            PortData::Integer(v) => Value::Real(arr0(*v as f64).into_dyn()),
+            PortData::Float(v) => Value::Real(arr0(*v).into_dyn()),
+            PortData::Complex((re, im)) => {
+                Value::Complex(arr0(Complex64::new(*re, *im)).into_dyn())
+            }
+            PortData::Boolean(v) => Value::Real(arr0(f64::from(*v)).into_dyn()),
+            PortData::Array(ForayArray::Integer(a)) => Value::Real(a.mapv(|v| v as f64)),
+            PortData::Array(ForayArray::Float(a)) => Value::Real(a.clone()),
+            PortData::Array(ForayArray::Complex(a)) => Value::Complex(a.clone()),
+            PortData::Array(ForayArray::Boolean(a)) => Value::Real(a.mapv(f64::from)),
+            data => {
+                return Err(ForayNodeError::NodeError(NodeError::Input(format!(
+                    "{name} should be numeric, found {:?}",
+                    PortType::from(data)
+                ))))
+            }
+        })
+    }
+
+    /// Scalars are output as scalar port data, rather than arrays
+    pub fn into_port_data(self) -> PortData {
+        match self {
+            Value::Real(a) if a.ndim() == 0 => PortData::Float(a.sum()),
+            Value::Complex(a) if a.ndim() == 0 => {
+                let v = a.sum();
+                PortData::Complex((v.re, v.im))
+            }
+            Value::Real(a) => PortData::Array(ForayArray::Float(a)),
+            Value::Complex(a) => PortData::Array(ForayArray::Complex(a)),
+        }
+    }
+}
+
+/// numpy's broadcasting rules: shapes are aligned at their last axis, and axes of length 1 are
+/// repeated to match
+fn broadcast_shape(a: &[usize], b: &[usize]) -> Result<Vec<usize>, ForayNodeError> {
+    let ndim = a.len().max(b.len());
+    let axis =
+        |shape: &[usize], i: usize| (i + shape.len()).checked_sub(ndim).map_or(1, |i| shape[i]);
+    (0..ndim)
+        .map(|i| match (axis(a, i), axis(b, i)) {
+            (x, y) if x == y || y == 1 => Ok(x),
+            (1, y) => Ok(y),
+            _ => Err(compute_error(format!(
+                "shapes {a:?} and {b:?} can't be broadcast together"
+            ))),
+        })
+        .collect()
+}
+
+fn broadcast_with<A: Copy, B>(
+    a: &ArrayD<A>,
+    b: &ArrayD<A>,
+    f: impl Fn(A, A) -> B,
+) -> Result<ArrayD<B>, ForayNodeError> {
+    let shape = broadcast_shape(a.shape(), b.shape())?;
+    match (a.broadcast(shape.clone()), b.broadcast(shape)) {
+        (Some(a), Some(b)) => Ok(Zip::from(&a).and(&b).map_collect(|a, b| f(*a, *b))),
+        _ => Err(compute_error("arrays can't be broadcast together")),
+    }
+}
+
+fn binary(op: BinaryOp, a: Value, b: Value) -> Result<Value, ForayNodeError> {
+    Ok(match (a, b) {
+        (Value::Real(a), Value::Real(b)) => Value::Real(broadcast_with(&a, &b, |a, b| match op {
+            BinaryOp::Add => a + b,
+            BinaryOp::Subtract => a - b,
+            BinaryOp::Multiply => a * b,
+            BinaryOp::Divide => a / b,
+            BinaryOp::Power => a.powf(b),
+        })?),
+        (a, b) => Value::Complex(broadcast_with(
+            &a.into_complex(),
+            &b.into_complex(),
+            |a, b| match op {
+                BinaryOp::Add => a + b,
+                BinaryOp::Subtract => a - b,
+                BinaryOp::Multiply => a * b,
+                BinaryOp::Divide => a / b,
+                BinaryOp::Power => a.powc(b),
+            },
+        )?),
+    })
+}
+
+fn elementwise(name: &str, value: Value) -> Value {
+    match (name, value) {
+        ("abs", Value::Complex(a)) => Value::Real(a.mapv(|v| v.norm())),
+        ("abs", value) => value.map(f64::abs, |v| v),
+        ("angle", Value::Real(a)) => Value::Real(a.mapv(|v| 0f64.atan2(v))),
+        ("angle", Value::Complex(a)) => Value::Real(a.mapv(|v| v.arg())),
+        ("real", Value::Complex(a)) => Value::Real(a.mapv(|v| v.re)),
+        ("imag", Value::Real(a)) => Value::Real(a.mapv(|_| 0.0)),
+        ("imag", Value::Complex(a)) => Value::Real(a.mapv(|v| v.im)),
+        ("real" | "conj", value) => value.map(|v| v, |v| v.conj()),
+        ("exp", value) => value.map(f64::exp, |v| v.exp()),
+        ("log", value) => value.map(f64::ln, |v| v.ln()),
+        ("log10", value) => value.map(f64::log10, |v| v.log10()),
+        ("sqrt", value) => value.map(f64::sqrt, |v| v.sqrt()),
+        ("sin", value) => value.map(f64::sin, |v| v.sin()),
+        ("cos", value) => value.map(f64::cos, |v| v.cos()),
+        ("tan", value) => value.map(f64::tan, |v| v.tan()),
+        ("tanh", value) => value.map(f64::tanh, |v| v.tanh()),
+        // Normalized, like numpy
+        ("sinc", value) => value.map(
+            |v| {
+                if v == 0.0 {
+                    1.0
+                } else {
+                    (consts::PI * v).sin() / (consts::PI * v)
+                }
+            },
+            |v| {
+                if v == Complex64::default() {
+                    Complex64::new(1.0, 0.0)
+                } else {
+                    (v * consts::PI).sin() / (v * consts::PI)
+                }
+            },
+        ),
+        (name, _) => unreachable!("{name} is checked when parsing"),
+    }
+}
+
+fn reduce(name: &str, value: Value, axis: Option<Value>) -> Result<Value, ForayNodeError> {
+    let ndim = match &value {
+        Value::Real(a) => a.ndim(),
+        Value::Complex(a) => a.ndim(),
+    };
+    let axis = match axis {
+        Some(Value::Real(axis)) if axis.ndim() == 0 && axis.iter().all(|v| v.fract() == 0.0) => {
+            Some(Axis(resolve_axis(axis.sum() as isize, ndim)?))
+        }
+        Some(_) => {
+            return Err(compute_error(format!(
+                "axis of {name} should be an integer"
+            )))
+        }
+        None => None,
+    };
+    let empty = || compute_error(format!("{name} of an empty array"));
+
+    Ok(match (name, value, axis) {
+        ("sum", Value::Real(a), None) => Value::Real(arr0(a.sum()).into_dyn()),
+        ("sum", Value::Real(a), Some(axis)) => Value::Real(a.sum_axis(axis)),
+        ("sum", Value::Complex(a), None) => Value::Complex(arr0(a.sum()).into_dyn()),
+        ("sum", Value::Complex(a), Some(axis)) => Value::Complex(a.sum_axis(axis)),
+        ("prod", Value::Real(a), None) => Value::Real(arr0(a.product()).into_dyn()),
+        ("prod", Value::Real(a), Some(axis)) => Value::Real(a.product_axis(axis)),
+        ("prod", Value::Complex(a), None) => Value::Complex(arr0(a.product()).into_dyn()),
+        ("prod", Value::Complex(a), Some(axis)) => Value::Complex(a.product_axis(axis)),
+        ("mean", Value::Real(a), None) => Value::Real(arr0(a.mean().ok_or_else(empty)?).into_dyn()),
+        ("mean", Value::Real(a), Some(axis)) => Value::Real(a.mean_axis(axis).ok_or_else(empty)?),
+        ("mean", Value::Complex(a), None) => {
+            Value::Complex(arr0(a.mean().ok_or_else(empty)?).into_dyn())
+        }
+        ("mean", Value::Complex(a), Some(axis)) => {
+            Value::Complex(a.mean_axis(axis).ok_or_else(empty)?)
+        }
+        ("min" | "max", Value::Complex(_), _) => {
+            return Err(compute_error(format!(
+                "{name} of complex values isn't defined"
+            )))
+        }
+        ("min" | "max", Value::Real(a), axis) => {
+            let (init, f): (f64, fn(f64, f64) -> f64) = match name {
+                "min" => (f64::INFINITY, f64::min),
+                _ => (f64::NEG_INFINITY, f64::max),
+            };
+            match axis {
+                _ if a.is_empty() => return Err(empty()),
+                Some(axis) => Value::Real(a.fold_axis(axis, init, |acc, v| f(*acc, *v))),
+                None => Value::Real(arr0(a.fold(init, |acc, v| f(acc, *v))).into_dyn()),
+            }
+        }
+        (name, _, _) => unreachable!("{name} is checked when parsing"),
+    })
+}
+
+/// Value of a variable that isn't connected to anything
+fn constant(name: &str) -> Option<f64> {
+    match name {
+        "pi" => Some(consts::PI),
+        "e" => Some(consts::E),
+        _ => None,
+    }
+}
+
+fn evaluate(expr: &Expr, variables: &Dict<String, Value>) -> Result<Value, ForayNodeError> {
+    Ok(match expr {
+        Expr::Real(v) => Value::Real(arr0(*v).into_dyn()),
+        Expr::Imaginary(v) => Value::Complex(arr0(Complex64::new(0.0, *v)).into_dyn()),
+        Expr::Variable(name) => match (variables.get(name), constant(name)) {
+            (Some(value), _) => value.clone(),
+            (None, Some(v)) => Value::Real(arr0(v).into_dyn()),
+            (None, None) => return Err(compute_error(format!("{name} is not defined"))),
+        },
+        Expr::Negate(e) => evaluate(e, variables)?.map(|v| -v, |v| -v),
+        Expr::Binary(op, a, b) => binary(*op, evaluate(a, variables)?, evaluate(b, variables)?)?,
+        Expr::Index(e, specs) => match evaluate(e, variables)? {
+            Value::Real(a) => Value::Real(slice(&a, specs)?),
+            Value::Complex(a) => Value::Complex(slice(&a, specs)?),
+        },
+        Expr::Call(name, args) => {
+            let mut args = args
+                .iter()
+                .map(|arg| evaluate(arg, variables))
+                .collect::<Result<Vec<_>, _>>()?
+                .into_iter();
+            let value = args.next().expect("functions have at least one argument");
+            if REDUCTIONS.contains(&name.as_str()) {
+                reduce(name, value, args.next())?
+            } else {
+                elementwise(name, value)
+            }
+        }
+    })
+}
+
+/// Names of the variables of the expression, or `None` if it can't be parsed
+pub fn expression_variables(parameters: Parameters) -> Option<Vec<PortName>> {
+    let expression = parameters
+        .string("expression")
+        .and_then(Expression::parse)
+        .ok()?;
+    Some(expression.variables().into_iter().collect())
+}
+
+/// An input for each variable of the last expression that could be parsed
+pub fn expression_inputs(variables: &[PortName]) -> Dict<PortName, PortType> {
+    variables
+        .iter()
+        .map(|name| (name.clone(), PortType::Any))
+        .collect()
+}
+
+pub fn expression_node(
+    inputs: &ArrayInputs,
+    parameters: Parameters,
+) -> Result<Dict<String, PortData>, ForayNodeError> {
+    let expression = Expression::parse(parameters.string("expression")?)?;
+    let variables = expression
+        .variables()
+        .into_iter()
+        .filter(|name| inputs.contains_key(name) || constant(name).is_none())
+        .map(|name| {
+            let data = inputs.get(&name).ok_or_else(|| {
+                ForayNodeError::NodeError(NodeError::Input(format!("{name} is not connected")))
+            })?;
+            Ok((name.clone(), Value::from_port_data(&name, data)?))
+        })
+        .collect::<Result<Dict<_, _>, ForayNodeError>>()?;
+    let out = expression.evaluate(&variables)?;
+    Ok([("out".to_string(), out.into_port_data())].into())
+}
+
+#[cfg(test)]
+mod test {
+    use ndarray::array;
+
+    use super::*;
+
+    fn eval(source: &str, variables: &[(&str, Value)]) -> Value {
+        let variables: Dict<String, Value> = variables
+            .iter()
+            .map(|(name, value)| (name.to_string(), value.clone()))
+            .collect();
+        Expression::parse(source)
+            .unwrap()
+            .evaluate(&variables)
+            .unwrap()
+    }
+
+    fn real(a: ArrayD<f64>) -> Value {
+        Value::Real(a)
+    }
+
+    #[test]
+    fn precedence() {
+        let scalar = |v: f64| real(arr0(v).into_dyn());
+        assert_eq!(eval("1 + 2 * 3", &[]), scalar(7.0));
+        assert_eq!(eval("(1 + 2) * 3", &[]), scalar(9.0));
+        assert_eq!(eval("-2**2", &[]), scalar(-4.0));
+        assert_eq!(eval("2**-1", &[]), scalar(0.5));
+        assert_eq!(eval("2**3**2", &[]), scalar(512.0));
+        assert_eq!(eval("8 / 4 / 2", &[]), scalar(1.0));
+        assert_eq!(eval("1.5e1 - 5", &[]), scalar(10.0));
+    }
+
+    #[test]
+    fn arrays() {
+        let a = real(array![1.0, 2.0, 3.0, 4.0].into_dyn());
+        let b = real(array![[10.0], [20.0]].into_dyn());
+
+        assert_eq!(
+            eval("a[::2] - mean(a)", &[("a", a.clone())]),
+            real(array![-1.5, 0.5].into_dyn())
+        );
+        assert_eq!(
+            eval("a[:2] + b", &[("a", a.clone()), ("b", b.clone())]),
+            real(array![[11.0, 12.0], [21.0, 22.0]].into_dyn())
+        );
+        assert_eq!(
+            eval("sum(a[:2] * b, -1)", &[("a", a.clone()), ("b", b)]),
+            real(array![30.0, 60.0].into_dyn())
+        );
+        assert_eq!(
+            eval("max(a)", &[("a", a.clone())]).into_port_data(),
+            PortData::Float(4.0)
+        );
+
+        let variables: Dict<String, Value> =
+            [("a".to_string(), a.clone()), ("b".to_string(), a)].into();
+        let expression = Expression::parse("a + b[:3]").unwrap();
+        assert!(expression.evaluate(&variables).is_err());
+    }
+
+    #[test]
+    fn complex() {
+        let a = real(array![-2.0, 3.0].into_dyn());
+        let b =
+            Value::Complex(array![Complex64::new(0.0, 1.0), Complex64::new(1.0, 0.0)].into_dyn());
+
+        let out = eval("abs(a) * exp(1j * angle(b))", &[("a", a), ("b", b)]);
+        let Value::Complex(out) = out else {
+            panic!("expected a complex result")
+        };
+        assert!((out[0] - Complex64::new(0.0, 2.0)).norm() < 1e-12);
+        assert!((out[1] - Complex64::new(3.0, 0.0)).norm() < 1e-12);
+    }
+
+    #[test]
+    fn variables() {
+        let expression = Expression::parse("abs(b) * exp(1j * angle(a)) + pi").unwrap();
+        assert_eq!(
+            expression.variables().into_iter().collect::<Vec<_>>(),
+            vec!["a", "b", "pi"]
+        );
+    }
+
+    #[test]
+    fn constants() {
+        let scalar = |v: f64| real(arr0(v).into_dyn());
+        assert_eq!(eval("2 * pi", &[]), scalar(2.0 * consts::PI));
+        assert_eq!(eval("e - 1", &[("e", scalar(3.0))]), scalar(2.0));
+    }
+
+    #[test]
+    fn parse_errors() {
+        let column = |source| match Expression::parse(source) {
+            Err(ForayNodeError::NodeError(NodeError::Parse { column, .. })) => column,
+            other => panic!("expected a parse error, found {other:?}"),
+        };
+        assert_eq!(column("abs(a"), 5);
+        assert_eq!(column("a + "), 4);
+        assert_eq!(column("a $ b"), 2);
+        assert_eq!(column("2 * foo(a)"), 4);
+        assert_eq!(column("a b"), 2);
+        assert_eq!(column("a[::0]"), 1);
+        assert_eq!(column("sum(a, 0, 1)"), 0);
+    }
+}
@@ -0,0 +1,269 @@
+//! Fourier transforms over any axes of an array, computed with rustfft
+
+use std::sync::Arc;
+
+use foray_data_model::node::{Dict, ForayArray, PortData, PortType};
+use foray_graph::graph::ForayNodeError;
+use foray_native::Parameters;
+use ndarray::{ArrayD, Axis};
+use numpy::Complex64;
+use rayon::iter::{IntoParallelIterator, ParallelIterator};
+use rustfft::{Fft, FftDirection, FftPlanner};
+
+use super::array::{
+    compute_error, input_array, output, parse_list, resolve_axis, roll, ArrayInputs,
+};
+
+/// How the results of forward and inverse transforms are scaled, with numpy's names
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Normalization {
+    /// Inverse transform is scaled by `1/n`
+    Backward,
+    /// Both transforms are scaled by `1/sqrt(n)`
+    Ortho,
+    /// Forward transform is scaled by `1/n`
+    Forward,
+}
+
+impl Normalization {
+    pub fn parse(s: &str) -> Result<Self, ForayNodeError> {
+        match s.trim() {
+            "backward" => Ok(Normalization::Backward),
+            "ortho" => Ok(Normalization::Ortho),
+            "forward" => Ok(Normalization::Forward),
+            s => Err(compute_error(format!(
+                "unknown normalization '{s}', expected backward, ortho or forward"
+            ))),
+        }
+    }
+
+    /// Scale applied to a transform in `direction` over `n` elements
+    fn scale(&self, direction: FftDirection, n: usize) -> f64 {
+        let n = n as f64;
+        match (self, direction) {
+            (Normalization::Ortho, _) => 1.0 / n.sqrt(),
+            (Normalization::Backward, FftDirection::Inverse)
+            | (Normalization::Forward, FftDirection::Forward) => 1.0 / n,
+            _ => 1.0,
+        }
+    }
+}
+
+/// Fourier transform of `a` over `axes`, or over every axis if none are given.
+///
+/// When `centered`, the zero frequency is in the center of the spectrum: the output of a forward
+/// transform is fftshifted, and the input of an inverse transform is ifftshifted
+pub fn fft(
+    mut a: ArrayD<Complex64>,
+    axes: &[isize],
+    direction: FftDirection,
+    normalization: Normalization,
+    centered: bool,
+) -> Result<ArrayD<Complex64>, ForayNodeError> {
+    let mut axes = if axes.is_empty() {
+        (0..a.ndim()).collect()
+    } else {
+        axes.iter()
+            .map(|axis| resolve_axis(*axis, a.ndim()))
+            .collect::<Result<Vec<_>, _>>()?
+    };
+    axes.sort_unstable();
+    axes.dedup();
+
+    if centered && direction == FftDirection::Inverse {
+        a = shift(a, &axes, direction)?;
+    }
+    let mut planner = FftPlanner::new();
+    for axis in &axes {
+        let len = a.len_of(Axis(*axis));
+        if len > 0 {
+            fft_axis(&mut a, Axis(*axis), planner.plan_fft(len, direction));
+        }
+    }
+    let n = axes.iter().map(|axis| a.len_of(Axis(*axis))).product();
+    let scale = normalization.scale(direction, n);
+    if scale != 1.0 {
+        a.mapv_inplace(|v| v * scale);
+    }
+    if centered && direction == FftDirection::Forward {
+        a = shift(a, &axes, direction)?;
+    }
+    Ok(a)
+}
+
+/// Transform every lane along `axis` in place, in parallel
+fn fft_axis(a: &mut ArrayD<Complex64>, axis: Axis, fft: Arc<dyn Fft<f64>>) {
+    let len = a.len_of(axis);
+    let lanes: Vec<_> = a.lanes_mut(axis).into_iter().collect();
+    lanes.into_par_iter().for_each_init(
+        // Each thread reuses its buffers, lanes aren't necessarily contiguous
+        || {
+            (
+                vec![Complex64::default(); len],
+                vec![Complex64::default(); fft.get_inplace_scratch_len()],
+            )
+        },
+        |(buffer, scratch), mut lane| {
+            buffer.iter_mut().zip(&lane).for_each(|(b, v)| *b = *v);
+            fft.process_with_scratch(buffer, scratch);
+            lane.iter_mut()
+                .zip(buffer.iter())
+                .for_each(|(v, b)| *v = *b);
+        },
+    );
+}
+
+/// fftshift for forward transforms, which moves the zero frequency to the center of each axis,
+/// or its inverse, ifftshift
+fn shift(
+    a: ArrayD<Complex64>,
+    axes: &[usize],
+    direction: FftDirection,
+) -> Result<ArrayD<Complex64>, ForayNodeError> {
+    axes.iter().try_fold(a, |a, axis| {
+        let half = (a.len_of(Axis(*axis)) / 2) as isize;
+        let shift = match direction {
+            FftDirection::Forward => half,
+            FftDirection::Inverse => -half,
+        };
+        roll(&a, shift, *axis as isize)
+    })
+}
+
+/// Input data as a complex array, promoting real arrays
+fn complex_input(inputs: &ArrayInputs) -> Result<ArrayD<Complex64>, ForayNodeError> {
+    match input_array(inputs, "a")? {
+        ForayArray::Complex(a) => Ok(a.clone()),
+        ForayArray::Float(a) => Ok(a.mapv(|v| Complex64::new(v, 0.0))),
+        ForayArray::Integer(a) => Ok(a.mapv(|v| Complex64::new(v as f64, 0.0))),
+        array => Err(compute_error(format!(
+            "can't transform an array of {:?}",
+            PortType::from(array)
+        ))),
+    }
+}
+
+/// Output type of FFT nodes, a complex array with the shape of the input `a`, if it's known
+pub fn fft_type(a: &PortType) -> PortType {
+    match a {
+        PortType::Array(_, shape) => PortType::Array(Box::new(PortType::Complex), shape.clone()),
+        _ => PortType::Any,
+    }
+}
+
+pub fn fft_node(
+    inputs: &ArrayInputs,
+    parameters: Parameters,
+    direction: FftDirection,
+) -> Result<Dict<String, PortData>, ForayNodeError> {
+    let axes = parse_list(parameters.string("axes")?)?;
+    let normalization = Normalization::parse(parameters.string("normalization")?)?;
+    let centered = parameters.boolean("centered")?;
+    let out = fft(
+        complex_input(inputs)?,
+        &axes,
+        direction,
+        normalization,
+        centered,
+    )?;
+    Ok(output(ForayArray::Complex(out)))
+}
+
+#[cfg(test)]
+mod test {
+    use ndarray::{array, ArrayD};
+
+    use super::*;
+
+    fn complex(a: ArrayD<f64>) -> ArrayD<Complex64> {
+        a.mapv(|v| Complex64::new(v, 0.0))
+    }
+
+    fn assert_close(a: &ArrayD<Complex64>, b: &ArrayD<Complex64>) {
+        assert_eq!(a.shape(), b.shape());
+        assert!(
+            a.iter().zip(b).all(|(a, b)| (a - b).norm() < 1e-9),
+            "{a} != {b}"
+        );
+    }
+
+    #[test]
+    fn transforms() {
+        let impulse = complex(array![[1.0, 0.0], [0.0, 0.0]].into_dyn());
+        let ones = complex(ArrayD::ones(vec![2, 2]));
+
+        let forward = |a, axes: &[isize], normalization| {
+            fft(a, axes, FftDirection::Forward, normalization, false).unwrap()
+        };
+        assert_close(
+            &forward(impulse.clone(), &[], Normalization::Backward),
+            &ones,
+        );
+        assert_close(
+            &forward(ones.clone(), &[], Normalization::Forward),
+            &impulse,
+        );
+        assert_close(
+            &forward(ones.clone(), &[-1], Normalization::Backward),
+            &complex(array![[2.0, 0.0], [2.0, 0.0]].into_dyn()),
+        );
+        assert!(fft(
+            ones,
+            &[2],
+            FftDirection::Forward,
+            Normalization::Ortho,
+            false
+        )
+        .is_err());
+    }
+
+    #[test]
+    fn round_trip() {
+        let a = complex(ArrayD::from_shape_fn(vec![3, 4, 5], |i| {
+            (i[0] * 20 + i[1] * 5 + i[2]) as f64
+        }));
+        for normalization in [
+            Normalization::Backward,
+            Normalization::Ortho,
+            Normalization::Forward,
+        ] {
+            for centered in [false, true] {
+                let spectrum = fft(
+                    a.clone(),
+                    &[0, 2],
+                    FftDirection::Forward,
+                    normalization,
+                    centered,
+                )
+                .unwrap();
+                let back = fft(
+                    spectrum,
+                    &[0, 2],
+                    FftDirection::Inverse,
+                    normalization,
+                    centered,
+                )
+                .unwrap();
+                assert_close(&back, &a);
+            }
+        }
+    }
+
+    #[test]
+    fn centered() {
+        // The zero frequency of an odd length axis is moved to the middle
+        let ones = complex(ArrayD::ones(vec![5]));
+        let spectrum = fft(
+            ones,
+            &[],
+            FftDirection::Forward,
+            Normalization::Backward,
+            true,
+        )
+        .unwrap();
+        assert_close(
+            &spectrum,
+            &complex(array![0.0, 0.0, 5.0, 0.0, 0.0].into_dyn()),
+        );
+    }
+}
@@ -0,0 +1,53 @@
+//! Helpers shared by the nodes that read and write files
+
+use std::path::{Path, PathBuf};
+
+use foray_data_model::node::{ForayArray, PortData};
+use foray_formats::err::FormatError;
+use foray_graph::graph::ForayNodeError;
+use foray_native::Parameters;
+use ndarray::Array1;
+
+use super::array::compute_error;
+
+/// The file chosen by the `path` parameter of nodes that read or write files
+pub(super) fn file_path(parameters: Parameters) -> Result<PathBuf, ForayNodeError> {
+    match parameters.string("path")? {
+        "" => Err(compute_error("no file selected")),
+        path => Ok(PathBuf::from(path)),
+    }
+}
+
+/// An error reading or writing the file at `path`, naming the file
+pub(super) fn file_error(path: &Path) -> impl Fn(FormatError) -> ForayNodeError + '_ {
+    move |e| compute_error(format!("{}: {e}", path.display()))
+}
+
+/// A vector of floats, for header fields of the file reading nodes
+pub(super) fn float_array(values: &[f64]) -> PortData {
+    PortData::Array(ForayArray::Float(
+        Array1::from_vec(values.to_vec()).into_dyn(),
+    ))
+}
+
+/// Parse a comma separated list of names, e.g. `"a, b"`, leaving out repeated names
+pub(super) fn parse_names(spec: &str) -> Vec<String> {
+    let mut names: Vec<String> = vec![];
+    for name in spec.split(',').map(str::trim) {
+        if !name.is_empty() && !names.iter().any(|n| n == name) {
+            names.push(name.to_string());
+        }
+    }
+    names
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn names() {
+        assert_eq!(parse_names(" a, b,,a , c "), ["a", "b", "c"]);
+        assert!(parse_names("").is_empty());
+    }
+}
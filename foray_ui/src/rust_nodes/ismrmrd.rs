@@ -0,0 +1,175 @@
+//! Node that reads raw MR data from an ISMRMRD file into k-space, with the scan's header
+
+use foray_data_model::node::{Dict, ForayArray, PortData, PortType, UIParameter};
+use foray_formats::ismrmrd::{self, Exclude, Header};
+use foray_graph::graph::ForayNodeError;
+use foray_native::Parameters;
+use ndarray::Array1;
+
+use super::files::{file_error, file_path, float_array};
+
+pub fn ismrmrd_parameters() -> Dict<String, UIParameter> {
+    [
+        ("path", UIParameter::FilePicker(String::new())),
+        ("group", UIParameter::TextInput("dataset".to_string())),
+        ("exclude noise", UIParameter::CheckBox(true)),
+        ("exclude navigators", UIParameter::CheckBox(true)),
+    ]
+    .map(|(name, parameter)| (name.to_string(), parameter))
+    .into()
+}
+
+/// Type of k-space, `[repetition, contrast, slice, coil, kz, ky, kx]`
+pub fn kspace_type() -> PortType {
+    PortType::Array(Box::new(PortType::Complex), vec![None; 7])
+}
+
+/// Type of the header object. Fields that are missing from the file's header are left out
+pub fn header_type() -> PortType {
+    let vector = |element: PortType, len| PortType::Array(Box::new(element), vec![len]);
+    PortType::Object(
+        [
+            ("encoded_matrix", vector(PortType::Integer, Some(3))),
+            ("encoded_fov", vector(PortType::Float, Some(3))),
+            ("recon_matrix", vector(PortType::Integer, Some(3))),
+            ("recon_fov", vector(PortType::Float, Some(3))),
+            ("trajectory", PortType::String),
+            ("limits", PortType::Object(Dict::new())),
+            ("receiver_channels", PortType::Integer),
+            ("field_strength", PortType::Float),
+            ("tr", vector(PortType::Float, None)),
+            ("te", vector(PortType::Float, None)),
+            ("ti", vector(PortType::Float, None)),
+            ("xml", PortType::String),
+        ]
+        .map(|(name, port_type)| (name.to_string(), port_type))
+        .into(),
+    )
+}
+
+fn integer_array(values: &[usize]) -> PortData {
+    PortData::Array(ForayArray::Integer(
+        values
+            .iter()
+            .map(|v| *v as i32)
+            .collect::<Array1<_>>()
+            .into_dyn(),
+    ))
+}
+
+/// Header fields as an object, with the limits of each encoding counter as
+/// `[minimum, maximum, center]`, and the whole XML header for the fields that aren't parsed
+pub fn header_object(header: &Header, xml: &str) -> PortData {
+    let limits = header
+        .limits
+        .iter()
+        .map(|(name, l)| {
+            (
+                name.clone(),
+                integer_array(&[l.minimum, l.maximum, l.center]),
+            )
+        })
+        .collect();
+    let fields = [
+        (
+            "encoded_matrix",
+            Some(integer_array(&header.encoded_matrix)),
+        ),
+        ("encoded_fov", Some(float_array(&header.encoded_fov))),
+        ("recon_matrix", Some(integer_array(&header.recon_matrix))),
+        ("recon_fov", Some(float_array(&header.recon_fov))),
+        (
+            "trajectory",
+            Some(PortData::String(header.trajectory.clone())),
+        ),
+        ("limits", Some(PortData::Object(limits))),
+        (
+            "receiver_channels",
+            header
+                .receiver_channels
+                .map(|n| PortData::Integer(n as i32)),
+        ),
+        ("field_strength", header.field_strength.map(PortData::Float)),
+        ("tr", Some(float_array(&header.tr))),
+        ("te", Some(float_array(&header.te))),
+        ("ti", Some(float_array(&header.ti))),
+        ("xml", Some(PortData::String(xml.to_string()))),
+    ];
+    PortData::Object(
+        fields
+            .into_iter()
+            .filter_map(|(name, data)| Some((name.to_string(), data?)))
+            .collect(),
+    )
+}
+
+pub fn read_ismrmrd_node(parameters: Parameters) -> Result<Dict<String, PortData>, ForayNodeError> {
+    let path = file_path(parameters)?;
+    let file_error = &file_error(&path);
+    let (xml, acquisitions) =
+        ismrmrd::load(&path, parameters.string("group")?).map_err(file_error)?;
+    let header = ismrmrd::parse_header(&xml).map_err(file_error)?;
+    let exclude = Exclude {
+        noise: parameters.boolean("exclude noise")?,
+        navigators: parameters.boolean("exclude navigators")?,
+    };
+    let kspace = ismrmrd::kspace(&header, &acquisitions, exclude).map_err(file_error)?;
+    Ok([
+        (
+            "out".to_string(),
+            PortData::Array(ForayArray::Complex(kspace)),
+        ),
+        ("header".to_string(), header_object(&header, &xml)),
+    ]
+    .into())
+}
+
+#[cfg(test)]
+mod test {
+    use foray_formats::ismrmrd::Limit;
+
+    use super::*;
+
+    #[test]
+    fn header_fields() {
+        let header = Header {
+            encoded_matrix: [256, 128, 1],
+            encoded_fov: [600.0, 300.0, 6.0],
+            trajectory: "cartesian".to_string(),
+            limits: [(
+                "kspace_encoding_step_1".to_string(),
+                Limit {
+                    minimum: 0,
+                    maximum: 127,
+                    center: 64,
+                },
+            )]
+            .into(),
+            field_strength: Some(3.0),
+            te: vec![2.0, 4.0],
+            ..Default::default()
+        };
+        let PortData::Object(object) = header_object(&header, "<ismrmrdHeader/>") else {
+            panic!("header should be an object")
+        };
+        assert_eq!(object["encoded_matrix"], integer_array(&[256, 128, 1]));
+        assert_eq!(object["te"], float_array(&[2.0, 4.0]));
+        assert_eq!(
+            object["limits"],
+            PortData::Object(
+                [(
+                    "kspace_encoding_step_1".to_string(),
+                    integer_array(&[0, 127, 64])
+                )]
+                .into()
+            )
+        );
+        assert_eq!(object["xml"], PortData::String("<ismrmrdHeader/>".into()));
+        // The number of channels isn't in the header
+        assert!(!object.contains_key("receiver_channels"));
+        let PortType::Object(fields) = header_type() else {
+            unreachable!()
+        };
+        assert!(object.keys().all(|name| fields.contains_key(name)));
+    }
+}
@@ -0,0 +1,78 @@
+//! Nodes that read and write MATLAB's `.mat` files, a port for each variable
+
+use foray_data_model::node::{Dict, NodeError, PortData, PortType, UIParameter};
+use foray_formats::mat;
+use foray_graph::graph::{ForayNodeError, PortName};
+use foray_native::Parameters;
+
+use super::{
+    array::ArrayInputs,
+    files::{file_error, file_path, parse_names},
+};
+
+/// Comma separated names of the variables written to a file, each is an input
+fn variable_names(parameters: Parameters) -> Vec<String> {
+    parse_names(parameters.string("variables").unwrap_or_default())
+}
+
+pub fn write_mat_parameters() -> Dict<String, UIParameter> {
+    [
+        ("path", UIParameter::FilePicker(String::new())),
+        ("variables", UIParameter::TextInput("a".to_string())),
+        ("compressed", UIParameter::CheckBox(true)),
+    ]
+    .map(|(name, parameter)| (name.to_string(), parameter))
+    .into()
+}
+
+/// Names of the variables in the selected file, or none if it can't be read.
+/// Called off the UI thread, by [`super::RustNodeTemplate::configure_from_files`]
+pub fn mat_names(parameters: Parameters) -> Vec<String> {
+    file_path(parameters)
+        .ok()
+        .and_then(|path| mat::names(&path).ok())
+        .unwrap_or_default()
+}
+
+/// An output for each variable in the selected file
+pub fn read_mat_outputs(names: &[String]) -> Dict<PortName, PortType> {
+    names
+        .iter()
+        .map(|name| (name.clone(), PortType::Any))
+        .collect()
+}
+
+pub fn write_mat_inputs(parameters: Parameters) -> Dict<PortName, PortType> {
+    variable_names(parameters)
+        .into_iter()
+        .map(|name| (name, PortType::Any))
+        .collect()
+}
+
+pub fn read_mat_node(parameters: Parameters) -> Result<Dict<String, PortData>, ForayNodeError> {
+    let path = file_path(parameters)?;
+    Ok(mat::load(&path)
+        .map_err(file_error(&path))?
+        .into_iter()
+        .collect())
+}
+
+/// Write any data, arrays, values, strings and objects, as variables
+pub fn write_mat_node(
+    inputs: &ArrayInputs,
+    parameters: Parameters,
+) -> Result<Dict<String, PortData>, ForayNodeError> {
+    let path = file_path(parameters)?;
+    let names = variable_names(parameters);
+    let variables = names
+        .iter()
+        .map(|name| match inputs.get(name) {
+            Some(data) => Ok((name.as_str(), &**data)),
+            None => Err(ForayNodeError::NodeError(NodeError::Input(format!(
+                "{name} is not connected"
+            )))),
+        })
+        .collect::<Result<Vec<_>, ForayNodeError>>()?;
+    mat::save(&path, &variables, parameters.boolean("compressed")?).map_err(file_error(&path))?;
+    Ok([].into())
+}
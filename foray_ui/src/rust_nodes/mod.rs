@@ -1,9 +1,22 @@
+pub mod array;
+pub mod dicom;
+pub mod expression;
+pub mod fft;
+mod files;
+pub mod ismrmrd;
+pub mod mat;
+pub mod nifti;
+pub mod numpy;
+
 use derive_more::Display;
 use foray_data_model::{
-    node::{Dict, PortData, PortType},
+    node::{Dict, PortData, PortType, UIParameter},
     WireDataContainer, WireDataReference,
 };
-use foray_graph::graph::{ForayNodeError, GraphNode};
+use foray_formats::dicom::Series;
+use foray_graph::graph::{ForayNodeError, PortName};
+use foray_native::Parameters;
+use rustfft::FftDirection;
 use serde::{Deserialize, Serialize};
 use strum::{EnumIter, VariantNames};
 
@@ -13,6 +26,47 @@ use strum::{EnumIter, VariantNames};
 pub enum RustNodeTemplate {
     Display,
     DisplaySeries,
+    Slice,
+    Transpose,
+    Reshape,
+    Squeeze,
+    #[display("Expand Dims")]
+    ExpandDims,
+    Concatenate,
+    Stack,
+    Flip,
+    Roll,
+    #[display("FFT")]
+    Fft,
+    #[display("IFFT")]
+    Ifft,
+    /// Keeps the variables of the last expression that could be parsed, so that its inputs stay
+    /// connected while the expression is edited
+    #[display("Expression")]
+    Expression(Vec<PortName>),
+    #[display("Read NIfTI")]
+    ReadNifti,
+    #[display("Write NIfTI")]
+    WriteNifti,
+    /// Keeps the series found in the selected directory, which is scanned when it changes
+    #[display("Read DICOM")]
+    ReadDicom(Vec<Series>),
+    #[display("Read ISMRMRD")]
+    ReadIsmrmrd,
+    #[display("Read npy")]
+    ReadNpy,
+    #[display("Write npy")]
+    WriteNpy,
+    /// Keeps the names of the arrays in the selected archive, its outputs
+    #[display("Read npz")]
+    ReadNpz(Vec<String>),
+    #[display("Write npz")]
+    WriteNpz,
+    /// Keeps the names of the variables in the selected file, its outputs
+    #[display("Read MAT")]
+    ReadMat(Vec<String>),
+    #[display("Write MAT")]
+    WriteMat,
     // Identity,
     // Constant(f64),
     // Add,
@@ -69,11 +123,120 @@ pub enum RustNodeTemplate {
 // }
 
 impl RustNodeTemplate {
+    /// Parameters shown in the side bar, with their default values
+    pub fn parameters(&self) -> Dict<String, UIParameter> {
+        let text_input = |name: &str, default: &str| {
+            (
+                name.to_string(),
+                UIParameter::TextInput(default.to_string()),
+            )
+        };
+        let number_field =
+            |name: &str, default: f64| (name.to_string(), UIParameter::NumberField(default));
+        match self {
+            RustNodeTemplate::Display | RustNodeTemplate::DisplaySeries => Dict::new(),
+            RustNodeTemplate::Slice => [text_input("slices", ":")].into(),
+            RustNodeTemplate::Transpose | RustNodeTemplate::Squeeze | RustNodeTemplate::Flip => {
+                [text_input("axes", "")].into()
+            }
+            RustNodeTemplate::Reshape => [text_input("shape", "-1")].into(),
+            RustNodeTemplate::ExpandDims => [number_field("axis", 0.)].into(),
+            RustNodeTemplate::Concatenate | RustNodeTemplate::Stack => {
+                [number_field("inputs", 2.), number_field("axis", 0.)].into()
+            }
+            RustNodeTemplate::Roll => [number_field("shift", 1.), number_field("axis", 0.)].into(),
+            RustNodeTemplate::Fft | RustNodeTemplate::Ifft => [
+                text_input("axes", ""),
+                ("centered".to_string(), UIParameter::CheckBox(true)),
+                (
+                    "normalization".to_string(),
+                    UIParameter::Dropdown(
+                        ["backward", "ortho", "forward"].map(String::from).into(),
+                        "backward".to_string(),
+                    ),
+                ),
+            ]
+            .into(),
+            RustNodeTemplate::Expression(_) => [text_input("expression", "a")].into(),
+            RustNodeTemplate::ReadNifti
+            | RustNodeTemplate::WriteNifti
+            | RustNodeTemplate::WriteNpy
+            | RustNodeTemplate::ReadNpz(_)
+            | RustNodeTemplate::ReadMat(_) => {
+                [("path".to_string(), UIParameter::FilePicker(String::new()))].into()
+            }
+            RustNodeTemplate::ReadDicom(series) => dicom::dicom_parameters(series),
+            RustNodeTemplate::ReadIsmrmrd => ismrmrd::ismrmrd_parameters(),
+            RustNodeTemplate::ReadNpy => numpy::read_npy_parameters(),
+            RustNodeTemplate::WriteNpz => numpy::write_npz_parameters(),
+            RustNodeTemplate::WriteMat => mat::write_mat_parameters(),
+        }
+    }
+
+    /// The template for the node's current parameters, for nodes that keep what they read from
+    /// their parameters
+    pub fn configure(&self, parameters: Parameters) -> Self {
+        match self {
+            RustNodeTemplate::Expression(_) => match expression::expression_variables(parameters) {
+                Some(variables) => RustNodeTemplate::Expression(variables),
+                None => self.clone(),
+            },
+            _ => self.clone(),
+        }
+    }
+
+    /// Whether the node keeps what it reads from the files its parameters select, which is too
+    /// slow for the UI thread
+    pub fn reads_files(&self) -> bool {
+        matches!(
+            self,
+            RustNodeTemplate::ReadDicom(_)
+                | RustNodeTemplate::ReadNpz(_)
+                | RustNodeTemplate::ReadMat(_)
+        )
+    }
+
+    /// The template for the files its current parameters select, for nodes that
+    /// [read files](Self::reads_files)
+    pub fn configure_from_files(&self, parameters: Parameters) -> Self {
+        match self {
+            RustNodeTemplate::ReadDicom(_) => {
+                RustNodeTemplate::ReadDicom(dicom::scan_directory(parameters))
+            }
+            RustNodeTemplate::ReadNpz(_) => RustNodeTemplate::ReadNpz(numpy::npz_names(parameters)),
+            RustNodeTemplate::ReadMat(_) => RustNodeTemplate::ReadMat(mat::mat_names(parameters)),
+            _ => self.clone(),
+        }
+    }
+
     fn fallible_compute(
         &mut self,
-        _inputs: Dict<String, WireDataReference<PortData>>,
+        inputs: Dict<String, WireDataReference<PortData>>,
+        parameters: Parameters,
     ) -> Result<Dict<String, PortData>, ForayNodeError> {
         Ok(match self {
+            RustNodeTemplate::Slice => array::slice_node(&inputs, parameters)?,
+            RustNodeTemplate::Transpose => array::transpose_node(&inputs, parameters)?,
+            RustNodeTemplate::Reshape => array::reshape_node(&inputs, parameters)?,
+            RustNodeTemplate::Squeeze => array::squeeze_node(&inputs, parameters)?,
+            RustNodeTemplate::ExpandDims => array::expand_dims_node(&inputs, parameters)?,
+            RustNodeTemplate::Concatenate => array::concatenate_node(&inputs, parameters)?,
+            RustNodeTemplate::Stack => array::stack_node(&inputs, parameters)?,
+            RustNodeTemplate::Flip => array::flip_node(&inputs, parameters)?,
+            RustNodeTemplate::Roll => array::roll_node(&inputs, parameters)?,
+            RustNodeTemplate::Fft => fft::fft_node(&inputs, parameters, FftDirection::Forward)?,
+            RustNodeTemplate::Ifft => fft::fft_node(&inputs, parameters, FftDirection::Inverse)?,
+            RustNodeTemplate::Expression(_) => expression::expression_node(&inputs, parameters)?,
+            RustNodeTemplate::ReadNifti => nifti::read_nifti_node(parameters)?,
+            RustNodeTemplate::WriteNifti => nifti::write_nifti_node(&inputs, parameters)?,
+            RustNodeTemplate::ReadDicom(series) => dicom::read_dicom_node(series, parameters)?,
+            RustNodeTemplate::ReadIsmrmrd => ismrmrd::read_ismrmrd_node(parameters)?,
+            RustNodeTemplate::ReadNpy => numpy::read_npy_node(parameters)?,
+            RustNodeTemplate::WriteNpy => numpy::write_npy_node(&inputs, parameters)?,
+            RustNodeTemplate::ReadNpz(_) => numpy::read_npz_node(parameters)?,
+            RustNodeTemplate::WriteNpz => numpy::write_npz_node(&inputs, parameters)?,
+            RustNodeTemplate::ReadMat(_) => mat::read_mat_node(parameters)?,
+            RustNodeTemplate::WriteMat => mat::write_mat_node(&inputs, parameters)?,
             // RustNodeTemplate::Identity => [(
             //     "out".to_string(),
             //     (**inputs
@@ -127,8 +290,10 @@ impl RustNodeTemplate {
 //     }
 // }
 
-impl GraphNode<PortType, PortData> for RustNodeTemplate {
-    fn inputs(&self) -> Dict<String, PortType> {
+/// Rust nodes are configured by the parameters of their node instance, so they implement the
+/// methods of [`foray_graph::graph::GraphNode`] with an extra `parameters` argument
+impl RustNodeTemplate {
+    pub fn inputs(&self, parameters: Parameters) -> Dict<PortName, PortType> {
         let prim_float = PortType::Float;
         // let binary_in = [
         //     ("a".to_string(), prim_float.clone()),
@@ -146,6 +311,37 @@ impl GraphNode<PortType, PortData> for RustNodeTemplate {
                 ("d".to_string(), prim_float.clone()),
             ]
             .into(),
+            RustNodeTemplate::Concatenate | RustNodeTemplate::Stack => {
+                array::combined_inputs(parameters)
+            }
+            RustNodeTemplate::Expression(variables) => expression::expression_inputs(variables),
+            RustNodeTemplate::ReadNifti
+            | RustNodeTemplate::ReadDicom(_)
+            | RustNodeTemplate::ReadIsmrmrd
+            | RustNodeTemplate::ReadNpy
+            | RustNodeTemplate::ReadNpz(_)
+            | RustNodeTemplate::ReadMat(_) => [].into(),
+            RustNodeTemplate::WriteNpz => numpy::write_npz_inputs(parameters),
+            RustNodeTemplate::WriteMat => mat::write_mat_inputs(parameters),
+            RustNodeTemplate::WriteNifti => [
+                ("a".to_string(), PortType::Any),
+                ("header".to_string(), nifti::header_type()),
+                (
+                    "affine".to_string(),
+                    PortType::Array(Box::new(PortType::Float), vec![Some(4), Some(4)]),
+                ),
+            ]
+            .into(),
+            RustNodeTemplate::Slice
+            | RustNodeTemplate::Transpose
+            | RustNodeTemplate::Reshape
+            | RustNodeTemplate::Squeeze
+            | RustNodeTemplate::ExpandDims
+            | RustNodeTemplate::Flip
+            | RustNodeTemplate::Roll
+            | RustNodeTemplate::WriteNpy
+            | RustNodeTemplate::Fft
+            | RustNodeTemplate::Ifft => [("a".to_string(), PortType::Any)].into(),
             // RustNodeTemplate::Identity => [("a".to_string(), prim_float)].into(),
             // RustNodeTemplate::Constant(_constant_node) => [].into(),
             // RustNodeTemplate::Add => binary_in,
@@ -166,17 +362,54 @@ impl GraphNode<PortType, PortData> for RustNodeTemplate {
         }
     }
 
-    fn outputs(&self) -> Dict<String, PortType> {
+    /// Outputs of array nodes have the element type of their input, and the shape of the result,
+    /// as far as the parameters and the types of the connected `inputs` determine them
+    pub fn outputs(
+        &self,
+        parameters: Parameters,
+        inputs: &Dict<PortName, PortType>,
+    ) -> Dict<PortName, PortType> {
+        let a = inputs.get("a").unwrap_or(&PortType::Any);
+        let out = |port_type| [("out".to_string(), port_type)].into();
         match self {
             Self::Display => [].into(),
             Self::DisplaySeries => [].into(),
+            Self::Slice => out(array::slice_type(parameters, a)),
+            Self::Transpose => out(array::transpose_type(parameters, a)),
+            Self::Reshape => out(array::reshape_type(parameters, a)),
+            Self::Squeeze => out(array::squeeze_type(parameters, a)),
+            Self::ExpandDims => out(array::expand_dims_type(parameters, a)),
+            Self::Flip | Self::Roll => out(array::same_shape_type(a)),
+            Self::Concatenate => out(array::combined_type(parameters, inputs, false)),
+            Self::Stack => out(array::combined_type(parameters, inputs, true)),
+            Self::Expression(_) | Self::ReadNpy => out(PortType::Any),
+            Self::Fft | Self::Ifft => out(fft::fft_type(a)),
+            Self::ReadNifti => [
+                ("out".to_string(), PortType::Any),
+                ("header".to_string(), nifti::header_type()),
+            ]
+            .into(),
+            Self::WriteNifti | Self::WriteNpy | Self::WriteNpz | Self::WriteMat => [].into(),
+            Self::ReadNpz(names) => numpy::read_npz_outputs(names),
+            Self::ReadMat(names) => mat::read_mat_outputs(names),
+            Self::ReadDicom(_) => [
+                ("out".to_string(), PortType::Any),
+                ("tags".to_string(), dicom::tags_type()),
+            ]
+            .into(),
+            Self::ReadIsmrmrd => [
+                ("out".to_string(), ismrmrd::kspace_type()),
+                ("header".to_string(), ismrmrd::header_type()),
+            ]
+            .into(),
         }
     }
 
-    fn compute(
+    pub fn compute(
         mut self,
-        inputs: Dict<String, WireDataContainer<PortData>>,
-    ) -> Result<Dict<String, PortData>, ForayNodeError> {
+        inputs: Dict<PortName, WireDataContainer<PortData>>,
+        parameters: Parameters,
+    ) -> Result<Dict<PortName, PortData>, ForayNodeError> {
         // unpack mutex
 
         // match &mut self.template {
@@ -185,7 +418,7 @@ impl GraphNode<PortType, PortData> for RustNodeTemplate {
             .keys()
             .map(|k| (k.clone(), inputs[k].read().unwrap()))
             .collect();
-        self.fallible_compute(data)
+        self.fallible_compute(data, parameters)
         // }
         // UINodeTemplate::PyNode(py_node) => match py_node.clone().compute(inputs) {
         //     Ok((calculated_ports, new_py_node)) => Ok((
@@ -0,0 +1,238 @@
+//! Nodes that read and write NIfTI images, `.nii` or gzipped `.nii.gz`.
+//! The header is passed along as an object, so an image can be processed and written back
+//! with the datatype and orientation it was read with
+
+use foray_data_model::node::{Dict, ForayArray, NodeError, PortData, PortType};
+use foray_formats::nifti::{self, datatype, Header};
+use foray_graph::graph::ForayNodeError;
+use foray_native::Parameters;
+use ndarray::Array2;
+
+use super::{
+    array::{input_array, ArrayInputs},
+    files::{file_error, file_path, float_array},
+};
+
+/// Type of the header object, fields that are missing when writing keep their defaults
+pub fn header_type() -> PortType {
+    let vector = |len| PortType::Array(Box::new(PortType::Float), vec![len]);
+    PortType::Object(
+        [
+            ("version", PortType::Integer),
+            ("datatype", PortType::String),
+            ("spacing", vector(None)),
+            (
+                "affine",
+                PortType::Array(Box::new(PortType::Float), vec![Some(4), Some(4)]),
+            ),
+            ("pixdim", vector(Some(8))),
+            ("scl_slope", PortType::Float),
+            ("scl_inter", PortType::Float),
+            ("xyzt_units", PortType::Integer),
+            ("description", PortType::String),
+            ("qform_code", PortType::Integer),
+            ("sform_code", PortType::Integer),
+            ("quatern", vector(Some(3))),
+            ("qoffset", vector(Some(3))),
+        ]
+        .map(|(name, port_type)| (name.to_string(), port_type))
+        .into(),
+    )
+}
+
+/// Header fields as an object, with the affine that maps voxel indices to world coordinates
+pub fn header_object(header: &Header) -> PortData {
+    let affine = Array2::from_shape_fn((4, 4), |(i, j)| header.affine()[i][j]);
+    PortData::Object(
+        [
+            ("version", PortData::Integer(header.version.into())),
+            (
+                "datatype",
+                PortData::String(datatype::name(header.datatype).unwrap_or_default().into()),
+            ),
+            ("spacing", float_array(&header.spacing())),
+            (
+                "affine",
+                PortData::Array(ForayArray::Float(affine.into_dyn())),
+            ),
+            ("pixdim", float_array(&header.pixdim)),
+            ("scl_slope", PortData::Float(header.scl_slope)),
+            ("scl_inter", PortData::Float(header.scl_inter)),
+            ("xyzt_units", PortData::Integer(header.xyzt_units.into())),
+            ("description", PortData::String(header.description.clone())),
+            ("qform_code", PortData::Integer(header.qform_code.into())),
+            ("sform_code", PortData::Integer(header.sform_code.into())),
+            ("quatern", float_array(&header.quatern)),
+            ("qoffset", float_array(&header.qoffset)),
+        ]
+        .map(|(name, data)| (name.to_string(), data))
+        .into(),
+    )
+}
+
+fn header_error(field: &str, expected: &str) -> ForayNodeError {
+    ForayNodeError::NodeError(NodeError::Input(format!(
+        "header field {field} should be {expected}"
+    )))
+}
+
+/// Elements of a float array field that has `N` elements
+fn floats<const N: usize>(field: &str, data: &PortData) -> Result<[f64; N], ForayNodeError> {
+    let expected = || header_error(field, &format!("an array of {N} numbers"));
+    match data {
+        PortData::Array(ForayArray::Float(a)) => a.iter().copied().collect::<Vec<_>>(),
+        PortData::Array(ForayArray::Integer(a)) => a.iter().map(|v| *v as f64).collect(),
+        _ => return Err(expected()),
+    }
+    .try_into()
+    .map_err(|_| expected())
+}
+
+fn affine(field: &str, data: &PortData) -> Result<[[f64; 4]; 4], ForayNodeError> {
+    let values = floats::<16>(field, data)?;
+    Ok([0, 1, 2, 3].map(|row| values[row * 4..row * 4 + 4].try_into().expect("4 elements")))
+}
+
+/// Header described by `object`. Fields that are missing keep the defaults of [`Header::new`],
+/// and the datatype is chosen by the array when it's missing.
+/// `spacing` is only informative, the voxel sizes are read from `pixdim`
+pub fn object_header(object: &Dict<String, PortData>) -> Result<Header, ForayNodeError> {
+    let mut header = Header::new(&[], datatype::UNKNOWN);
+    for (field, data) in object {
+        let integer = || match data {
+            PortData::Integer(v) => Ok(*v),
+            PortData::Float(v) if v.fract() == 0.0 => Ok(*v as i32),
+            _ => Err(header_error(field, "an integer")),
+        };
+        let float = || match data {
+            PortData::Float(v) => Ok(*v),
+            PortData::Integer(v) => Ok(*v as f64),
+            _ => Err(header_error(field, "a number")),
+        };
+        match field.as_str() {
+            "version" => header.version = integer()?.clamp(1, 2) as u8,
+            "datatype" => {
+                header.datatype = match data {
+                    PortData::String(name) if name.is_empty() => datatype::UNKNOWN,
+                    PortData::String(name) => datatype::from_name(name)
+                        .ok_or_else(|| header_error(field, "a NIfTI datatype like int16"))?,
+                    _ => return Err(header_error(field, "a string")),
+                }
+            }
+            "affine" => {
+                let [x, y, z, _] = affine(field, data)?;
+                header.srow = [x, y, z];
+            }
+            "pixdim" => header.pixdim = floats(field, data)?,
+            "scl_slope" => header.scl_slope = float()?,
+            "scl_inter" => header.scl_inter = float()?,
+            "xyzt_units" => header.xyzt_units = integer()? as u8,
+            "description" => match data {
+                PortData::String(description) => header.description = description.clone(),
+                _ => return Err(header_error(field, "a string")),
+            },
+            "qform_code" => header.qform_code = integer()? as i16,
+            "sform_code" => header.sform_code = integer()? as i16,
+            "quatern" => header.quatern = floats(field, data)?,
+            "qoffset" => header.qoffset = floats(field, data)?,
+            "spacing" => {}
+            _ => return Err(header_error(field, "a NIfTI header field")),
+        }
+    }
+    Ok(header)
+}
+
+pub fn read_nifti_node(parameters: Parameters) -> Result<Dict<String, PortData>, ForayNodeError> {
+    let path = file_path(parameters)?;
+    let (header, image) = nifti::load(&path).map_err(file_error(&path))?;
+    Ok([
+        ("out".to_string(), PortData::Array(image)),
+        ("header".to_string(), header_object(&header)),
+    ]
+    .into())
+}
+
+/// Write the array `a`, with the optional `header` object.
+/// A connected `affine` replaces the header's orientation
+pub fn write_nifti_node(
+    inputs: &ArrayInputs,
+    parameters: Parameters,
+) -> Result<Dict<String, PortData>, ForayNodeError> {
+    let path = file_path(parameters)?;
+    let image = input_array(inputs, "a")?;
+    let mut header = match inputs.get("header").map(|data| &**data) {
+        Some(PortData::Object(object)) => object_header(object)?,
+        Some(_) => return Err(header_error("header", "an object")),
+        None => Header::new(&[], datatype::UNKNOWN),
+    };
+    if let Some(data) = inputs.get("affine") {
+        header.set_affine(affine("affine", data)?);
+    }
+    nifti::save(&path, &header, image).map_err(file_error(&path))?;
+    Ok([].into())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn header_round_trip() {
+        let mut header = Header::new(&[3, 4, 5], datatype::INT16);
+        header.description = "t1".to_string();
+        header.scl_slope = 2.0;
+        header.qform_code = 1;
+        header.quatern = [0.0, 0.0, 1.0];
+        header.set_affine([
+            [0.0, 2.0, 0.0, -10.0],
+            [-3.0, 0.0, 0.0, 5.0],
+            [0.0, 0.0, 1.5, 0.0],
+            [0.0, 0.0, 0.0, 1.0],
+        ]);
+        let PortData::Object(object) = header_object(&header) else {
+            panic!("header should be an object")
+        };
+        assert_eq!(PortType::from(&PortData::Object(object.clone())), {
+            let PortType::Object(mut fields) = header_type() else {
+                unreachable!()
+            };
+            fields.insert(
+                "spacing".to_string(),
+                PortType::Array(Box::new(PortType::Float), vec![Some(3)]),
+            );
+            PortType::Object(fields)
+        });
+        assert_eq!(object["spacing"], float_array(&[3.0, 2.0, 1.5]));
+        // The shape is taken from the written array
+        assert_eq!(
+            object_header(&object).unwrap(),
+            Header {
+                shape: vec![],
+                ..header
+            }
+        );
+    }
+
+    #[test]
+    fn invalid_headers() {
+        let object = |field: &str, data| [(field.to_string(), data)].into();
+        assert_eq!(
+            object_header(&object("datatype", PortData::String("float16".into()))),
+            Err(header_error("datatype", "a NIfTI datatype like int16"))
+        );
+        assert_eq!(
+            object_header(&object("quatern", float_array(&[1.0]))),
+            Err(header_error("quatern", "an array of 3 numbers"))
+        );
+        assert_eq!(
+            object_header(&object("dim", PortData::Integer(3))),
+            Err(header_error("dim", "a NIfTI header field"))
+        );
+        assert_eq!(
+            object_header(&object("datatype", PortData::String(String::new())))
+                .unwrap()
+                .datatype,
+            datatype::UNKNOWN
+        );
+    }
+}
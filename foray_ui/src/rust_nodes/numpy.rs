@@ -0,0 +1,135 @@
+//! Nodes that read and write numpy's `.npy` files, and `.npz` archives of them.
+//! `.npy` files are memory mapped, so a slice of an array that doesn't fit in memory can be read.
+//! Only the node's `slices` avoid loading the whole array: its output is a regular array, so
+//! nodes downstream, like the slice node, get everything the read node selected
+
+use foray_data_model::node::{Dict, PortData, PortType, UIParameter};
+use foray_formats::{npy, npz};
+use foray_graph::graph::{ForayNodeError, PortName};
+use foray_native::Parameters;
+
+use super::{
+    array::{compute_error, input_array, parse_slices, slice_view, ArrayInputs},
+    files::{file_error, file_path, parse_names},
+};
+
+/// Comma separated names of the arrays written to an archive, each is an input
+fn array_names(parameters: Parameters) -> Vec<String> {
+    parse_names(parameters.string("arrays").unwrap_or_default())
+}
+
+pub fn read_npy_parameters() -> Dict<String, UIParameter> {
+    [
+        ("path", UIParameter::FilePicker(String::new())),
+        ("slices", UIParameter::TextInput(String::new())),
+        (
+            "slices note",
+            UIParameter::TextDisplay(
+                "Only the slices are read from the file, without them the whole array is loaded. \
+                 Slicing downstream doesn't read less"
+                    .to_string(),
+            ),
+        ),
+    ]
+    .map(|(name, parameter)| (name.to_string(), parameter))
+    .into()
+}
+
+pub fn write_npz_parameters() -> Dict<String, UIParameter> {
+    [
+        ("path", UIParameter::FilePicker(String::new())),
+        ("arrays", UIParameter::TextInput("a".to_string())),
+        ("compressed", UIParameter::CheckBox(false)),
+    ]
+    .map(|(name, parameter)| (name.to_string(), parameter))
+    .into()
+}
+
+/// Names of the arrays in the selected archive, none if it can't be read.
+/// Reading the archive is slow, so it's done in the background when the path changes
+pub fn npz_names(parameters: Parameters) -> Vec<String> {
+    file_path(parameters)
+        .ok()
+        .and_then(|path| npz::names(&path).ok())
+        .unwrap_or_default()
+}
+
+/// An output for each array in the selected archive
+pub fn read_npz_outputs(names: &[String]) -> Dict<PortName, PortType> {
+    names
+        .iter()
+        .map(|name| (name.clone(), PortType::Any))
+        .collect()
+}
+
+pub fn write_npz_inputs(parameters: Parameters) -> Dict<PortName, PortType> {
+    array_names(parameters)
+        .into_iter()
+        .map(|name| (name, PortType::Any))
+        .collect()
+}
+
+/// Read the elements selected by `slices`, numpy style like the slice node, from the mapped file
+pub fn read_npy_node(parameters: Parameters) -> Result<Dict<String, PortData>, ForayNodeError> {
+    let path = file_path(parameters)?;
+    let mapped = npy::map(&path).map_err(file_error(&path))?;
+    let specs = parse_slices(parameters.string("slices")?)?;
+    let ndim = mapped.header.shape.len();
+    if specs.len() > ndim {
+        return Err(compute_error(format!(
+            "{} slices given for an array with {ndim} dimensions",
+            specs.len()
+        )));
+    }
+    // The last axis of the bytes is the bytes of each element, which is never sliced
+    let bytes = slice_view(mapped.bytes(), &specs)?;
+    let array = npy::decode_view(mapped.header.dtype, bytes).map_err(file_error(&path))?;
+    Ok([("out".to_string(), PortData::Array(array))].into())
+}
+
+pub fn write_npy_node(
+    inputs: &ArrayInputs,
+    parameters: Parameters,
+) -> Result<Dict<String, PortData>, ForayNodeError> {
+    let path = file_path(parameters)?;
+    npy::save(&path, input_array(inputs, "a")?).map_err(file_error(&path))?;
+    Ok([].into())
+}
+
+pub fn read_npz_node(parameters: Parameters) -> Result<Dict<String, PortData>, ForayNodeError> {
+    let path = file_path(parameters)?;
+    Ok(npz::load(&path)
+        .map_err(file_error(&path))?
+        .into_iter()
+        .map(|(name, array)| (name, PortData::Array(array)))
+        .collect())
+}
+
+pub fn write_npz_node(
+    inputs: &ArrayInputs,
+    parameters: Parameters,
+) -> Result<Dict<String, PortData>, ForayNodeError> {
+    let path = file_path(parameters)?;
+    let names = array_names(parameters);
+    let arrays = names
+        .iter()
+        .map(|name| Ok((name.as_str(), input_array(inputs, name)?)))
+        .collect::<Result<Vec<_>, ForayNodeError>>()?;
+    npz::save(&path, &arrays, parameters.boolean("compressed")?).map_err(file_error(&path))?;
+    Ok([].into())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn unique_array_names() {
+        let parameters = [(
+            "arrays".to_string(),
+            PortData::String(" a, b,,a , c".into()),
+        )]
+        .into();
+        assert_eq!(array_names(Parameters(&parameters)), ["a", "b", "c"]);
+    }
+}
@@ -11,6 +11,9 @@ pub struct UserData {
     most_recent_network_file: Option<PathBuf>,
     /// Expanded folder path when selecting new node
     new_node_path: Vec<String>,
+    /// Directory the data is saved in, the user's cache directory if not set
+    #[serde(skip)]
+    dir: Option<PathBuf>,
 }
 
 /// Getters and Setters, are used to serialize to
@@ -33,32 +36,41 @@ impl UserData {
     }
 }
 impl UserData {
-    fn user_data_dir() -> PathBuf {
+    fn user_data_dir(&self) -> PathBuf {
+        if let Some(dir) = &self.dir {
+            return dir.clone();
+        }
         let binding = directories::ProjectDirs::from("", "", "gpi")
             .expect("application configuration folder is accessible");
         binding.cache_dir().to_path_buf()
     }
-    fn user_data_file() -> PathBuf {
-        Self::user_data_dir().join("user_data.ron")
+    fn user_data_file(&self) -> PathBuf {
+        self.user_data_dir().join("user_data.ron")
     }
-    pub fn read_user_data() -> Self {
-        let user_data_file = Self::user_data_file();
+    /// Read the user data saved in `dir`, or in the user's cache directory
+    pub fn read_user_data(dir: Option<PathBuf>) -> Self {
+        let default = UserData {
+            dir,
+            ..Default::default()
+        };
+        let user_data_file = default.user_data_file();
         match read_to_string(&user_data_file).map(|s| ron::from_str::<UserData>(&s)) {
             Ok(Ok(c)) => {
                 info!("Loaded UserData: {user_data_file:?}");
-                c
+                UserData {
+                    dir: default.dir,
+                    ..c
+                }
             }
             Ok(Err(e)) => {
                 error!("Error reading user data {user_data_file:?}, using default. \n{e}");
-                let new_data = UserData::default();
-                new_data.write();
-                new_data
+                default.write();
+                default
             }
             Err(e) => {
                 warn!("Could not read user data file {user_data_file:?}, using default. \n{e}");
-                let new_data = UserData::default();
-                new_data.write();
-                new_data
+                default.write();
+                default
             }
         }
     }
@@ -75,8 +87,8 @@ impl UserData {
     }
 
     fn write(&self) {
-        let user_data_file = Self::user_data_file();
-        let _ = std::fs::create_dir_all(Self::user_data_dir());
+        let user_data_file = self.user_data_file();
+        let _ = std::fs::create_dir_all(self.user_data_dir());
         std::fs::write(
             &user_data_file,
             ron::to_string(&self)
@@ -0,0 +1,408 @@
+//! Creating and updating a workspace's python virtual environment, with `uv` when it's
+//! available, otherwise with `venv` and `pip`
+use std::{
+    env,
+    fmt::Display,
+    io::{BufRead, BufReader, Read},
+    path::{Path, PathBuf},
+    process::{Command, Stdio},
+    sync::mpsc,
+    thread,
+};
+
+use iced::{futures::channel::mpsc as async_mpsc, Task};
+use log::info;
+
+use crate::python_env::PythonVersion;
+
+/// Directory of a workspace's virtual environment, unless it's configured
+pub const VENV_DIR: &str = ".venv";
+
+/// Tool used to create and update environments
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Installer {
+    /// `uv`, which keeps the environment in sync with `pyproject.toml` and `uv.lock`
+    Uv(PathBuf),
+    /// `python3 -m venv` and `pip`
+    Pip,
+}
+
+impl Installer {
+    /// `uv` if it's on `PATH`, otherwise `venv` and `pip`
+    pub fn detect() -> Self {
+        env::var_os("PATH")
+            .iter()
+            .flat_map(env::split_paths)
+            .map(|dir| dir.join(format!("uv{}", env::consts::EXE_SUFFIX)))
+            .find(|uv| uv.is_file())
+            .map_or(Installer::Pip, Installer::Uv)
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Installer::Uv(_) => "uv",
+            Installer::Pip => "pip",
+        }
+    }
+}
+
+/// A command run to create or update an environment
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct EnvCommand {
+    pub program: PathBuf,
+    pub args: Vec<String>,
+}
+
+impl EnvCommand {
+    fn new(program: impl Into<PathBuf>, args: &[&str]) -> Self {
+        EnvCommand {
+            program: program.into(),
+            args: args.iter().map(|arg| arg.to_string()).collect(),
+        }
+    }
+}
+
+impl Display for EnvCommand {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.program.display())?;
+        self.args.iter().try_for_each(|arg| write!(f, " {arg}"))
+    }
+}
+
+/// Progress of environment commands, as they run
+#[derive(Clone, Debug)]
+pub enum EnvEvent {
+    /// A command started
+    Command(String),
+    /// A line the command printed
+    Output(String),
+    /// All commands finished, or one of them failed
+    Finished(Result<(), EnvError>),
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum EnvError {
+    /// The command could not be started, e.g. python isn't installed
+    Spawn { command: String, error: String },
+    /// The command exited unsuccessfully, `code` is `None` if it was killed by a signal
+    Failed { command: String, code: Option<i32> },
+}
+
+impl Display for EnvError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            EnvError::Spawn { command, error } => write!(f, "Could not run `{command}`: {error}"),
+            EnvError::Failed {
+                command,
+                code: Some(code),
+            } => write!(f, "`{command}` exited with code {code}"),
+            EnvError::Failed {
+                command,
+                code: None,
+            } => write!(f, "`{command}` was killed"),
+        }
+    }
+}
+
+impl std::error::Error for EnvError {}
+
+/// Python interpreter of a virtual environment
+pub fn venv_python(venv_dir: &Path) -> PathBuf {
+    match cfg!(windows) {
+        true => venv_dir.join("Scripts/python.exe"),
+        false => venv_dir.join("bin/python"),
+    }
+}
+
+/// Commands that create the workspace's environment if it doesn't exist, and install the
+/// workspace's `pyproject.toml`, if it has one, into it.
+/// The environment is created with the `python` interpreter if it's configured, and otherwise for
+/// python `version`, the embedded interpreter's, so the nodes can be loaded
+pub fn sync_commands(
+    installer: &Installer,
+    workspace_dir: &Path,
+    venv_dir: &Path,
+    python: Option<&Path>,
+    version: Option<PythonVersion>,
+) -> Vec<EnvCommand> {
+    let venv = venv_dir.to_string_lossy();
+    let has_project = workspace_dir.join("pyproject.toml").is_file();
+    let python = python.map(Path::to_string_lossy);
+    let version = version.map(|version| version.to_string());
+    let uv_python = match python.as_deref().or(version.as_deref()) {
+        Some(python) => vec!["--python", python],
+        None => vec![],
+    };
+    match installer {
+        // Creates the environment, and the lock file if there isn't one yet
+        Installer::Uv(uv) if has_project => {
+            vec![EnvCommand::new(uv, &[&["sync"], &uv_python[..]].concat())]
+        }
+        Installer::Uv(uv) => vec![EnvCommand::new(
+            uv,
+            &[&["venv", "--allow-existing", &venv], &uv_python[..]].concat(),
+        )],
+        Installer::Pip => {
+            let venv_python = venv_python(venv_dir);
+            let mut commands = vec![];
+            if !venv_python.is_file() {
+                let system_python = match (&python, &version) {
+                    (Some(python), _) => python.to_string(),
+                    _ if cfg!(windows) => "python".to_string(),
+                    (_, Some(version)) => format!("python{version}"),
+                    (_, None) => "python3".to_string(),
+                };
+                commands.push(EnvCommand::new(system_python, &["-m", "venv", &venv]));
+            }
+            if has_project {
+                commands.push(EnvCommand::new(
+                    &venv_python,
+                    &["-m", "pip", "install", "-e", "."],
+                ));
+            }
+            commands
+        }
+    }
+}
+
+/// Commands that replace the workspace's environment with one for python `version`,
+/// and reinstall the workspace's `pyproject.toml` into it
+pub fn recreate_commands(
+    installer: &Installer,
+    workspace_dir: &Path,
+    venv_dir: &Path,
+    version: PythonVersion,
+) -> Vec<EnvCommand> {
+    let venv = venv_dir.to_string_lossy();
+    let version = version.to_string();
+    let has_project = workspace_dir.join("pyproject.toml").is_file();
+    match installer {
+        // Replaces an environment of another version
+        Installer::Uv(uv) if has_project => {
+            vec![EnvCommand::new(uv, &["sync", "--python", &version])]
+        }
+        Installer::Uv(uv) => vec![EnvCommand::new(
+            uv,
+            &["venv", "--clear", "--python", &version, &venv],
+        )],
+        Installer::Pip => {
+            let system_python = match cfg!(windows) {
+                true => "python".to_string(),
+                false => format!("python{version}"),
+            };
+            let mut commands = vec![EnvCommand::new(
+                system_python,
+                &["-m", "venv", "--clear", &venv],
+            )];
+            if has_project {
+                commands.push(EnvCommand::new(
+                    venv_python(venv_dir),
+                    &["-m", "pip", "install", "-e", "."],
+                ));
+            }
+            commands
+        }
+    }
+}
+
+/// Commands that install a package into the workspace's environment.
+/// `package` is a requirement, like `numpy>=2`, or the path of a local package, like `./my_nodes`,
+/// which is installed in editable mode. With `uv` the package is also added to `pyproject.toml`,
+/// so that the environment can be recreated from it
+pub fn install_commands(
+    installer: &Installer,
+    workspace_dir: &Path,
+    venv_dir: &Path,
+    package: &str,
+) -> Vec<EnvCommand> {
+    let is_local = is_local_package(package) && workspace_dir.join(package).exists();
+    let has_project = workspace_dir.join("pyproject.toml").is_file();
+    let editable: &[&str] = match is_local {
+        true => &["--editable", package],
+        false => &[package],
+    };
+    let command =
+        |program: &Path, args: &[&str]| EnvCommand::new(program, &[args, editable].concat());
+    match installer {
+        Installer::Uv(uv) if has_project => vec![command(uv, &["add"])],
+        Installer::Uv(uv) => vec![command(
+            uv,
+            &["pip", "install", "--python", &venv_dir.to_string_lossy()],
+        )],
+        Installer::Pip => vec![command(&venv_python(venv_dir), &["-m", "pip", "install"])],
+    }
+}
+
+/// Whether `package` is written as a path, rather than a requirement that happens to name a
+/// directory in the workspace, like `numpy`
+fn is_local_package(package: &str) -> bool {
+    package.starts_with('.') || package.contains('/') || package.contains(std::path::MAIN_SEPARATOR)
+}
+
+/// Run commands in `workspace_dir` in order, stopping at the first that fails.
+/// Their output is passed to `on_event` line by line, ending with [`EnvEvent::Finished`]
+pub fn run_env_commands(
+    workspace_dir: &Path,
+    venv_dir: &Path,
+    commands: &[EnvCommand],
+    mut on_event: impl FnMut(EnvEvent),
+) -> Result<(), EnvError> {
+    let result = commands
+        .iter()
+        .try_for_each(|command| run_env_command(workspace_dir, venv_dir, command, &mut on_event));
+    on_event(EnvEvent::Finished(result.clone()));
+    result
+}
+
+fn run_env_command(
+    workspace_dir: &Path,
+    venv_dir: &Path,
+    command: &EnvCommand,
+    on_event: &mut impl FnMut(EnvEvent),
+) -> Result<(), EnvError> {
+    info!("Running {command}");
+    on_event(EnvEvent::Command(command.to_string()));
+    let spawn_error = |error: std::io::Error| EnvError::Spawn {
+        command: command.to_string(),
+        error: error.to_string(),
+    };
+    let mut child = Command::new(&command.program)
+        .args(&command.args)
+        .current_dir(workspace_dir)
+        // uv uses this environment for projects, rather than one it found elsewhere
+        .env("UV_PROJECT_ENVIRONMENT", venv_dir)
+        .stdin(Stdio::null())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .map_err(spawn_error)?;
+
+    // Lines from both pipes, in the order they were printed
+    let (sender, receiver) = mpsc::channel();
+    let readers = [
+        forward_lines(child.stdout.take(), sender.clone()),
+        forward_lines(child.stderr.take(), sender),
+    ];
+    receiver
+        .into_iter()
+        .for_each(|line| on_event(EnvEvent::Output(line)));
+    readers.into_iter().for_each(|reader| {
+        let _ = reader.join();
+    });
+
+    let status = child.wait().map_err(spawn_error)?;
+    match status.success() {
+        true => Ok(()),
+        false => Err(EnvError::Failed {
+            command: command.to_string(),
+            code: status.code(),
+        }),
+    }
+}
+
+fn forward_lines(
+    pipe: Option<impl Read + Send + 'static>,
+    sender: mpsc::Sender<String>,
+) -> thread::JoinHandle<()> {
+    thread::spawn(move || {
+        let Some(pipe) = pipe else {
+            return;
+        };
+        for line in BufReader::new(pipe).lines().map_while(Result::ok) {
+            if sender.send(line).is_err() {
+                return;
+            }
+        }
+    })
+}
+
+/// Run commands on another thread, producing a message for each of their events
+pub fn env_commands_task<M: Send + 'static>(
+    workspace_dir: PathBuf,
+    venv_dir: PathBuf,
+    commands: Vec<EnvCommand>,
+    message: fn(EnvEvent) -> M,
+) -> Task<M> {
+    let (sender, receiver) = async_mpsc::unbounded();
+    thread::spawn(move || {
+        let _ = run_env_commands(&workspace_dir, &venv_dir, &commands, |event| {
+            let _ = sender.unbounded_send(event);
+        });
+    });
+    Task::run(receiver, message)
+}
+
+/// Run commands from the command line, printing their output
+pub fn print_env_commands(
+    workspace_dir: &Path,
+    venv_dir: &Path,
+    commands: &[EnvCommand],
+) -> Result<(), EnvError> {
+    run_env_commands(workspace_dir, venv_dir, commands, |event| match event {
+        EnvEvent::Command(command) => println!("$ {command}"),
+        EnvEvent::Output(line) => println!("{line}"),
+        EnvEvent::Finished(_) => {}
+    })
+}
+
+#[cfg(all(test, unix))]
+mod test {
+    use super::*;
+
+    #[test]
+    fn commands() {
+        let workspace = Path::new("/nonexistent/workspace");
+        let venv = &workspace.join(VENV_DIR);
+        assert_eq!(
+            sync_commands(&Installer::Pip, workspace, venv, None, None)
+                .iter()
+                .map(|c| c.to_string())
+                .collect::<Vec<_>>(),
+            vec!["python3 -m venv /nonexistent/workspace/.venv"]
+        );
+        let version = PythonVersion {
+            major: 3,
+            minor: 13,
+        };
+        assert_eq!(
+            sync_commands(&Installer::Pip, workspace, venv, None, Some(version))[0].to_string(),
+            "python3.13 -m venv /nonexistent/workspace/.venv"
+        );
+        assert_eq!(
+            sync_commands(
+                &Installer::Uv("uv".into()),
+                workspace,
+                venv,
+                None,
+                Some(version)
+            )[0]
+            .to_string(),
+            "uv venv --allow-existing /nonexistent/workspace/.venv --python 3.13"
+        );
+        let python = Some(Path::new("/opt/python3.12/bin/python3"));
+        assert_eq!(
+            sync_commands(&Installer::Pip, workspace, venv, python, Some(version))[0].to_string(),
+            "/opt/python3.12/bin/python3 -m venv /nonexistent/workspace/.venv"
+        );
+        assert_eq!(
+            sync_commands(&Installer::Uv("uv".into()), workspace, venv, python, Some(version))[0].to_string(),
+            "uv venv --allow-existing /nonexistent/workspace/.venv --python /opt/python3.12/bin/python3"
+        );
+        assert_eq!(
+            install_commands(&Installer::Uv("uv".into()), workspace, venv, "numpy>=2")[0]
+                .to_string(),
+            "uv pip install --python /nonexistent/workspace/.venv numpy>=2"
+        );
+        assert_eq!(
+            install_commands(&Installer::Pip, workspace, venv, "numpy")[0].to_string(),
+            "/nonexistent/workspace/.venv/bin/python -m pip install numpy"
+        );
+        assert!(is_local_package("./my_nodes"));
+        assert!(is_local_package("nodes/my_nodes"));
+        assert!(!is_local_package("numpy"));
+        assert_eq!(
+            recreate_commands(&Installer::Pip, workspace, venv, version)[0].to_string(),
+            "python3.13 -m venv --clear /nonexistent/workspace/.venv"
+        );
+    }
+}
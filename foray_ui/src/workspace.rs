@@ -1,42 +1,68 @@
-use crate::app::file_dialog;
+use crate::app::{directory_dialog, file_dialog};
+use crate::config::{Config, ConfigError};
 use crate::file_watch::make_file_watch_sub;
 use crate::interface::add_node::add_node_tree_panel;
+use crate::interface::code_editor::CodeEditor;
+use crate::interface::environment::EnvironmentLog;
 use crate::interface::node_canvas::camera::Camera;
 use crate::interface::node_canvas::node_canvas;
+use crate::interface::node_help::node_help_pane;
 use crate::interface::{side_bar::side_bar, SEPERATOR};
 use crate::math::{Point, Vector};
-use crate::network::Network;
+use crate::network::{invalid_ports, Network};
 use crate::node_instance::visualization_parameters::VisualizationParameters;
 use crate::node_instance::visualiztion::{NDimVis, Visualization};
 use crate::node_instance::{ForayNodeInstance, ForayNodeTemplate, NodeStatus};
-use crate::project::{read_python_projects, rust_project, Project};
-use crate::python_env;
+use crate::project::{
+    command_projects, native_projects, plugin_projects, read_python_projects, rust_project, Project,
+};
+use crate::python_env::{self, PythonEnvError};
 use crate::rust_nodes::RustNodeTemplate;
 use crate::style::theme::AppTheme;
 use crate::user_data::UserData;
+use crate::venv::{
+    env_commands_task, install_commands, sync_commands, EnvCommand, EnvEvent, Installer,
+};
 
-use foray_data_model::node::{Dict, PortData};
+use foray_command::node::{is_command_file, load_command};
+use foray_data_model::context::{Cancellation, ComputeContext, Progress};
+use foray_data_model::node::{Dict, NodeError, PortData, UIParameter};
 use foray_data_model::WireDataContainer;
 use foray_data_vis::series_vis::SeriesVisOptions;
-use foray_graph::graph::{ForayNodeError, Graph, PortRef, IO};
+use foray_graph::graph::{ForayNodeError, Graph, GraphNode, PortRef, IO};
+use foray_plugin::node::{is_plugin_file, load_plugin, PluginNodeTemplate};
 
+use foray_py::discover::DiscoverError;
 use foray_py::err::PyNodeConfigError;
-use foray_py::py_node::{PyConfig, PyNodeTemplate};
+use foray_py::py_node::{py_migrate, PyConfig, PyNodeTemplate};
+use foray_py::reload::changed_node_modules;
+use foray_py::reproducer::write_reproducer;
 use iced::event::listen_with;
+use iced::futures::channel::mpsc;
+use iced::futures::{stream, StreamExt};
 use iced::keyboard::key::Named;
 use iced::keyboard::{Event::KeyPressed, Key, Modifiers};
-use iced::widget::{container, mouse_area, row, rule, space, stack, text};
+use iced::widget::{
+    button, column, container, markdown, mouse_area, row, rule, space, stack, text, text_editor,
+};
 use iced::Event::Keyboard;
 use iced::Length::Fill;
 use iced::{mouse, window, Element, Renderer, Subscription, Task, Theme};
 use itertools::Itertools;
 use log::{error, info, trace, warn};
+use std::collections::{HashMap, VecDeque};
+use std::convert::identity;
 use std::fs::{self, read_to_string};
 use std::iter::once;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 use std::sync::Arc;
 use std::time::{Duration, Instant};
 
+/// Directory, relative to the workspace, that node reproducers are saved into
+pub const REPRODUCER_DIR: &str = "reproducers";
+/// How long typing has to pause before edited code is compiled
+const CODE_EDIT_DEBOUNCE: Duration = Duration::from_millis(400);
+
 #[derive(Default, Clone, PartialEq)]
 pub enum Action {
     #[default]
@@ -53,19 +79,39 @@ pub enum Action {
 
 pub struct Workspace {
     pub workspace_dir: PathBuf,
+    /// User configuration, layered with the workspace's `foray.toml`
+    pub config: Config,
 
     /// Node, Wire and Shape data that is executed, and saved to disk
     pub network: Network,
 
     /// List of all known Node types, including system and user nodes
     pub projects: Vec<Project>,
+    /// Node packages that could not be loaded
+    pub broken_packages: Vec<DiscoverError>,
+    /// Directories of python nodes that aren't installed as packages
+    pub node_dirs: Vec<PathBuf>,
+    /// Parsed markdown documentation of each python node, by python path
+    pub node_docs: HashMap<String, Vec<markdown::Item>>,
 
     pub main_window_id: Option<window::Id>,
     pub user_data: UserData,
     //// UI
     pub action: Action,
     pub cursor_position: Point,
-    running_node_task_handles: Dict<u32, iced::task::Handle>,
+    pub add_node_search: String,
+    pub show_node_help: bool,
+    /// Migrations applied to nodes when they were reloaded, shown until dismissed
+    pub migration_summary: Vec<String>,
+    /// Editor of the selected node's code parameter, if it has one
+    pub code_editor: Option<CodeEditor>,
+    /// Output of the last environment sync or package install
+    pub environment_log: EnvironmentLog,
+    /// Package to install, typed in the side bar
+    pub install_package: String,
+    running_node_task_handles: Dict<u32, (iced::task::Handle, Cancellation)>,
+    /// Nodes waiting for a running node to finish, when the number of concurrent computes is limited
+    waiting_nodes: VecDeque<u32>,
 }
 
 #[derive(Clone, Debug)]
@@ -83,17 +129,30 @@ pub enum WorkspaceMessage {
     OnCanvasDown(Option<u32>),
     OnCanvasUp,
     OpenAddNodeUi,
+    AddNodeSearch(String),
     AddNode(ForayNodeTemplate),
     SelectNodeGroup(Vec<String>),
+    ToggleNodeHelp,
+    DismissMigrationSummary,
+    OpenLink(markdown::Uri),
 
     UpdateNodeTemplate(u32, ForayNodeTemplate),
     UpdateNodeParameter(u32, String, PortData),
+    EditCode(u32, String, text_editor::Action),
+    /// Configure a node in the background, if these are still its parameters
+    ConfigureNode(u32, Dict<String, PortData>),
+    /// Template of a node, configured in the background for these parameters
+    NodeConfigured(u32, Dict<String, PortData>, ForayNodeTemplate),
     UpdateVisualization(u32, VisualizationParameters),
     SeriesVisUpdate(u32, SeriesVisOptions),
     StartWidgetFilePicker(u32, String),
+    StartWidgetDirectoryPicker(u32, String),
     DeleteSelectedNodes,
+    SaveReproducer(u32),
 
     QueueCompute(u32),
+    CancelCompute(u32),
+    ComputeProgress(u32, Progress),
     ComputeComplete(u32, Result<Dict<String, PortData>, ForayNodeError>),
     ComputeAll,
 
@@ -105,6 +164,13 @@ pub enum WorkspaceMessage {
     StartSaveNetwork,
     EndSaveNetwork(Option<PathBuf>),
     ReloadNodes,
+    /// Reload only the nodes affected by changes to these python files
+    ReloadChangedFiles(Vec<PathBuf>),
+    /// Create or update the python environment from the workspace's `pyproject.toml`
+    SyncEnvironment,
+    InstallPackageInput(String),
+    InstallPackage,
+    EnvironmentEvent(EnvEvent),
     ResizeWindow(window::Id, iced::Size),
 
     Cancel,
@@ -123,6 +189,7 @@ impl Workspace {
         match message {
             WorkspaceMessage::OnMove(_) => {}
             WorkspaceMessage::AnimationTick => {}
+            WorkspaceMessage::ComputeProgress(..) => {}
             _ => info!("---Message--- {message:?} {:?}", Instant::now()),
         }
         match message {
@@ -226,7 +293,23 @@ impl Workspace {
                     self.action = self
                         .network
                         .select_node(nx, modifiers, self.cursor_position);
-                    return Task::done(WorkspaceMessage::QueueCompute(nx));
+                    // Copies start with default parameters, which their template may not be
+                    // configured for yet
+                    let copies = match modifiers.command() {
+                        true => self.network.selected_shapes.clone(),
+                        false => Default::default(),
+                    };
+                    let configure_copies = copies.into_iter().filter_map(|copy| {
+                        let node = self.network.graph.get_node(copy);
+                        node.template.configures_in_background().then(|| {
+                            let parameters = node.parameters_values.clone();
+                            Task::done(WorkspaceMessage::ConfigureNode(copy, parameters))
+                        })
+                    });
+                    return Task::batch(
+                        once(Task::done(WorkspaceMessage::QueueCompute(nx)))
+                            .chain(configure_copies),
+                    );
                 } else
                 //// Clicked on the canvas background
                 {
@@ -261,9 +344,40 @@ impl Workspace {
             WorkspaceMessage::UpdateNodeParameter(id, name, updated_widget) => {
                 //TODO: move into Network
                 self.network.stash_state();
-                let old_template = &mut self.network.graph.get_mut_node(id);
-                old_template.parameters_values.insert(name, updated_widget);
-                return Task::done(WorkspaceMessage::QueueCompute(id));
+                self.network.set_parameter(id, name, updated_widget);
+                return self.configure_and_compute(id);
+            }
+            WorkspaceMessage::EditCode(id, name, action) => {
+                let code = self
+                    .code_editor
+                    .as_mut()
+                    .filter(|editor| editor.node == id && editor.parameter == name)
+                    .and_then(|editor| editor.perform(action));
+                if let Some(code) = code {
+                    self.network.stash_state();
+                    self.network.set_parameter(id, name, PortData::String(code));
+                    // Only configure the node once typing pauses, if the code is still the same
+                    let parameters = self.network.graph.get_node(id).parameters_values.clone();
+                    return Task::perform(tokio::time::sleep(CODE_EDIT_DEBOUNCE), move |_| {
+                        WorkspaceMessage::ConfigureNode(id, parameters.clone())
+                    });
+                }
+            }
+            WorkspaceMessage::ConfigureNode(id, parameters) => {
+                if self.network.graph.nodes_ref().contains(&id)
+                    && self.network.graph.get_node(id).parameters_values == parameters
+                {
+                    return self.configure_and_compute(id);
+                }
+            }
+            WorkspaceMessage::NodeConfigured(id, parameters, template) => {
+                // If the parameters changed in the meantime, a newer configuration is on its way
+                if self.network.graph.nodes_ref().contains(&id)
+                    && self.network.graph.get_node(id).parameters_values == parameters
+                {
+                    self.network.set_configured_template(id, template);
+                    return Task::done(WorkspaceMessage::QueueCompute(id));
+                }
             }
             WorkspaceMessage::UpdateVisualization(id, visualization_parameters) => {
                 self.network.stash_state();
@@ -302,7 +416,32 @@ impl Workspace {
                     }
                 })
             }
-            WorkspaceMessage::OpenAddNodeUi => self.action = Action::AddingNode,
+            WorkspaceMessage::StartWidgetDirectoryPicker(id, param_name) => {
+                return Task::perform(
+                    directory_dialog(self.workspace_dir.clone()),
+                    move |maybe_path| {
+                        if let Some(path) = maybe_path {
+                            WorkspaceMessage::UpdateNodeParameter(
+                                id,
+                                param_name.clone(),
+                                PortData::String(path.to_string_lossy().to_string()),
+                            )
+                        } else {
+                            WorkspaceMessage::Cancel
+                        }
+                    },
+                )
+            }
+            WorkspaceMessage::OpenAddNodeUi => {
+                self.add_node_search.clear();
+                self.action = Action::AddingNode
+            }
+            WorkspaceMessage::AddNodeSearch(search) => self.add_node_search = search,
+            WorkspaceMessage::ToggleNodeHelp => self.show_node_help = !self.show_node_help,
+            WorkspaceMessage::DismissMigrationSummary => self.migration_summary.clear(),
+            WorkspaceMessage::OpenLink(link) => {
+                let _ = open::that_in_background(&link);
+            }
             WorkspaceMessage::SelectNodeGroup(selected_tree_path) => match &self.action {
                 Action::AddingNode => {
                     let current_path = self.user_data.get_new_node_path();
@@ -322,6 +461,7 @@ impl Workspace {
                 //TODO: move into Network
                 self.network.stash_state();
                 let id = self.network.graph.node(template.into());
+                self.network.update_dynamic_ports(id);
                 self.network.selected_shapes = [id].into();
 
                 let initial_position = self
@@ -343,18 +483,59 @@ impl Workspace {
                 //TODO: move into Network
                 if !self.network.selected_shapes.is_empty() {
                     self.network.stash_state();
+                    let mut aborted = 0;
                     self.network.selected_shapes.iter().for_each(|id| {
                         info!("Deleting node {id}");
-                        if let Some(handle) = self.running_node_task_handles.get(id) {
+                        if let Some((handle, cancellation)) =
+                            self.running_node_task_handles.remove(id)
+                        {
                             info!("Aborting compute task for {id}");
+                            cancellation.cancel();
                             handle.abort();
+                            aborted += 1;
                         }
                         self.network.graph.delete_node(*id);
                         self.network.shapes.shape_positions.swap_remove(id);
                     });
                     self.network.selected_shapes = [].into();
+                    self.network.update_input_types();
 
-                    return Task::none();
+                    // Aborted nodes don't complete, start nodes waiting for them instead
+                    let waiting: Vec<_> = (0..aborted).map(|_| self.queue_waiting_node()).collect();
+                    return Task::batch(waiting);
+                }
+            }
+            WorkspaceMessage::SaveReproducer(nx) => {
+                let node = self.network.graph.get_node(nx);
+                if let ForayNodeTemplate::PyNode(py_node) = &node.template {
+                    let inputs = self
+                        .network
+                        .graph
+                        .get_input_data(&nx)
+                        .into_iter()
+                        .map(|(k, v)| (k, v.read().unwrap().clone()))
+                        .collect();
+                    let error = match &node.status {
+                        NodeStatus::Error(errors) => {
+                            Some(errors.iter().map(|e| e.to_string()).join("\n\n"))
+                        }
+                        _ => None,
+                    };
+                    match write_reproducer(
+                        &self.workspace_dir.join(REPRODUCER_DIR),
+                        py_node,
+                        inputs,
+                        node.parameters_values.clone(),
+                        error,
+                    ) {
+                        Ok(dir) => {
+                            info!("Saved reproducer for {} #{nx} to {dir:?}", py_node.name);
+                            let _ = open::that_in_background(dir);
+                        }
+                        Err(e) => {
+                            error!("Could not save reproducer for {} #{nx}\n{e}", py_node.name)
+                        }
+                    }
                 }
             }
 
@@ -375,7 +556,7 @@ impl Workspace {
                     }
                 }
                 self.network = Network::default();
-                self.reload_nodes();
+                self.reload_nodes(None);
             }
             WorkspaceMessage::StartLoadNetwork => {
                 if self.network.unsaved_changes {
@@ -408,7 +589,7 @@ impl Workspace {
 
                             self.network.file = Some(file.clone());
                             self.user_data.set_recent_network_file(Some(file));
-                            self.reload_nodes();
+                            self.reload_nodes(None);
                             return Task::done(WorkspaceMessage::ComputeAll);
                         }
                         Err(err) => {
@@ -452,9 +633,81 @@ impl Workspace {
                 }
             }
             WorkspaceMessage::ReloadNodes => {
-                self.reload_nodes();
+                self.reload_nodes(None);
                 return Task::done(WorkspaceMessage::ComputeAll);
             }
+            WorkspaceMessage::SyncEnvironment => {
+                let commands = sync_commands(
+                    &Installer::detect(),
+                    &self.workspace_dir,
+                    &self.venv_dir(),
+                    self.config.python(&self.workspace_dir).as_deref(),
+                    python_env::embedded_version(),
+                );
+                return self.run_env_commands(commands);
+            }
+            WorkspaceMessage::InstallPackageInput(package) => self.install_package = package,
+            WorkspaceMessage::InstallPackage => {
+                let package = self.install_package.trim();
+                if !package.is_empty() {
+                    let commands = install_commands(
+                        &Installer::detect(),
+                        &self.workspace_dir,
+                        &self.venv_dir(),
+                        package,
+                    );
+                    return self.run_env_commands(commands);
+                }
+            }
+            WorkspaceMessage::EnvironmentEvent(event) => {
+                let succeeded = matches!(event, EnvEvent::Finished(Ok(())));
+                self.environment_log.update(event);
+                if succeeded {
+                    // List the nodes of newly installed packages
+                    python_env::update_sys_path(&self.venv_dir());
+                    self.install_package.clear();
+                    return Task::done(WorkspaceMessage::ReloadNodes);
+                }
+            }
+            WorkspaceMessage::ReloadChangedFiles(paths) => {
+                let (libraries, paths): (Vec<_>, Vec<_>) =
+                    paths.into_iter().partition(|path| is_plugin_file(path));
+                let reloaded_plugins = self.reload_plugins(&libraries);
+                let (specs, paths): (Vec<_>, Vec<_>) =
+                    paths.into_iter().partition(|path| is_command_file(path));
+                let reloaded_commands = self.reload_commands(&specs);
+
+                let node_py_paths: Vec<String> = self
+                    .projects
+                    .iter()
+                    .flat_map(|project| project.node_tree.leaves())
+                    .filter_map(|(_, template)| match template {
+                        ForayNodeTemplate::PyNode(py_node) => Some(py_node.py_path.clone()),
+                        ForayNodeTemplate::RustNode(_)
+                        | ForayNodeTemplate::Plugin(_)
+                        | ForayNodeTemplate::Native(_)
+                        | ForayNodeTemplate::PySnippet(_)
+                        | ForayNodeTemplate::Command(_) => None,
+                    })
+                    .collect();
+                let changed = changed_node_modules(&paths, &node_py_paths);
+                info!("Reloading changed nodes: {changed:?}");
+
+                let mut reloaded = self.reload_nodes(Some(changed.as_slice()));
+                reloaded.extend(reloaded_plugins);
+                reloaded.extend(reloaded_commands);
+                // Nodes downstream of another reloaded node are computed when it completes
+                let descendants: Vec<u32> = reloaded
+                    .iter()
+                    .flat_map(|nx| self.network.graph.descendants(nx))
+                    .collect();
+                return Task::batch(
+                    reloaded
+                        .into_iter()
+                        .filter(|nx| !descendants.contains(nx))
+                        .map(|nx| Task::done(WorkspaceMessage::QueueCompute(nx))),
+                );
+            }
             WorkspaceMessage::ResizeWindow(id, size) => {
                 if Some(id) == self.main_window_id {
                     self.network.shapes.camera.bounds_size = size;
@@ -507,9 +760,10 @@ impl Workspace {
                     // Check if in an error state
                     if let NodeStatus::Error(e) = &node.status {
                         if e.iter().any(|e| match e {
-                            ForayNodeError::PyNodeConifgError(PyNodeConfigError::Runtime(_)) => {
-                                false
-                            }
+                            ForayNodeError::PyNodeConifgError(PyNodeConfigError::Runtime(_))
+                            | ForayNodeError::NodeError(NodeError::Compute(_))
+                            | ForayNodeError::Plugin(_)
+                            | ForayNodeError::Command(_) => false,
                             _ => true,
                         }) {
                             warn!(
@@ -525,9 +779,22 @@ impl Workspace {
                         self.network.queued_nodes.insert(nx);
                         return Task::none();
                     };
+                    // Wait for a running node to finish
+                    if self
+                        .config
+                        .executor
+                        .max_concurrent_nodes
+                        .is_some_and(|max| self.running_node_task_handles.len() >= max)
+                    {
+                        if !self.waiting_nodes.contains(&nx) {
+                            self.waiting_nodes.push_back(nx);
+                        }
+                        return Task::none();
+                    }
 
                     node.status = NodeStatus::Running {
                         start: Instant::now(),
+                        progress: None,
                     };
                     trace!("Beginning compute: {:?} #{nx}", node.template,);
                 }
@@ -536,25 +803,76 @@ impl Workspace {
                     nx: u32,
                     node: ForayNodeInstance,
                     input_guarded: Dict<String, WireDataContainer<PortData>>,
+                    context: ComputeContext,
                 ) -> (u32, Result<Dict<String, PortData>, ForayNodeError>) {
-                    let results = Graph::async_compute(nx, node.clone(), input_guarded).await;
+                    // Compute on a blocking thread, so that progress updates
+                    // can be delivered while the node is still running
+                    let results = tokio::task::spawn_blocking(move || {
+                        context.scope(|| Graph::compute_node(nx, node, input_guarded))
+                    })
+                    .await
+                    .unwrap_or_else(|e| {
+                        let message = format!("Compute thread failed: {e}");
+                        (
+                            nx,
+                            Err(ForayNodeError::NodeError(NodeError::Compute(message))),
+                        )
+                    });
                     // Give the async runtime something to catch on if the task is cancelled
                     tokio::time::sleep(Duration::from_nanos(1)).await;
                     results
                 }
                 //// Queue compute
+                let (progress_sender, progress_receiver) = mpsc::unbounded();
+                let cancellation = Cancellation::default();
+                let context = ComputeContext {
+                    progress: Some(Arc::new(move |progress| {
+                        let _ = progress_sender.unbounded_send(progress);
+                    })),
+                    cancellation: Some(cancellation.clone()),
+                };
                 let node = self.network.graph.get_node(nx);
-                let (task, handle) = Task::perform(
-                    abortable_compute(nx, node.clone(), self.network.graph.get_input_data(&nx)),
-                    move |(nx, res)| WorkspaceMessage::ComputeComplete(nx, res),
+                let compute = abortable_compute(
+                    nx,
+                    node.clone(),
+                    self.network.graph.get_input_data(&nx),
+                    context,
+                );
+                let (task, handle) = Task::run(
+                    stream::select(
+                        progress_receiver
+                            .map(move |progress| WorkspaceMessage::ComputeProgress(nx, progress)),
+                        stream::once(compute)
+                            .map(|(nx, res)| WorkspaceMessage::ComputeComplete(nx, res)),
+                    ),
+                    identity,
                 )
                 .abortable();
-                self.running_node_task_handles.insert(nx, handle);
+                self.running_node_task_handles
+                    .insert(nx, (handle, cancellation));
                 return task;
             }
+            WorkspaceMessage::CancelCompute(nx) => {
+                // The node stops at its next check, and completes with an error
+                if let Some((_, cancellation)) = self.running_node_task_handles.get(&nx) {
+                    info!("Cancelling compute of #{nx}");
+                    cancellation.cancel();
+                }
+            }
+            WorkspaceMessage::ComputeProgress(nx, new_progress) => {
+                // The node may have been deleted since the update was sent
+                if self.network.graph.nodes_ref().contains(&nx) {
+                    if let NodeStatus::Running { progress, .. } =
+                        &mut self.network.graph.get_mut_node(nx).status
+                    {
+                        *progress = Some(new_progress);
+                    }
+                }
+            }
             WorkspaceMessage::ComputeComplete(nx, result) => {
                 // Remove handle from list of running tasks
                 self.running_node_task_handles.remove(&nx);
+                let waiting = self.queue_waiting_node();
 
                 //TODO: move into Network
                 let node = self.network.graph.get_node(nx);
@@ -563,9 +881,9 @@ impl Workspace {
                         // Assert that status is what is expected
                         let run_time = match &node.status {
                             NodeStatus::Idle { .. } => panic!("Node should not be idle here!"),
-                            NodeStatus::Running { start: start_inst } => {
-                                Instant::now() - *start_inst
-                            }
+                            NodeStatus::Running {
+                                start: start_inst, ..
+                            } => Instant::now() - *start_inst,
                             NodeStatus::Error(py_node_error) => panic!(
                                 "Node should not be in an error state here!{py_node_error:?}"
                             ),
@@ -579,6 +897,9 @@ impl Workspace {
                         // Grab values early to satisfy borrow checker
                         //
                         let parameters_values = node.parameters_values.clone();
+                        let last_reloaded = node.last_reloaded;
+                        // Connections may have changed while the node was computing
+                        let input_types = self.network.graph.get_node(nx).input_types.clone();
 
                         // We *don't* update template here for some nodes
                         // because that causes stuttery behaviour for
@@ -587,7 +908,10 @@ impl Workspace {
                         // might address this, and may be necessary in the future.
                         // similar to TODO: below
                         let template = match node.template {
-                            ForayNodeTemplate::PyNode(_) => {
+                            ForayNodeTemplate::PyNode(_)
+                            | ForayNodeTemplate::PySnippet(_)
+                            | ForayNodeTemplate::Plugin(_)
+                            | ForayNodeTemplate::Command(_) => {
                                 self.network.graph.get_node(nx).template.clone()
                             }
                             _ => node.template.clone(),
@@ -633,8 +957,13 @@ impl Workspace {
                                         Default::default(),
                                     )),
                                 },
+                                _ => None,
                             },
-                            ForayNodeTemplate::PyNode(_pt) => None,
+                            ForayNodeTemplate::PyNode(_)
+                            | ForayNodeTemplate::Plugin(_)
+                            | ForayNodeTemplate::Native(_)
+                            | ForayNodeTemplate::PySnippet(_)
+                            | ForayNodeTemplate::Command(_) => None,
                         };
 
                         //// Update node
@@ -646,6 +975,8 @@ impl Workspace {
                                 },
                                 parameters_values,
                                 visualization,
+                                last_reloaded,
+                                input_types,
                                 // run_time: Some(run_time),
                                 template,
                             },
@@ -670,7 +1001,8 @@ impl Workspace {
                         return Task::batch(
                             to_queue
                                 .into_iter()
-                                .map(|node| Task::done(WorkspaceMessage::QueueCompute(node))),
+                                .map(|node| Task::done(WorkspaceMessage::QueueCompute(node)))
+                                .chain([waiting]),
                         );
                     }
                     Err(node_error) => {
@@ -686,7 +1018,7 @@ impl Workspace {
                         //// Update wire
                         self.network.graph.clear_outputs(nx);
 
-                        return Task::none();
+                        return waiting;
                     }
                 };
             }
@@ -717,7 +1049,10 @@ impl Workspace {
             row![
                 iced::widget::opaque(side_bar(self)),
                 rule::vertical(SEPERATOR),
-                space::horizontal()
+                container(iced::widget::opaque(self.migration_summary_view()))
+                    .align_right(Fill)
+                    .padding(8),
+                self.node_help_view(app_theme),
             ],
         ];
         //match self.show_palette_ui {
@@ -741,7 +1076,9 @@ impl Workspace {
                 container(
                     mouse_area(add_node_tree_panel(
                         &self.projects,
-                        self.user_data.get_new_node_path()
+                        &self.broken_packages,
+                        self.user_data.get_new_node_path(),
+                        &self.add_node_search,
                     ))
                     .interaction(mouse::Interaction::Idle) //.on_press(Message::NOP)
                 )
@@ -775,12 +1112,19 @@ impl Workspace {
 
 #[derive(Debug)]
 pub enum WorkspaceError {
+    /// The user or workspace config file is invalid
+    Config(ConfigError),
     NoVenv,
+    /// The environment doesn't match the embedded python
+    Python(PythonEnvError),
 }
 
 impl Workspace {
-    pub fn is_valid_workspace(workspace_dir: &PathBuf) -> bool {
-        workspace_dir.join(".venv").is_dir()
+    /// Whether the workspace has a python environment, which depends on its config
+    pub fn is_valid_workspace(workspace_dir: &Path) -> Result<bool, ConfigError> {
+        Ok(Config::load(workspace_dir)?
+            .venv_dir(workspace_dir)
+            .is_dir())
     }
 
     pub fn new(
@@ -788,11 +1132,14 @@ impl Workspace {
         network_path: Option<PathBuf>,
         main_window_id: Option<window::Id>,
     ) -> Result<Self, WorkspaceError> {
-        if !Self::is_valid_workspace(&workspace_dir) {
+        let config = Config::load(&workspace_dir).map_err(WorkspaceError::Config)?;
+        let venv_dir = config.venv_dir(&workspace_dir);
+        if !venv_dir.is_dir() {
             return Err(WorkspaceError::NoVenv);
         };
+        python_env::preflight(&venv_dir).map_err(WorkspaceError::Python)?;
 
-        let network = match network_path {
+        let network = match network_path.or_else(|| config.default_network(&workspace_dir)) {
             Some(np) => match Network::load_network(&np) {
                 Ok(n) => n,
                 Err(err) => {
@@ -802,12 +1149,11 @@ impl Workspace {
                     Network::default()
                 }
             },
-            //// If no network provided, get the most recent network
             None => Network::default(),
         };
-        let venv_dir = workspace_dir.join(".venv");
         python_env::setup_python(venv_dir);
-        let projects = read_python_projects();
+        let node_dirs = config.node_dirs(&workspace_dir);
+        let (projects, broken_packages) = read_python_projects(&node_dirs, &HashMap::new());
         trace!(
             "Configured Python Projects: {:?}",
             projects
@@ -816,89 +1162,288 @@ impl Workspace {
                 .collect::<Vec<_>>()
         );
 
+        let user_data = UserData::read_user_data(config.cache_dir(&workspace_dir));
         let mut workspace = Self {
             workspace_dir,
+            config,
             network,
             projects,
-            user_data: UserData::read_user_data(),
+            broken_packages,
+            node_dirs,
+            node_docs: Default::default(),
+            user_data,
             main_window_id,
             action: Default::default(),
             cursor_position: Default::default(),
+            add_node_search: Default::default(),
+            show_node_help: false,
+            migration_summary: vec![],
+            code_editor: None,
+            environment_log: Default::default(),
+            install_package: String::new(),
             running_node_task_handles: Default::default(),
+            waiting_nodes: Default::default(),
         };
-        workspace.reload_nodes();
+        workspace.reload_nodes(None);
         Ok(workspace)
     }
 
+    /// Keep the code editor on the code parameter of the selected node, rebuilding it when the
+    /// selection changes, or the code was changed by something other than the editor
+    pub fn sync_code_editor(&mut self) {
+        let editor = self.code_editor.take();
+        self.code_editor = self.network.selected_shapes.iter().next().and_then(|nx| {
+            let node = self.network.graph.get_node(*nx);
+            let (parameter, _) = node
+                .template
+                .parameters()
+                .into_iter()
+                .find(|(_, widget)| matches!(widget, UIParameter::CodeEditor(_)))?;
+            let Some(PortData::String(code)) = node.parameters_values.get(&parameter) else {
+                return None;
+            };
+            match editor {
+                Some(editor)
+                    if editor.node == *nx
+                        && editor.parameter == parameter
+                        && editor.code == *code =>
+                {
+                    Some(editor)
+                }
+                _ => Some(CodeEditor::new(*nx, parameter, code.clone())),
+            }
+        });
+    }
+
+    /// Summary of what migration was applied to each node, since it was last dismissed
+    fn migration_summary_view(&self) -> Element<'_, WorkspaceMessage> {
+        if self.migration_summary.is_empty() {
+            return space::horizontal().width(0).into();
+        }
+        container(
+            column![
+                text("Migrated nodes to their latest version").size(14),
+                column(
+                    self.migration_summary
+                        .iter()
+                        .map(|summary| text(summary.clone()).size(10).into())
+                )
+                .spacing(2),
+                button(text("dismiss"))
+                    .style(button::secondary)
+                    .padding([1, 4])
+                    .on_press(WorkspaceMessage::DismissMigrationSummary),
+            ]
+            .spacing(4),
+        )
+        .padding(6)
+        .max_width(400)
+        .style(container::rounded_box)
+        .into()
+    }
+
+    /// Parse the markdown docs of every available python node
+    fn parse_node_docs(&self) -> HashMap<String, Vec<markdown::Item>> {
+        self.projects
+            .iter()
+            .flat_map(|project| project.node_tree.leaves())
+            .filter_map(|(_, template)| match template {
+                ForayNodeTemplate::PyNode(py_node) => Some((
+                    py_node.py_path.clone(),
+                    markdown::parse(&py_node.metadata().docs?).collect(),
+                )),
+                ForayNodeTemplate::RustNode(_)
+                | ForayNodeTemplate::Plugin(_)
+                | ForayNodeTemplate::Native(_)
+                | ForayNodeTemplate::PySnippet(_)
+                | ForayNodeTemplate::Command(_) => None,
+            })
+            .collect()
+    }
+
+    /// Help pane for the selected node, if it is open
+    fn node_help_view<'a>(&'a self, app_theme: &'a AppTheme) -> Element<'a, WorkspaceMessage> {
+        match (
+            self.show_node_help,
+            self.network.selected_shapes.iter().exactly_one(),
+        ) {
+            (true, Ok(nx)) => {
+                let node = self.network.graph.get_node(*nx);
+                let docs = match &node.template {
+                    ForayNodeTemplate::PyNode(py_node) => self.node_docs.get(&py_node.py_path),
+                    ForayNodeTemplate::RustNode(_)
+                    | ForayNodeTemplate::Plugin(_)
+                    | ForayNodeTemplate::Native(_)
+                    | ForayNodeTemplate::PySnippet(_)
+                    | ForayNodeTemplate::Command(_) => None,
+                };
+                row![
+                    rule::vertical(SEPERATOR),
+                    iced::widget::opaque(node_help_pane(node, docs, app_theme))
+                ]
+                .into()
+            }
+            _ => space::horizontal().width(0).into(),
+        }
+    }
+
     pub fn get_and_create_network_default_dir(&self) -> PathBuf {
-        let network_dir = self.workspace_dir.join("networks");
+        let network_dir = self.config.networks_dir(&self.workspace_dir);
 
         // Create the network directory if it doesn't exist
         let _ = fs::create_dir_all(&network_dir);
         network_dir
     }
 
-    /// Read node definitions from disk, and copies node configuration (parameters and port connections) forward.
-    /// *Does not trigger the compute function of any nodes.*
-    /// TODO: This has been edited several times as the data model has changed. This may be
-    /// able to be cleaned up significantly
-    fn reload_nodes(&mut self) {
+    /// Compute a node, after configuring its template for its parameters in the background if
+    /// that's too slow for the UI thread, like compiling a snippet
+    fn configure_and_compute(&self, nx: u32) -> Task<WorkspaceMessage> {
+        let node = self.network.graph.get_node(nx);
+        if !node.template.configures_in_background() {
+            return Task::done(WorkspaceMessage::QueueCompute(nx));
+        }
+        let template = node.template.clone();
+        let parameters = node.parameters_values.clone();
+        Task::perform(
+            tokio::task::spawn_blocking(move || {
+                let configured = template.configure(nx, &parameters);
+                (parameters, configured)
+            }),
+            move |result| match result {
+                Ok((parameters, template)) => {
+                    WorkspaceMessage::NodeConfigured(nx, parameters, template)
+                }
+                Err(e) => {
+                    error!("Configuring node #{nx} failed: {e}");
+                    WorkspaceMessage::Cancel
+                }
+            },
+        )
+    }
+
+    /// Re-import python nodes from disk, and copy node configuration (parameters and port connections) forward.
+    /// Renamed ports and parameters, and parameters of older node versions, are migrated.
+    /// If `py_paths` is given, only those nodes are reloaded, and other templates are reused.
+    /// Returns the graph nodes that were reloaded. *Does not trigger the compute function of any nodes.*
+    fn reload_nodes(&mut self, py_paths: Option<&[String]>) -> Vec<u32> {
+        let mut reloaded_nodes = vec![];
+        let mut loaded: HashMap<String, PyNodeTemplate> = match py_paths {
+            Some(py_paths) => self
+                .projects
+                .iter()
+                .flat_map(|project| project.node_tree.leaves())
+                .filter_map(|(_, template)| match template {
+                    ForayNodeTemplate::PyNode(py_node) => Some(py_node.clone()),
+                    ForayNodeTemplate::RustNode(_)
+                    | ForayNodeTemplate::Plugin(_)
+                    | ForayNodeTemplate::Native(_)
+                    | ForayNodeTemplate::PySnippet(_)
+                    | ForayNodeTemplate::Command(_) => None,
+                })
+                .filter(|py_node| !py_paths.contains(&py_node.py_path))
+                .map(|py_node| (py_node.py_path.clone(), py_node))
+                .collect(),
+            None => HashMap::new(),
+        };
+
         // Update any existing nodes in the graph that could change based on file changes
-        self.network.graph.nodes_ref().iter().for_each(|nx| {
-            let node = self.network.graph.get_node(*nx).clone();
+        for nx in self.network.graph.nodes_ref() {
+            let node = self.network.graph.get_node(nx).clone();
             if let ForayNodeTemplate::PyNode(old_py_node) = node.template {
                 let PyNodeTemplate {
-                    name: _node_name,
+                    name: node_name,
                     py_path,
                     config: old_config,
                 } = old_py_node;
+                if py_paths.is_some_and(|py_paths| !py_paths.contains(&py_path)) {
+                    continue;
+                }
 
                 let PyConfig {
                     inputs: old_inputs,
                     outputs: old_outputs,
                     parameters: _old_parameters,
+                    metadata: old_metadata,
+                    migrations: _old_migrations,
+                    dynamic: _old_dynamic,
                 } = old_config.unwrap_or_default();
 
-                //// Read new node from disk
-                let new_py_node_template = PyNodeTemplate::new(py_path);
+                //// Read new node from disk, once for all instances of the node
+                let new_py_node_template = loaded
+                    .entry(py_path.clone())
+                    .or_insert_with(|| PyNodeTemplate::new(py_path))
+                    .clone();
+                let migrations = new_py_node_template.migrations();
+                let mut migrated = vec![];
+
+                //// Carry parameter values forward
+                let mut parameters: Dict<String, PortData> = node
+                    .parameters_values
+                    .into_iter()
+                    .map(
+                        |(key, value)| match migrations.renamed_parameters.get(&key) {
+                            Some(new_key) => {
+                                migrated.push(format!("parameter {key} -> {new_key}"));
+                                (new_key.clone(), value)
+                            }
+                            None => (key, value),
+                        },
+                    )
+                    .collect();
+
+                let (old_version, new_version) = (
+                    old_metadata.version,
+                    new_py_node_template.metadata().version,
+                );
+                if new_version > old_version {
+                    match py_migrate(&new_py_node_template, old_version, parameters.clone()) {
+                        Ok(Some(migrated_parameters)) => {
+                            parameters = migrated_parameters;
+                            migrated.push(format!(
+                                "parameters migrated from version {old_version} to {new_version}"
+                            ));
+                        }
+                        Ok(None) => {}
+                        Err(e) => {
+                            warn!("Could not migrate parameters of {node_name} #{nx}\n{e}")
+                        }
+                    }
+                }
+
+                // Dynamic nodes have ports that depend on their parameter values
+                let new_py_node_template = new_py_node_template.configure(&parameters);
 
                 //// Update Ports, and Graph Edges
                 {
+                    let mut old_in_ports = old_inputs.unwrap_or_default();
+                    let mut old_out_ports = old_outputs.unwrap_or_default();
+
+                    // Move edges of renamed ports to their new name
+                    for (old_name, new_name) in &migrations.renamed_ports {
+                        for (io, old_ports) in
+                            [(IO::In, &mut old_in_ports), (IO::Out, &mut old_out_ports)]
+                        {
+                            if let Some(port_type) = old_ports.remove(old_name) {
+                                self.network.graph.rename_port(nx, io, old_name, new_name);
+                                old_ports.insert(new_name.clone(), port_type);
+                                migrated.push(format!("port {old_name} -> {new_name}"));
+                            }
+                        }
+                    }
+
+                    // Remove invalid edges from Graph
                     let new_in_ports = new_py_node_template.inputs().unwrap_or_default();
                     let new_out_ports = new_py_node_template.outputs().unwrap_or_default();
-
-                    // Get old version's ports
-                    let old_in_ports = old_inputs.unwrap_or_default();
-                    let old_out_ports = old_outputs.unwrap_or_default();
-
-                    // Find any nodes that previously existed, but now doesn't
-                    let invalid_in = old_in_ports
-                        .into_iter()
-                        .filter(|(old_name, old_type)| new_in_ports.get(old_name) != Some(old_type))
-                        .map(|(old_name, _)| PortRef {
-                            node: *nx,
-                            name: old_name,
-                            io: IO::In,
-                        });
-                    let invalid_out = old_out_ports
+                    invalid_ports(nx, IO::In, old_in_ports, &new_in_ports)
                         .into_iter()
-                        .filter(|(old_name, old_type)| {
-                            new_out_ports.get(old_name) != Some(old_type)
-                        })
-                        .map(|(old_name, _)| PortRef {
-                            node: *nx,
-                            name: old_name,
-                            io: IO::Out,
+                        .chain(invalid_ports(nx, IO::Out, old_out_ports, &new_out_ports))
+                        .for_each(|p| {
+                            warn!("Removing port {:?} from node {:?}", p.name, node_name);
+                            if self.network.graph.is_connected(&p) {
+                                migrated.push(format!("disconnected port {}", p.name));
+                            }
+                            self.network.graph.remove_edge(&p);
                         });
-
-                    // Remove invalid edges from Graph
-                    invalid_in.chain(invalid_out).for_each(|p| {
-                        warn!(
-                            "Removing port {:?} from node {:?}",
-                            p.name, new_py_node_template.name
-                        );
-                        self.network.graph.remove_edge(&p);
-                    });
                 }
 
                 let mut new_node_instance: ForayNodeInstance =
@@ -906,22 +1451,182 @@ impl Workspace {
 
                 // Copy forward status
                 new_node_instance.status = node.status;
+                if py_paths.is_some() {
+                    new_node_instance.last_reloaded = Some(Instant::now());
+                }
 
                 // Merge parameters
-                node.parameters_values.into_iter().for_each(|(key, value)| {
+                parameters.into_iter().for_each(|(key, value)| {
                     new_node_instance
                         .parameters_values
                         .entry(key)
                         .and_modify(|v| *v = value);
                 });
                 // Update Graph Node
-                self.network.graph.set_node_data(*nx, new_node_instance);
+                self.network.graph.set_node_data(nx, new_node_instance);
+
+                if !migrated.is_empty() {
+                    let summary = format!("{node_name} #{nx}: {}", migrated.join(", "));
+                    info!("Migrated {summary}");
+                    self.migration_summary.push(summary);
+                }
+                reloaded_nodes.push(nx);
             }
-        });
+        }
         // Update list of available nodes
-        let mut projects = read_python_projects();
+        let (mut projects, mut broken_packages) = read_python_projects(&self.node_dirs, &loaded);
+        let (plugins, broken_plugins) = plugin_projects(&self.node_dirs);
+        projects.extend(plugins);
+        broken_packages.extend(broken_plugins);
+        let (commands, broken_commands) = command_projects(&self.node_dirs);
+        projects.extend(commands);
+        broken_packages.extend(broken_commands);
+        projects.extend(native_projects());
         projects.push(rust_project());
         self.projects = projects;
+        self.broken_packages = broken_packages;
+        self.node_docs = self.parse_node_docs();
+        self.network.update_input_types();
+        reloaded_nodes
+    }
+
+    /// Load plugin libraries again from disk, updating the nodes in the graph they define.
+    /// *Does not trigger the compute function of any nodes.*
+    /// Returns the graph nodes that were reloaded
+    fn reload_plugins(&mut self, libraries: &[PathBuf]) -> Vec<u32> {
+        let mut reloaded_nodes = vec![];
+        let mut loaded: HashMap<PathBuf, Vec<PluginNodeTemplate>> = HashMap::new();
+        libraries
+            .iter()
+            .for_each(|library| foray_plugin::reload_library(library));
+
+        for nx in self.network.graph.nodes_ref() {
+            let node = self.network.graph.get_node(nx).clone();
+            let ForayNodeTemplate::Plugin(old_plugin) = node.template else {
+                continue;
+            };
+            if !libraries.contains(&old_plugin.library) {
+                continue;
+            }
+
+            //// Read new library from disk, once for all nodes it defines
+            let new_plugin = loaded
+                .entry(old_plugin.library.clone())
+                .or_insert_with(|| {
+                    load_plugin(&old_plugin.library).unwrap_or_else(|e| {
+                        warn!("Could not reload {:?}: {e}", old_plugin.library);
+                        vec![]
+                    })
+                })
+                .iter()
+                .find(|new_plugin| new_plugin.name == old_plugin.name)
+                .cloned();
+            // Keep the old template, computing it will report what's wrong with the library
+            let Some(new_plugin) = new_plugin else {
+                continue;
+            };
+
+            self.swap_template(nx, ForayNodeTemplate::Plugin(new_plugin));
+            reloaded_nodes.push(nx);
+        }
+        self.network.update_input_types();
+        reloaded_nodes
+    }
+
+    /// The workspace's python virtual environment
+    pub fn venv_dir(&self) -> PathBuf {
+        self.config.venv_dir(&self.workspace_dir)
+    }
+
+    /// Queue the node that has waited longest for a running node to finish
+    fn queue_waiting_node(&mut self) -> Task<WorkspaceMessage> {
+        while let Some(nx) = self.waiting_nodes.pop_front() {
+            // The node may have been deleted while it waited
+            if self.network.graph.nodes_ref().contains(&nx) {
+                return Task::done(WorkspaceMessage::QueueCompute(nx));
+            }
+        }
+        Task::none()
+    }
+
+    /// Run environment commands, unless others are still running
+    fn run_env_commands(&mut self, commands: Vec<EnvCommand>) -> Task<WorkspaceMessage> {
+        if self.environment_log.running {
+            return Task::none();
+        }
+        self.environment_log = EnvironmentLog::start();
+        env_commands_task(
+            self.workspace_dir.clone(),
+            self.venv_dir(),
+            commands,
+            WorkspaceMessage::EnvironmentEvent,
+        )
+    }
+
+    /// Read command specs again from disk, updating the nodes in the graph they declare.
+    /// *Does not trigger the compute function of any nodes.*
+    /// Returns the graph nodes that were reloaded
+    fn reload_commands(&mut self, specs: &[PathBuf]) -> Vec<u32> {
+        let mut reloaded_nodes = vec![];
+        for nx in self.network.graph.nodes_ref() {
+            let node = self.network.graph.get_node(nx).clone();
+            let ForayNodeTemplate::Command(old_command) = node.template else {
+                continue;
+            };
+            if !specs.contains(&old_command.path) {
+                continue;
+            }
+
+            // Keep the old template while the spec is invalid, e.g. half way through an edit
+            let new_command = match load_command(&old_command.path) {
+                Ok(new_command) => new_command,
+                Err(e) => {
+                    warn!("Could not reload {:?}: {e}", old_command.path);
+                    continue;
+                }
+            };
+
+            self.swap_template(nx, ForayNodeTemplate::Command(new_command));
+            reloaded_nodes.push(nx);
+        }
+        self.network.update_input_types();
+        reloaded_nodes
+    }
+
+    /// Replace the template of a node with one reloaded from disk. Edges of ports that went away
+    /// or changed type are removed, and parameters that still exist keep their values
+    fn swap_template(&mut self, nx: u32, new_template: ForayNodeTemplate) {
+        let node = self.network.graph.get_node(nx).clone();
+        let mut new_node_instance: ForayNodeInstance = new_template.into();
+        new_node_instance.status = node.status.clone();
+        new_node_instance.last_reloaded = Some(Instant::now());
+
+        // Merge parameters
+        for (key, value) in &node.parameters_values {
+            new_node_instance
+                .parameters_values
+                .entry(key.clone())
+                .and_modify(|v| *v = value.clone());
+        }
+
+        //// Remove invalid edges from Graph
+        invalid_ports(nx, IO::In, node.inputs(), &new_node_instance.inputs())
+            .into_iter()
+            .chain(invalid_ports(
+                nx,
+                IO::Out,
+                node.outputs(),
+                &new_node_instance.outputs(),
+            ))
+            .for_each(|p| {
+                warn!(
+                    "Removing port {:?} from node {:?}",
+                    p.name,
+                    node.template.name()
+                );
+                self.network.graph.remove_edge(&p);
+            });
+        self.network.graph.set_node_data(nx, new_node_instance);
     }
 
     pub fn subscriptions(&self) -> Subscription<WorkspaceMessage> {
@@ -931,8 +1636,7 @@ impl Workspace {
                 .filter(|p| !p.absolute_path.to_string_lossy().is_empty())
                 .map(|p| {
                     make_file_watch_sub(p.absolute_path.clone())
-                        //TODO: only reload nodes that changed
-                        .map(|_paths| WorkspaceMessage::ReloadNodes)
+                        .map(WorkspaceMessage::ReloadChangedFiles)
                 })
                 .chain([
                     window::resize_events()